@@ -269,6 +269,8 @@ private fun Content(
             when (state.apiAccessMethod) {
                 ApiAccessMethod.EncryptedDns -> onNavigateToEncryptedDnsInfoDialog
                 ApiAccessMethod.Bridges,
+                ApiAccessMethod.DomainFronting,
+                is ApiAccessMethod.CustomDomainFronting,
                 is ApiAccessMethod.CustomProxy,
                 ApiAccessMethod.Direct -> null
             },
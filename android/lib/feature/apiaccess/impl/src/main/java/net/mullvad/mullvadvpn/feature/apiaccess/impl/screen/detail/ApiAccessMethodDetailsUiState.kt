@@ -18,16 +18,19 @@ sealed interface ApiAccessMethodDetailsUiState {
         val isTestingAccessMethod: Boolean,
     ) : ApiAccessMethodDetailsUiState {
         override val apiAccessMethodId: ApiAccessMethodId = apiAccessMethodSetting.id
-        val isEditable: Boolean =
-            apiAccessMethodSetting.apiAccessMethod is ApiAccessMethod.CustomProxy
+        val isEditable: Boolean = apiAccessMethodSetting.apiAccessMethod.isEditable()
         val name: ApiAccessMethodName = apiAccessMethodSetting.name
         val enabled: Boolean = apiAccessMethodSetting.enabled
         val apiAccessMethod: ApiAccessMethod = apiAccessMethodSetting.apiAccessMethod
     }
 
-    fun canBeEdited() = this is Content && apiAccessMethod is ApiAccessMethod.CustomProxy
+    fun canBeEdited() = this is Content && apiAccessMethod.isEditable()
 
     fun testingAccessMethod() = this is Content && isTestingAccessMethod
 
     fun currentMethod() = this is Content && isCurrentMethod
 }
+
+// HTTP proxies can not be edited in the app, since the form has no field for the TLS server name
+private fun ApiAccessMethod.isEditable() =
+    this is ApiAccessMethod.CustomProxy && this !is ApiAccessMethod.CustomProxy.HttpConnect
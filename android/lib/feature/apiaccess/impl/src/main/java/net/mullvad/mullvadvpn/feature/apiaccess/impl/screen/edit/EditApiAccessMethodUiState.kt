@@ -74,6 +74,8 @@ data class EditApiAccessFormData(
                         username = customProxy.auth?.username ?: "",
                         password = customProxy.auth?.password ?: "",
                     )
+                is ApiAccessMethod.CustomProxy.HttpConnect ->
+                    error("HTTP proxy api access type can not be edited")
             }
     }
 }
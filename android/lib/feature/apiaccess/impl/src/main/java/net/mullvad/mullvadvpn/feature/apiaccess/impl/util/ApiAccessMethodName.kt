@@ -13,6 +13,8 @@ fun ApiAccessMethodSetting?.toDisplayName() =
         ApiAccessMethod.Direct -> stringResource(R.string.direct)
         ApiAccessMethod.Bridges,
         ApiAccessMethod.EncryptedDns,
+        ApiAccessMethod.DomainFronting,
+        is ApiAccessMethod.CustomDomainFronting,
         is ApiAccessMethod.CustomProxy -> this.name.toString()
         null -> "-"
     }
@@ -22,5 +24,7 @@ fun ApiAccessMethodSetting.toDisplayName(resources: Resources) =
         ApiAccessMethod.Direct -> resources.getString(R.string.direct)
         ApiAccessMethod.Bridges,
         ApiAccessMethod.EncryptedDns,
+        ApiAccessMethod.DomainFronting,
+        is ApiAccessMethod.CustomDomainFronting,
         is ApiAccessMethod.CustomProxy -> this.name.toString()
     }
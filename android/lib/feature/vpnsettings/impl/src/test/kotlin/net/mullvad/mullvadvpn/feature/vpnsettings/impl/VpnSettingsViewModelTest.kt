@@ -272,6 +272,7 @@ class VpnSettingsViewModelTest {
                         shadowsocks = ShadowsocksObfuscationSettings(Constraint.Any),
                         wireguardPort = Constraint.Any,
                         lwo = LwoObfuscationSettings(Constraint.Any),
+                        customBridge = null,
                     ),
                 customLists = emptyList(),
                 allowLan = false,
@@ -19,6 +19,7 @@ import net.mullvad.mullvadvpn.lib.model.DnsState
 import net.mullvad.mullvadvpn.lib.model.EntryConstraints
 import net.mullvad.mullvadvpn.lib.model.ExitConstraints
 import net.mullvad.mullvadvpn.lib.model.GeoLocationId
+import net.mullvad.mullvadvpn.lib.model.HttpAuth
 import net.mullvad.mullvadvpn.lib.model.IpVersion
 import net.mullvad.mullvadvpn.lib.model.LwoObfuscationSettings
 import net.mullvad.mullvadvpn.lib.model.MultihopConstraints
@@ -94,6 +95,12 @@ internal fun ObfuscationSettings.fromDomain(): ManagementInterface.ObfuscationSe
         .setShadowsocks(shadowsocks.fromDomain())
         .setWireguardPort(wireguardPort.fromDomain())
         .setLwo(lwo.fromDomain())
+        .setCustomBridge(
+            ManagementInterface.ObfuscationSettings.CustomBridge.newBuilder().let {
+                customBridge?.let { bridge -> it.setBridge(bridge.fromDomain()) }
+                it.build()
+            }
+        )
         .build()
 
 internal fun ObfuscationMode.fromDomain():
@@ -109,6 +116,8 @@ internal fun ObfuscationMode.fromDomain():
         ObfuscationMode.Lwo -> ManagementInterface.ObfuscationSettings.SelectedObfuscation.LWO
         ObfuscationMode.Auto -> ManagementInterface.ObfuscationSettings.SelectedObfuscation.AUTO
         ObfuscationMode.Off -> ManagementInterface.ObfuscationSettings.SelectedObfuscation.OFF
+        ObfuscationMode.CustomBridge ->
+            ManagementInterface.ObfuscationSettings.SelectedObfuscation.CUSTOM_BRIDGE
     }
 
 internal fun Udp2TcpObfuscationSettings.fromDomain():
@@ -227,16 +236,29 @@ internal fun ApiAccessMethod.fromDomain(): ManagementInterface.AccessMethod =
                     it.setEncryptedDnsProxy(
                         ManagementInterface.AccessMethod.EncryptedDnsProxy.getDefaultInstance()
                     )
+                ApiAccessMethod.DomainFronting ->
+                    it.setDomainFronting(
+                        ManagementInterface.AccessMethod.DomainFronting.getDefaultInstance()
+                    )
+                is ApiAccessMethod.CustomDomainFronting -> it.setCustomDomainFronting(fromDomain())
             }
         }
         .build()
 
+internal fun ApiAccessMethod.CustomDomainFronting.fromDomain():
+    ManagementInterface.CustomDomainFronting =
+    ManagementInterface.CustomDomainFronting.newBuilder()
+        .setFront(front)
+        .setProxyHost(proxyHost)
+        .build()
+
 internal fun ApiAccessMethod.CustomProxy.fromDomain(): ManagementInterface.CustomProxy =
     ManagementInterface.CustomProxy.newBuilder()
         .let {
             when (this) {
                 is ApiAccessMethod.CustomProxy.Shadowsocks -> it.setShadowsocks(fromDomain())
                 is ApiAccessMethod.CustomProxy.Socks5Remote -> it.setSocks5Remote(fromDomain())
+                is ApiAccessMethod.CustomProxy.HttpConnect -> it.setHttpConnect(fromDomain())
             }
         }
         .build()
@@ -251,6 +273,16 @@ internal fun ApiAccessMethod.CustomProxy.Socks5Remote.fromDomain():
 internal fun SocksAuth.fromDomain(): ManagementInterface.SocksAuth =
     ManagementInterface.SocksAuth.newBuilder().setUsername(username).setPassword(password).build()
 
+internal fun ApiAccessMethod.CustomProxy.HttpConnect.fromDomain(): ManagementInterface.HttpConnect =
+    ManagementInterface.HttpConnect.newBuilder().setIp(ip).setPort(port.value).let {
+        auth?.let { auth -> it.setAuth(auth.fromDomain()) }
+        tlsServerName?.let { tlsServerName -> it.setTlsServerName(tlsServerName) }
+        it.build()
+    }
+
+internal fun HttpAuth.fromDomain(): ManagementInterface.HttpAuth =
+    ManagementInterface.HttpAuth.newBuilder().setUsername(username).setPassword(password).build()
+
 internal fun ApiAccessMethod.CustomProxy.Shadowsocks.fromDomain(): ManagementInterface.Shadowsocks =
     ManagementInterface.Shadowsocks.newBuilder()
         .setIp(ip)
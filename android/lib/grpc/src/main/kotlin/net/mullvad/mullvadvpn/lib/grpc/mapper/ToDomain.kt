@@ -48,6 +48,7 @@ import net.mullvad.mullvadvpn.lib.model.ExitRecent
 import net.mullvad.mullvadvpn.lib.model.FeatureIndicator
 import net.mullvad.mullvadvpn.lib.model.GeoIpLocation
 import net.mullvad.mullvadvpn.lib.model.GeoLocationId
+import net.mullvad.mullvadvpn.lib.model.HttpAuth
 import net.mullvad.mullvadvpn.lib.model.IncompatibleConstraints
 import net.mullvad.mullvadvpn.lib.model.IpVersion
 import net.mullvad.mullvadvpn.lib.model.LatLong
@@ -254,6 +255,8 @@ internal fun ManagementInterface.ObfuscationEndpoint.ObfuscationType.toDomain():
             ObfuscationType.Shadowsocks
         ManagementInterface.ObfuscationEndpoint.ObfuscationType.QUIC -> ObfuscationType.Quic
         ManagementInterface.ObfuscationEndpoint.ObfuscationType.LWO -> ObfuscationType.Lwo
+        ManagementInterface.ObfuscationEndpoint.ObfuscationType.CUSTOM_BRIDGE ->
+            ObfuscationType.CustomBridge
         ManagementInterface.ObfuscationEndpoint.ObfuscationType.UNRECOGNIZED ->
             throw IllegalArgumentException("Unrecognized obfuscation type")
     }
@@ -460,6 +463,12 @@ internal fun ManagementInterface.ObfuscationSettings.toDomain(): ObfuscationSett
         shadowsocks = shadowsocks.toDomain(),
         wireguardPort = wireguardPort.toDomain(),
         lwo = lwo.toDomain(),
+        customBridge =
+            if (hasCustomBridge() && customBridge.hasBridge()) {
+                customBridge.bridge.toDomain()
+            } else {
+                null
+            },
     )
 
 internal fun ManagementInterface.ObfuscationSettings.SelectedObfuscation.toDomain():
@@ -473,6 +482,8 @@ internal fun ManagementInterface.ObfuscationSettings.SelectedObfuscation.toDomai
             ObfuscationMode.Shadowsocks
         ManagementInterface.ObfuscationSettings.SelectedObfuscation.QUIC -> ObfuscationMode.Quic
         ManagementInterface.ObfuscationSettings.SelectedObfuscation.LWO -> ObfuscationMode.Lwo
+        ManagementInterface.ObfuscationSettings.SelectedObfuscation.CUSTOM_BRIDGE ->
+            ObfuscationMode.CustomBridge
         ManagementInterface.ObfuscationSettings.SelectedObfuscation.WIREGUARD_PORT ->
             ObfuscationMode.WireguardPort
         ManagementInterface.ObfuscationSettings.SelectedObfuscation.UNRECOGNIZED ->
@@ -713,6 +724,9 @@ internal fun ManagementInterface.ApiAccessMethodSettings.toDomain(): List<ApiAcc
         add(direct.toDomain())
         add(mullvadBridges.toDomain())
         add(encryptedDnsProxy.toDomain())
+        if (hasDomainFronting()) {
+            add(domainFronting.toDomain())
+        }
         addAll(customList.map { it.toDomain() })
     }
 
@@ -730,13 +744,20 @@ internal fun ManagementInterface.AccessMethod.toDomain(): ApiAccessMethod =
         hasBridges() -> ApiAccessMethod.Bridges
         hasEncryptedDnsProxy() -> ApiAccessMethod.EncryptedDns
         hasCustom() -> custom.toDomain()
+        hasDomainFronting() -> ApiAccessMethod.DomainFronting
+        hasCustomDomainFronting() -> customDomainFronting.toDomain()
         else -> error("Type not found")
     }
 
+internal fun ManagementInterface.CustomDomainFronting.toDomain():
+    ApiAccessMethod.CustomDomainFronting =
+    ApiAccessMethod.CustomDomainFronting(front = front, proxyHost = proxyHost)
+
 internal fun ManagementInterface.CustomProxy.toDomain(): ApiAccessMethod.CustomProxy =
     when {
         hasShadowsocks() -> shadowsocks.toDomain()
         hasSocks5Remote() -> socks5Remote.toDomain()
+        hasHttpConnect() -> httpConnect.toDomain()
         hasSocks5Local() -> error("Socks5 local not supported")
         else -> error("Custom proxy not found")
     }
@@ -764,6 +785,27 @@ internal fun ManagementInterface.Socks5Remote.toDomain(): ApiAccessMethod.Custom
 internal fun ManagementInterface.SocksAuth.toDomain(): SocksAuth =
     SocksAuth(username = username, password = password)
 
+internal fun ManagementInterface.HttpConnect.toDomain(): ApiAccessMethod.CustomProxy.HttpConnect =
+    ApiAccessMethod.CustomProxy.HttpConnect(
+        ip = ip,
+        port = Port(port),
+        auth =
+            if (hasAuth()) {
+                auth.toDomain()
+            } else {
+                null
+            },
+        tlsServerName =
+            if (hasTlsServerName()) {
+                tlsServerName
+            } else {
+                null
+            },
+    )
+
+internal fun ManagementInterface.HttpAuth.toDomain(): HttpAuth =
+    HttpAuth(username = username, password = password)
+
 internal fun ManagementInterface.FeatureIndicators.toDomain(): List<FeatureIndicator> =
     activeFeaturesList.map { it.toDomain() }.sorted()
 
@@ -10,6 +10,11 @@ sealed interface ApiAccessMethod : Parcelable {
 
     @Parcelize data object EncryptedDns : ApiAccessMethod
 
+    @Parcelize data object DomainFronting : ApiAccessMethod
+
+    @Parcelize
+    data class CustomDomainFronting(val front: String, val proxyHost: String) : ApiAccessMethod
+
     sealed interface CustomProxy : ApiAccessMethod {
         @Parcelize
         data class Socks5Remote(val ip: String, val port: Port, val auth: SocksAuth?) : CustomProxy
@@ -21,5 +26,13 @@ sealed interface ApiAccessMethod : Parcelable {
             val password: String?,
             val cipher: Cipher,
         ) : CustomProxy
+
+        @Parcelize
+        data class HttpConnect(
+            val ip: String,
+            val port: Port,
+            val auth: HttpAuth?,
+            val tlsServerName: String?,
+        ) : CustomProxy
     }
 }
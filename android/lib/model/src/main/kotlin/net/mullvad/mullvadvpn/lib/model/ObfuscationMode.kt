@@ -8,4 +8,5 @@ enum class ObfuscationMode {
     Quic,
     Lwo,
     WireguardPort,
+    CustomBridge,
 }
@@ -9,6 +9,8 @@ data class ObfuscationSettings(
     val shadowsocks: ShadowsocksObfuscationSettings,
     val wireguardPort: Constraint<Port>,
     val lwo: LwoObfuscationSettings,
+    // Only Shadowsocks and remote SOCKS5 proxies can be used as custom bridges
+    val customBridge: ApiAccessMethod.CustomProxy?,
 ) {
     companion object
 }
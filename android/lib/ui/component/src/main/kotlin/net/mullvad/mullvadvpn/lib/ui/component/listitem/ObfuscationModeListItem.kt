@@ -84,6 +84,7 @@ fun ObfuscationMode.toTitle() =
         ObfuscationMode.Quic -> stringResource(id = R.string.quic)
         ObfuscationMode.Lwo -> stringResource(id = R.string.lwo)
         ObfuscationMode.WireguardPort -> stringResource(id = R.string.wireguard_port_title)
+        ObfuscationMode.CustomBridge -> stringResource(id = R.string.custom_bridge)
     }
 
 @Composable
@@ -66,6 +66,7 @@ export function getDefaultSettings(): ISettings {
       lwoSettings: {
         port: 'any',
       },
+      customBridgeSettings: {},
     },
     customLists: [],
     apiAccessMethods: getDefaultApiAccessMethods(),
@@ -99,12 +100,12 @@ export function getDefaultApiAccessMethods(): ApiAccessMethodSettings {
       enabled: false,
       type: 'encrypted-dns-proxy',
     },
-    // domainFronting: {
-    //   id: '',
-    //   enabled: false,
-    //   name: 'Domain fronting',
-    //   type: 'domain-fronting',
-    // },
+    domainFronting: {
+      id: '',
+      name: 'Domain fronting',
+      enabled: false,
+      type: 'domain-fronting',
+    },
     custom: [],
   };
 }
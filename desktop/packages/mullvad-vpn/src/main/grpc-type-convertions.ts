@@ -17,6 +17,7 @@ import {
   DeviceEvent,
   DeviceState,
   DirectMethod,
+  DomainFronting,
   EncryptedDnsProxy,
   EndpointObfuscationType,
   ErrorStateCause,
@@ -24,6 +25,7 @@ import {
   FeatureIndicator,
   FirewallPolicyError,
   FirewallPolicyErrorType,
+  HttpAuth,
   IAppVersionInfo,
   ICustomList,
   IDevice,
@@ -419,6 +421,9 @@ function convertFromObfuscationEndpoint(
     case grpcTypes.ObfuscationEndpoint.ObfuscationType.LWO:
       translatedType = 'lwo';
       break;
+    case grpcTypes.ObfuscationEndpoint.ObfuscationType.CUSTOM_BRIDGE:
+      translatedType = 'custom-bridge';
+      break;
     default:
       throw new Error('unsupported obfuscation protocol');
   }
@@ -442,7 +447,7 @@ export function convertFromSettings(settings: grpcTypes.Settings): ISettings | u
   const relaySettings = convertFromRelaySettings(settings.getRelaySettings())!;
   const tunnelOptions = convertFromTunnelOptions(settingsObject.tunnelOptions!);
   const splitTunnel = settingsObject.splitTunnel ?? { enableExclusions: false, appsList: [] };
-  const obfuscationSettings = convertFromObfuscationSettings(settingsObject.obfuscationSettings);
+  const obfuscationSettings = convertFromObfuscationSettings(settings.getObfuscationSettings());
   const customLists = convertFromCustomListSettings(settings.getCustomLists());
   const apiAccessMethods = convertFromApiAccessMethodSettings(settings.getApiAccessMethods()!);
   const relayOverrides = settingsObject.relayOverridesList;
@@ -670,8 +675,9 @@ function convertFromQuantumResistantState(state?: grpcTypes.QuantumResistantStat
 }
 
 function convertFromObfuscationSettings(
-  obfuscationSettings?: grpcTypes.ObfuscationSettings.AsObject,
+  grpcObfuscationSettings?: grpcTypes.ObfuscationSettings,
 ): ObfuscationSettings {
+  const obfuscationSettings = grpcObfuscationSettings?.toObject();
   let selectedObfuscationType = ObfuscationType.auto;
   switch (obfuscationSettings?.selectedObfuscation) {
     case grpcTypes.ObfuscationSettings.SelectedObfuscation.OFF:
@@ -692,8 +698,13 @@ function convertFromObfuscationSettings(
     case grpcTypes.ObfuscationSettings.SelectedObfuscation.WIREGUARD_PORT:
       selectedObfuscationType = ObfuscationType.wireGuardPort;
       break;
+    case grpcTypes.ObfuscationSettings.SelectedObfuscation.CUSTOM_BRIDGE:
+      selectedObfuscationType = ObfuscationType.customBridge;
+      break;
   }
 
+  const customBridge = grpcObfuscationSettings?.getCustomBridge()?.getBridge();
+
   return {
     selectedObfuscation: selectedObfuscationType,
     udp2tcpSettings: obfuscationSettings?.udp2tcp
@@ -708,6 +719,9 @@ function convertFromObfuscationSettings(
     lwoSettings: obfuscationSettings?.lwo
       ? { port: convertFromConstraint(obfuscationSettings.lwo.port) }
       : { port: 'any' },
+    customBridgeSettings: {
+      bridge: customBridge === undefined ? undefined : convertFromCustomProxy(customBridge),
+    },
   };
 }
 
@@ -963,6 +977,11 @@ export function convertToAntiCensorshipSettings(obfuscationSettings: Obfuscation
         grpcTypes.ObfuscationSettings.SelectedObfuscation.WIREGUARD_PORT,
       );
       break;
+    case ObfuscationType.customBridge:
+      grpcObfuscationSettings.setSelectedObfuscation(
+        grpcTypes.ObfuscationSettings.SelectedObfuscation.CUSTOM_BRIDGE,
+      );
+      break;
   }
 
   if (obfuscationSettings.udp2tcpSettings) {
@@ -997,6 +1016,14 @@ export function convertToAntiCensorshipSettings(obfuscationSettings: Obfuscation
     grpcObfuscationSettings.setLwo(lwoSettings);
   }
 
+  const customBridgeSettings = new grpcTypes.ObfuscationSettings.CustomBridge();
+  if (obfuscationSettings.customBridgeSettings.bridge !== undefined) {
+    customBridgeSettings.setBridge(
+      convertToCustomProxy(obfuscationSettings.customBridgeSettings.bridge),
+    );
+  }
+  grpcObfuscationSettings.setCustomBridge(customBridgeSettings);
+
   return grpcObfuscationSettings;
 }
 
@@ -1214,11 +1241,18 @@ function fillApiAccessMethodSetting<T extends grpcTypes.NewAccessMethodSetting>(
       accessMethod.setEncryptedDnsProxy(encryptedDnsProxy);
       break;
     }
-    // case 'domain-fronting': {
-    //   const domainFronting = new grpcTypes.AccessMethod.DomainFronting();
-    //   accessMethod.setDomainFronting(domainFronting);
-    //   break;
-    // }
+    case 'domain-fronting': {
+      const domainFronting = new grpcTypes.AccessMethod.DomainFronting();
+      accessMethod.setDomainFronting(domainFronting);
+      break;
+    }
+    case 'custom-domain-fronting': {
+      const customDomainFronting = new grpcTypes.CustomDomainFronting();
+      customDomainFronting.setFront(method.front);
+      customDomainFronting.setProxyHost(method.proxyHost);
+      accessMethod.setCustomDomainFronting(customDomainFronting);
+      break;
+    }
     default:
       accessMethod.setCustom(convertToCustomProxy(method));
   }
@@ -1261,6 +1295,19 @@ export function convertToCustomProxy(proxy: CustomProxy): grpcTypes.CustomProxy
       customProxy.setShadowsocks(shadowsocks);
       break;
     }
+    case 'http-connect': {
+      const httpConnect = new grpcTypes.HttpConnect();
+      httpConnect.setIp(proxy.ip);
+      httpConnect.setPort(proxy.port);
+      if (proxy.authentication !== undefined) {
+        httpConnect.setAuth(convertToHttpAuth(proxy.authentication));
+      }
+      if (proxy.tlsServerName !== undefined) {
+        httpConnect.setTlsServerName(proxy.tlsServerName);
+      }
+      customProxy.setHttpConnect(httpConnect);
+      break;
+    }
   }
 
   return customProxy;
@@ -1300,6 +1347,13 @@ function convertToSocksAuth(authentication: SocksAuth): grpcTypes.SocksAuth {
   return auth;
 }
 
+function convertToHttpAuth(authentication: HttpAuth): grpcTypes.HttpAuth {
+  const auth = new grpcTypes.HttpAuth();
+  auth.setUsername(authentication.username);
+  auth.setPassword(authentication.password);
+  return auth;
+}
+
 function convertFromApiAccessMethodSettings(
   accessMethods: grpcTypes.ApiAccessMethodSettings,
 ): ApiAccessMethodSettings {
@@ -1315,9 +1369,9 @@ function convertFromApiAccessMethodSettings(
       "no 'Encrypted DNS proxy' access method was found",
     ),
   ) as AccessMethodSetting<EncryptedDnsProxy>;
-  // const domainFronting = convertFromApiAccessMethodSetting(
-  //   ensureExists(accessMethods.getDomainFronting(), "no 'Domain fronting' access method was found"),
-  // ) as AccessMethodSetting<DomainFronting>;
+  const domainFronting = convertFromApiAccessMethodSetting(
+    ensureExists(accessMethods.getDomainFronting(), "no 'Domain fronting' access method was found"),
+  ) as AccessMethodSetting<DomainFronting>;
   const custom = accessMethods
     .getCustomList()
     .filter((setting) => setting.hasId() && setting.hasAccessMethod())
@@ -1329,7 +1383,7 @@ function convertFromApiAccessMethodSettings(
     direct,
     mullvadBridges: bridges,
     encryptedDnsProxy,
-    // domainFronting,
+    domainFronting,
     custom,
   };
 }
@@ -1340,7 +1394,9 @@ function isCustomProxy(
   return (
     accessMethod.type !== 'direct' &&
     accessMethod.type !== 'bridges' &&
-    accessMethod.type !== 'encrypted-dns-proxy'
+    accessMethod.type !== 'encrypted-dns-proxy' &&
+    accessMethod.type !== 'domain-fronting' &&
+    accessMethod.type !== 'custom-domain-fronting'
   );
 }
 
@@ -1366,8 +1422,16 @@ function convertFromAccessMethod(method: grpcTypes.AccessMethod): AccessMethod {
       return { type: 'bridges' };
     case grpcTypes.AccessMethod.AccessMethodCase.ENCRYPTED_DNS_PROXY:
       return { type: 'encrypted-dns-proxy' };
-    // case grpcTypes.AccessMethod.AccessMethodCase.DOMAIN_FRONTING:
-    //   return { type: 'domain-fronting' };
+    case grpcTypes.AccessMethod.AccessMethodCase.DOMAIN_FRONTING:
+      return { type: 'domain-fronting' };
+    case grpcTypes.AccessMethod.AccessMethodCase.CUSTOM_DOMAIN_FRONTING: {
+      const customDomainFronting = method.getCustomDomainFronting()!;
+      return {
+        type: 'custom-domain-fronting',
+        front: customDomainFronting.getFront(),
+        proxyHost: customDomainFronting.getProxyHost(),
+      };
+    }
     case grpcTypes.AccessMethod.AccessMethodCase.CUSTOM: {
       return convertFromCustomProxy(method.getCustom()!);
     }
@@ -1410,6 +1474,17 @@ function convertFromCustomProxy(proxy: grpcTypes.CustomProxy): CustomProxy {
         cipher: convertFromGrpcShadowsocksCipher(shadowsocks.getCipher()!),
       };
     }
+    case grpcTypes.CustomProxy.ProxyMethodCase.HTTP_CONNECT: {
+      const httpConnect = proxy.getHttpConnect()!;
+      const auth = httpConnect.getAuth();
+      return {
+        type: 'http-connect',
+        ip: httpConnect.getIp(),
+        port: httpConnect.getPort(),
+        authentication: auth === undefined ? undefined : convertFromHttpAuth(auth),
+        tlsServerName: httpConnect.hasTlsServerName() ? httpConnect.getTlsServerName() : undefined,
+      };
+    }
     case grpcTypes.CustomProxy.ProxyMethodCase.PROXY_METHOD_NOT_SET:
       throw new Error('Custom method not set, which should always be set');
   }
@@ -1422,6 +1497,13 @@ function convertFromSocksAuth(auth: grpcTypes.SocksAuth): SocksAuth {
   };
 }
 
+function convertFromHttpAuth(auth: grpcTypes.HttpAuth): HttpAuth {
+  return {
+    username: auth.getUsername(),
+    password: auth.getPassword(),
+  };
+}
+
 export function convertToNewCustomList(customList: NewCustomList): grpcTypes.NewCustomList {
   const newCustomList = new grpcTypes.NewCustomList();
   newCustomList.setName(customList.name);
@@ -121,12 +121,10 @@ export function ApiAccessView() {
                     method={methods.encryptedDnsProxy}
                     inUse={methods.encryptedDnsProxy.id === currentMethod?.id}
                   />
-                  {/*
                   <ApiAccessMethod
                     method={methods.domainFronting}
                     inUse={methods.domainFronting.id === currentMethod?.id}
                   />
-                  */}
                   {methods.custom.map((method) => (
                     <ApiAccessMethod
                       key={method.id}
@@ -419,5 +417,13 @@ function cloneMethod<T extends AccessMethodSetting>(method: T): T {
     clonedMethod.authentication = { ...method.authentication };
   }
 
+  if (
+    method.type === 'http-connect' &&
+    clonedMethod.type === 'http-connect' &&
+    method.authentication !== undefined
+  ) {
+    clonedMethod.authentication = { ...method.authentication };
+  }
+
   return clonedMethod;
 }
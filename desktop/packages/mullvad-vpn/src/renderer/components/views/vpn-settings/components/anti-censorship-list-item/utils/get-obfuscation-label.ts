@@ -22,6 +22,8 @@ export function getObfuscationLabel(obfuscationType: ObfuscationType): string {
       return sprintf(messages.pgettext('wireguard-settings-view', '%(wireguard)s port'), {
         wireguard: strings.wireguard,
       });
+    case ObfuscationType.customBridge:
+      return messages.pgettext('wireguard-settings-view', 'Custom bridge');
     default:
       return obfuscationType satisfies never;
   }
@@ -191,6 +191,7 @@ const initialState: ISettingsReduxState = {
     lwoSettings: {
       port: 'any',
     },
+    customBridgeSettings: {},
   },
   customLists: [],
   recents: undefined,
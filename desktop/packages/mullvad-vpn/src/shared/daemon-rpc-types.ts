@@ -113,7 +113,7 @@ export type ErrorStateDetails =
 export type AfterDisconnect = 'nothing' | 'block' | 'reconnect';
 
 export type RelayProtocol = 'tcp' | 'udp';
-export type EndpointObfuscationType = 'udp2tcp' | 'shadowsocks' | 'quic' | 'lwo';
+export type EndpointObfuscationType = 'udp2tcp' | 'shadowsocks' | 'quic' | 'lwo' | 'custom-bridge';
 
 export type Constraint<T> = 'any' | { only: T };
 export type LiftedConstraint<T> = 'any' | T;
@@ -526,6 +526,11 @@ export type ShadowsocksSettings = {
   port: Constraint<number>;
 };
 
+// Only Shadowsocks and remote SOCKS5 proxies can be used as custom bridges
+export type CustomBridgeSettings = {
+  bridge?: CustomProxy;
+};
+
 export enum ObfuscationType {
   auto,
   off,
@@ -534,6 +539,7 @@ export enum ObfuscationType {
   quic,
   lwo,
   wireGuardPort,
+  customBridge,
 }
 
 export type ObfuscationSettings = {
@@ -542,6 +548,7 @@ export type ObfuscationSettings = {
   shadowsocksSettings: ShadowsocksSettings;
   wireGuardPortSettings: WireGuardPortObfuscationSettings;
   lwoSettings: LwoSettings;
+  customBridgeSettings: CustomBridgeSettings;
 };
 
 export interface ISocketAddress {
@@ -581,16 +588,44 @@ export type ShadowsocksCustomProxy = {
   cipher: ShadowsocksCipher;
 };
 
-export type CustomProxy = Socks5LocalCustomProxy | Socks5RemoteCustomProxy | ShadowsocksCustomProxy;
+export interface HttpAuth {
+  username: string;
+  password: string;
+}
+
+export type HttpConnectCustomProxy = {
+  type: 'http-connect';
+  ip: string;
+  port: number;
+  authentication?: HttpAuth;
+  // If set, the proxy is reached over TLS, and its certificate must be valid for this name.
+  tlsServerName?: string;
+};
+
+export type CustomProxy =
+  | Socks5LocalCustomProxy
+  | Socks5RemoteCustomProxy
+  | ShadowsocksCustomProxy
+  | HttpConnectCustomProxy;
 export type NamedCustomProxy = CustomProxy & { name: string };
 
 export type DirectMethod = { type: 'direct' };
 export type BridgesMethod = { type: 'bridges' };
 export type EncryptedDnsProxy = { type: 'encrypted-dns-proxy' };
 export type DomainFronting = { type: 'domain-fronting' };
+export type CustomDomainFronting = {
+  type: 'custom-domain-fronting';
+  front: string;
+  proxyHost: string;
+};
 
-export type AccessMethod = DirectMethod | BridgesMethod | EncryptedDnsProxy | CustomProxy;
-// | DomainFronting;
+export type AccessMethod =
+  | DirectMethod
+  | BridgesMethod
+  | EncryptedDnsProxy
+  | DomainFronting
+  | CustomDomainFronting
+  | CustomProxy;
 
 export type NamedAccessMethod<T extends AccessMethod> = T & { name: string };
 
@@ -607,7 +642,7 @@ export type ApiAccessMethodSettings = {
   direct: AccessMethodSetting<DirectMethod>;
   mullvadBridges: AccessMethodSetting<BridgesMethod>;
   encryptedDnsProxy: AccessMethodSetting<EncryptedDnsProxy>;
-  // domainFronting: AccessMethodSetting<DomainFronting>;
+  domainFronting: AccessMethodSetting<DomainFronting>;
   custom: Array<AccessMethodSetting<CustomProxy>>;
 };
 
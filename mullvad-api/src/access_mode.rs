@@ -635,4 +635,85 @@ mod tests {
             "AccessModeSelector died after a dropped receiver"
         );
     }
+
+    /// A mock resolver that records which access methods it is asked to resolve.
+    struct RecordingResolver {
+        resolved: Arc<std::sync::Mutex<Vec<AccessMethod>>>,
+    }
+
+    #[async_trait]
+    impl AccessMethodResolver for RecordingResolver {
+        async fn resolve_access_method_setting(
+            &mut self,
+            access_method: &AccessMethod,
+        ) -> Option<(AllowedEndpoint, ApiConnectionMode)> {
+            self.resolved.lock().unwrap().push(access_method.clone());
+            Some((test_endpoint(), ApiConnectionMode::Direct))
+        }
+
+        async fn default_connection_mode(&self) -> AllowedEndpoint {
+            test_endpoint()
+        }
+    }
+
+    /// Test that rotating through the access methods reaches both the built-in and custom domain
+    /// fronting methods, and that disabled methods are skipped.
+    #[tokio::test]
+    async fn rotation_includes_domain_fronting() {
+        use mullvad_types::access_method::{BuiltInAccessMethod, CustomDomainFronting};
+
+        let custom = AccessMethod::from(CustomDomainFronting {
+            front: "front.example.com".to_owned(),
+            proxy_host: "proxy.example.com".to_owned(),
+        });
+        let mut settings = Settings::default();
+        settings
+            .append(AccessMethodSetting::new(
+                "custom".to_owned(),
+                true,
+                custom.clone(),
+            ))
+            .unwrap();
+        settings.update_builtin(
+            |setting| setting.access_method == AccessMethod::from(BuiltInAccessMethod::Bridge),
+            |setting| setting.disable(),
+        );
+        settings.update_builtin(
+            |setting| {
+                setting.access_method == AccessMethod::from(BuiltInAccessMethod::DomainFronting)
+            },
+            |setting| setting.enable(),
+        );
+
+        let resolved = Arc::new(std::sync::Mutex::new(vec![]));
+        let resolver = RecordingResolver {
+            resolved: resolved.clone(),
+        };
+        let (event_tx, _event_rx) = mpsc::unbounded();
+        let (handle, _provider) = AccessModeSelector::spawn(
+            resolver,
+            settings.clone(),
+            #[cfg(feature = "api-override")]
+            ApiEndpoint::new(
+                "mullvad.net".into(),
+                "127.0.0.1:1234".parse().unwrap(),
+                true,
+            ),
+            event_tx,
+        )
+        .await
+        .expect("Failed to spawn AccessModeSelector");
+
+        for _ in 0..settings.cardinality() {
+            handle
+                .rotate()
+                .await
+                .expect("Failed to rotate access method");
+        }
+
+        let resolved = resolved.lock().unwrap();
+        assert!(resolved.contains(&AccessMethod::from(BuiltInAccessMethod::DomainFronting)));
+        assert!(resolved.contains(&custom));
+        assert!(!resolved.contains(&AccessMethod::from(BuiltInAccessMethod::Bridge)));
+    }
 }
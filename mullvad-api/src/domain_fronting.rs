@@ -1,29 +1,54 @@
 //! Built-in domain fronting access method configuration.
 
 use crate::proxy::{ApiConnectionMode, DomainFrontingConfig, ProxyConfig};
+use domain_fronting::DomainFronting;
+use mullvad_encrypted_dns_proxy::config_resolver;
+use std::net::SocketAddr;
 
 const FRONT: &str = "www.phpmyadmin.net";
 const PROXY_HOST: &str = "1105015943.rsc.cdn77.org";
 const SESSION_HEADER: &str = "X-Mullvad-Session";
+const HTTPS_PORT: u16 = 443;
 
 /// Resolve the built-in domain fronting configuration.
 ///
 /// Performs DNS resolution of the front domain and returns the
 /// corresponding [`ApiConnectionMode`].
 pub async fn resolve() -> Option<ApiConnectionMode> {
-    match DomainFrontingConfig::resolve(
-        FRONT.to_string(),
-        PROXY_HOST.to_string(),
-        SESSION_HEADER.to_string(),
-    )
-    .await
-    {
-        Ok(config) => Some(ApiConnectionMode::Proxied(ProxyConfig::DomainFronting(
-            config,
-        ))),
+    resolve_custom(FRONT, PROXY_HOST).await
+}
+
+/// Resolve a domain fronting configuration with a user-defined front domain and proxy host.
+///
+/// The front domain is resolved using DNS over HTTPS, the same way as the encrypted DNS proxy
+/// configurations are fetched, since plain DNS may be blocked by the firewall or tampered with
+/// by a censor.
+pub async fn resolve_custom(front: &str, proxy_host: &str) -> Option<ApiConnectionMode> {
+    let addrs = match config_resolver::resolve_default_ips(front).await {
+        Ok(addrs) => addrs,
         Err(error) => {
-            log::warn!("Failed to resolve domain fronting config: {error}");
-            None
+            log::warn!("Failed to resolve domain fronting config for {front}: {error}");
+            return None;
         }
-    }
+    };
+    // Prefer IPv4, since IPv6 connectivity is less common
+    let Some(ip) = addrs
+        .iter()
+        .find(|addr| addr.is_ipv4())
+        .or_else(|| addrs.first())
+    else {
+        log::warn!("Failed to resolve domain fronting config for {front}: no addresses");
+        return None;
+    };
+    let config = DomainFrontingConfig {
+        addr: SocketAddr::new(*ip, HTTPS_PORT),
+        domain_fronting: DomainFronting::new(
+            front.to_string(),
+            proxy_host.to_string(),
+            SESSION_HEADER.to_string(),
+        ),
+    };
+    Some(ApiConnectionMode::Proxied(ProxyConfig::DomainFronting(
+        config,
+    )))
 }
@@ -0,0 +1,260 @@
+//! Tunnel connections through an HTTP proxy using the `CONNECT` method.
+//! See RFC 9110: <https://datatracker.ietf.org/doc/html/rfc9110#name-connect>.
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{Arc, LazyLock},
+};
+use talpid_types::net::proxy::HttpAuth;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+use tokio_rustls::{
+    TlsConnector,
+    rustls::{self, ClientConfig, pki_types::ServerName},
+};
+
+/// Maximum size of the response header sent by the proxy.
+const MAX_RESPONSE_HEADER_SIZE: usize = 8 * 1024;
+
+/// A stream to the proxy, with or without TLS.
+pub(crate) trait ProxyStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> ProxyStream for T {}
+
+/// Unlike the API itself, the proxy is not ours, so its certificate is verified using the
+/// public web PKI.
+static PROXY_TLS_CONFIG: LazyLock<Arc<ClientConfig>> = LazyLock::new(|| {
+    let root_store = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    Arc::new(
+        ClientConfig::builder()
+            .with_root_certificates(root_store)
+            .with_no_client_auth(),
+    )
+});
+
+/// Establish a TLS connection to the proxy, verifying its certificate against `server_name`.
+pub(crate) async fn tls_connect(
+    stream: TcpStream,
+    server_name: &str,
+) -> io::Result<tokio_rustls::client::TlsStream<TcpStream>> {
+    let server_name = ServerName::try_from(server_name.to_owned()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid proxy server name \"{server_name}\""),
+        )
+    })?;
+    TlsConnector::from(Arc::clone(&PROXY_TLS_CONFIG))
+        .connect(server_name, stream)
+        .await
+}
+
+/// Ask the proxy at the other end of `stream` to open a tunnel to `target`. Once this returns,
+/// `stream` is connected to `target`.
+pub(crate) async fn connect<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    target: &SocketAddr,
+    auth: Option<&HttpAuth>,
+) -> io::Result<S> {
+    stream
+        .write_all(connect_request(target, auth).as_bytes())
+        .await?;
+    stream.flush().await?;
+
+    let header = read_response_header(&mut stream).await?;
+    match parse_status(&header)? {
+        200..=299 => Ok(stream),
+        407 => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "HTTP proxy authentication failed",
+        )),
+        status => Err(io::Error::other(format!(
+            "HTTP proxy refused to connect: status {status}"
+        ))),
+    }
+}
+
+fn connect_request(target: &SocketAddr, auth: Option<&HttpAuth>) -> String {
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(auth) = auth {
+        let credentials =
+            BASE64_STANDARD.encode(format!("{}:{}", auth.username(), auth.password()));
+        request.push_str(&format!("Proxy-Authorization: Basic {credentials}\r\n"));
+    }
+    request.push_str("\r\n");
+    request
+}
+
+/// Read the response header, one byte at a time so that nothing sent after it is consumed.
+async fn read_response_header<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<Vec<u8>> {
+    let mut header = Vec::new();
+    while !header.ends_with(b"\r\n\r\n") {
+        if header.len() >= MAX_RESPONSE_HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "HTTP proxy response header is too large",
+            ));
+        }
+        header.push(stream.read_u8().await?);
+    }
+    Ok(header)
+}
+
+fn parse_status(header: &[u8]) -> io::Result<u16> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid HTTP proxy response");
+
+    let status_line = header
+        .split(|&byte| byte == b'\n')
+        .next()
+        .and_then(|line| std::str::from_utf8(line).ok())
+        .ok_or_else(invalid)?;
+    let mut parts = status_line.split_ascii_whitespace();
+    if !parts
+        .next()
+        .is_some_and(|version| version.starts_with("HTTP/1."))
+    {
+        return Err(invalid());
+    }
+    parts
+        .next()
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const TARGET: &str = "192.0.2.1:443";
+
+    /// Stand-in for an HTTP proxy. Accepts a single connection, checks the `CONNECT` request and
+    /// responds with `status`. If the tunnel is established, echoes everything back.
+    async fn spawn_proxy(expected_auth: Option<&'static str>, status: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_response_header(&mut stream).await.unwrap();
+            let request = String::from_utf8(request).unwrap();
+
+            let mut lines = request.lines();
+            assert_eq!(lines.next(), Some(&*format!("CONNECT {TARGET} HTTP/1.1")));
+            let auth = lines.find_map(|line| line.strip_prefix("Proxy-Authorization: Basic "));
+            let status = if auth == expected_auth {
+                status
+            } else {
+                "407 Proxy Authentication Required"
+            };
+
+            stream
+                .write_all(format!("HTTP/1.1 {status}\r\nServer: test\r\n\r\n").as_bytes())
+                .await
+                .unwrap();
+            if status.starts_with("200") {
+                let (mut reader, mut writer) = stream.split();
+                let _ = tokio::io::copy(&mut reader, &mut writer).await;
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_connect() {
+        // "user:hunter2"
+        let proxy = spawn_proxy(Some("dXNlcjpodW50ZXIy"), "200 Connection established").await;
+        let auth = HttpAuth::new("user".to_owned(), "hunter2".to_owned()).unwrap();
+
+        let stream = TcpStream::connect(proxy).await.unwrap();
+        let mut stream = connect(stream, &TARGET.parse().unwrap(), Some(&auth))
+            .await
+            .unwrap();
+
+        stream.write_all(b"ping").await.unwrap();
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_connect_authentication_failed() {
+        let proxy = spawn_proxy(Some("dXNlcjpodW50ZXIy"), "200 Connection established").await;
+        let auth = HttpAuth::new("user".to_owned(), "wrong".to_owned()).unwrap();
+
+        let stream = TcpStream::connect(proxy).await.unwrap();
+        let error = connect(stream, &TARGET.parse().unwrap(), Some(&auth))
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_connect_refused() {
+        let proxy = spawn_proxy(None, "403 Forbidden").await;
+
+        let stream = TcpStream::connect(proxy).await.unwrap();
+        assert!(
+            connect(stream, &TARGET.parse().unwrap(), None)
+                .await
+                .is_err()
+        );
+    }
+
+    /// The proxy certificate cannot be verified without a certificate issued by a public CA, so
+    /// only check that a TLS handshake is started for the right server name, and that a server
+    /// that does not complete it is rejected.
+    #[tokio::test]
+    async fn test_tls_connect() {
+        const SERVER_NAME: &str = "proxy.example.com";
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut client_hello = vec![0u8; 512];
+            let n = stream.read(&mut client_hello).await.unwrap();
+            client_hello.truncate(n);
+            stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\n\r\n")
+                .await
+                .unwrap();
+            client_hello
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        assert!(tls_connect(stream, SERVER_NAME).await.is_err());
+
+        let client_hello = server.await.unwrap();
+        // TLS handshake record
+        assert_eq!(client_hello.first(), Some(&0x16));
+        // Server name indication
+        assert!(
+            client_hello
+                .windows(SERVER_NAME.len())
+                .any(|window| window == SERVER_NAME.as_bytes())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tls_connect_invalid_server_name() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let error = tls_connect(stream, "not a hostname").await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status(b"HTTP/1.1 200 OK\r\n\r\n").unwrap(), 200);
+        assert_eq!(parse_status(b"HTTP/1.0 407\r\n\r\n").unwrap(), 407);
+        assert!(parse_status(b"SSH-2.0-OpenSSH\r\n\r\n").is_err());
+        assert!(parse_status(b"HTTP/1.1 OK\r\n\r\n").is_err());
+    }
+}
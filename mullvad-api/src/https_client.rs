@@ -2,6 +2,7 @@ use crate::proxy::DomainFrontingConfig;
 use crate::{
     DnsResolver,
     abortable_stream::{AbortableStream, AbortableStreamHandle},
+    http_connect::{self, ProxyStream},
     proxy::{ApiConnection, ApiConnectionMode, ProxyConfig},
     tls_stream::TlsStream,
 };
@@ -84,6 +85,8 @@ pub(crate) enum InnerConnectionMode {
     Shadowsocks(ShadowsocksConfig),
     /// Connect to the destination via a Socks proxy.
     Socks5(SocksConfig),
+    /// Connect to the destination via an HTTP proxy, using the `CONNECT` method.
+    HttpConnect(proxy::HttpConnect),
     /// Connect to the destination via Mullvad Encrypted DNS proxy.
     /// See [`mullvad_encrypted_dns_proxy`] for how the proxy works.
     EncryptedDnsProxy(EncryptedDNSConfig),
@@ -170,6 +173,29 @@ impl InnerConnectionMode {
                 )
                 .await
             }
+            // Set up a tunnel through an HTTP proxy.
+            InnerConnectionMode::HttpConnect(http) => {
+                let first_hop = http.endpoint;
+                let make_proxy_stream = |tcp_stream| async {
+                    let stream: Box<dyn ProxyStream> = match &http.tls_server_name {
+                        Some(server_name) => {
+                            Box::new(http_connect::tls_connect(tcp_stream, server_name).await?)
+                        }
+                        None => Box::new(tcp_stream),
+                    };
+                    http_connect::connect(stream, addr, http.auth.as_ref()).await
+                };
+                Self::connect_proxied(
+                    first_hop,
+                    hostname,
+                    make_proxy_stream,
+                    #[cfg(target_os = "android")]
+                    socket_bypass_tx,
+                    #[cfg(any(feature = "api-override", test))]
+                    disable_tls,
+                )
+                .await
+            }
             InnerConnectionMode::EncryptedDnsProxy(proxy_config) => {
                 let first_hop = SocketAddr::V4(proxy_config.addr);
                 let make_proxy_stream = |tcp_stream| async {
@@ -374,6 +400,7 @@ impl From<ApiConnectionMode> for InnerConnectionMode {
                     peer: config.endpoint,
                     authentication: config.auth,
                 }),
+                ProxyConfig::HttpConnect(config) => InnerConnectionMode::HttpConnect(config),
                 ProxyConfig::EncryptedDnsProxy(config) => {
                     InnerConnectionMode::EncryptedDnsProxy(config)
                 }
@@ -19,8 +19,10 @@ pub mod version;
 mod abortable_stream;
 pub mod access_mode;
 pub mod domain_fronting;
+mod http_connect;
 mod https_client;
 pub mod proxy;
+pub mod stats;
 mod tls_stream;
 #[cfg(target_os = "android")]
 pub use crate::https_client::SocketBypassRequest;
@@ -316,6 +318,7 @@ where
     handle: tokio::runtime::Handle,
     address_cache: Arc<AddressCache<B>>,
     api_availability: availability::ApiAvailability,
+    api_stats: stats::ApiStats,
     endpoint: ApiEndpoint,
     #[cfg(target_os = "android")]
     socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
@@ -348,6 +351,7 @@ impl Runtime {
             handle,
             address_cache,
             api_availability: ApiAvailability::default(),
+            api_stats: stats::ApiStats::default(),
             endpoint: endpoint.clone(),
             #[cfg(target_os = "android")]
             socket_bypass_tx,
@@ -409,6 +413,7 @@ impl Runtime {
             handle,
             address_cache,
             api_availability,
+            api_stats: stats::ApiStats::default(),
             endpoint: endpoint.clone(),
             #[cfg(target_os = "android")]
             socket_bypass_tx,
@@ -445,6 +450,7 @@ impl<B: AddressCacheBacking> Runtime<B> {
             handle,
             address_cache,
             api_availability: ApiAvailability::default(),
+            api_stats: stats::ApiStats::default(),
             endpoint: endpoint.clone(),
             #[cfg(target_os = "android")]
             socket_bypass_tx,
@@ -486,6 +492,7 @@ impl<B: AddressCacheBacking> Runtime<B> {
     ) -> rest::RequestServiceHandle {
         rest::RequestService::spawn(
             self.api_availability.clone(),
+            self.api_stats.clone(),
             connection_mode_provider,
             dns_resolver,
             #[cfg(target_os = "android")]
@@ -502,6 +509,11 @@ impl<B: AddressCacheBacking> Runtime<B> {
     pub fn availability_handle(&self) -> ApiAvailability {
         self.api_availability.clone()
     }
+
+    /// Returns a handle to the statistics of all requests made through this runtime.
+    pub fn stats_handle(&self) -> stats::ApiStats {
+        self.api_stats.clone()
+    }
 }
 
 #[derive(Clone)]
@@ -75,6 +75,7 @@ pub enum ProxyConfig {
     Shadowsocks(proxy::Shadowsocks),
     Socks5Local(proxy::Socks5Local),
     Socks5Remote(proxy::Socks5Remote),
+    HttpConnect(proxy::HttpConnect),
     EncryptedDnsProxy(mullvad_encrypted_dns_proxy::config::ProxyConfig),
     DomainFronting(DomainFrontingConfig),
 }
@@ -112,6 +113,9 @@ impl ProxyConfig {
             ProxyConfig::Socks5Remote(remote) => {
                 Endpoint::from_socket_address(remote.endpoint, TransportProtocol::Tcp)
             }
+            ProxyConfig::HttpConnect(http) => {
+                Endpoint::from_socket_address(http.endpoint, TransportProtocol::Tcp)
+            }
             ProxyConfig::EncryptedDnsProxy(proxy) => {
                 let addr = SocketAddr::V4(proxy.addr);
                 Endpoint::from_socket_address(addr, TransportProtocol::Tcp)
@@ -129,6 +133,7 @@ impl From<proxy::CustomProxy> for ProxyConfig {
             proxy::CustomProxy::Shadowsocks(shadowsocks) => ProxyConfig::Shadowsocks(shadowsocks),
             proxy::CustomProxy::Socks5Local(socks) => ProxyConfig::Socks5Local(socks),
             proxy::CustomProxy::Socks5Remote(socks) => ProxyConfig::Socks5Remote(socks),
+            proxy::CustomProxy::HttpConnect(http) => ProxyConfig::HttpConnect(http),
         }
     }
 }
@@ -2,6 +2,7 @@
 
 use crate::rest;
 
+use chrono::{DateTime, Utc};
 use hyper::{StatusCode, body::Incoming, header};
 use mullvad_types::{
     location,
@@ -140,6 +141,10 @@ pub struct CachedRelayList {
     #[serde(flatten)]
     relay_list: ServerRelayList,
     etag: Option<ETag>,
+    /// When the relay list was downloaded. This is missing from the relay list bundled with the
+    /// app.
+    #[serde(default)]
+    downloaded: Option<DateTime<Utc>>,
 }
 
 /// An [ETag header](https://developer.mozilla.org/en-US/docs/Web/HTTP/Reference/Headers/ETag) returned by the relay list API.
@@ -150,18 +155,20 @@ pub struct ETag(pub String);
 
 impl ServerRelayList {
     /// Associate this relay list with a specific [`ETag`].
-    const fn cache(self, etag: ETag) -> CachedRelayList {
+    fn cache(self, etag: ETag) -> CachedRelayList {
         CachedRelayList {
             relay_list: self,
             etag: Some(etag),
+            downloaded: Some(Utc::now()),
         }
     }
 
     /// There is no associated [`ETag`].
-    const fn uncacheable(self) -> CachedRelayList {
+    fn uncacheable(self) -> CachedRelayList {
         CachedRelayList {
             relay_list: self,
             etag: None,
+            downloaded: Some(Utc::now()),
         }
     }
 
@@ -223,6 +230,11 @@ impl CachedRelayList {
         self.etag.as_ref()
     }
 
+    /// When the relay list was downloaded, if known.
+    pub const fn downloaded(&self) -> Option<DateTime<Utc>> {
+        self.downloaded
+    }
+
     /// See [`ServerRelayList::into_internal_repr`].
     pub fn into_internal_repr(self) -> (relay_list::RelayList, BridgeList) {
         self.relay_list.into_internal_repr()
@@ -6,6 +6,7 @@ use crate::{
     availability::ApiAvailability,
     https_client::{HttpsConnector, HttpsConnectorHandle, InnerConnectionMode},
     proxy::ConnectionModeProvider,
+    stats::ApiStats,
 };
 use futures::{
     channel::{mpsc, oneshot},
@@ -25,7 +26,7 @@ use std::{
     error::Error as StdError,
     str::FromStr,
     sync::{Arc, Weak},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use talpid_types::ErrorExt;
 
@@ -156,12 +157,14 @@ pub(crate) struct RequestService<T: ConnectionModeProvider> {
     connection_mode_provider: T,
     connection_mode_generation: usize,
     api_availability: ApiAvailability,
+    api_stats: ApiStats,
 }
 
 impl<T: ConnectionModeProvider + 'static> RequestService<T> {
     /// Constructs a new request service.
     pub fn spawn(
         api_availability: ApiAvailability,
+        api_stats: ApiStats,
         connection_mode_provider: T,
         dns_resolver: Arc<dyn DnsResolver>,
         #[cfg(target_os = "android")] socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
@@ -195,6 +198,7 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
             connection_mode_provider,
             connection_mode_generation: 0,
             api_availability,
+            api_stats,
         };
         let handle = RequestServiceHandle { tx: command_tx };
         tokio::spawn(service.into_future());
@@ -248,12 +252,15 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
         let tx = self.command_tx.upgrade();
 
         let api_availability = self.api_availability.clone();
+        let api_stats = self.api_stats.clone();
         let request_future = request.into_future(self.client.clone(), api_availability.clone());
 
         let connection_mode_generation = self.connection_mode_generation;
 
         tokio::spawn(async move {
+            let start = Instant::now();
             let response = request_future.await.map_err(|error| error.map_aborted());
+            api_stats.record(start.elapsed(), &response);
 
             // Switch API endpoint if the request failed due to a network error
             if let Err(err) = &response
@@ -845,6 +852,7 @@ mod tests {
     fn client(server: &ServerGuard) -> (RequestServiceHandle, RequestFactory) {
         let service = RequestService::spawn(
             ApiAvailability::new(State::default()),
+            ApiStats::default(),
             StaticConnectionModeProvider::new(ApiConnectionMode::Direct),
             Arc::new(DefaultDnsResolver),
             #[cfg(target_os = "android")]
@@ -0,0 +1,81 @@
+//! Statistics about the requests made by the REST request service.
+
+use crate::rest;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Upper bounds of the request latency histogram buckets, in seconds.
+pub const LATENCY_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// Shared collector of request statistics. Cloning it yields a handle to the same statistics.
+#[derive(Clone, Debug, Default)]
+pub struct ApiStats(Arc<Mutex<ApiStatsSnapshot>>);
+
+/// Statistics about all requests made since the request service was started.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ApiStatsSnapshot {
+    /// Number of completed requests, including failed ones.
+    pub requests: u64,
+    /// Number of requests that completed within each bound in [`LATENCY_BUCKETS`]. The counts
+    /// are cumulative, so a request is counted by every bucket whose bound it did not exceed.
+    pub latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    /// Total time spent on all completed requests.
+    pub latency_sum: Duration,
+    /// Number of failed requests, by kind of failure.
+    pub failures: BTreeMap<&'static str, u64>,
+}
+
+impl ApiStats {
+    /// Record a completed request.
+    pub(crate) fn record<T>(&self, latency: Duration, result: &rest::Result<T>) {
+        let mut stats = self.0.lock().unwrap();
+        stats.requests += 1;
+        stats.latency_sum += latency;
+        for (count, bound) in stats.latency_buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if latency.as_secs_f64() <= bound {
+                *count += 1;
+            }
+        }
+        if let Err(error) = result {
+            *stats.failures.entry(failure_kind(error)).or_default() += 1;
+        }
+    }
+
+    /// Return a copy of the current statistics.
+    pub fn snapshot(&self) -> ApiStatsSnapshot {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+fn failure_kind(error: &rest::Error) -> &'static str {
+    match error {
+        rest::Error::TimeoutError => "timeout",
+        rest::Error::Aborted => "aborted",
+        rest::Error::RestServiceDown => "service_down",
+        rest::Error::ApiError(..) => "api",
+        error if error.is_network_error() => "network",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_request() {
+        let stats = ApiStats::default();
+        stats.record(Duration::from_millis(200), &Ok(()));
+        stats.record::<()>(Duration::from_secs(3), &Err(rest::Error::TimeoutError));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.requests, 2);
+        assert_eq!(snapshot.latency_sum, Duration::from_millis(3200));
+        assert_eq!(snapshot.latency_buckets, [0, 0, 1, 1, 1, 1, 2, 2, 2]);
+        assert_eq!(snapshot.failures.get("timeout"), Some(&1));
+        assert_eq!(snapshot.failures.len(), 1);
+    }
+}
@@ -4,10 +4,16 @@ use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
     relay_constraints::{
-        LwoSettings, ObfuscationSettings, SelectedObfuscation, ShadowsocksSettings,
-        Udp2TcpObfuscationSettings, WireguardPortSettings,
+        CustomBridgeSettings, LwoSettings, ObfuscationSettings, SelectedObfuscation,
+        ShadowsocksSettings, Udp2TcpObfuscationSettings, WireguardPortSettings,
     },
 };
+use talpid_types::net::{
+    obfuscation::CustomBridge,
+    proxy::{Shadowsocks, Socks5Remote},
+};
+
+use super::proxies::{ShadowsocksAdd, Socks5RemoteAdd};
 
 #[derive(Subcommand, Debug)]
 pub enum AntiCensorship {
@@ -51,6 +57,24 @@ pub enum SetCommands {
         #[arg(long, short = 'p')]
         port: Constraint<u16>,
     },
+
+    /// Configure a proxy of your own to relay WireGuard traffic through.
+    /// It is used when the mode is set to `custom-bridge`.
+    #[clap(subcommand)]
+    CustomBridge(CustomBridgeCommands),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CustomBridgeCommands {
+    /// Relay WireGuard traffic through a Shadowsocks server. Only AEAD ciphers are supported.
+    Shadowsocks(ShadowsocksAdd),
+
+    /// Relay WireGuard traffic through a SOCKS5 server. The server must support the UDP
+    /// ASSOCIATE command, and must relay UDP on the same IP and port as it listens on.
+    Socks5(Socks5RemoteAdd),
+
+    /// Remove the custom bridge
+    Remove,
 }
 
 impl AntiCensorship {
@@ -67,6 +91,10 @@ impl AntiCensorship {
                     obfuscation_settings.wireguard_port
                 );
                 println!("lwo settings: {}", obfuscation_settings.lwo);
+                println!(
+                    "custom bridge settings: {}",
+                    obfuscation_settings.custom_bridge
+                );
                 Ok(())
             }
             AntiCensorship::Set(subcmd) => Self::set(subcmd).await,
@@ -125,6 +153,24 @@ impl AntiCensorship {
                 })
                 .await?;
             }
+            SetCommands::CustomBridge(subcmd) => {
+                let bridge = match subcmd {
+                    CustomBridgeCommands::Shadowsocks(add) => {
+                        Some(CustomBridge::Shadowsocks(Shadowsocks::from(add)))
+                    }
+                    CustomBridgeCommands::Socks5(add) => {
+                        Some(CustomBridge::Socks5(Socks5Remote::try_from(add)?))
+                    }
+                    CustomBridgeCommands::Remove => None,
+                };
+                let custom_bridge = CustomBridgeSettings { bridge };
+                custom_bridge.validate()?;
+                rpc.set_obfuscation_settings(ObfuscationSettings {
+                    custom_bridge,
+                    ..current_settings
+                })
+                .await?;
+            }
         }
 
         println!("Updated anti-censorship settings");
@@ -1,11 +1,13 @@
 use anyhow::{Result, anyhow};
 use mullvad_management_interface::MullvadProxyClient;
-use mullvad_types::access_method::{AccessMethod, AccessMethodSetting};
+use mullvad_types::access_method::{AccessMethod, AccessMethodSetting, CustomDomainFronting};
 use talpid_types::net::proxy::CustomProxy;
 
 use clap::{Args, Subcommand};
 
-use super::proxies::{ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd};
+use super::proxies::{
+    HttpConnectAdd, ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd,
+};
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum ApiAccess {
@@ -100,14 +102,23 @@ impl ApiAccess {
 
     /// Edit the data of an API access method.
     async fn edit(cmd: EditCustomCommands) -> Result<()> {
-        use talpid_types::net::proxy::{Shadowsocks, Socks5Local, Socks5Remote, SocksAuth};
+        use talpid_types::net::proxy::{
+            HttpAuth, HttpConnect, Shadowsocks, Socks5Local, Socks5Remote, SocksAuth,
+        };
         let mut rpc = MullvadProxyClient::new().await?;
         let mut api_access_method = Self::get_access_method(&mut rpc, &cmd.item).await?;
+        cmd.check_applicable(&api_access_method.access_method)?;
 
         // Create a new access method combining the new params with the previous values
-        let access_method = match api_access_method.as_custom() {
-            None => return Err(anyhow!("Can not edit built-in access method")),
-            Some(x) => match x.clone() {
+        let access_method = match api_access_method.access_method.clone() {
+            AccessMethod::BuiltIn(_) => return Err(anyhow!("Can not edit built-in access method")),
+            AccessMethod::CustomDomainFronting(domain_fronting) => {
+                AccessMethod::from(CustomDomainFronting {
+                    front: cmd.front.unwrap_or(domain_fronting.front),
+                    proxy_host: cmd.proxy_host.unwrap_or(domain_fronting.proxy_host),
+                })
+            }
+            AccessMethod::Custom(proxy) => match proxy {
                 CustomProxy::Shadowsocks(shadowsocks) => {
                     let ip = cmd.params.ip.unwrap_or(shadowsocks.endpoint.ip());
                     let port = cmd.params.port.unwrap_or(shadowsocks.endpoint.port());
@@ -154,6 +165,31 @@ impl ApiAccess {
                         }
                     })
                 }
+                CustomProxy::HttpConnect(http) => {
+                    let ip = cmd.params.ip.unwrap_or(http.endpoint.ip());
+                    let port = cmd.params.port.unwrap_or(http.endpoint.port());
+                    let username = cmd
+                        .params
+                        .username
+                        .or(http.auth.as_ref().map(|auth| auth.username().to_string()));
+                    let password = cmd
+                        .params
+                        .password
+                        .or(http.auth.as_ref().map(|auth| auth.password().to_string()));
+                    let auth = username
+                        .map(|username| HttpAuth::new(username, password.unwrap_or_default()))
+                        .transpose()?;
+                    let tls_server_name = match cmd.params.tls_server_name {
+                        Some(name) if name.is_empty() => None,
+                        Some(name) => Some(name),
+                        None => http.tls_server_name,
+                    };
+                    AccessMethod::from(HttpConnect {
+                        endpoint: (ip, port).into(),
+                        auth,
+                        tls_server_name,
+                    })
+                }
             },
         };
 
@@ -267,6 +303,30 @@ pub enum AddCustomCommands {
         #[clap(flatten)]
         add: ShadowsocksAdd,
     },
+    /// Configure an HTTP proxy that supports the CONNECT method to use as an API access method
+    Http {
+        /// An easy to remember name for this custom proxy
+        name: String,
+        /// Disable the use of this custom access method. It has to be manually
+        /// enabled at a later stage to be used when accessing the Mullvad API.
+        #[arg(default_value_t = false, short, long)]
+        disabled: bool,
+        #[clap(flatten)]
+        add: HttpConnectAdd,
+    },
+    /// Configure domain fronting through a CDN to use as an API access method
+    DomainFronting {
+        /// An easy to remember name for this access method
+        name: String,
+        /// Disable the use of this custom access method. It has to be manually
+        /// enabled at a later stage to be used when accessing the Mullvad API.
+        #[arg(default_value_t = false, short, long)]
+        disabled: bool,
+        /// Domain on the CDN that is visible to observers of the connection
+        front: String,
+        /// Host on the CDN that forwards requests to the Mullvad API
+        proxy_host: String,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -299,6 +359,8 @@ impl AddCustomCommands {
     fn name(&self) -> &str {
         match self {
             AddCustomCommands::Shadowsocks { name, .. }
+            | AddCustomCommands::Http { name, .. }
+            | AddCustomCommands::DomainFronting { name, .. }
             | AddCustomCommands::Socks5(AddSocks5Commands::Remote { name, .. })
             | AddCustomCommands::Socks5(AddSocks5Commands::Local { name, .. }) => name,
         }
@@ -307,6 +369,8 @@ impl AddCustomCommands {
     fn enabled(&self) -> bool {
         match self {
             AddCustomCommands::Shadowsocks { disabled, .. }
+            | AddCustomCommands::Http { disabled, .. }
+            | AddCustomCommands::DomainFronting { disabled, .. }
             | AddCustomCommands::Socks5(AddSocks5Commands::Remote { disabled, .. })
             | AddCustomCommands::Socks5(AddSocks5Commands::Local { disabled, .. }) => !disabled,
         }
@@ -344,11 +408,63 @@ pub struct EditCustomCommands {
     /// Name of the API access method in the Mullvad client \[All\]
     #[arg(long)]
     name: Option<String>,
+    /// Domain on the CDN that is visible to observers of the connection \[Domain fronting\]
+    #[arg(long)]
+    front: Option<String>,
+    /// Host on the CDN that forwards requests to the Mullvad API \[Domain fronting\]
+    #[arg(long)]
+    proxy_host: Option<String>,
     /// Editing parameters
     #[clap(flatten)]
     params: ProxyEditParams,
 }
 
+impl EditCustomCommands {
+    /// Fail if a flag was given that does not apply to `access_method`, rather than silently
+    /// ignoring it.
+    fn check_applicable(&self, access_method: &AccessMethod) -> Result<()> {
+        let applicable: &[&str] = match access_method {
+            AccessMethod::BuiltIn(_) => &[],
+            AccessMethod::CustomDomainFronting(_) => &["front", "proxy-host"],
+            AccessMethod::Custom(CustomProxy::Shadowsocks(_)) => {
+                &["ip", "port", "password", "cipher"]
+            }
+            AccessMethod::Custom(CustomProxy::Socks5Local(_)) => {
+                &["ip", "port", "local-port", "transport-protocol"]
+            }
+            AccessMethod::Custom(CustomProxy::Socks5Remote(remote)) if remote.auth.is_some() => {
+                &["ip", "port", "username", "password"]
+            }
+            AccessMethod::Custom(CustomProxy::Socks5Remote(_)) => &["ip", "port"],
+            AccessMethod::Custom(CustomProxy::HttpConnect(_)) => {
+                &["ip", "port", "username", "password", "tls-server-name"]
+            }
+        };
+        let given = [
+            ("front", self.front.is_some()),
+            ("proxy-host", self.proxy_host.is_some()),
+            ("username", self.params.username.is_some()),
+            ("password", self.params.password.is_some()),
+            ("cipher", self.params.cipher.is_some()),
+            ("ip", self.params.ip.is_some()),
+            ("port", self.params.port.is_some()),
+            ("local-port", self.params.local_port.is_some()),
+            (
+                "transport-protocol",
+                self.params.transport_protocol.is_some(),
+            ),
+            ("tls-server-name", self.params.tls_server_name.is_some()),
+        ];
+        match given
+            .into_iter()
+            .find(|(flag, is_given)| *is_given && !applicable.contains(flag))
+        {
+            Some((flag, _)) => Err(anyhow!("--{flag} does not apply to this access method")),
+            None => Ok(()),
+        }
+    }
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct EditParams {
     /// Name of the API access method in the Mullvad client \[All\]
@@ -402,6 +518,14 @@ mod conversions {
                         add.password,
                     ),
                 )),
+                AddCustomCommands::Http { add, .. } => Ok(daemon_types::AccessMethod::from(
+                    talpid_types::HttpConnect::try_from(add)?,
+                )),
+                AddCustomCommands::DomainFronting {
+                    front, proxy_host, ..
+                } => Ok(daemon_types::AccessMethod::from(
+                    daemon_types::CustomDomainFronting { front, proxy_host },
+                )),
             }
         }
     }
@@ -409,7 +533,7 @@ mod conversions {
 
 /// Pretty printing of [`AccessMethodSetting`]s
 mod pp {
-    use crate::cmds::proxies::pp::CustomProxyFormatter;
+    use crate::{cmds::proxies::pp::CustomProxyFormatter, print_option};
     use mullvad_types::access_method::{AccessMethod, AccessMethodSetting};
 
     pub struct ApiAccessMethodFormatter<'a> {
@@ -470,6 +594,17 @@ mod pp {
                     write!(f, "{formatter}")?;
                     Ok(())
                 }
+                AccessMethod::CustomDomainFronting(method) => {
+                    write!(f, "{}", self.api_access_method.get_name())?;
+                    if self.settings.write_enabled {
+                        write_status(f, self.api_access_method.enabled())?;
+                    }
+                    writeln!(f)?;
+                    print_option!("Protocol", "Domain fronting");
+                    print_option!("Front", method.front);
+                    print_option!("Proxy host", method.proxy_host);
+                    Ok(())
+                }
             }
         }
     }
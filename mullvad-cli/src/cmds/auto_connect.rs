@@ -1,8 +1,10 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::settings::{TrustedNetwork, TrustedNetworks};
 
 use super::BooleanOption;
+use crate::print_option;
 
 #[derive(Subcommand, Debug)]
 pub enum AutoConnect {
@@ -10,6 +12,52 @@ pub enum AutoConnect {
     Get,
     /// Change auto-connect setting
     Set { policy: BooleanOption },
+    /// Manage trusted networks. When enabled, the tunnel is disconnected when joining a trusted
+    /// network, and connected when joining any other network. Only supported on Linux
+    Rules {
+        #[clap(subcommand)]
+        cmd: Rules,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Rules {
+    /// Display the trusted networks and the current network
+    List,
+    /// Enable or disable trusted networks
+    Set { policy: BooleanOption },
+    /// Add a trusted network
+    Add {
+        #[clap(subcommand)]
+        network: Network,
+    },
+    /// Remove a trusted network
+    Remove {
+        #[clap(subcommand)]
+        network: Network,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Network {
+    /// Wi-Fi network with the given SSID
+    Ssid { ssid: String },
+    /// Network whose default gateway has the given MAC address
+    GatewayMac { mac: String },
+    /// Network reached through the given interface
+    Interface { name: String },
+}
+
+impl TryFrom<Network> for TrustedNetwork {
+    type Error = anyhow::Error;
+
+    fn try_from(network: Network) -> Result<Self> {
+        Ok(match network {
+            Network::Ssid { ssid } => TrustedNetwork::Ssid(ssid),
+            Network::GatewayMac { mac } => TrustedNetwork::gateway_mac(&mac)?,
+            Network::Interface { name } => TrustedNetwork::Interface(name),
+        })
+    }
 }
 
 impl AutoConnect {
@@ -17,6 +65,42 @@ impl AutoConnect {
         match self {
             AutoConnect::Get => Self::get().await,
             AutoConnect::Set { policy } => Self::set(policy).await,
+            AutoConnect::Rules { cmd: Rules::List } => Self::list_rules().await,
+            AutoConnect::Rules {
+                cmd: Rules::Set { policy },
+            } => {
+                Self::update_rules(|trusted_networks| {
+                    trusted_networks.enabled = *policy;
+                    Ok(())
+                })
+                .await
+            }
+            AutoConnect::Rules {
+                cmd: Rules::Add { network },
+            } => {
+                let network = TrustedNetwork::try_from(network)?;
+                Self::update_rules(|trusted_networks| {
+                    if !trusted_networks.rules.contains(&network) {
+                        trusted_networks.rules.push(network);
+                    }
+                    Ok(())
+                })
+                .await
+            }
+            AutoConnect::Rules {
+                cmd: Rules::Remove { network },
+            } => {
+                let network = TrustedNetwork::try_from(network)?;
+                Self::update_rules(|trusted_networks| {
+                    let num_rules = trusted_networks.rules.len();
+                    trusted_networks.rules.retain(|rule| *rule != network);
+                    if trusted_networks.rules.len() == num_rules {
+                        return Err(anyhow!("{network} is not a trusted network"));
+                    }
+                    Ok(())
+                })
+                .await
+            }
         }
     }
 
@@ -33,4 +117,41 @@ impl AutoConnect {
         println!("Autoconnect: {auto_connect}");
         Ok(())
     }
+
+    async fn list_rules() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let trusted_networks = rpc.get_settings().await?.trusted_networks;
+        println!(
+            "Trusted networks: {}",
+            BooleanOption::from(trusted_networks.enabled)
+        );
+        for rule in &trusted_networks.rules {
+            print_option!(rule);
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let network = rpc.get_current_network().await?;
+            let trusted = if trusted_networks.is_trusted(&network) {
+                "trusted"
+            } else {
+                "not trusted"
+            };
+            println!("Current network: {trusted}");
+            print_option!("Interface", network.interface.as_deref().unwrap_or("-"));
+            print_option!("SSID", network.ssid.as_deref().unwrap_or("-"));
+            print_option!("Gateway MAC", network.gateway_mac.as_deref().unwrap_or("-"));
+        }
+
+        Ok(())
+    }
+
+    async fn update_rules(update: impl FnOnce(&mut TrustedNetworks) -> Result<()>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut trusted_networks = rpc.get_settings().await?.trusted_networks;
+        update(&mut trusted_networks)?;
+        rpc.set_trusted_networks(trusted_networks).await?;
+        println!("Changed trusted networks");
+        Ok(())
+    }
 }
@@ -1,9 +1,17 @@
 use super::{relay::resolve_location_constraint, relay_constraints::LocationArgs};
-use anyhow::{Result, anyhow, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
-    constraints::Constraint, relay_constraints::GeographicLocationConstraint, relay_list::RelayList,
+    constraints::Constraint,
+    custom_list::{CustomListFilter, CustomListsSettings, ExportedCustomLists, ImportMode},
+    relay_constraints::{GeographicLocationConstraint, Ownership},
+    relay_list::RelayList,
+};
+use std::{
+    fs::File,
+    io::{BufReader, read_to_string, stdin},
+    str::FromStr,
 };
 
 /// Custom list length, expressed as a number of UTF8 codepoints (i.e. chars).
@@ -35,6 +43,29 @@ pub enum CustomList {
         /// A custom list
         name: String,
     },
+
+    /// Export custom lists as JSON, for importing them on another device
+    Export {
+        /// Custom lists to export. If omitted, all custom lists are exported
+        names: Vec<String>,
+
+        /// File to write to. If omitted or "-", write to standard output
+        #[arg(long, short = 'o', default_value = "-")]
+        output: String,
+    },
+
+    /// Import custom lists exported by `mullvad custom-list export`
+    ///
+    /// Lists that do not exist are created. By default, the locations of a list with the same name
+    /// as an existing list are added to the existing list.
+    Import {
+        /// File to read from. If this is "-", read from standard input
+        file: String,
+
+        /// Replace the locations of existing lists with the same name, instead of adding to them
+        #[arg(long)]
+        replace: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -64,6 +95,47 @@ pub enum EditCommand {
         #[clap(value_parser = parse_custom_list_name)]
         new_name: String,
     },
+
+    /// Make a custom list also contain every relay that satisfies all of the given rules
+    ///
+    /// The rules are evaluated against the current relay list, so relays that are added later
+    /// become part of the custom list automatically. Rules that are not given are not checked,
+    /// but at least one rule must be given. This replaces any previous rules of the custom list.
+    SetFilter {
+        /// A custom list
+        name: String,
+
+        /// Only include relays in this country, given as a two-letter country code. Can be given
+        /// multiple times
+        #[arg(long = "country", value_name = "COUNTRY", value_parser = parse_country_code)]
+        countries: Vec<String>,
+
+        /// Only include relays hosted by this provider. Can be given multiple times
+        #[arg(long = "provider", value_name = "PROVIDER")]
+        providers: Vec<String>,
+
+        /// Only include relays that are 'owned' or 'rented'
+        #[arg(long, value_parser = Ownership::from_str)]
+        ownership: Option<Ownership>,
+
+        /// Only include relays that support DAITA
+        #[arg(long)]
+        daita: bool,
+
+        /// Only include relays that support QUIC obfuscation
+        #[arg(long)]
+        quic: bool,
+
+        /// Never include this relay. Can be given multiple times
+        #[arg(long = "exclude", value_name = "HOSTNAME")]
+        excluded_hostnames: Vec<String>,
+    },
+
+    /// Remove the rules of a custom list, leaving only its locations
+    ClearFilter {
+        /// A custom list
+        name: String,
+    },
 }
 
 impl CustomList {
@@ -73,12 +145,34 @@ impl CustomList {
             CustomList::List { name: Some(name) } => Self::get(name).await,
             CustomList::New { name } => Self::create_list(name).await,
             CustomList::Delete { name } => Self::delete_list(name).await,
+            CustomList::Export { names, output } => Self::export(names, output).await,
+            CustomList::Import { file, replace } => Self::import(file, replace).await,
             CustomList::Edit(cmd) => match cmd {
                 EditCommand::Add { name, location } => Self::add_location(name, location).await,
                 EditCommand::Rename { name, new_name } => Self::rename_list(name, new_name).await,
                 EditCommand::Remove { name, location } => {
                     Self::remove_location(name, location).await
                 }
+                EditCommand::SetFilter {
+                    name,
+                    countries,
+                    providers,
+                    ownership,
+                    daita,
+                    quic,
+                    excluded_hostnames,
+                } => {
+                    let filter = CustomListFilter {
+                        countries: countries.into_iter().collect(),
+                        providers: providers.into_iter().collect(),
+                        ownership,
+                        daita,
+                        quic,
+                        excluded_hostnames: excluded_hostnames.into_iter().collect(),
+                    };
+                    Self::set_filter(name, filter).await
+                }
+                EditCommand::ClearFilter { name } => Self::clear_filter(name).await,
             },
         }
     }
@@ -174,6 +268,93 @@ impl CustomList {
         Ok(())
     }
 
+    async fn export(names: Vec<String>, output: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let custom_lists = rpc.get_settings().await?.custom_lists;
+        if let Some(name) = names
+            .iter()
+            .find(|name| !custom_lists.iter().any(|list| &list.name == *name))
+        {
+            bail!("List not found: {name}");
+        }
+        let custom_lists: Vec<_> = custom_lists
+            .into_iter()
+            .filter(|list| names.is_empty() || names.contains(&list.name))
+            .collect();
+
+        let exported = ExportedCustomLists::from(CustomListsSettings::from(custom_lists));
+        let json = serde_json::to_string_pretty(&exported)?;
+        match output.as_str() {
+            "-" => {
+                println!("{json}");
+                Ok(())
+            }
+            _ => tokio::fs::write(&output, json)
+                .await
+                .context(format!("Failed to write to path {output}")),
+        }
+    }
+
+    async fn import(source: String, replace: bool) -> Result<()> {
+        let json = tokio::task::spawn_blocking(move || match source.as_str() {
+            "-" => read_to_string(BufReader::new(stdin())).context("Failed to read from stdin"),
+            _ => File::open(&source)
+                .and_then(read_to_string)
+                .context(format!("Failed to read from path: {source}")),
+        })
+        .await
+        .context("Failed to read custom lists")??;
+        let imported: ExportedCustomLists =
+            serde_json::from_str(&json).context("Invalid custom list file")?;
+
+        let mode = if replace {
+            ImportMode::Replace
+        } else {
+            ImportMode::Merge
+        };
+        let mut rpc = MullvadProxyClient::new().await?;
+        let summary = rpc
+            .import_custom_lists(imported.custom_lists, mode)
+            .await
+            .context("Failed to import custom lists")?;
+
+        for name in &summary.created {
+            println!("Created custom list {name}");
+        }
+        for name in &summary.updated {
+            println!("Updated custom list {name}");
+        }
+        for (name, location) in &summary.unknown_locations {
+            eprintln!("Warning: {location} in custom list {name} does not match any relay");
+        }
+        Ok(())
+    }
+
+    async fn set_filter(name: String, filter: CustomListFilter) -> Result<()> {
+        if filter == CustomListFilter::default() {
+            bail!("No rules were given. Use 'clear-filter' to remove the rules of a custom list");
+        }
+        let mut rpc = MullvadProxyClient::new().await?;
+        let relay_list = rpc.get_relay_locations().await?;
+        for country in &filter.countries {
+            if !relay_list.countries.iter().any(|c| &c.code == country) {
+                bail!("No relays are located in the country '{country}'");
+            }
+        }
+        let mut list = find_list_by_name(&mut rpc, &name).await?;
+        list.filter = Some(filter);
+        rpc.update_custom_list(list).await?;
+        Ok(())
+    }
+
+    async fn clear_filter(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut list = find_list_by_name(&mut rpc, &name).await?;
+        list.filter = None;
+        rpc.update_custom_list(list).await?;
+        Ok(())
+    }
+
     fn print_custom_list(custom_list: &mullvad_types::custom_list::CustomList, cache: &RelayList) {
         println!("{}", custom_list.name);
         Self::print_custom_list_content(custom_list, cache);
@@ -189,6 +370,9 @@ impl CustomList {
                 GeographicLocationConstraintFormatter::from_constraint(location, cache)
             );
         }
+        if let Some(filter) = &custom_list.filter {
+            println!("\t{filter}");
+        }
     }
 }
 
@@ -266,6 +450,14 @@ pub async fn find_list_by_name(
         .ok_or(anyhow!("List not found"))
 }
 
+/// Lowercase a country code, since relay locations use lowercase country codes.
+fn parse_country_code(s: &str) -> Result<String> {
+    if s.len() != 2 || !s.chars().all(|c| c.is_ascii_alphabetic()) {
+        bail!("'{s}' is not a two-letter country code");
+    }
+    Ok(s.to_ascii_lowercase())
+}
+
 /// Trim the string and validate the length against [CUSTOM_LIST_MAX_LEN].
 // NOTE: should only be used when *creating* custom lists, as we don't want to make it impossible
 // to reference any custom lists created before the max length and whitespace restrictions were put
@@ -1,10 +1,15 @@
 use anyhow::{Result, bail};
-use mullvad_management_interface::MullvadProxyClient;
+use itertools::Itertools;
+use mullvad_management_interface::{MullvadProxyClient, client::RelaySelectorClient};
 use mullvad_types::{
     constraints::Constraint,
+    leak_check::{LeakCheckOutcome, LeakCheckReport},
     relay_constraints::{RelayConstraints, RelaySettings},
+    relay_selector::history::{CandidateSummary, SelectionHistory, SelectionOutcome},
 };
 
+use crate::print_option;
+
 #[derive(clap::Subcommand, Debug)]
 pub enum DebugCommands {
     /// Block all internet connection by setting an invalid relay constraint.
@@ -15,6 +20,14 @@ pub enum DebugCommands {
     /// Handy commands for interacting with the app release rollout system.
     #[clap(subcommand)]
     Rollout(RolloutDebugCommands),
+    /// Try to send traffic outside the tunnel, to check whether the firewall is leaking. The tunnel
+    /// must be connected.
+    LeakCheck {
+        /// Show the report from the most recent leak check instead of running a new one. This
+        /// includes the checks that are run automatically after connecting.
+        #[arg(long)]
+        last: bool,
+    },
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -25,6 +38,9 @@ pub enum RelayDebugCommands {
     /// (Re)Activate this _category of relays_ - a category can be one of the following: a relay, a
     /// city, a country.
     Enable { relay: String },
+    /// Show the most recently selected relays, why they were selected, and whether connecting
+    /// to them succeeded.
+    History,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -49,7 +65,8 @@ impl DebugCommands {
                 let relay_settings = settings.get_relay_settings();
                 let mut constraints = match relay_settings {
                     RelaySettings::Normal(normal) => normal,
-                    RelaySettings::CustomTunnelEndpoint(_custom) => {
+                    RelaySettings::CustomTunnelEndpoint(_)
+                    | RelaySettings::SavedCustomEndpoints => {
                         println!("Removing custom relay settings");
                         RelayConstraints::default()
                     }
@@ -81,9 +98,98 @@ impl DebugCommands {
                 println!("{relay} is now marked as active");
                 Ok(())
             }
+            DebugCommands::Relay(RelayDebugCommands::History) => {
+                let mut rpc = RelaySelectorClient::new().await?;
+                print_selection_history(&rpc.get_selection_history().await?);
+                Ok(())
+            }
             DebugCommands::Rollout(rollout_cmd) => rollout_cmd.handle().await,
+            DebugCommands::LeakCheck { last } => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                let report = if last {
+                    rpc.get_leak_check_report().await?
+                } else {
+                    println!("Running leak check...");
+                    rpc.run_leak_check().await?
+                };
+                print_leak_check_report(&report);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn print_leak_check_report(report: &LeakCheckReport) {
+    match &report.outcome {
+        LeakCheckOutcome::NoLeak => println!("No leak detected"),
+        LeakCheckOutcome::LeakDetected => println!("LEAK DETECTED"),
+        LeakCheckOutcome::Failed(error) => println!("Leak check failed: {error}"),
+    }
+    print_option!(
+        "Interface",
+        report.interface.as_deref().unwrap_or("unknown")
+    );
+    print_option!("Destination", report.destination);
+    print_option!("Probe TTLs", report.probe_ttls.iter().join(", "));
+    if !report.reachable_nodes.is_empty() {
+        print_option!("Reachable nodes", report.reachable_nodes.iter().join(", "));
+    }
+    print_option!("Started", report.started.with_timezone(&chrono::Local));
+    print_option!("Finished", report.finished.with_timezone(&chrono::Local));
+}
+
+fn print_selection_history(history: &SelectionHistory) {
+    if history.is_empty() {
+        println!("No relays have been selected yet");
+        return;
+    }
+    for selection in history.iter() {
+        let relays = match &selection.entry {
+            Some(entry) => format!("{} via {entry}", selection.exit),
+            None => selection.exit.clone(),
+        };
+        println!(
+            "{}: {relays}",
+            selection
+                .time
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        );
+        print_option!("Attempt", selection.retry_attempt);
+        print_option!("Query", selection.query);
+        print_option!(
+            "Exit candidates",
+            format_candidates(&selection.exit_candidates)
+        );
+        if let Some(entry_candidates) = &selection.entry_candidates {
+            print_option!("Entry candidates", format_candidates(entry_candidates));
+        }
+        if let Some(obfuscator) = &selection.obfuscator {
+            print_option!("Obfuscation", obfuscator);
         }
+        let outcome = match &selection.outcome {
+            SelectionOutcome::Pending => "connecting".to_owned(),
+            SelectionOutcome::Connected => "connected".to_owned(),
+            SelectionOutcome::Failed(error) => format!("failed: {error}"),
+            SelectionOutcome::Superseded => "replaced before connecting".to_owned(),
+            SelectionOutcome::Cancelled => "disconnected before connecting".to_owned(),
+        };
+        print_option!("Outcome", outcome);
+    }
+}
+
+fn format_candidates(candidates: &CandidateSummary) -> String {
+    let discarded: usize = candidates.discards.iter().map(|(_, count)| count).sum();
+    let mut summary = format!("{} matched, {discarded} discarded", candidates.matches);
+    if !candidates.discards.is_empty() {
+        let reasons = candidates
+            .discards
+            .iter()
+            .map(|(reasons, count)| format!("{count} {}", reasons.iter().join(" and ")))
+            .join("; ");
+        summary.push_str(&format!(" ({reasons})"));
     }
+    summary
 }
 
 impl RolloutDebugCommands {
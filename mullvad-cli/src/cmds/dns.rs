@@ -1,8 +1,14 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use clap::Subcommand;
+use itertools::Itertools;
 use mullvad_management_interface::MullvadProxyClient;
-use mullvad_types::settings::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
+use mullvad_types::settings::{
+    CustomDnsOptions, DefaultDnsOptions, DnsDomainRule, DnsEncryption, DnsOptions, DnsState,
+};
 use std::net::IpAddr;
+use talpid_types::net::dns::DnsLeakTestReport;
+
+use crate::print_option;
 
 #[derive(Subcommand, Debug)]
 pub enum Dns {
@@ -14,6 +20,16 @@ pub enum Dns {
         #[clap(subcommand)]
         cmd: DnsSet,
     },
+
+    /// Use specific DNS servers for some domains. Only supported on macOS
+    Rule {
+        #[clap(subcommand)]
+        cmd: DnsRule,
+    },
+
+    /// Check that DNS queries only reach the DNS servers used by the tunnel.
+    /// The tunnel must be connected. Only supported on macOS
+    Test,
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -51,6 +67,36 @@ pub enum DnsSet {
         /// One or more IP addresses pointing to DNS resolvers
         #[arg(required(true), num_args = 1..)]
         servers: Vec<IpAddr>,
+
+        /// Use DNS over TLS, verifying the server certificate against this name.
+        /// Only supported on macOS
+        #[arg(long, value_name = "SERVER_NAME")]
+        tls: Option<String>,
+
+        /// Use DNS over HTTPS with this URL, such as `https://dns.example/dns-query`.
+        /// Only supported on macOS
+        #[arg(long, value_name = "URL", conflicts_with = "tls")]
+        https: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DnsRule {
+    /// Send queries for a domain, and all of its subdomains, to specific DNS servers.
+    /// Servers with private IPs are reached outside the tunnel.
+    /// This replaces any existing rule for the domain
+    Add {
+        /// Domain that the rule applies to, such as `corp.example`
+        domain: String,
+        /// One or more IP addresses pointing to DNS resolvers
+        #[arg(required(true), num_args = 1..)]
+        servers: Vec<IpAddr>,
+    },
+
+    /// Remove the rule for a domain
+    Remove {
+        /// Domain of the rule to remove
+        domain: String,
     },
 }
 
@@ -80,8 +126,27 @@ impl Dns {
                 .await
             }
             Dns::Set {
-                cmd: DnsSet::Custom { servers },
-            } => Self::set_custom(servers).await,
+                cmd:
+                    DnsSet::Custom {
+                        servers,
+                        tls,
+                        https,
+                    },
+            } => {
+                let encryption = match (tls, https) {
+                    (Some(server_name), _) => Some(DnsEncryption::tls(&server_name)?),
+                    (None, Some(url)) => Some(DnsEncryption::https(&url)?),
+                    (None, None) => None,
+                };
+                Self::set_custom(servers, encryption).await
+            }
+            Dns::Rule {
+                cmd: DnsRule::Add { domain, servers },
+            } => Self::add_rule(&domain, servers).await,
+            Dns::Rule {
+                cmd: DnsRule::Remove { domain },
+            } => Self::remove_rule(&domain).await,
+            Dns::Test => Self::test().await,
         }
     }
 
@@ -110,12 +175,57 @@ impl Dns {
                 for server in &options.custom_options.addresses {
                     println!("{server}");
                 }
+                match &options.custom_options.encryption {
+                    Some(encryption) => println!("Encryption: {encryption}"),
+                    None => println!("Encryption: none"),
+                }
+            }
+        }
+
+        if !options.domain_rules.is_empty() {
+            println!("Domain rules:");
+            for rule in &options.domain_rules {
+                print_option!(rule.domain, rule.servers.iter().join(", "));
             }
         }
 
         Ok(())
     }
 
+    async fn add_rule(domain: &str, servers: Vec<IpAddr>) -> Result<()> {
+        let rule = DnsDomainRule::new(domain, servers)?;
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc.get_settings().await?.tunnel_options.dns_options;
+        options
+            .domain_rules
+            .retain(|existing| existing.domain != rule.domain);
+        options.domain_rules.push(rule);
+        rpc.set_dns_options(options).await?;
+        println!("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn remove_rule(domain: &str) -> Result<()> {
+        let domain = DnsDomainRule::new(domain, vec![])?.domain;
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc.get_settings().await?.tunnel_options.dns_options;
+        let num_rules = options.domain_rules.len();
+        options.domain_rules.retain(|rule| rule.domain != domain);
+        if options.domain_rules.len() == num_rules {
+            return Err(anyhow!("No rule exists for {domain}"));
+        }
+        rpc.set_dns_options(options).await?;
+        println!("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn test() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let report = rpc.run_dns_leak_test().await?;
+        print_dns_leak_test_report(&report);
+        Ok(())
+    }
+
     async fn set_default(
         block_ads: bool,
         block_trackers: bool,
@@ -143,12 +253,15 @@ impl Dns {
         Ok(())
     }
 
-    async fn set_custom(servers: Vec<IpAddr>) -> Result<()> {
+    async fn set_custom(servers: Vec<IpAddr>, encryption: Option<DnsEncryption>) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let settings = rpc.get_settings().await?;
         rpc.set_dns_options(DnsOptions {
             state: DnsState::Custom,
-            custom_options: CustomDnsOptions { addresses: servers },
+            custom_options: CustomDnsOptions {
+                addresses: servers,
+                encryption,
+            },
             ..settings.tunnel_options.dns_options
         })
         .await?;
@@ -156,3 +269,29 @@ impl Dns {
         Ok(())
     }
 }
+
+fn print_dns_leak_test_report(report: &DnsLeakTestReport) {
+    if report.is_leaking() {
+        println!("DNS LEAK DETECTED");
+    } else {
+        println!("No DNS leak detected");
+    }
+    print_option!(
+        "Expected servers",
+        report.expected_servers.iter().join(", ")
+    );
+    print_option!("Queries", "");
+    for query in &report.queries {
+        let outcome = match &query.upstream_servers {
+            None => "did not reach the local resolver".to_owned(),
+            Some(servers) if servers.is_empty() => "was not forwarded".to_owned(),
+            Some(servers) => format!("forwarded to {}", servers.iter().join(", ")),
+        };
+        let verdict = if query.arrived_only_at(&report.expected_servers) {
+            "ok"
+        } else {
+            "MISMATCH"
+        };
+        print_option!(format!("{}: {outcome} ({verdict})", query.name));
+    }
+}
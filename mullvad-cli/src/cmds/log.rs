@@ -1,7 +1,12 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use clap::Subcommand;
 use futures::StreamExt;
 use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::session_log::SessionRecord;
+
+use super::BooleanOption;
+use crate::print_option;
 
 #[derive(Subcommand, Debug)]
 pub enum Log {
@@ -15,6 +20,23 @@ pub enum Log {
     SetRustLog { filter: String },
     /// Follow live updates to the daemon log file. Analogue to running `tail -f` on the daemon log file.
     Listen,
+    /// Manage the session log, which records each tunnel session when enabled.
+    #[clap(subcommand)]
+    Sessions(Sessions),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Sessions {
+    /// List recorded tunnel sessions, oldest first
+    List {
+        /// Only list sessions that ended at or after this time, e.g. 2025-06-01T12:00:00Z
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+    },
+    /// Display whether tunnel sessions are recorded
+    Get,
+    /// Change whether tunnel sessions are recorded
+    Set { policy: BooleanOption },
 }
 
 /// See <https://docs.rs/log/latest/log/enum.Level.html>
@@ -41,10 +63,59 @@ impl Log {
             Log::SetLevel { level } => set_level(level).await,
             Log::SetRustLog { filter } => set_rust_log(filter).await,
             Log::Listen => on_listen().await,
+            Log::Sessions(cmd) => cmd.handle().await,
         }
     }
 }
 
+impl Sessions {
+    async fn handle(self) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        match self {
+            Sessions::List { since } => {
+                let sessions = rpc.get_session_log(since).await?;
+                if sessions.is_empty() {
+                    println!("No sessions have been recorded");
+                }
+                for session in &sessions {
+                    print_session(session);
+                }
+            }
+            Sessions::Get => {
+                let enabled = BooleanOption::from(rpc.get_settings().await?.session_log);
+                println!("Session log: {enabled}");
+            }
+            Sessions::Set { policy } => {
+                rpc.set_session_log(*policy).await?;
+                println!("Session log: {policy}");
+            }
+        }
+        Ok(())
+    }
+}
+
+fn print_session(session: &SessionRecord) {
+    let format_time = |time: DateTime<Utc>| {
+        time.with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M:%S")
+    };
+    println!(
+        "{} - {}",
+        format_time(session.start),
+        format_time(session.end)
+    );
+    if let Some(exit) = &session.exit_hostname {
+        print_option!("Exit relay", exit);
+    }
+    if let Some(entry) = &session.entry_hostname {
+        print_option!("Entry relay", entry);
+    }
+    print_option!("Endpoint", session.endpoint);
+    print_option!("Received", format!("{} bytes", session.rx_bytes));
+    print_option!("Sent", format!("{} bytes", session.tx_bytes));
+    print_option!("Ended", session.end_reason);
+}
+
 async fn on_listen() -> std::result::Result<(), anyhow::Error> {
     let mut rpc = MullvadProxyClient::new().await?;
     let log_stream = rpc.log_listen().await?;
@@ -2,7 +2,9 @@ use clap::Args;
 use std::net::{IpAddr, SocketAddr};
 use talpid_types::net::{
     Endpoint, TransportProtocol,
-    proxy::{Shadowsocks, ShadowsocksCipher, Socks5Local, Socks5Remote, SocksAuth},
+    proxy::{
+        HttpAuth, HttpConnect, Shadowsocks, ShadowsocksCipher, Socks5Local, Socks5Remote, SocksAuth,
+    },
 };
 
 #[derive(thiserror::Error, Debug)]
@@ -85,6 +87,45 @@ impl From<ShadowsocksAdd> for Shadowsocks {
     }
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct HttpConnectAdd {
+    /// The IP of the remote HTTP proxy
+    pub remote_ip: IpAddr,
+    /// The port of the remote HTTP proxy
+    pub remote_port: u16,
+    /// Connect to the proxy over TLS. The certificate of the proxy must be valid for this name
+    #[arg(long)]
+    pub tls_server_name: Option<String>,
+
+    #[clap(flatten)]
+    pub authentication: Option<HttpAuthentication>,
+}
+
+impl TryFrom<HttpConnectAdd> for HttpConnect {
+    type Error = Error;
+    fn try_from(add: HttpConnectAdd) -> Result<Self, Self::Error> {
+        Ok(Self {
+            endpoint: SocketAddr::new(add.remote_ip, add.remote_port),
+            auth: add
+                .authentication
+                .map(|auth| HttpAuth::new(auth.username, auth.password))
+                .transpose()?,
+            tls_server_name: add.tls_server_name.filter(|name| !name.is_empty()),
+        })
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+#[group(requires_all = ["username", "password"])] // https://github.com/clap-rs/clap/issues/5092
+pub struct HttpAuthentication {
+    /// Username for basic authentication against a remote HTTP proxy
+    #[arg(short, long, required = false)]
+    pub username: String,
+    /// Password for basic authentication against a remote HTTP proxy
+    #[arg(short, long, required = false)]
+    pub password: String,
+}
+
 #[derive(Args, Debug, Clone)]
 #[group(requires_all = ["username", "password"])] // https://github.com/clap-rs/clap/issues/5092
 pub struct SocksAuthentication {
@@ -98,19 +139,19 @@ pub struct SocksAuthentication {
 
 #[derive(Args, Debug, Clone)]
 pub struct ProxyEditParams {
-    /// Username for authentication \[Socks5 (Remote proxy)\]
+    /// Username for authentication \[Socks5 (Remote proxy), HTTP\]
     #[arg(long)]
     pub username: Option<String>,
-    /// Password for authentication \[Socks5 (Remote proxy), Shadowsocks\]
+    /// Password for authentication \[Socks5 (Remote proxy), Shadowsocks, HTTP\]
     #[arg(long)]
     pub password: Option<String>,
     /// Cipher to use \[Shadowsocks\]
     #[arg(long)]
     pub cipher: Option<ShadowsocksCipher>,
-    /// The IP of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks\]
+    /// The IP of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks, HTTP\]
     #[arg(long)]
     pub ip: Option<IpAddr>,
-    /// The port of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks\]
+    /// The port of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks, HTTP\]
     #[arg(long)]
     pub port: Option<u16>,
     /// The port that the server on localhost is listening on \[Socks5 (Local proxy)\]
@@ -119,6 +160,10 @@ pub struct ProxyEditParams {
     /// The transport protocol used by the remote proxy \[Socks5 (Local proxy)\]
     #[arg(long)]
     pub transport_protocol: Option<TransportProtocol>,
+    /// Server name to verify the certificate of the proxy against. An empty name disables TLS
+    /// \[HTTP\]
+    #[arg(long)]
+    pub tls_server_name: Option<String>,
 }
 
 pub mod pp {
@@ -162,6 +207,18 @@ pub mod pp {
                     print_option!("Local port", local.local_port);
                     Ok(())
                 }
+                CustomProxy::HttpConnect(http) => {
+                    print_option!("Protocol", "HTTP CONNECT");
+                    print_option!("Peer", http.endpoint);
+                    if let Some(server_name) = &http.tls_server_name {
+                        print_option!("TLS server name", server_name);
+                    }
+                    if let Some(credentials) = &http.auth {
+                        print_option!("Username", credentials.username());
+                        print_option!("Password", credentials.password());
+                    }
+                    Ok(())
+                }
             }
         }
     }
@@ -1,26 +1,29 @@
 use anyhow::{Context, Result, bail};
-use clap::Subcommand;
+use clap::{Args, Subcommand};
 use itertools::Itertools;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     CustomTunnelEndpoint,
     constraints::{Constraint, Match},
+    custom_endpoint::SavedCustomEndpoint,
     location::CountryCode,
     relay_constraints::{
         GeographicLocationConstraint, LocationConstraint, LocationConstraintFormatter, Multihop,
-        Ownership, Provider, Providers, RelayConstraints, RelayOverride, RelaySettings,
-        WireguardConstraints, allowed_ip::AllowedIps,
+        Ownership, Provider, Providers, RelayConstraints, RelayExclusions, RelayOverride,
+        RelaySettings, RelayStrategy, WireguardConstraints, allowed_ip::AllowedIps,
     },
     relay_list::RelayListCountry,
+    wg_quick::WgQuickConfig,
 };
 use std::{
     collections::HashMap,
-    io::BufRead,
+    io::{BufRead, Write},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
 };
 use talpid_types::net::{IpVersion, wireguard};
 
-use super::relay_constraints::LocationArgs;
+use super::relay_constraints::{LocationArgs, NearArgs};
 use crate::{cmds::receive_confirmation, print_option};
 
 #[derive(Subcommand, Debug)]
@@ -38,9 +41,26 @@ pub enum Relay {
     /// Update the relay list
     Update,
 
+    /// Export the configuration of the current tunnel as a wg-quick configuration file.
+    /// The file contains the private key of the tunnel, which is the private key of this device
+    /// unless a custom relay is used
+    Export {
+        /// File to write the configuration to. If omitted, it is written to standard output
+        file: Option<PathBuf>,
+    },
+
+    /// Manage saved custom WireGuard relays
+    #[clap(subcommand)]
+    CustomEndpoint(CustomEndpointCommands),
+
     /// Override options for individual relays/servers
     #[clap(subcommand)]
     Override(OverrideCommands),
+
+    /// Never select specific relays, or relays from specific providers. This applies to both
+    /// entry and exit relays
+    #[clap(subcommand)]
+    Exclude(ExcludeCommands),
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -69,6 +89,26 @@ pub enum SetCommands {
     )]
     Location(LocationArgs),
 
+    /// Select the relays nearest to this device, or to the given coordinates.
+    /// The location of this device is looked up from its IP address while
+    /// disconnected.
+    #[command(
+        override_usage = "mullvad relay set near [LATITUDE LONGITUDE] [--within <KM>]
+
+  Select the relays nearest to this device:
+
+\tmullvad relay set near
+
+  Select any relay within 500 km of this device:
+
+\tmullvad relay set near --within 500
+
+  Select the relays nearest to Berlin:
+
+\tmullvad relay set near 52.52 13.405"
+    )]
+    Near(NearArgs),
+
     /// Set custom list to select relays from. Use the 'custom-lists list'
     /// command to show available alternatives.
     CustomList {
@@ -90,6 +130,15 @@ pub enum SetCommands {
         ownership: Constraint<Ownership>,
     },
 
+    /// Choose among the matching relays based on measured latency or recent
+    /// connection failures. The 'list' command shows the measured latencies.
+    Strategy {
+        /// Strategy to use: 'any' to pick relays at random, 'fastest' to
+        /// prefer the relays with the lowest latency, or 'reliable' to avoid
+        /// relays that recently failed to connect
+        strategy: Constraint<RelayStrategy>,
+    },
+
     /// Set tunnel IP version constraint
     IpVersion {
         /// IP protocol to use, or 'any'
@@ -105,23 +154,66 @@ pub enum SetCommands {
     Entry(EntryArgs),
 
     /// Set a custom WireGuard relay
-    Custom {
-        /// Hostname or IP
-        host: String,
-        /// Remote port
-        port: u16,
-        /// Base64 encoded public key of remote peer
-        #[arg(value_parser = wireguard::PublicKey::from_base64)]
-        peer_pubkey: wireguard::PublicKey,
-        /// IP addresses of local tunnel interface
-        #[arg(required = true, num_args = 1..)]
-        tunnel_ip: Vec<IpAddr>,
-        /// IPv4 gateway address
-        #[arg(long)]
-        v4_gateway: Ipv4Addr,
-        /// IPv6 gateway address
-        #[arg(long)]
-        v6_gateway: Option<Ipv6Addr>,
+    Custom(CustomEndpointArgs),
+
+    /// Use the saved custom WireGuard relays. They are tried in priority order, moving on to
+    /// the next one when connecting fails. See 'mullvad relay custom-endpoint'
+    CustomEndpoints,
+}
+
+/// Parameters of a custom WireGuard relay
+#[derive(Args, Debug, Clone)]
+pub struct CustomEndpointArgs {
+    /// Hostname or IP
+    #[arg(required_unless_present = "from_file")]
+    host: Option<String>,
+    /// Remote port
+    #[arg(required_unless_present = "from_file")]
+    port: Option<u16>,
+    /// Base64 encoded public key of remote peer
+    #[arg(value_parser = wireguard::PublicKey::from_base64, required_unless_present = "from_file")]
+    peer_pubkey: Option<wireguard::PublicKey>,
+    /// IP addresses of local tunnel interface
+    #[arg(required_unless_present = "from_file", num_args = 1..)]
+    tunnel_ip: Vec<IpAddr>,
+    /// IPv4 gateway address
+    #[arg(long)]
+    v4_gateway: Ipv4Addr,
+    /// IPv6 gateway address
+    #[arg(long)]
+    v6_gateway: Option<Ipv6Addr>,
+    /// Import the relay from a wg-quick configuration file. When setting a custom relay, the MTU
+    /// in the file is applied to the settings as well. DNS servers in the file are used while
+    /// connected to the relay, unless custom DNS servers or content blockers are enabled
+    #[arg(long, conflicts_with_all = ["host", "port", "peer_pubkey", "tunnel_ip"])]
+    from_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CustomEndpointCommands {
+    /// List the saved custom relays in priority order
+    List,
+    /// Save a custom WireGuard relay with the lowest priority
+    Add {
+        /// Unique name of the relay
+        name: String,
+        #[command(flatten)]
+        endpoint: CustomEndpointArgs,
+    },
+    /// Replace the parameters of a saved custom relay
+    Update {
+        /// Name of the relay to replace
+        name: String,
+        #[command(flatten)]
+        endpoint: CustomEndpointArgs,
+    },
+    /// Remove a saved custom relay
+    Remove { name: String },
+    /// Change the priority of a saved custom relay
+    Move {
+        name: String,
+        /// New position in the list, where 0 is tried first
+        position: u32,
     },
 }
 
@@ -149,6 +241,8 @@ pub enum EntryArgs {
 \tmullvad relay set tunnel wireguard entry-location se-got-wg-004"
     )]
     Location(LocationArgs),
+    /// Select the entry relays nearest to this device, or to the given coordinates.
+    Near(NearArgs),
     /// Name of custom list to use to pick entry endpoint.
     CustomList { custom_list_name: String },
 }
@@ -171,6 +265,31 @@ pub enum OverrideCommands {
     },
 }
 
+#[derive(Subcommand, Debug, Clone)]
+pub enum ExcludeCommands {
+    /// Show the excluded relays and providers
+    List,
+    /// Exclude a relay or provider
+    #[clap(subcommand)]
+    Add(Exclusion),
+    /// Stop excluding a relay or provider
+    #[clap(subcommand)]
+    Remove(Exclusion),
+    /// Stop excluding all relays and providers
+    Clear,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Exclusion {
+    /// A single relay
+    Relay {
+        /// The unique hostname of the relay, e.g. se-got-wg-001
+        hostname: String,
+    },
+    /// All relays hosted by a provider
+    Provider { name: String },
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum OverrideSetCommands {
     /// Override entry IPv4 address for a given relay
@@ -197,14 +316,73 @@ pub enum OverrideUnsetCommands {
     Ipv6 { hostname: String },
 }
 
+impl CustomEndpointArgs {
+    /// Read the custom relay from the arguments, or from the wg-quick configuration file if one
+    /// was given. The parsed configuration is returned as well, so that its remaining options
+    /// can be applied.
+    async fn read(self) -> Result<(CustomTunnelEndpoint, Option<WgQuickConfig>)> {
+        match self {
+            CustomEndpointArgs {
+                v4_gateway,
+                v6_gateway,
+                from_file: Some(path),
+                ..
+            } => {
+                let config = read_wg_quick_config(&path).await?;
+                let endpoint = config.to_custom_tunnel_endpoint(v4_gateway, v6_gateway);
+                Ok((endpoint, Some(config)))
+            }
+            CustomEndpointArgs {
+                host: Some(host),
+                port: Some(port),
+                peer_pubkey: Some(peer_pubkey),
+                tunnel_ip,
+                v4_gateway,
+                v6_gateway,
+                from_file: None,
+            } => {
+                let endpoint = Relay::read_custom_wireguard_relay(
+                    host,
+                    port,
+                    peer_pubkey,
+                    tunnel_ip,
+                    v4_gateway,
+                    v6_gateway,
+                )
+                .await?;
+                Ok((endpoint, None))
+            }
+            CustomEndpointArgs { .. } => {
+                unreachable!("Either a file or all relay parameters are required")
+            }
+        }
+    }
+}
+
+async fn read_wg_quick_config(path: &Path) -> Result<WgQuickConfig> {
+    let contents = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let config: WgQuickConfig = contents
+        .parse()
+        .with_context(|| format!("Invalid wg-quick configuration in {}", path.display()))?;
+    for key in &config.ignored_keys {
+        eprintln!("Ignoring unsupported key: {key}");
+    }
+    Ok(config)
+}
+
 impl Relay {
     pub async fn handle(self) -> Result<()> {
         match self {
             Relay::Get => Self::get().await,
             Relay::List => Self::list().await,
             Relay::Update => Self::update().await,
+            Relay::Export { file } => Self::export(file).await,
             Relay::Set(subcmd) => Self::set(subcmd).await,
+            Relay::CustomEndpoint(subcmd) => Self::custom_endpoint(subcmd).await,
             Relay::Override(subcmd) => Self::r#override(subcmd).await,
+            Relay::Exclude(subcmd) => Self::exclude(subcmd).await,
         }
     }
 
@@ -218,6 +396,13 @@ impl Relay {
                 println!("Custom endpoint: {endpoint}")
             }
 
+            RelaySettings::SavedCustomEndpoints => {
+                println!("Saved custom endpoints, in priority order");
+                for saved in settings.custom_endpoints.iter() {
+                    print_option!(saved.name, saved.endpoint);
+                }
+            }
+
             RelaySettings::Normal(constraints) => {
                 println!("Generic constraints");
 
@@ -234,6 +419,7 @@ impl Relay {
 
                 print_option!("Provider(s)", constraints.providers,);
                 print_option!("Ownership", constraints.ownership,);
+                print_option!("Strategy", constraints.strategy);
 
                 println!("WireGuard constraints");
 
@@ -259,6 +445,9 @@ impl Relay {
 
     async fn list() -> Result<()> {
         let mut countries = get_active_relays().await?;
+        let mut rpc = MullvadProxyClient::new().await?;
+        let latencies = rpc.get_relay_latencies().await?;
+        let exclusions = rpc.get_settings().await?.relay_exclusions;
         countries.sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
         for mut country in countries {
             country
@@ -282,8 +471,17 @@ impl Relay {
                     if let Some(ipv6_addr) = relay.ipv6_addr_in {
                         addresses.push(ipv6_addr.into());
                     }
+                    let latency = latencies
+                        .get(&relay.hostname)
+                        .map(|latency| format!(" - {} ms", latency.as_millis()))
+                        .unwrap_or_default();
+                    let excluded = if exclusions.excludes(relay) {
+                        " - excluded"
+                    } else {
+                        ""
+                    };
                     println!(
-                        "\t\t{} ({}) - hosted by {} ({ownership})",
+                        "\t\t{} ({}) - hosted by {} ({ownership}){latency}{excluded}",
                         relay.hostname,
                         addresses.iter().join(", "),
                         relay.provider
@@ -311,7 +509,7 @@ impl Relay {
         let relay_settings = settings.get_relay_settings();
         let mut constraints = match relay_settings {
             RelaySettings::Normal(normal) => normal,
-            RelaySettings::CustomTunnelEndpoint(_custom) => {
+            RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints => {
                 println!("Removing custom relay settings");
                 RelayConstraints::default()
             }
@@ -326,11 +524,18 @@ impl Relay {
     async fn set(subcmd: SetCommands) -> Result<()> {
         match subcmd {
             SetCommands::Location(location) => Self::set_location(location).await,
+            SetCommands::Near(near) => Self::set_near(near).await,
             SetCommands::CustomList { custom_list_name } => {
                 Self::set_custom_list(custom_list_name).await
             }
             SetCommands::Provider { providers } => Self::set_providers(providers).await,
             SetCommands::Ownership { ownership } => Self::set_ownership(ownership).await,
+            SetCommands::Strategy { strategy } => {
+                Self::update_constraints(|constraints| {
+                    constraints.strategy = strategy;
+                })
+                .await
+            }
             SetCommands::IpVersion { ip_version } => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let mut wireguard_constraints = Self::get_wireguard_constraints(&mut rpc).await?;
@@ -368,6 +573,10 @@ impl Relay {
                         wireguard_constraints.entry_location =
                             location_constraint.map(LocationConstraint::from);
                     }
+                    EntryArgs::Near(near) => {
+                        wireguard_constraints.entry_location =
+                            Constraint::Only(LocationConstraint::try_from(near)?);
+                    }
                     EntryArgs::CustomList { custom_list_name } => {
                         let list_id =
                             super::custom_list::find_list_by_name(&mut rpc, &custom_list_name)
@@ -383,30 +592,94 @@ impl Relay {
                 })
                 .await
             }
-            SetCommands::Custom {
-                host,
-                port,
-                peer_pubkey,
-                tunnel_ip,
-                v4_gateway,
-                v6_gateway,
-            } => {
-                let custom_endpoint = Self::read_custom_wireguard_relay(
-                    host,
-                    port,
-                    peer_pubkey,
-                    tunnel_ip,
-                    v4_gateway,
-                    v6_gateway,
-                )
-                .await?;
+            SetCommands::Custom(args) => {
+                let (custom_endpoint, wg_quick_config) = args.read().await?;
                 let mut rpc = MullvadProxyClient::new().await?;
                 rpc.set_relay_settings(RelaySettings::CustomTunnelEndpoint(custom_endpoint))
                     .await?;
                 println!("Relay constraints updated");
+                if let Some(mtu) = wg_quick_config.and_then(|config| config.mtu) {
+                    rpc.set_wireguard_mtu(Some(mtu)).await?;
+                    println!("MTU parameter has been updated");
+                }
                 Ok(())
             }
+            SetCommands::CustomEndpoints => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                if rpc.get_settings().await?.custom_endpoints.is_empty() {
+                    eprintln!(
+                        "Warning: No custom relays have been saved. Add one with 'mullvad relay custom-endpoint add'"
+                    );
+                }
+                rpc.set_relay_settings(RelaySettings::SavedCustomEndpoints)
+                    .await?;
+                println!("Relay constraints updated");
+                Ok(())
+            }
+        }
+    }
+
+    async fn custom_endpoint(subcmd: CustomEndpointCommands) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        match subcmd {
+            CustomEndpointCommands::List => {
+                let settings = rpc.get_settings().await?;
+                for (position, saved) in settings.custom_endpoints.iter().enumerate() {
+                    println!("{position}. {}", saved.name);
+                    print_option!("Endpoint", saved.endpoint);
+                }
+                if !matches!(settings.relay_settings, RelaySettings::SavedCustomEndpoints) {
+                    println!(
+                        "Saved custom relays are not in use. Use them with 'mullvad relay set custom-endpoints'"
+                    );
+                }
+            }
+            CustomEndpointCommands::Add { name, endpoint } => {
+                let endpoint = Self::read_saved_custom_endpoint(name, endpoint).await?;
+                rpc.add_custom_endpoint(endpoint).await?;
+                println!("Saved custom relay");
+            }
+            CustomEndpointCommands::Update { name, endpoint } => {
+                let endpoint = Self::read_saved_custom_endpoint(name, endpoint).await?;
+                rpc.update_custom_endpoint(endpoint).await?;
+                println!("Updated custom relay");
+            }
+            CustomEndpointCommands::Remove { name } => {
+                rpc.remove_custom_endpoint(name).await?;
+                println!("Removed custom relay");
+            }
+            CustomEndpointCommands::Move { name, position } => {
+                rpc.move_custom_endpoint(name, position).await?;
+                println!("Updated custom relay priority");
+            }
+        }
+        Ok(())
+    }
+
+    async fn read_saved_custom_endpoint(
+        name: String,
+        args: CustomEndpointArgs,
+    ) -> Result<SavedCustomEndpoint> {
+        let (mut endpoint, wg_quick_config) = args.read().await?;
+        if !endpoint.dns.is_empty() || wg_quick_config.is_some_and(|config| config.mtu.is_some()) {
+            eprintln!("Ignoring DNS servers and MTU, which are not used with saved relays");
+            endpoint.dns.clear();
+        }
+        SavedCustomEndpoint::new(name, endpoint).context("Invalid custom relay name")
+    }
+
+    async fn export(file: Option<PathBuf>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let config = rpc.export_wireguard_config().await?;
+        match file {
+            Some(path) => {
+                write_private_file(&path, config.as_bytes())
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                println!("Exported tunnel configuration to {}", path.display());
+            }
+            None => print!("{config}"),
         }
+        Ok(())
     }
 
     async fn read_custom_wireguard_relay(
@@ -455,13 +728,17 @@ impl Relay {
                 #[cfg(target_os = "linux")]
                 fwmark: None,
             },
+            preshared_key: None,
+            dns: vec![],
         })
     }
 
     async fn set_location(location_constraint_args: LocationArgs) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let relay_settings = rpc.get_settings().await?.get_relay_settings();
-        if let RelaySettings::CustomTunnelEndpoint(_custom) = relay_settings {
+        if let RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints =
+            relay_settings
+        {
             bail!("Cannot change location while custom endpoint is set");
         }
 
@@ -475,6 +752,20 @@ impl Relay {
         .await
     }
 
+    async fn set_near(near: NearArgs) -> Result<()> {
+        let location = LocationConstraint::try_from(near)?;
+        let mut rpc = MullvadProxyClient::new().await?;
+        if let RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints =
+            rpc.get_settings().await?.relay_settings
+        {
+            bail!("Cannot change location while custom endpoint is set");
+        }
+        Self::update_constraints(|constraints| {
+            constraints.location = Constraint::Only(location);
+        })
+        .await
+    }
+
     async fn set_custom_list(custom_list_name: String) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let list_id = super::custom_list::find_list_by_name(&mut rpc, &custom_list_name)
@@ -510,7 +801,7 @@ impl Relay {
     ) -> Result<WireguardConstraints> {
         match rpc.get_settings().await?.relay_settings {
             RelaySettings::Normal(settings) => Ok(settings.wireguard_constraints),
-            RelaySettings::CustomTunnelEndpoint(_settings) => {
+            RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints => {
                 println!("Clearing custom tunnel constraints");
                 Ok(WireguardConstraints::default())
             }
@@ -547,6 +838,66 @@ impl Relay {
         Ok(())
     }
 
+    async fn exclude(subcmd: ExcludeCommands) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut exclusions = rpc.get_settings().await?.relay_exclusions;
+        match subcmd {
+            ExcludeCommands::List => {
+                println!("Excluded relays:");
+                for hostname in &exclusions.relays {
+                    print_option!(hostname);
+                }
+                println!("Excluded providers:");
+                for provider in &exclusions.providers {
+                    print_option!(provider);
+                }
+                return Ok(());
+            }
+            ExcludeCommands::Add(Exclusion::Relay { hostname }) => {
+                let relay_list = rpc.get_relay_locations().await?;
+                let Some(relay) = relay_list
+                    .relays()
+                    .find(|relay| relay.hostname.eq_ignore_ascii_case(&hostname))
+                else {
+                    bail!("Unknown relay: {hostname}");
+                };
+                exclusions.relays.insert(relay.hostname.clone());
+            }
+            ExcludeCommands::Add(Exclusion::Provider { name }) => {
+                let relay_list = rpc.get_relay_locations().await?;
+                let Some(relay) = relay_list
+                    .relays()
+                    .find(|relay| relay.provider.eq_ignore_ascii_case(&name))
+                else {
+                    bail!("Unknown provider: {name}");
+                };
+                exclusions.providers.insert(relay.provider.clone());
+            }
+            ExcludeCommands::Remove(Exclusion::Relay { hostname }) => {
+                let num_relays = exclusions.relays.len();
+                exclusions
+                    .relays
+                    .retain(|excluded| !excluded.eq_ignore_ascii_case(&hostname));
+                if exclusions.relays.len() == num_relays {
+                    bail!("{hostname} is not excluded");
+                }
+            }
+            ExcludeCommands::Remove(Exclusion::Provider { name }) => {
+                let num_providers = exclusions.providers.len();
+                exclusions
+                    .providers
+                    .retain(|excluded| !excluded.eq_ignore_ascii_case(&name));
+                if exclusions.providers.len() == num_providers {
+                    bail!("{name} is not excluded");
+                }
+            }
+            ExcludeCommands::Clear => exclusions = RelayExclusions::default(),
+        }
+        rpc.set_relay_exclusions(exclusions).await?;
+        println!("Updated excluded relays");
+        Ok(())
+    }
+
     async fn r#override(subcmd: OverrideCommands) -> Result<()> {
         match subcmd {
             OverrideCommands::Get => {
@@ -678,6 +1029,25 @@ impl Relay {
     }
 }
 
+/// Write `contents` to `path`, making the file readable only by the current user.
+fn write_private_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    // The mode above only applies to new files
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents)
+}
+
 fn relay_to_geographical_constraint(
     relay: mullvad_types::relay_list::WireguardRelay,
 ) -> GeographicLocationConstraint {
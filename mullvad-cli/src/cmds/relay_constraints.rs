@@ -1,8 +1,10 @@
 use clap::Args;
 use mullvad_types::{
     constraints::Constraint,
-    location::{CityCode, CountryCode, Hostname},
-    relay_constraints::{GeographicLocationConstraint, LocationConstraint},
+    location::{CityCode, Coordinates, CountryCode, Hostname, InvalidCoordinates},
+    relay_constraints::{
+        DistanceConstraint, DistanceOrigin, GeographicLocationConstraint, LocationConstraint,
+    },
 };
 
 #[derive(Args, Debug, Clone)]
@@ -49,3 +51,34 @@ impl From<LocationArgs> for Constraint<LocationConstraint> {
         Constraint::Only(LocationConstraint::Location(location))
     }
 }
+
+#[derive(Args, Debug, Clone)]
+pub struct NearArgs {
+    /// Latitude to measure the distance from, in degrees. If omitted, the distance is measured
+    /// from the location of this device.
+    #[arg(requires = "longitude", allow_negative_numbers = true)]
+    pub latitude: Option<f64>,
+    /// Longitude to measure the distance from, in degrees.
+    #[arg(allow_negative_numbers = true)]
+    pub longitude: Option<f64>,
+    /// Select any relay within this many kilometers, rather than only the nearest relays.
+    #[arg(long)]
+    pub within: Option<u32>,
+}
+
+impl TryFrom<NearArgs> for LocationConstraint {
+    type Error = InvalidCoordinates;
+
+    fn try_from(value: NearArgs) -> Result<Self, Self::Error> {
+        let origin = match (value.latitude, value.longitude) {
+            (Some(latitude), Some(longitude)) => {
+                DistanceOrigin::Coordinates(Coordinates::new(latitude, longitude)?)
+            }
+            _ => DistanceOrigin::GeoIp,
+        };
+        Ok(LocationConstraint::Distance(DistanceConstraint {
+            origin,
+            max_distance_km: value.within,
+        }))
+    }
+}
@@ -22,8 +22,8 @@ impl ValueEnum for SettingsKey {
             Self(AutoConnect),
             Self(TunnelOptions),
             Self(RelayOverrides),
+            Self(RelayExclusions),
             Self(ShowBetaReleases),
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             Self(SplitTunnel),
             Self(Recents),
         ]
@@ -56,10 +56,12 @@ impl ValueEnum for SettingsKey {
             mullvad_types::settings::SettingsKey::RelayOverrides => {
                 PossibleValue::new("relay-overrides")
             }
+            mullvad_types::settings::SettingsKey::RelayExclusions => {
+                PossibleValue::new("relay-exclusions")
+            }
             mullvad_types::settings::SettingsKey::ShowBetaReleases => {
                 PossibleValue::new("show-beta-releases")
             }
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             mullvad_types::settings::SettingsKey::SplitTunnel => PossibleValue::new("split-tunnel"),
             mullvad_types::settings::SettingsKey::Recents => PossibleValue::new("recents"),
         })
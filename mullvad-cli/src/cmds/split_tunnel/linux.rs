@@ -1,11 +1,26 @@
 use anyhow::Result;
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
 use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::settings::SplitTunnelMode;
+use std::path::PathBuf;
 
-/// Manage split tunneling. To launch applications outside the tunnel, use the program
-/// 'mullvad-exclude' instead of this command
+use super::super::BooleanOption;
+
+/// Manage split tunneling. To launch a single instance of an application outside the tunnel, use
+/// the program 'mullvad-exclude' instead of this command
 #[derive(Subcommand, Debug)]
 pub enum SplitTunnel {
+    /// Display the split tunnel status and excluded applications
+    Get,
+    /// Enable or disable excluding applications by path
+    Set { policy: BooleanOption },
+    /// Set whether split applications and processes are excluded from the tunnel, or are the only
+    /// ones that use it
+    Mode { mode: Mode },
+    /// Manage applications to exclude from the tunnel. Processes started from these executables
+    /// are excluded automatically, also after restarting the daemon
+    #[clap(subcommand)]
+    App(App),
     /// List all processes that are excluded from the tunnel
     List,
     /// Add a PID to exclude from the tunnel
@@ -16,9 +31,63 @@ pub enum SplitTunnel {
     Clear,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Mode {
+    /// Split applications and processes are excluded from the tunnel
+    Exclude,
+    /// Only split applications and processes use the tunnel. All other traffic is excluded
+    Include,
+}
+
+impl From<Mode> for SplitTunnelMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Exclude => SplitTunnelMode::Exclude,
+            Mode::Include => SplitTunnelMode::Include,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum App {
+    Add { path: PathBuf },
+    Remove { path: PathBuf },
+    Clear,
+}
+
 impl SplitTunnel {
     pub async fn handle(self) -> Result<()> {
         match self {
+            SplitTunnel::Get => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                let settings = rpc.get_settings().await?.split_tunnel;
+
+                let enable_exclusions = BooleanOption::from(settings.enable_exclusions);
+
+                println!("Split tunneling state: {enable_exclusions}");
+                println!("Split tunneling mode: {}", settings.mode);
+
+                println!("Split applications:");
+                for path in &settings.apps {
+                    println!("{}", path.display());
+                }
+
+                Ok(())
+            }
+            SplitTunnel::Set { policy } => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                rpc.set_split_tunnel_state(*policy).await?;
+                println!("Split tunnel policy: {policy}");
+                Ok(())
+            }
+            SplitTunnel::Mode { mode } => {
+                let mode = SplitTunnelMode::from(mode);
+                let mut rpc = MullvadProxyClient::new().await?;
+                rpc.set_split_tunnel_mode(mode).await?;
+                println!("Split tunnel mode: {mode}");
+                Ok(())
+            }
+            SplitTunnel::App(subcmd) => Self::app(subcmd).await,
             SplitTunnel::List => {
                 let pids = MullvadProxyClient::new()
                     .await?
@@ -58,4 +127,33 @@ impl SplitTunnel {
             }
         }
     }
+
+    async fn app(subcmd: App) -> Result<()> {
+        match subcmd {
+            App::Add { path } => {
+                MullvadProxyClient::new()
+                    .await?
+                    .add_split_tunnel_app(path)
+                    .await?;
+                println!("Added path to excluded apps list");
+                Ok(())
+            }
+            App::Remove { path } => {
+                MullvadProxyClient::new()
+                    .await?
+                    .remove_split_tunnel_app(path)
+                    .await?;
+                println!("Stopped excluding app from tunnel");
+                Ok(())
+            }
+            App::Clear => {
+                MullvadProxyClient::new()
+                    .await?
+                    .clear_split_tunnel_apps()
+                    .await?;
+                println!("Stopped excluding all apps");
+                Ok(())
+            }
+        }
+    }
 }
@@ -1,12 +1,16 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use clap::{Args, Subcommand};
 use futures::StreamExt;
 use mullvad_management_interface::{MullvadProxyClient, client::DaemonEvent};
-use mullvad_types::{device::DeviceState, states::TunnelState};
+use mullvad_types::{
+    device::DeviceState,
+    states::TunnelState,
+    tunnel_stats::{PeerTrafficStats, TunnelStats},
+};
 use serde::Serialize;
 use std::fmt::Debug;
 
-use crate::format;
+use crate::{format, print_option};
 
 #[derive(Subcommand, Debug, PartialEq)]
 pub enum Status {
@@ -27,6 +31,10 @@ pub struct StatusArgs {
     /// Format output as JSON
     #[arg(long, short = 'j', conflicts_with_all = ["verbose", "debug"])]
     json: bool,
+
+    /// Continuously print traffic statistics for the active tunnel
+    #[arg(long)]
+    stats: bool,
 }
 
 impl Status {
@@ -91,12 +99,95 @@ pub async fn handle(cmd: Option<Status>, args: StatusArgs) -> Result<()> {
         format::print_state(&state, None, args.verbose);
     }
 
-    if cmd == Some(Status::Listen) {
+    if args.stats {
+        if cmd.is_some() {
+            bail!("--stats cannot be combined with a subcommand");
+        }
+        listen_stats(rpc, args).await?;
+    } else if cmd == Some(Status::Listen) {
         Status::listen(rpc, args, state).await?;
     }
     Ok(())
 }
 
+async fn listen_stats(mut rpc: MullvadProxyClient, args: StatusArgs) -> Result<()> {
+    let mut stats_stream = rpc.tunnel_stats_listen().await?;
+    while let Some(stats) = stats_stream.next().await {
+        let stats = stats?;
+        if !print_debug_or_json(&args, "Tunnel stats", &stats)? {
+            print_stats(&stats);
+        }
+    }
+    Ok(())
+}
+
+fn print_stats(stats: &TunnelStats) {
+    if stats.peers.is_empty() {
+        println!("No active tunnel");
+        return;
+    }
+    for peer in &stats.peers {
+        print_peer_stats(peer);
+    }
+}
+
+fn print_peer_stats(peer: &PeerTrafficStats) {
+    println!("Peer: {}", peer.public_key);
+    print_option!(
+        "Received",
+        format!(
+            "{} ({}/s)",
+            format_bytes(peer.rx_bytes),
+            format_bytes(peer.rx_bytes_per_second)
+        ),
+    );
+    print_option!(
+        "Sent",
+        format!(
+            "{} ({}/s)",
+            format_bytes(peer.tx_bytes),
+            format_bytes(peer.tx_bytes_per_second)
+        ),
+    );
+    match peer.last_handshake_age {
+        Some(age) => print_option!("Latest handshake", format!("{} s ago", age.as_secs())),
+        None => print_option!("Latest handshake", "none"),
+    }
+    if let Some(daita) = &peer.daita {
+        print_option!(
+            "DAITA padding",
+            format!(
+                "{} sent, {} received",
+                format_bytes(daita.tx_padding_bytes),
+                format_bytes(daita.rx_padding_bytes)
+            ),
+        );
+        print_option!(
+            "DAITA decoy packets",
+            format!(
+                "{} sent, {} received",
+                format_bytes(daita.tx_decoy_packet_bytes),
+                format_bytes(daita.rx_decoy_packet_bytes)
+            ),
+        );
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[unit])
+    } else {
+        format!("{value:.2} {}", UNITS[unit])
+    }
+}
+
 fn print_account_logged_out(state: &TunnelState, device: &DeviceState) {
     match state {
         TunnelState::Connecting { .. } | TunnelState::Connected { .. } | TunnelState::Error(_) => {
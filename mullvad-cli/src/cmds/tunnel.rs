@@ -4,8 +4,10 @@ use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
     relay_constraints::{AllowedIps, RelaySettings, WireguardConstraints},
+    relay_rotation::{RotationPolicy, TimeOfDay},
     wireguard::{QuantumResistantState, RotationInterval},
 };
+use std::time::Duration;
 
 use super::BooleanOption;
 use crate::print_option;
@@ -54,6 +56,34 @@ pub enum TunnelOptions {
 
     /// Use userspace WireGuard.
     Userspace { state: BooleanOption },
+
+    /// Reconnect to a different exit relay matching the relay constraints on a schedule
+    #[clap(subcommand)]
+    Rotation(RotationOptions),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum RotationOptions {
+    /// Rotate after being connected for this many minutes
+    Interval {
+        #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+        minutes: u64,
+    },
+
+    /// Rotate after this many bytes have been sent and received through the tunnel
+    Traffic {
+        #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+        bytes: u64,
+    },
+
+    /// Rotate at these local times of day, formatted as HH:MM
+    At {
+        #[arg(required = true, num_args = 1..)]
+        times: Vec<TimeOfDay>,
+    },
+
+    /// Never rotate the exit relay
+    Off,
 }
 
 impl Tunnel {
@@ -102,7 +132,9 @@ impl Tunnel {
         // Get the WireGuard allowed IPs
         let wireguard_constraints = match rpc.get_settings().await?.relay_settings {
             RelaySettings::Normal(settings) => settings.wireguard_constraints,
-            RelaySettings::CustomTunnelEndpoint(_) => WireguardConstraints::default(),
+            RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints => {
+                WireguardConstraints::default()
+            }
         };
 
         print_option!(
@@ -125,6 +157,13 @@ impl Tunnel {
                 "off"
             }
         );
+        print_option!(
+            "Relay rotation",
+            match tunnel_options.relay_rotation {
+                Some(policy) => policy.to_string(),
+                None => "off".to_string(),
+            },
+        );
 
         Ok(())
     }
@@ -175,6 +214,29 @@ impl Tunnel {
                 rpc.set_userspace_wireguard(*state).await?;
                 println!("Userspace WireGuard: {state}");
             }
+            TunnelOptions::Rotation(rotation) => {
+                let policy = match rotation {
+                    RotationOptions::Interval { minutes } => Some(RotationPolicy::Interval(
+                        Duration::from_secs(minutes.saturating_mul(60)),
+                    )),
+                    RotationOptions::Traffic { bytes } => Some(RotationPolicy::Traffic(bytes)),
+                    RotationOptions::At { times } => {
+                        Some(RotationPolicy::TimesOfDay(times.into_iter().collect()))
+                    }
+                    RotationOptions::Off => None,
+                };
+                match policy {
+                    Some(policy) => {
+                        policy.validate()?;
+                        rpc.set_relay_rotation(&policy).await?;
+                        println!("Rotating exit relay {policy}");
+                    }
+                    None => {
+                        rpc.clear_relay_rotation().await?;
+                        println!("Relay rotation has been disabled");
+                    }
+                }
+            }
         }
 
         Ok(())
@@ -65,12 +65,14 @@ pub fn print_state(state: &TunnelState, previous_state: Option<&TunnelState>, ve
             endpoint,
             location,
             feature_indicators,
+            rotation,
         } => {
             let (old_endpoint, old_location, old_feature_indicators) = match previous_state {
                 Some(Connecting {
                     endpoint,
                     location,
                     feature_indicators,
+                    ..
                 }) => {
                     if verbose {
                         println!("Connecting")
@@ -79,6 +81,12 @@ pub fn print_state(state: &TunnelState, previous_state: Option<&TunnelState>, ve
                 }
                 _ => {
                     println!("Connecting");
+                    if let Some(rotation) = rotation {
+                        print_option!(
+                            "Rotating from",
+                            format!("{} ({})", rotation.previous_exit, rotation.policy),
+                        );
+                    }
                     (None, &None, None)
                 }
             };
@@ -113,6 +121,7 @@ pub fn print_state(state: &TunnelState, previous_state: Option<&TunnelState>, ve
                     endpoint,
                     location,
                     feature_indicators,
+                    ..
                 }) => {
                     println!("Connected");
                     (Some(endpoint), location, Some(feature_indicators))
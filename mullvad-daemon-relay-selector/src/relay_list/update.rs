@@ -3,6 +3,7 @@
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use chrono::{DateTime, Utc};
 use futures::channel::mpsc;
 use futures::future::{Fuse, FusedFuture};
 use futures::{Future, FutureExt, SinkExt, StreamExt};
@@ -72,10 +73,12 @@ impl RelayListUpdaterHandle {
 pub struct RelayListUpdater {
     api_client: RelayListProxy,
     cache_path: PathBuf,
-    on_update: Box<dyn Fn(&RelayList) + Send + 'static>,
+    on_update: Box<dyn Fn(&RelayList, Option<DateTime<Utc>>) + Send + 'static>,
     last_check: SystemTime,
     api_availability: ApiAvailability,
     etag: Option<ETag>,
+    /// When the current relay list was downloaded, if known.
+    downloaded: Option<DateTime<Utc>>,
     // Keep tabs on the up-to-date relay list.
     // Use [RelayListUpdater::get_final_relay_list] when exposing the relay list to other parts of
     // the app.
@@ -92,18 +95,19 @@ impl RelayListUpdater {
         api_handle: MullvadRestHandle,
         cache_dir: &Path,
         overrides: Vec<RelayOverride>,
-        on_update: impl Fn(&RelayList) + Send + 'static,
+        on_update: impl Fn(&RelayList, Option<DateTime<Utc>>) + Send + 'static,
         cached_relay_list: Option<CachedRelayList>,
     ) -> RelayListUpdaterHandle {
         let (tx, cmd_rx) = mpsc::channel(1);
         let api_availability = api_handle.availability.clone();
         let api_client = RelayListProxy::new(api_handle);
 
-        let (relay_list, bridge_list, etag) = cached_relay_list
+        let (relay_list, bridge_list, etag, downloaded) = cached_relay_list
             .map(|cached_relay_list| {
                 let etag = cached_relay_list.etag().cloned();
+                let downloaded = cached_relay_list.downloaded();
                 let (relay_list, bridge_list) = cached_relay_list.into_internal_repr();
-                (relay_list, bridge_list, etag)
+                (relay_list, bridge_list, etag, downloaded)
             })
             .unwrap_or_default();
         let updater = RelayListUpdater {
@@ -113,6 +117,7 @@ impl RelayListUpdater {
             on_update: Box::new(on_update),
             last_check: UNIX_EPOCH,
             etag,
+            downloaded,
             overrides,
             api_availability,
             relay_list,
@@ -237,6 +242,7 @@ impl RelayListUpdater {
         }
         // Cache the ETag so that we send the correct one in the next request
         self.etag = new_relay_list.etag().cloned();
+        self.downloaded = new_relay_list.downloaded();
         // Propagate the new relay list to the relay selector
         let (relay_list, bridge_list) = new_relay_list.into_internal_repr();
         self.relay_list = relay_list;
@@ -253,7 +259,7 @@ impl RelayListUpdater {
         self.relay_selector.set_bridges(bridge_list);
         // Note: It is important that dependants are updated after relay selector state has been
         // updated, since they might depend on the relay selector's state ..
-        (self.on_update)(&relay_list);
+        (self.on_update)(&relay_list, self.downloaded);
     }
 
     /// Write a [`CachedRelayList`] to the file at `cache_path`.
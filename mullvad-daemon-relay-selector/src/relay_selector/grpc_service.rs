@@ -26,4 +26,12 @@ impl RelaySelectorService for RelaySelectorServer {
         let partitions = proto::RelayPartitions::from(partitions);
         Ok(Response::new(partitions))
     }
+
+    async fn get_selection_history(
+        &self,
+        _: Request<()>,
+    ) -> Result<Response<proto::SelectionHistory>, Status> {
+        let history = proto::SelectionHistory::from(self.0.selection_history());
+        Ok(Response::new(history))
+    }
 }
@@ -1,18 +1,40 @@
 pub mod grpc_service;
 
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
 
+use chrono::Utc;
 use mullvad_relay_selector::query::RelayQuery;
-use mullvad_relay_selector::{EntrySpecificConstraints, Error, GetRelay, RelaySelector};
+use mullvad_relay_selector::{
+    EntrySpecificConstraints, Error, GetRelay, RelaySelector, WireguardConfig,
+};
 use mullvad_types::custom_list::CustomListsSettings;
+use mullvad_types::location::Coordinates;
 use mullvad_types::relay_list::{BridgeList, RelayList};
+use mullvad_types::relay_selector::{
+    history::{RelaySelection, SelectionHistory, SelectionOutcome},
+    penalties::RelayPenalties,
+};
 use mullvad_types::settings::Settings;
-use talpid_types::net::{IpAvailability, IpVersion};
+use talpid_types::{
+    ErrorExt,
+    net::{IpAvailability, IpVersion},
+};
+use tokio::{io::AsyncWriteExt, sync::watch};
 
 use crate::relay_list;
 
+/// Name of the file in the cache directory that relay penalties are persisted to.
+const PENALTIES_FILENAME: &str = "relay-penalties.json";
+/// Name of the file in the cache directory that the last known location of this device is
+/// persisted to.
+const USER_LOCATION_FILENAME: &str = "user-location.json";
+/// How long to wait after relay penalties change before persisting them, so that a burst of
+/// changes results in a single write.
+const PENALTIES_WRITE_DELAY: Duration = Duration::from_secs(5);
+
 /// [`RETRY_ORDER`] defines an ordered set of entry-relay parameters which the relay selector
 /// should prioritize on successive connection attempts. Note that these will *never* override user
 /// preferences. See [the documentation on `RelayQuery`][RelayQuery] for further details.
@@ -53,6 +75,23 @@ pub static RETRY_ORDER: LazyLock<Vec<EntrySpecificConstraints>> = LazyLock::new(
 pub struct RelaySelectorIO {
     inner: RelaySelector,
     config: Config,
+    history: Arc<Mutex<SelectionHistory>>,
+    /// Exit relay that the next selection should avoid, if possible.
+    rotate_from: Arc<Mutex<Option<String>>>,
+    /// Sends relay penalties to the task that persists them, if any.
+    penalties_tx: Arc<Mutex<Option<watch::Sender<RelayPenalties>>>>,
+    /// File that the location of this device is persisted to, if any.
+    user_location_path: Arc<Mutex<Option<PathBuf>>>,
+    offline: Arc<Mutex<OfflineState>>,
+}
+
+/// Whether the device is offline. Connection attempts that fail while the device is offline are
+/// not the fault of the relay, so they are not penalized.
+#[derive(Debug, Default)]
+struct OfflineState {
+    offline: bool,
+    /// Whether the device has been offline since the most recent relay was selected.
+    since_selection: bool,
 }
 
 impl Deref for RelaySelectorIO {
@@ -72,7 +111,15 @@ impl RelaySelectorIO {
             RelaySelector::new(initial_relay_list.clone(), initial_bridge_list.clone())
         };
         let config = Config::from(custom_lists);
-        RelaySelectorIO { inner, config }
+        RelaySelectorIO {
+            inner,
+            config,
+            history: Default::default(),
+            rotate_from: Default::default(),
+            penalties_tx: Default::default(),
+            user_location_path: Default::default(),
+            offline: Default::default(),
+        }
     }
 
     /// Try to initialize [RelaySelectorIO] from cached relay list.
@@ -89,7 +136,15 @@ impl RelaySelectorIO {
             RelaySelector::new(initial_relay_list.clone(), initial_bridge_list.clone())
         };
         let config = Config::from(custom_lists);
-        Ok(RelaySelectorIO { inner, config })
+        Ok(RelaySelectorIO {
+            inner,
+            config,
+            history: Default::default(),
+            rotate_from: Default::default(),
+            penalties_tx: Default::default(),
+            user_location_path: Default::default(),
+            offline: Default::default(),
+        })
     }
 
     /// Try to initialize [RelaySelectorIO] from cached relay list. If that fails, fall back to
@@ -108,14 +163,111 @@ impl RelaySelectorIO {
         relays: RelayList,
         bridges: BridgeList,
     ) -> RelaySelectorIO {
-        let config = Config::from(settings);
         let inner = RelaySelector::new(relays, bridges);
-        RelaySelectorIO { inner, config }
+        inner.set_exclusions(settings.relay_exclusions.clone());
+        let config = Config::from(settings);
+        RelaySelectorIO {
+            inner,
+            config,
+            history: Default::default(),
+            rotate_from: Default::default(),
+            penalties_tx: Default::default(),
+            user_location_path: Default::default(),
+            offline: Default::default(),
+        }
+    }
+
+    /// Load relay penalties from `cache_dir`, and persist them there whenever they change.
+    ///
+    /// Penalties are written by a background task, so this must be called within a Tokio runtime.
+    pub async fn load_penalties(&self, cache_dir: impl AsRef<Path>) {
+        let path = cache_dir.as_ref().join(PENALTIES_FILENAME);
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => match serde_json::from_slice::<RelayPenalties>(&bytes) {
+                Ok(mut penalties) => {
+                    penalties.prune(Utc::now());
+                    self.inner.set_penalties(penalties);
+                }
+                Err(error) => log::warn!("Failed to parse relay penalties: {error}"),
+            },
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(error) => log::warn!("Failed to read relay penalties: {error}"),
+        }
+        let (tx, rx) = watch::channel(self.inner.get_penalties());
+        tokio::spawn(persist_penalties(path, rx));
+        *self.penalties_tx.lock().unwrap() = Some(tx);
+    }
+
+    /// Load the last known location of this device from `cache_dir`, and persist it there whenever
+    /// it changes. This lets relays near this device be selected before its location has been
+    /// looked up.
+    ///
+    /// The location is written by a background task, so this must be called within a Tokio
+    /// runtime.
+    pub async fn load_user_location(&self, cache_dir: impl AsRef<Path>) {
+        let path = cache_dir.as_ref().join(USER_LOCATION_FILENAME);
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => match serde_json::from_slice::<Coordinates>(&bytes) {
+                Ok(user_location) => self.inner.set_user_location(user_location),
+                Err(error) => log::warn!("Failed to parse last known location: {error}"),
+            },
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(error) => log::warn!("Failed to read last known location: {error}"),
+        }
+        *self.user_location_path.lock().unwrap() = Some(path);
+    }
+
+    /// Update the location of this device and persist it, if a cache directory has been set by
+    /// [`Self::load_user_location`].
+    pub fn set_user_location(&self, user_location: Coordinates) {
+        self.inner.set_user_location(user_location);
+        let Some(path) = self.user_location_path.lock().unwrap().clone() else {
+            return;
+        };
+        let bytes = serde_json::to_vec(&user_location);
+        tokio::spawn(async move {
+            let result = match bytes {
+                Ok(bytes) => write_cache_file(&path, &bytes).await,
+                Err(error) => Err(error.into()),
+            };
+            if let Err(error) = result {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg(&format!(
+                        "Failed to write last known location to {}",
+                        path.display()
+                    ))
+                );
+            }
+        });
+    }
+
+    /// Update the relay penalties and persist them, if a cache directory has been set by
+    /// [`Self::load_penalties`].
+    fn update_penalties(&self, update: impl FnOnce(&mut RelayPenalties) -> bool) {
+        let mut penalties = self.inner.get_penalties();
+        if !update(&mut penalties) {
+            return;
+        }
+        penalties.prune(Utc::now());
+        self.inner.set_penalties(penalties.clone());
+
+        if let Some(tx) = &*self.penalties_tx.lock().unwrap() {
+            tx.send_replace(penalties);
+        }
+    }
+
+    /// Set whether the device is offline.
+    pub fn set_offline(&self, offline: bool) {
+        let mut state = self.offline.lock().unwrap();
+        state.offline = offline;
+        state.since_selection |= offline;
     }
 
     /// Update the relay selector config.
     pub fn set_config(&self, settings: Settings) {
         let config = &self.config;
+        self.inner.set_exclusions(settings.relay_exclusions.clone());
         *config.custom_lists.lock().unwrap() = settings.custom_lists.clone();
         *config.query.lock().unwrap() = RelayQuery::from(settings);
     }
@@ -145,6 +297,10 @@ impl RelaySelectorIO {
         runtime_ip_availability: IpAvailability,
     ) -> Result<GetRelay, Error> {
         let mut user_query = self.config.query.lock().unwrap().clone();
+        let rotate_from = self.rotate_from.lock().unwrap().take();
+        if retry_attempt > 0 {
+            self.penalize_unconnected_relay();
+        }
         // Runtime parameters may shrink the set of usable IP versions — apply that *before*
         // merging with retry_order so an IPv6-only retry attempt is correctly rejected when only
         // IPv4 is available.
@@ -156,19 +312,134 @@ impl RelaySelectorIO {
         let maybe_relay = retry_order
             .iter()
             .filter_map(|retry| user_query.clone().merge_retry(retry.clone()))
-            .filter_map(|query| self.get_relay_by_query(query).ok())
+            .filter_map(|query| {
+                self.get_relay_by_query_avoiding(query, rotate_from.as_deref())
+                    .ok()
+            })
             .cycle()
             .nth(retry_attempt);
 
-        match maybe_relay {
-            Some(v) => Ok(v),
+        let relay = match maybe_relay {
+            Some(v) => v,
             // If no retry merged with `user_query` yields a relay, fall back to the user's
             // preferences alone.
-            None => self.get_relay_by_query(user_query),
+            None => self.get_relay_by_query_avoiding(user_query, rotate_from.as_deref())?,
+        };
+        self.record_selection(retry_attempt, &relay);
+        Ok(relay)
+    }
+
+    /// Add a selection to the history, along with a summary of the relays that were candidates
+    /// for the query that it was selected by.
+    fn record_selection(&self, retry_attempt: usize, relay: &GetRelay) {
+        let (exit, entry) = match &relay.inner {
+            WireguardConfig::Singlehop { exit } => (exit, None),
+            WireguardConfig::Multihop { exit, entry } => (exit, Some(entry)),
+        };
+        let selection = RelaySelection {
+            time: Utc::now(),
+            retry_attempt: u32::try_from(retry_attempt).unwrap_or(u32::MAX),
+            query: relay.query.to_string(),
+            exit_candidates: relay.candidates.exit.clone(),
+            entry_candidates: relay.candidates.entry.clone(),
+            exit: exit.inner.hostname.clone(),
+            entry: entry.map(|entry| entry.inner.hostname.clone()),
+            obfuscator: relay.obfuscator.as_ref().map(ToString::to_string),
+            outcome: SelectionOutcome::Pending,
+        };
+        self.history.lock().unwrap().push(selection);
+        let mut offline = self.offline.lock().unwrap();
+        offline.since_selection = offline.offline;
+    }
+
+    /// Penalize the relay that the client tried to connect to directly in the most recent
+    /// selection, if the connection attempt never resolved. This happens when it timed out.
+    /// Attempts during which the device was offline are not penalized, since they would have
+    /// failed regardless of the relay.
+    fn penalize_unconnected_relay(&self) {
+        let hostname = {
+            let history = self.history.lock().unwrap();
+            match history.latest() {
+                Some(selection) if selection.outcome == SelectionOutcome::Pending => {
+                    selection.first_hop().to_owned()
+                }
+                _ => return,
+            }
+        };
+        if self.offline.lock().unwrap().since_selection {
+            log::debug!("Not penalizing relay {hostname}, since the device was offline");
+            return;
+        }
+        log::debug!("Penalizing relay {hostname} for failing to connect");
+        self.update_penalties(|penalties| {
+            penalties.penalize(&hostname, Utc::now());
+            true
+        });
+    }
+
+    /// Return the most recent relay selections, oldest first.
+    pub fn selection_history(&self) -> SelectionHistory {
+        self.history.lock().unwrap().clone()
+    }
+
+    /// Make the next selection avoid the most recently selected exit relay, unless no other relay
+    /// matches the constraints. Returns the hostname of the avoided relay.
+    pub fn rotate_exit(&self) -> Option<String> {
+        let previous_exit = self.history.lock().unwrap().latest()?.exit.clone();
+        *self.rotate_from.lock().unwrap() = Some(previous_exit.clone());
+        Some(previous_exit)
+    }
+
+    /// Record what happened after the most recent relay was selected, unless that is already
+    /// known.
+    pub fn set_selection_outcome(&self, outcome: SelectionOutcome) {
+        let connected = {
+            let mut history = self.history.lock().unwrap();
+            let connected = match history.latest() {
+                Some(selection) if selection.outcome == SelectionOutcome::Pending => (outcome
+                    == SelectionOutcome::Connected)
+                    .then(|| selection.first_hop().to_owned()),
+                _ => None,
+            };
+            history.set_outcome(outcome);
+            connected
+        };
+        // A relay that connects is no longer penalized for earlier failures
+        if let Some(hostname) = connected {
+            self.update_penalties(|penalties| penalties.forgive(&hostname));
+        }
+    }
+}
+
+/// Write relay penalties to `path` whenever they change, at most once per
+/// [`PENALTIES_WRITE_DELAY`].
+async fn persist_penalties(path: PathBuf, mut rx: watch::Receiver<RelayPenalties>) {
+    while rx.changed().await.is_ok() {
+        tokio::time::sleep(PENALTIES_WRITE_DELAY).await;
+        let bytes = serde_json::to_vec(&*rx.borrow_and_update());
+        let result = match bytes {
+            Ok(bytes) => write_cache_file(&path, &bytes).await,
+            Err(error) => Err(error.into()),
+        };
+        if let Err(error) = result {
+            log::warn!(
+                "{}",
+                error.display_chain_with_msg(&format!(
+                    "Failed to write relay penalties to {}",
+                    path.display()
+                ))
+            );
         }
     }
 }
 
+/// Atomically replace the contents of the file at `path` with `bytes`.
+async fn write_cache_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut file = mullvad_fs::AtomicFile::new(path).await?;
+    file.write_all(bytes).await?;
+    file.finalize().await
+}
+
 /// Relay selector configuration. This datastructure keeps the relay selector in sync with
 /// mullvad-daemon.
 ///
@@ -76,12 +76,16 @@ impl AccessMethodResolver for DaemonAccessMethodResolver {
                     };
                     ApiConnectionMode::Proxied(ProxyConfig::from(edp))
                 }
-                // AccessMethod::BuiltIn(BuiltInAccessMethod::DomainFronting) => {
-                //     mullvad_api::domain_fronting::resolve().await?
-                // }
+                AccessMethod::BuiltIn(BuiltInAccessMethod::DomainFronting) => {
+                    mullvad_api::domain_fronting::resolve().await?
+                }
                 AccessMethod::Custom(config) => {
                     ApiConnectionMode::Proxied(ProxyConfig::from(config.clone()))
                 }
+                AccessMethod::CustomDomainFronting(config) => {
+                    mullvad_api::domain_fronting::resolve_custom(&config.front, &config.proxy_host)
+                        .await?
+                }
             }
         };
         let endpoint =
@@ -155,9 +159,11 @@ pub(crate) fn create_bypass_tx(
     Some(bypass_tx)
 }
 
-/// Forwards the received values from `offline_state_rx` to the [`ApiAvailability`].
+/// Forwards the received values from `offline_state_rx` to the [`ApiAvailability`] and the
+/// relay selector.
 pub(crate) fn forward_offline_state(
     api_availability: ApiAvailability,
+    relay_selector: RelaySelectorIO,
     mut offline_state_rx: mpsc::UnboundedReceiver<Connectivity>,
 ) {
     tokio::spawn(async move {
@@ -171,10 +177,12 @@ pub(crate) fn forward_offline_state(
             state = if is_offline { "offline" } else { "online" },
         );
         api_availability.set_offline(is_offline);
+        relay_selector.set_offline(is_offline);
 
         while let Some(state) = offline_state_rx.next().await {
             log::info!("Detecting changes to offline state - {state:?}");
             api_availability.set_offline(state.is_offline());
+            relay_selector.set_offline(state.is_offline());
         }
     });
 }
@@ -1,4 +1,5 @@
 use clap::{Args, Parser};
+use mullvad_daemon::metrics::MetricsEndpoint;
 use std::sync::LazyLock;
 
 static ENV_DESC: LazyLock<String> = LazyLock::new(|| {
@@ -35,6 +36,10 @@ struct Cli {
     /// Don't log timestamps when logging to stdout, useful when running as a systemd service
     #[arg(long)]
     disable_stdout_timestamps: bool,
+    /// Serve OpenMetrics on this endpoint. Either a loopback address, such as 127.0.0.1:9100,
+    /// or an absolute path to a Unix socket
+    #[arg(long, value_name = "ENDPOINT")]
+    metrics_endpoint: Option<MetricsEndpoint>,
 
     #[command(flatten)]
     command: CommandFlags,
@@ -69,6 +74,7 @@ pub struct Config {
     pub log_level: log::LevelFilter,
     pub log_to_file: bool,
     pub log_stdout_timestamps: bool,
+    pub metrics_endpoint: Option<MetricsEndpoint>,
 
     pub command: Command,
 }
@@ -137,6 +143,7 @@ fn create_config() -> Config {
         log_level,
         log_to_file: !app.disable_log_to_file,
         log_stdout_timestamps: !app.disable_stdout_timestamps,
+        metrics_endpoint: app.metrics_endpoint,
         command: app.command.into(),
     }
 }
@@ -0,0 +1,68 @@
+use crate::{Daemon, Error};
+use mullvad_types::{
+    custom_endpoint::{CustomEndpointsSettings, Error as CustomEndpointError, SavedCustomEndpoint},
+    relay_constraints::RelaySettings,
+};
+
+impl Daemon {
+    /// Save a new custom endpoint with the lowest priority.
+    ///
+    /// Returns an error if the name is not unique.
+    pub async fn add_custom_endpoint(
+        &mut self,
+        endpoint: SavedCustomEndpoint,
+    ) -> Result<(), Error> {
+        self.update_custom_endpoints(|endpoints| endpoints.add(endpoint))
+            .await
+    }
+
+    /// Replace the saved custom endpoint with the same name.
+    ///
+    /// Returns an error if the endpoint doesn't exist.
+    pub async fn update_custom_endpoint(
+        &mut self,
+        endpoint: SavedCustomEndpoint,
+    ) -> Result<(), Error> {
+        self.update_custom_endpoints(|endpoints| endpoints.update(endpoint))
+            .await
+    }
+
+    /// Remove a saved custom endpoint.
+    ///
+    /// Returns an error if the endpoint doesn't exist.
+    pub async fn remove_custom_endpoint(&mut self, name: String) -> Result<(), Error> {
+        self.update_custom_endpoints(|endpoints| endpoints.remove(&name))
+            .await
+    }
+
+    /// Change the priority of a saved custom endpoint, where 0 is the highest priority.
+    ///
+    /// Returns an error if the endpoint doesn't exist.
+    pub async fn move_custom_endpoint(
+        &mut self,
+        name: String,
+        position: usize,
+    ) -> Result<(), Error> {
+        self.update_custom_endpoints(|endpoints| endpoints.move_to(&name, position))
+            .await
+    }
+
+    async fn update_custom_endpoints(
+        &mut self,
+        update: impl FnOnce(&mut CustomEndpointsSettings) -> Result<(), CustomEndpointError>,
+    ) -> Result<(), Error> {
+        let settings_changed = self
+            .settings
+            .try_update(|settings| update(&mut settings.custom_endpoints))
+            .await
+            .map_err(Error::SettingsError)?;
+
+        if settings_changed
+            && let RelaySettings::SavedCustomEndpoints = self.settings.relay_settings
+        {
+            log::info!("Initiating tunnel restart because the saved custom endpoints changed");
+            self.reconnect_tunnel();
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
 use crate::{Daemon, Error};
 use mullvad_types::relay_constraints::{GeographicLocationConstraint, Multihop};
 use mullvad_types::{
-    constraints::Constraint,
-    custom_list::{CustomList, Id},
+    constraints::{Constraint, Match},
+    custom_list::{
+        CustomList, CustomListFilter, ExportedCustomList, Id, ImportMode, ImportSummary,
+    },
     relay_constraints::{LocationConstraint, RelaySettings},
 };
 use std::collections::BTreeSet;
@@ -15,9 +17,11 @@ impl Daemon {
         &mut self,
         name: String,
         locations: BTreeSet<GeographicLocationConstraint>,
+        filter: Option<CustomListFilter>,
     ) -> Result<Id, crate::Error> {
         let mut new_list = CustomList::new(name).map_err(crate::Error::CustomListError)?;
         new_list.append(locations);
+        new_list.filter = filter;
 
         let id = new_list.id();
 
@@ -108,6 +112,51 @@ impl Daemon {
         Ok(())
     }
 
+    /// Create or update many custom lists at once. See [`CustomListsSettings::import`] for how
+    /// name conflicts are handled.
+    ///
+    /// [`CustomListsSettings::import`]: mullvad_types::custom_list::CustomListsSettings::import
+    pub async fn import_custom_lists(
+        &mut self,
+        lists: Vec<ExportedCustomList>,
+        mode: ImportMode,
+    ) -> Result<ImportSummary, Error> {
+        let relay_list = self.relay_selector.get_relays();
+        let unknown_locations = lists
+            .iter()
+            .flat_map(|list| {
+                list.locations
+                    .iter()
+                    .filter(|location| !relay_list.relays().any(|relay| location.0.matches(relay)))
+                    .map(|location| (list.name.clone(), location.0.clone()))
+            })
+            .collect();
+
+        let mut summary = ImportSummary::default();
+        let settings_changed = self
+            .settings
+            .try_update(|settings| {
+                summary = settings.custom_lists.import(lists, mode)?;
+                Ok::<_, mullvad_types::custom_list::Error>(())
+            })
+            .await
+            .map_err(Error::SettingsError);
+
+        if let Ok(true) = settings_changed {
+            self.relay_selector
+                .set_custom_lists(self.settings.custom_lists.clone());
+
+            if self.change_should_cause_reconnect(None) {
+                log::info!("Initiating tunnel restart because a selected custom list changed");
+                self.reconnect_tunnel();
+            }
+        }
+
+        settings_changed?;
+        summary.unknown_locations = unknown_locations;
+        Ok(summary)
+    }
+
     /// Check whether we need to reconnect after changing custom lists.
     ///
     /// If `custom_list_id` is `Some`, only changes to that custom list will trigger a reconnect.
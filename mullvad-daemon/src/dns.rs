@@ -1,8 +1,11 @@
 use std::net::{IpAddr, Ipv4Addr};
 
-use mullvad_types::settings::{DnsOptions, DnsState};
+use mullvad_types::{
+    relay_constraints::RelaySettings,
+    settings::{DnsDomainRule, DnsEncryption, DnsOptions, DnsState, Settings},
+};
 use talpid_core::firewall::is_local_address;
-use talpid_dns::DnsConfig;
+use talpid_dns::{DnsConfig, DomainRule};
 
 /// When we want to block certain contents with the help of DNS server side,
 /// we compute the resolver IP to use based on these constants. The last
@@ -15,8 +18,52 @@ const DNS_ADULT_BLOCKING_IP_BIT: u8 = 1 << 3; // 0b00001000
 const DNS_GAMBLING_BLOCKING_IP_BIT: u8 = 1 << 4; // 0b00010000
 const DNS_SOCIAL_MEDIA_BLOCKING_IP_BIT: u8 = 1 << 5; // 0b00100000
 
+/// DNS settings that cannot be applied on this platform.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Per-domain DNS rules require the local DNS resolver, which is only used on macOS")]
+    DomainRulesUnsupported,
+    #[error("Encrypted DNS requires the local DNS resolver, which is only used on macOS")]
+    EncryptionUnsupported,
+}
+
+/// Return an error if `options` contain settings that would be ignored on this platform.
+pub fn check_supported(options: &DnsOptions) -> Result<(), Error> {
+    if !options.domain_rules.is_empty() && !talpid_core::local_dns_resolver_enabled() {
+        return Err(Error::DomainRulesUnsupported);
+    }
+    if options.state == DnsState::Custom
+        && options.custom_options.encryption.is_some()
+        && !talpid_core::local_dns_resolver_enabled()
+    {
+        return Err(Error::EncryptionUnsupported);
+    }
+    Ok(())
+}
+
+/// Return the DNS resolvers to use with the current relay settings. The DNS servers of a custom
+/// relay are used unless custom DNS servers or content blockers are enabled.
+pub fn config_from_settings(settings: &Settings) -> DnsConfig {
+    let options = &settings.tunnel_options.dns_options;
+    if let RelaySettings::CustomTunnelEndpoint(endpoint) = &settings.relay_settings
+        && !endpoint.dns.is_empty()
+        && default_addresses_from_options(options) == DnsConfig::default()
+    {
+        let (tunnel_config, non_tunnel_config) = partition_addresses(&endpoint.dns);
+        let domain_rules = options.domain_rules.iter().map(domain_rule).collect();
+        return DnsConfig::from_addresses(&tunnel_config, &non_tunnel_config)
+            .with_domain_rules(domain_rules);
+    }
+    addresses_from_options(options)
+}
+
 /// Return the DNS resolvers to use
 pub fn addresses_from_options(options: &DnsOptions) -> DnsConfig {
+    let domain_rules = options.domain_rules.iter().map(domain_rule).collect();
+    default_addresses_from_options(options).with_domain_rules(domain_rules)
+}
+
+fn default_addresses_from_options(options: &DnsOptions) -> DnsConfig {
     match options.state {
         DnsState::Default => {
             // Check if we should use a custom blocking DNS resolver.
@@ -52,23 +99,56 @@ pub fn addresses_from_options(options: &DnsOptions) -> DnsConfig {
         }
         DnsState::Custom if options.custom_options.addresses.is_empty() => DnsConfig::default(),
         DnsState::Custom => {
-            let (non_tunnel_config, tunnel_config): (Vec<_>, Vec<_>) = options
-                .custom_options
-                .addresses
-                .iter()
-                .copied()
-                // Private IP ranges should not be tunneled
-                .partition(|addr| is_local_address(*addr));
+            let (tunnel_config, non_tunnel_config) =
+                partition_addresses(&options.custom_options.addresses);
             DnsConfig::from_addresses(&tunnel_config, &non_tunnel_config)
+                .with_encryption(options.custom_options.encryption.as_ref().map(encryption))
         }
     }
 }
 
+fn encryption(encryption: &DnsEncryption) -> talpid_dns::DnsEncryption {
+    match encryption.clone() {
+        DnsEncryption::Tls { server_name } => talpid_dns::DnsEncryption::Tls { server_name },
+        DnsEncryption::Https { server_name, path } => {
+            talpid_dns::DnsEncryption::Https { server_name, path }
+        }
+    }
+}
+
+fn domain_rule(rule: &DnsDomainRule) -> DomainRule {
+    let (tunnel_config, non_tunnel_config) = partition_addresses(&rule.servers);
+    DomainRule {
+        domain: rule.domain.clone(),
+        tunnel_config,
+        non_tunnel_config,
+    }
+}
+
+/// Split `addresses` into addresses to reach inside and outside the tunnel, respectively
+fn partition_addresses(addresses: &[IpAddr]) -> (Vec<IpAddr>, Vec<IpAddr>) {
+    let (non_tunnel_config, tunnel_config) = addresses
+        .iter()
+        .copied()
+        // Private IP ranges should not be tunneled
+        .partition(|addr| is_local_address(*addr));
+    (tunnel_config, non_tunnel_config)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::dns::addresses_from_options;
-    use mullvad_types::settings::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
-    use talpid_dns::DnsConfig;
+    use crate::dns::{addresses_from_options, check_supported, config_from_settings};
+    use mullvad_types::{
+        CustomTunnelEndpoint,
+        relay_constraints::RelaySettings,
+        settings::{
+            CustomDnsOptions, DefaultDnsOptions, DnsDomainRule, DnsEncryption, DnsOptions,
+            DnsState, Settings,
+        },
+    };
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use talpid_dns::{DnsConfig, DomainRule};
+    use talpid_types::net::wireguard;
 
     #[test]
     fn test_default_dns() {
@@ -76,6 +156,7 @@ mod test {
             state: DnsState::Default,
             custom_options: CustomDnsOptions::default(),
             default_options: DefaultDnsOptions::default(),
+            domain_rules: vec![],
         };
 
         assert_eq!(addresses_from_options(&public_cfg), DnsConfig::default());
@@ -90,6 +171,7 @@ mod test {
                 block_ads: true,
                 ..DefaultDnsOptions::default()
             },
+            domain_rules: vec![],
         };
 
         assert_eq!(
@@ -107,8 +189,10 @@ mod test {
             state: DnsState::Custom,
             custom_options: CustomDnsOptions {
                 addresses: vec![public_ip, private_ip],
+                encryption: None,
             },
             default_options: DefaultDnsOptions::default(),
+            domain_rules: vec![],
         };
 
         assert_eq!(
@@ -116,4 +200,171 @@ mod test {
             DnsConfig::from_addresses(&[public_ip], &[private_ip],)
         );
     }
+
+    // Domain rules apply in addition to the default servers, with private IPs outside the tunnel
+    #[test]
+    fn test_domain_rules() {
+        let public_ip = "1.2.3.4".parse().unwrap();
+        let private_ip = "10.0.0.53".parse().unwrap();
+        let cfg = DnsOptions {
+            state: DnsState::Default,
+            custom_options: CustomDnsOptions::default(),
+            default_options: DefaultDnsOptions::default(),
+            domain_rules: vec![
+                DnsDomainRule::new("corp.example", vec![private_ip]).unwrap(),
+                DnsDomainRule::new("internal", vec![public_ip]).unwrap(),
+            ],
+        };
+
+        assert_eq!(
+            addresses_from_options(&cfg),
+            DnsConfig::default().with_domain_rules(vec![
+                DomainRule {
+                    domain: "corp.example".to_owned(),
+                    tunnel_config: vec![],
+                    non_tunnel_config: vec![private_ip],
+                },
+                DomainRule {
+                    domain: "internal".to_owned(),
+                    tunnel_config: vec![public_ip],
+                    non_tunnel_config: vec![],
+                },
+            ])
+        );
+    }
+
+    // Encryption applies to custom DNS servers only
+    #[test]
+    fn test_custom_dns_encryption() {
+        let public_ip = "1.2.3.4".parse().unwrap();
+        let mut cfg = DnsOptions {
+            state: DnsState::Custom,
+            custom_options: CustomDnsOptions {
+                addresses: vec![public_ip],
+                encryption: Some(DnsEncryption::tls("dns.example").unwrap()),
+            },
+            default_options: DefaultDnsOptions::default(),
+            domain_rules: vec![],
+        };
+
+        assert_eq!(
+            addresses_from_options(&cfg),
+            DnsConfig::from_addresses(&[public_ip], &[]).with_encryption(Some(
+                talpid_dns::DnsEncryption::Tls {
+                    server_name: "dns.example".to_owned(),
+                }
+            ))
+        );
+
+        cfg.state = DnsState::Default;
+        assert_eq!(addresses_from_options(&cfg), DnsConfig::default());
+    }
+
+    // Domain rules are rejected where the local DNS resolver would not apply them
+    #[test]
+    fn test_domain_rules_supported() {
+        let mut cfg = DnsOptions {
+            state: DnsState::Default,
+            custom_options: CustomDnsOptions::default(),
+            default_options: DefaultDnsOptions::default(),
+            domain_rules: vec![],
+        };
+        assert!(check_supported(&cfg).is_ok());
+
+        cfg.domain_rules =
+            vec![DnsDomainRule::new("corp.example", vec!["10.0.0.53".parse().unwrap()]).unwrap()];
+        assert_eq!(
+            check_supported(&cfg).is_ok(),
+            talpid_core::local_dns_resolver_enabled()
+        );
+    }
+
+    // Encrypted DNS is rejected where it would fall back to plain DNS
+    #[test]
+    fn test_encryption_supported() {
+        let mut cfg = DnsOptions {
+            state: DnsState::Default,
+            custom_options: CustomDnsOptions {
+                addresses: vec!["1.2.3.4".parse().unwrap()],
+                encryption: Some(DnsEncryption::tls("dns.example").unwrap()),
+            },
+            default_options: DefaultDnsOptions::default(),
+            domain_rules: vec![],
+        };
+        // Unused custom options are ignored
+        assert!(check_supported(&cfg).is_ok());
+
+        cfg.state = DnsState::Custom;
+        assert_eq!(
+            check_supported(&cfg).is_ok(),
+            talpid_core::local_dns_resolver_enabled()
+        );
+    }
+
+    #[test]
+    fn test_custom_relay_dns() {
+        let relay_dns: IpAddr = "1.1.1.1".parse().unwrap();
+        let endpoint = CustomTunnelEndpoint {
+            dns: vec![relay_dns],
+            ..CustomTunnelEndpoint::new(
+                "192.0.2.1".to_owned(),
+                wireguard::ConnectionConfig {
+                    tunnel: wireguard::TunnelConfig {
+                        private_key: wireguard::PrivateKey::new_from_random(),
+                        addresses: vec![Ipv4Addr::new(10, 64, 0, 2).into()],
+                    },
+                    peer: wireguard::PeerConfig {
+                        public_key: wireguard::PrivateKey::new_from_random().public_key(),
+                        allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+                        endpoint: SocketAddr::from((Ipv4Addr::UNSPECIFIED, 51820)),
+                        psk: None,
+                        constant_packet_size: false,
+                    },
+                    exit_peer: None,
+                    ipv4_gateway: Ipv4Addr::new(10, 64, 0, 1),
+                    ipv6_gateway: None,
+                    #[cfg(target_os = "linux")]
+                    fwmark: None,
+                },
+            )
+        };
+        let mut settings = Settings::default();
+        settings.relay_settings = RelaySettings::CustomTunnelEndpoint(endpoint);
+
+        // The DNS servers of the custom relay are used by default
+        assert_eq!(
+            config_from_settings(&settings),
+            DnsConfig::from_addresses(&[relay_dns], &[])
+        );
+
+        // Content blockers take precedence
+        settings
+            .tunnel_options
+            .dns_options
+            .default_options
+            .block_ads = true;
+        assert_eq!(
+            config_from_settings(&settings),
+            addresses_from_options(&settings.tunnel_options.dns_options)
+        );
+        assert_ne!(
+            config_from_settings(&settings),
+            DnsConfig::from_addresses(&[relay_dns], &[])
+        );
+
+        // So do custom DNS servers
+        settings.tunnel_options.dns_options = DnsOptions {
+            state: DnsState::Custom,
+            custom_options: CustomDnsOptions {
+                addresses: vec!["8.8.8.8".parse().unwrap()],
+                ..CustomDnsOptions::default()
+            },
+            default_options: DefaultDnsOptions::default(),
+            domain_rules: vec![],
+        };
+        assert_eq!(
+            config_from_settings(&settings),
+            addresses_from_options(&settings.tunnel_options.dns_options)
+        );
+    }
 }
@@ -1,9 +1,15 @@
+use chrono::Utc;
 use futures::{FutureExt, select};
 pub use mullvad_leak_checker::LeakInfo;
+use mullvad_leak_checker::traceroute::DEFAULT_MAX_TTL;
+use mullvad_types::leak_check::{LeakCheckOutcome, LeakCheckReport};
 use std::time::Duration;
 use talpid_routing::RouteManagerHandle;
 use talpid_types::{net::Endpoint, tunnel::TunnelStateTransition};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
+
+/// How long to wait after connecting before running the automatic leak check.
+const SETTLE_DELAY: Duration = Duration::from_millis(5000);
 
 /// An actor that tries to leak traffic outside the tunnel while we are connected.
 pub struct LeakChecker {
@@ -15,11 +21,21 @@ struct Task {
     events_rx: mpsc::UnboundedReceiver<TaskEvent>,
     route_manager: RouteManagerHandle,
     callbacks: Vec<Box<dyn LeakCheckerCallback>>,
+    /// Highest TTL value of the probe packets.
+    max_ttl: u16,
+    /// The most recent tunnel state, if any.
+    tunnel_state: Option<TunnelStateTransition>,
+    /// Report from the most recently completed leak check.
+    last_report: Option<LeakCheckReport>,
+    /// Requested leak checks, answered when the current leak check completes.
+    pending_requests: Vec<oneshot::Sender<Option<LeakCheckReport>>>,
 }
 
 enum TaskEvent {
     NewTunnelState(TunnelStateTransition),
     AddCallback(Box<dyn LeakCheckerCallback>),
+    RunCheck(oneshot::Sender<Option<LeakCheckReport>>),
+    GetLastReport(oneshot::Sender<Option<LeakCheckReport>>),
 }
 
 #[derive(PartialEq, Eq)]
@@ -43,6 +59,10 @@ impl LeakChecker {
             events_rx,
             route_manager,
             callbacks: vec![],
+            max_ttl: DEFAULT_MAX_TTL,
+            tunnel_state: None,
+            last_report: None,
+            pending_requests: vec![],
         };
 
         tokio::task::spawn(task.run());
@@ -60,6 +80,21 @@ impl LeakChecker {
         self.send(TaskEvent::AddCallback(Box::new(callback)))
     }
 
+    /// Run a leak check right away. Returns `None` if the tunnel is not connected, or if the tunnel
+    /// state changes before the check completes.
+    pub fn run_check(&mut self) -> impl Future<Output = Option<LeakCheckReport>> + use<> {
+        let (tx, rx) = oneshot::channel();
+        self.send(TaskEvent::RunCheck(tx));
+        async move { rx.await.ok().flatten() }
+    }
+
+    /// Return the report from the most recently completed leak check, if any.
+    pub fn last_report(&mut self) -> impl Future<Output = Option<LeakCheckReport>> + use<> {
+        let (tx, rx) = oneshot::channel();
+        self.send(TaskEvent::GetLastReport(tx));
+        async move { rx.await.ok().flatten() }
+    }
+
     /// Send a [TaskEvent] to the running [Task];
     fn send(&mut self, event: TaskEvent) {
         if self.task_event_tx.send(event).is_err() {
@@ -76,8 +111,15 @@ impl Task {
             };
 
             match event {
-                TaskEvent::NewTunnelState(s) => self.on_new_tunnel_state(s).await,
+                TaskEvent::NewTunnelState(s) => {
+                    self.tunnel_state = Some(s.clone());
+                    self.on_new_tunnel_state(s, SETTLE_DELAY).await
+                }
                 TaskEvent::AddCallback(c) => self.on_add_callback(c),
+                TaskEvent::RunCheck(tx) => self.on_run_check(tx).await,
+                TaskEvent::GetLastReport(tx) => {
+                    let _ = tx.send(self.last_report.clone());
+                }
             }
         }
     }
@@ -86,19 +128,36 @@ impl Task {
         self.callbacks.push(c);
     }
 
-    async fn on_new_tunnel_state(&mut self, mut tunnel_state: TunnelStateTransition) {
+    async fn on_run_check(&mut self, tx: oneshot::Sender<Option<LeakCheckReport>>) {
+        let Some(tunnel_state) = self.tunnel_state.clone() else {
+            let _ = tx.send(None);
+            return;
+        };
+        self.pending_requests.push(tx);
+        self.on_new_tunnel_state(tunnel_state, Duration::ZERO).await
+    }
+
+    /// Run a leak check after `settle_delay` if `tunnel_state` is connected.
+    async fn on_new_tunnel_state(
+        &mut self,
+        mut tunnel_state: TunnelStateTransition,
+        settle_delay: Duration,
+    ) {
         'leak_test: loop {
             let TunnelStateTransition::Connected(tunnel) = &tunnel_state else {
                 break 'leak_test;
             };
 
             let ping_destination = tunnel.endpoint;
+            let max_ttl = self.max_ttl;
             let route_manager = self.route_manager.clone();
             let leak_test = async {
                 // Give the connection a little time to settle before starting the test.
-                tokio::time::sleep(Duration::from_millis(5000)).await;
+                tokio::time::sleep(settle_delay).await;
 
-                check_for_leaks(&route_manager, ping_destination).await
+                let started = Utc::now();
+                let result = check_for_leaks(&route_manager, ping_destination, max_ttl).await;
+                (started, result)
             };
 
             // Make sure the tunnel state doesn't change while we're doing the leak test.
@@ -111,8 +170,18 @@ impl Task {
                             self.on_add_callback(c);
                             continue 'listen_for_events;
                         }
+                        TaskEvent::RunCheck(tx) => {
+                            // Answer with the result of the leak check that is already running.
+                            self.pending_requests.push(tx);
+                            continue 'listen_for_events;
+                        }
+                        TaskEvent::GetLastReport(tx) => {
+                            let _ = tx.send(self.last_report.clone());
+                            continue 'listen_for_events;
+                        }
                     };
 
+                    self.tunnel_state = Some(new_state.clone());
                     if let TunnelStateTransition::Connected(..) = new_state {
                         // Still connected, all is well...
                     } else {
@@ -123,16 +192,25 @@ impl Task {
                 }
             };
 
-            let leak_result = select! {
+            let (started, leak_result) = select! {
                 // If tunnel state changes, restart the test.
                 _ = another_tunnel_state.fuse() => continue 'leak_test,
 
                 leak_result = leak_test.fuse() => leak_result,
             };
 
+            let report = create_report(ping_destination, max_ttl, started, &leak_result);
+            self.last_report = Some(report.clone());
+            for tx in self.pending_requests.drain(..) {
+                let _ = tx.send(Some(report.clone()));
+            }
+
             let leak_info = match leak_result {
-                Ok(Some(leak_info)) => leak_info,
-                Ok(None) => {
+                Ok(Probe {
+                    leak: Some(leak_info),
+                    ..
+                }) => leak_info,
+                Ok(Probe { leak: None, .. }) => {
                     log::debug!("No leak detected");
                     break 'leak_test;
                 }
@@ -149,6 +227,52 @@ impl Task {
 
             break 'leak_test;
         }
+
+        // The tunnel is not connected, so any requested leak check could not be run.
+        for tx in self.pending_requests.drain(..) {
+            let _ = tx.send(None);
+        }
+    }
+}
+
+/// The result of sending probe packets outside the tunnel.
+struct Probe {
+    /// The interface that probe packets were sent on, if any.
+    interface: Option<String>,
+    /// Details about the leak, if one was detected.
+    leak: Option<LeakInfo>,
+}
+
+fn create_report(
+    destination: Endpoint,
+    max_ttl: u16,
+    started: chrono::DateTime<Utc>,
+    result: &anyhow::Result<Probe>,
+) -> LeakCheckReport {
+    let (interface, reachable_nodes, outcome) = match result {
+        Ok(Probe {
+            interface,
+            leak: Some(leak_info),
+        }) => (
+            interface.clone(),
+            leak_info.reachable_nodes.clone(),
+            LeakCheckOutcome::LeakDetected,
+        ),
+        Ok(Probe {
+            interface,
+            leak: None,
+        }) => (interface.clone(), vec![], LeakCheckOutcome::NoLeak),
+        Err(error) => (None, vec![], LeakCheckOutcome::Failed(format!("{error:#}"))),
+    };
+
+    LeakCheckReport {
+        interface,
+        destination: destination.address.ip(),
+        probe_ttls: (1..=max_ttl).collect(),
+        reachable_nodes,
+        started,
+        finished: Utc::now(),
+        outcome,
     }
 }
 
@@ -157,16 +281,18 @@ impl Task {
 async fn check_for_leaks(
     _route_manager: &RouteManagerHandle,
     _destination: Endpoint,
-) -> anyhow::Result<Option<LeakInfo>> {
+    _max_ttl: u16,
+) -> anyhow::Result<Probe> {
     // TODO: We currently don't have a way to get the non-tunnel interface on Android.
-    Ok(None)
+    Err(anyhow::anyhow!("Leak checks are not supported on Android"))
 }
 
 #[cfg(not(target_os = "android"))]
 async fn check_for_leaks(
     route_manager: &RouteManagerHandle,
     destination: Endpoint,
-) -> anyhow::Result<Option<LeakInfo>> {
+    max_ttl: u16,
+) -> anyhow::Result<Probe> {
     use anyhow::{Context, anyhow};
     use mullvad_leak_checker::{LeakStatus, traceroute::TracerouteOpt};
 
@@ -227,6 +353,7 @@ async fn check_for_leaks(
 
     log::debug!("Attempting to leak traffic on interface {interface:?} to {destination}");
 
+    let interface_name = interface.to_string();
     mullvad_leak_checker::traceroute::try_run_leak_test(&TracerouteOpt {
         interface,
         destination: destination.address.ip(),
@@ -237,12 +364,17 @@ async fn check_for_leaks(
         exclude_port: None,
         #[cfg(unix)]
         icmp: true,
+
+        max_ttl,
     })
     .await
     .map_err(|e| anyhow!("{e:#}"))
-    .map(|status| match status {
-        LeakStatus::NoLeak => None,
-        LeakStatus::LeakDetected(info) => Some(info),
+    .map(|status| Probe {
+        interface: Some(interface_name),
+        leak: match status {
+            LeakStatus::NoLeak => None,
+            LeakStatus::LeakDetected(info) => Some(info),
+        },
     })
 }
 
@@ -258,3 +390,58 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+    use talpid_types::net::TransportProtocol;
+
+    fn destination() -> Endpoint {
+        Endpoint::new(Ipv4Addr::new(192, 0, 2, 1), 51820, TransportProtocol::Udp)
+    }
+
+    #[test]
+    fn test_report_no_leak() {
+        let probe = Ok(Probe {
+            interface: Some("eth0".to_owned()),
+            leak: None,
+        });
+        let report = create_report(destination(), 3, Utc::now(), &probe);
+
+        assert_eq!(report.outcome, LeakCheckOutcome::NoLeak);
+        assert_eq!(report.interface.as_deref(), Some("eth0"));
+        assert_eq!(report.destination, destination().address.ip());
+        assert_eq!(report.probe_ttls, vec![1, 2, 3]);
+        assert!(report.reachable_nodes.is_empty());
+    }
+
+    #[test]
+    fn test_report_leak() {
+        let node = IpAddr::from(Ipv4Addr::new(192, 168, 1, 1));
+        let probe = Ok(Probe {
+            interface: Some("eth0".to_owned()),
+            leak: Some(LeakInfo {
+                reachable_nodes: vec![node],
+                interface: mullvad_leak_checker::Interface::Name("eth0".to_owned()),
+            }),
+        });
+        let report = create_report(destination(), 5, Utc::now(), &probe);
+
+        assert_eq!(report.outcome, LeakCheckOutcome::LeakDetected);
+        assert_eq!(report.probe_ttls, vec![1, 2, 3, 4, 5]);
+        assert_eq!(report.reachable_nodes, vec![node]);
+    }
+
+    #[test]
+    fn test_report_failed() {
+        let probe = Err(anyhow::anyhow!("no route"));
+        let report = create_report(destination(), 3, Utc::now(), &probe);
+
+        assert_eq!(
+            report.outcome,
+            LeakCheckOutcome::Failed("no route".to_owned())
+        );
+        assert_eq!(report.interface, None);
+    }
+}
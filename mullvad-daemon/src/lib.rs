@@ -8,6 +8,7 @@ mod api;
 mod api_address_updater;
 #[cfg(not(target_os = "android"))]
 mod cleanup;
+mod custom_endpoint;
 mod custom_list;
 pub mod device;
 mod dns;
@@ -18,21 +19,30 @@ pub mod logging;
 #[cfg(target_os = "macos")]
 mod macos;
 pub mod management_interface;
+pub mod metrics;
 mod migrations;
 #[cfg(not(target_os = "android"))]
+mod relay_latency;
+mod relay_rotation;
+#[cfg(not(target_os = "android"))]
 pub mod rpc_uniqueness_check;
 pub mod runtime;
+mod session_log;
 pub mod settings;
 pub mod shutdown;
 mod target_state;
+mod trusted_networks;
 mod tunnel;
+mod tunnel_traffic;
 pub mod version;
 
 use crate::{
     migrations::{MigrationData, multihop::scenario::Scenario},
     target_state::PersistentTargetState,
+    trusted_networks::TrustedNetworkState,
 };
 use api::DaemonAccessMethodResolver;
+use chrono::{DateTime, Utc};
 use device::{AccountEvent, PrivateAccountAndDevice, PrivateDeviceEvent};
 use futures::{
     StreamExt,
@@ -55,8 +65,9 @@ use mullvad_daemon_relay_selector::{
 use mullvad_encrypted_dns_proxy::state::EncryptedDnsProxyState;
 #[cfg(target_os = "android")]
 use mullvad_types::account::{PlayExternalObfuscatedAccountId, PlayPurchase};
-#[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
 use mullvad_types::settings::SplitApp;
+#[cfg(target_os = "linux")]
+use mullvad_types::settings::SplitTunnelMode;
 use mullvad_types::{
     access_method::{AccessMethod, AccessMethodSetting},
     account::{AccountData, AccountNumber, VoucherSubmission},
@@ -65,14 +76,20 @@ use mullvad_types::{
     custom_list::CustomList,
     device::{Device, DeviceEvent, DeviceEventCause, DeviceId, DeviceState, RemoveDeviceEvent},
     features::{FeatureIndicator, FeatureIndicators, compute_feature_indicators},
-    location::{GeoIpLocation, LocationEventData},
+    leak_check::LeakCheckReport,
+    location::{Coordinates, GeoIpLocation, LocationEventData},
     relay_constraints::{
-        ObfuscationSettings, RelayOverride, RelaySettings, allowed_ip::AllowedIps,
+        InvalidCustomBridge, ObfuscationSettings, RelayExclusions, RelayOverride, RelaySettings,
+        allowed_ip::AllowedIps,
     },
     relay_list::RelayList,
-    settings::{DnsOptions, Settings, SettingsKeyList},
+    relay_rotation::{InvalidRotationPolicy, Rotation, RotationPolicy},
+    relay_selector::history::SelectionOutcome,
+    session_log::SessionRecord,
+    settings::{DnsOptions, Settings, SettingsKeyList, TrustedNetworks},
     states::{Secured, TargetState, TargetStateStrict, TunnelState},
     version::AppVersionInfo,
+    wg_quick::WgQuickConfig,
     wireguard::{PublicKey, QuantumResistantState, RotationInterval},
 };
 use mullvad_types::{
@@ -84,8 +101,7 @@ use mullvad_types::{
 #[cfg(not(target_os = "android"))]
 use mullvad_update::version::rollout::Rollout;
 use settings::SettingsPersister;
-#[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 #[cfg(target_os = "android")]
 use std::os::unix::io::RawFd;
 use std::{collections::BTreeSet, path::Path};
@@ -108,11 +124,13 @@ use talpid_core::{
 use talpid_routing::RouteManagerHandle;
 #[cfg(target_os = "android")]
 use talpid_types::android::AndroidContext;
+#[cfg(target_os = "macos")]
+use talpid_types::net::dns::{DnsLeakTestError, DnsLeakTestReport};
 #[cfg(target_os = "windows")]
 use talpid_types::split_tunnel::ExcludedProcess;
 use talpid_types::{
     ErrorExt,
-    net::{IpVersion, proxy::ShadowsocksCipher},
+    net::{IpVersion, NetworkInfo, proxy::ShadowsocksCipher, wireguard::PeerStats},
     tunnel::{ErrorStateCause, TunnelStateTransition},
 };
 use tokio::io;
@@ -185,13 +203,15 @@ pub enum Error {
     #[error("Unable to initialize split tunneling")]
     InitSplitTunneling(#[source] split_tunnel::Error),
 
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     #[error("Split tunneling error")]
     SplitTunnelError(#[source] split_tunnel::Error),
 
     #[error("An account is already set")]
     AlreadyLoggedIn,
 
+    #[error("Changing the account is forbidden by the administrator policy")]
+    AccountLocked,
+
     #[error("No account number is set")]
     NoAccountNumber,
 
@@ -227,6 +247,9 @@ pub enum Error {
     #[error("No custom bridge has been specified")]
     NoCustomProxySaved,
 
+    #[error("Session log error")]
+    SessionLog(#[source] session_log::Error),
+
     #[cfg(target_os = "macos")]
     #[error("Failed to set exclusion group")]
     GroupIdError(#[source] io::Error),
@@ -248,6 +271,16 @@ pub enum DaemonCommand {
     Reconnect(oneshot::Sender<bool>),
     /// Request the current state.
     GetState(oneshot::Sender<TunnelState>),
+    /// Request traffic statistics for the active tunnel. Returns `None` if there is no tunnel.
+    GetTunnelStats(oneshot::Sender<Option<Vec<PeerStats>>>),
+    /// Run a leak check. Returns `None` if the tunnel is not connected.
+    RunLeakCheck(oneshot::Sender<Option<LeakCheckReport>>),
+    /// Request the report from the most recent leak check, if any.
+    GetLeakCheckReport(oneshot::Sender<Option<LeakCheckReport>>),
+    /// Run a DNS leak test. Returns `None` if the tunnel is not connected, or if the local DNS
+    /// resolver is not in use.
+    #[cfg(target_os = "macos")]
+    RunDnsLeakTest(oneshot::Sender<Result<DnsLeakTestReport, DnsLeakTestError>>),
     CreateNewAccount(ResponseTx<String, Error>),
     /// Request the metadata for an account.
     GetAccountData(
@@ -264,6 +297,11 @@ pub enum DaemonCommand {
     ClearAccountHistory(ResponseTx<(), Error>),
     /// Get the list of countries and cities where there are relays.
     GetRelayLocations(oneshot::Sender<RelayList>),
+    /// Export the configuration of the connected tunnel in the wg-quick format. Returns `None`
+    /// if the tunnel is not connected, or if it uses multihop.
+    ExportWireguardConfig(oneshot::Sender<Option<String>>),
+    /// Get the most recently measured round-trip times to relays, by hostname.
+    GetRelayLatencies(oneshot::Sender<HashMap<String, Duration>>),
     /// Delete the account and log out the user
     #[cfg(target_os = "android")]
     DeleteAccount(ResponseTx<(), Error>),
@@ -290,11 +328,20 @@ pub enum DaemonCommand {
     SetAllowLan(ResponseTx<(), settings::Error>, bool),
     /// Set the beta program setting.
     SetShowBetaReleases(ResponseTx<(), settings::Error>, bool),
+    /// Set whether tunnel sessions should be recorded in the session log.
+    SetSessionLog(ResponseTx<(), settings::Error>, bool),
+    /// Return the sessions in the session log that ended at or after the given time.
+    GetSessionLog(ResponseTx<Vec<SessionRecord>, Error>, Option<DateTime<Utc>>),
     /// Set the lockdown_mode setting.
     #[cfg(not(target_os = "android"))]
     SetLockdownMode(ResponseTx<(), settings::Error>, bool),
     /// Set the auto-connect setting.
     SetAutoConnect(ResponseTx<(), settings::Error>, bool),
+    /// Set the networks on which the tunnel is automatically disconnected.
+    SetTrustedNetworks(ResponseTx<(), settings::Error>, TrustedNetworks),
+    /// Return the network that trusted network rules are evaluated against.
+    #[cfg(target_os = "linux")]
+    GetCurrentNetwork(oneshot::Sender<NetworkInfo>),
     /// Set if IPv6 should be enabled in the tunnel
     SetEnableIpv6(ResponseTx<(), settings::Error>, bool),
     /// Set if userspace WireGuard should be forced.
@@ -311,6 +358,10 @@ pub enum DaemonCommand {
     SetRelayOverride(ResponseTx<(), settings::Error>, RelayOverride),
     /// Remove all relay override options
     ClearAllRelayOverrides(ResponseTx<(), settings::Error>),
+    /// Set the relays and providers that are never selected
+    SetRelayExclusions(ResponseTx<(), settings::Error>, RelayExclusions),
+    /// Set when to reconnect to a different exit relay, if ever
+    SetRelayRotation(ResponseTx<(), settings::Error>, Option<RotationPolicy>),
     /// Toggle macOS network check leak
     /// Set MTU for wireguard tunnels
     SetWireguardMtu(ResponseTx<(), settings::Error>, Option<u16>),
@@ -331,6 +382,7 @@ pub enum DaemonCommand {
         ResponseTx<mullvad_types::custom_list::Id, Error>,
         String,
         BTreeSet<GeographicLocationConstraint>,
+        Option<mullvad_types::custom_list::CustomListFilter>,
     ),
     /// Delete custom list
     DeleteCustomList(ResponseTx<(), Error>, mullvad_types::custom_list::Id),
@@ -338,6 +390,26 @@ pub enum DaemonCommand {
     UpdateCustomList(ResponseTx<(), Error>, CustomList),
     /// Remove all custom lists
     ClearCustomLists(ResponseTx<(), Error>),
+    /// Create or update custom lists in bulk
+    ImportCustomLists(
+        ResponseTx<mullvad_types::custom_list::ImportSummary, Error>,
+        Vec<mullvad_types::custom_list::ExportedCustomList>,
+        mullvad_types::custom_list::ImportMode,
+    ),
+    /// Save a custom tunnel endpoint with the lowest priority
+    AddCustomEndpoint(
+        ResponseTx<(), Error>,
+        mullvad_types::custom_endpoint::SavedCustomEndpoint,
+    ),
+    /// Replace the saved custom endpoint with the same name
+    UpdateCustomEndpoint(
+        ResponseTx<(), Error>,
+        mullvad_types::custom_endpoint::SavedCustomEndpoint,
+    ),
+    /// Remove the saved custom endpoint with the given name
+    RemoveCustomEndpoint(ResponseTx<(), Error>, String),
+    /// Move the saved custom endpoint with the given name to a new priority
+    MoveCustomEndpoint(ResponseTx<(), Error>, String, usize),
     /// Add API access methods
     AddApiAccessMethod(
         ResponseTx<mullvad_types::access_method::Id, Error>,
@@ -389,17 +461,16 @@ pub enum DaemonCommand {
     #[cfg(target_os = "linux")]
     ClearSplitTunnelProcesses(ResponseTx<(), split_tunnel::Error>),
     /// Exclude traffic of an application from the tunnel
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     AddSplitTunnelApp(ResponseTx<(), Error>, SplitApp),
     /// Remove application from list of apps to exclude from the tunnel
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     RemoveSplitTunnelApp(ResponseTx<(), Error>, SplitApp),
     /// Clear list of apps to exclude from the tunnel
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     ClearSplitTunnelApps(ResponseTx<(), Error>),
     /// Enable or disable split tunneling
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     SetSplitTunnelState(ResponseTx<(), Error>, bool),
+    /// Set whether split tunneling apps are excluded from the tunnel or are the only ones using it
+    #[cfg(target_os = "linux")]
+    SetSplitTunnelMode(ResponseTx<(), Error>, SplitTunnelMode),
     /// Returns all processes currently being excluded from the tunnel
     #[cfg(target_os = "windows")]
     GetSplitTunnelProcesses(ResponseTx<Vec<ExcludedProcess>, split_tunnel::Error>),
@@ -502,13 +573,16 @@ pub(crate) enum InternalDaemonEvent {
     /// A generic event for when any settings change.
     SettingsChanged,
     /// The split tunnel paths or state were updated.
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     ExcludedPathsEvent(ExcludedPathsUpdate, oneshot::Sender<Result<(), Error>>),
     /// A network leak was detected.
     LeakDetected(LeakInfo),
+    /// The host joined a different network.
+    #[cfg(target_os = "linux")]
+    NetworkChanged(NetworkInfo),
+    /// The relay rotation policy says that the exit relay should be rotated.
+    RelayRotationDue(RotationPolicy),
 }
 
-#[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
 pub(crate) enum ExcludedPathsUpdate {
     SetState(bool),
     SetPaths(HashSet<SplitApp>),
@@ -553,6 +627,19 @@ impl From<LocationEventData> for InternalDaemonEvent {
     }
 }
 
+#[cfg(target_os = "linux")]
+impl From<NetworkInfo> for InternalDaemonEvent {
+    fn from(network: NetworkInfo) -> Self {
+        InternalDaemonEvent::NetworkChanged(network)
+    }
+}
+
+impl From<relay_rotation::RotationDue> for InternalDaemonEvent {
+    fn from(event: relay_rotation::RotationDue) -> Self {
+        InternalDaemonEvent::RelayRotationDue(event.0)
+    }
+}
+
 pub struct DaemonCommandChannel {
     sender: DaemonCommandSender,
     receiver: mpsc::UnboundedReceiver<InternalDaemonEvent>,
@@ -686,6 +773,9 @@ pub struct Daemon {
     rx: mpsc::UnboundedReceiver<InternalDaemonEvent>,
     tx: DaemonEventSender<InternalDaemonEvent>,
     reconnection_job: Option<AbortHandle>,
+    relay_rotation_job: Option<AbortHandle>,
+    /// Set when reconnecting because of the relay rotation policy, until the tunnel is connecting.
+    pending_rotation: Option<Rotation>,
     management_interface: ManagementInterfaceServer,
     migration_complete: migrations::MigrationComplete,
     settings: SettingsPersister,
@@ -706,6 +796,14 @@ pub struct Daemon {
     location_handler: GeoIpHandler,
     leak_checker: LeakChecker,
     cache_dir: PathBuf,
+    /// The network that the host is connected to. Only monitored on Linux.
+    current_network: NetworkInfo,
+    /// Decides when trusted network rules connect or disconnect the tunnel.
+    trusted_network: TrustedNetworkState,
+    metrics: metrics::Metrics,
+    session_log: session_log::SessionLog,
+    #[cfg(not(target_os = "android"))]
+    relay_latency_prober: relay_latency::RelayLatencyProber,
 }
 pub struct DaemonConfig {
     pub log_dir: Option<PathBuf>,
@@ -714,6 +812,8 @@ pub struct DaemonConfig {
     pub cache_dir: PathBuf,
     pub rpc_socket_path: PathBuf,
     pub endpoint: ApiEndpoint,
+    /// Where to serve metrics, if anywhere.
+    pub metrics_endpoint: Option<metrics::MetricsEndpoint>,
     #[cfg(target_os = "android")]
     pub android_context: AndroidContext,
     pub log_handle: logging::LogHandle,
@@ -760,11 +860,35 @@ impl Daemon {
             // TODO: This should preferably be done once, by the relay list updater.
             let initial_relay_list =
                 initial_relay_list.apply_overrides(settings.relay_overrides.clone());
-            RelaySelectorIO::from_settings(
+            let relay_selector = RelaySelectorIO::from_settings(
                 settings.to_settings(),
                 initial_relay_list.clone(),
                 initial_bridge_list.clone(),
-            )
+            );
+            relay_selector.load_penalties(&config.cache_dir).await;
+            relay_selector.load_user_location(&config.cache_dir).await;
+            relay_selector
+        };
+        #[cfg(not(target_os = "android"))]
+        let relay_latency_prober = relay_latency::RelayLatencyProber::spawn(relay_selector.clone());
+
+        #[cfg(unix)]
+        let access_policy = {
+            use mullvad_management_interface::authorization::{
+                ACCESS_POLICY_FILENAME, AccessPolicy,
+            };
+            let policy = AccessPolicy::load(&config.settings_dir.join(ACCESS_POLICY_FILENAME));
+            match policy.as_ref().map(AccessPolicy::admin_group) {
+                Some(Some(group)) => log::info!(
+                    "Management interface RPCs that are not read-only are restricted to root \
+                     and members of {group}"
+                ),
+                Some(None) => log::info!(
+                    "Management interface RPCs that are not read-only are restricted to root"
+                ),
+                None => (),
+            }
+            policy
         };
 
         let command_sender = daemon_command_channel.sender();
@@ -775,6 +899,9 @@ impl Daemon {
             app_upgrade_broadcast.clone(),
             config.log_handle,
             relay_selector.clone(),
+            #[cfg(unix)]
+            access_policy,
+            settings.policy(),
         )
         .map_err(Error::ManagementInterfaceError)?;
 
@@ -804,6 +931,21 @@ impl Daemon {
         let api_availability = api_runtime.availability_handle();
         api_availability.suspend();
 
+        let metrics = metrics::Metrics::new(api_runtime.stats_handle());
+        metrics.on_relay_list(
+            initial_relay_list
+                .as_ref()
+                .and_then(CachedRelayList::downloaded),
+        );
+        if let Some(endpoint) = config.metrics_endpoint
+            && let Err(error) = metrics::spawn_server(endpoint, metrics.clone()).await
+        {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to start metrics server")
+            );
+        }
+
         let settings_event_listener = management_interface.notifier().clone();
         settings.register_change_listener(move |settings| {
             // Notify management interface server of changes to the settings
@@ -827,6 +969,11 @@ impl Daemon {
             )
             .await
             .map_err(Error::ApiConnectionModeError)?;
+        let current_access_method = access_mode_handler
+            .get_current()
+            .await
+            .map_err(Error::ApiConnectionModeError)?;
+        metrics.on_access_method(&current_access_method.setting.name);
 
         let api_handle = api_runtime.mullvad_rest_handle(access_mode_provider);
 
@@ -898,12 +1045,29 @@ impl Daemon {
         };
 
         #[cfg(target_os = "linux")]
-        let split_tunneling_pid_manager = split_tunnel::PidManager::default();
+        let mut split_tunneling_pid_manager = split_tunnel::PidManager::default();
+        #[cfg(target_os = "linux")]
+        if settings.split_tunnel.enable_exclusions
+            && let Err(error) = split_tunneling_pid_manager.set_excluded_paths(
+                settings
+                    .split_tunnel
+                    .apps
+                    .iter()
+                    .map(|app| app.as_path().to_path_buf())
+                    .collect(),
+            )
+        {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to set initial split tunnel paths")
+            );
+        }
 
         let parameters_generator = tunnel::ParametersGenerator::new(
             account_manager.clone(),
             relay_selector.clone(),
             settings.relay_settings.clone(),
+            settings.custom_endpoints.clone(),
             settings.tunnel_options.clone(),
         );
 
@@ -946,20 +1110,24 @@ impl Daemon {
         let (offline_state_tx, offline_state_rx) = mpsc::unbounded();
         #[cfg(target_os = "windows")]
         let (volume_update_tx, volume_update_rx) = mpsc::unbounded();
+        if let Err(error) = dns::check_supported(&settings.tunnel_options.dns_options) {
+            log::warn!(
+                "{}",
+                error.display_chain_with_msg("Unsupported DNS settings")
+            );
+        }
         let tunnel_state_machine_handle = tunnel_state_machine::spawn(
             tunnel_state_machine::InitialTunnelState {
                 allow_lan: settings.allow_lan,
                 #[cfg(not(target_os = "android"))]
                 lockdown_mode: LockdownMode::from(settings.lockdown_mode),
-                dns_config: dns::addresses_from_options(&settings.tunnel_options.dns_options),
-                allowed_endpoint: access_mode_handler
-                    .get_current()
-                    .await
-                    .map_err(Error::ApiConnectionModeError)?
-                    .endpoint,
+                dns_config: dns::config_from_settings(&settings),
+                allowed_endpoint: current_access_method.endpoint,
                 reset_firewall: *target_state != TargetState::Secured,
                 #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
                 exclude_paths,
+                #[cfg(target_os = "linux")]
+                split_tunnel_mode: settings.split_tunnel.effective_mode(),
             },
             parameters_generator.clone(),
             config.log_dir,
@@ -984,11 +1152,31 @@ impl Daemon {
         .await
         .map_err(Error::TunnelError)?;
 
-        api::forward_offline_state(api_availability.clone(), offline_state_rx);
+        api::forward_offline_state(
+            api_availability.clone(),
+            relay_selector.clone(),
+            offline_state_rx,
+        );
+
+        #[cfg(target_os = "linux")]
+        if let Err(error) = talpid_core::spawn_network_monitor(
+            internal_event_tx.to_unbounded_sender(),
+            route_manager.clone(),
+            Some(mullvad_types::TUNNEL_FWMARK),
+        )
+        .await
+        {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to start network monitor")
+            );
+        }
 
         let relay_list_listener = management_interface.notifier().clone();
         let internal_event_tx_clone = internal_event_tx.clone();
-        let on_relay_list_update = move |relay_list: &RelayList| {
+        let relay_list_metrics = metrics.clone();
+        let on_relay_list_update = move |relay_list: &RelayList, downloaded| {
+            relay_list_metrics.on_relay_list(downloaded);
             relay_list_listener.notify_relay_list(relay_list.clone());
             let (tx, _) = oneshot::channel();
             let _ = internal_event_tx_clone.send(InternalDaemonEvent::Command(
@@ -1070,6 +1258,12 @@ impl Daemon {
             leak_checker
         };
 
+        let session_log = session_log::SessionLog::new(
+            config.log_dir.as_deref(),
+            settings.session_log,
+            internal_event_tx.to_specialized_sender(),
+        );
+
         let daemon = Daemon {
             tunnel_state: TunnelState::Disconnected {
                 location: None,
@@ -1082,6 +1276,8 @@ impl Daemon {
             rx: internal_event_rx,
             tx: internal_event_tx,
             reconnection_job: None,
+            relay_rotation_job: None,
+            pending_rotation: None,
             management_interface,
             migration_complete,
             settings,
@@ -1102,6 +1298,12 @@ impl Daemon {
             location_handler,
             leak_checker,
             cache_dir: config.cache_dir,
+            current_network: NetworkInfo::default(),
+            trusted_network: TrustedNetworkState::default(),
+            metrics,
+            session_log,
+            #[cfg(not(target_os = "android"))]
+            relay_latency_prober,
         };
 
         api_availability.unsuspend();
@@ -1247,14 +1449,18 @@ impl Daemon {
             LocationEvent(location_data) => self.handle_location_event(location_data),
             SettingsChanged => {
                 self.update_feature_indicators_on_settings_changed();
+                #[cfg(not(target_os = "android"))]
+                self.update_relay_latency_prober();
             }
-            #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
             ExcludedPathsEvent(update, tx) => self.handle_new_excluded_paths(update, tx).await,
             LeakDetected(leak_info) => {
                 log::warn!("Network leak detected! Please contact Mullvad support.");
                 log::warn!("{leak_info:?}");
                 self.handle_leak_event(leak_info)
             }
+            #[cfg(target_os = "linux")]
+            NetworkChanged(network) => self.handle_network_change(network).await,
+            RelayRotationDue(policy) => self.handle_relay_rotation_due(policy),
         }
         should_stop
     }
@@ -1288,6 +1494,7 @@ impl Daemon {
                     endpoint,
                     location: self.parameters_generator.get_last_location().await,
                     feature_indicators,
+                    rotation: self.pending_rotation.take(),
                 }
             }
             TunnelStateTransition::Connected(endpoint) => {
@@ -1315,6 +1522,12 @@ impl Daemon {
             self.unschedule_reconnect();
         }
 
+        if !tunnel_state.is_connected() {
+            self.unschedule_relay_rotation();
+        } else if !self.tunnel_state.is_connected() {
+            self.schedule_relay_rotation();
+        }
+
         if self.tunnel_state.is_disconnected() && !tunnel_state.is_disconnected() {
             // Enable background API requests when leaving the disconnected state.
             self.api_handle.availability.resume_background();
@@ -1325,6 +1538,12 @@ impl Daemon {
         match tunnel_state {
             TunnelState::Disconnected { .. } => {
                 self.api_handle.availability.reset_inactivity_timer();
+                self.pending_rotation = None;
+            }
+            TunnelState::Error(_) => {
+                self.api_handle.availability.stop_inactivity_timer();
+                // The rotation was not carried out, so don't report it after recovering
+                self.pending_rotation = None;
             }
             _ => {
                 self.api_handle.availability.stop_inactivity_timer();
@@ -1357,11 +1576,56 @@ impl Daemon {
             _ => {}
         }
 
+        self.update_relay_selection_outcome(&tunnel_state);
+        self.metrics.on_tunnel_state(&tunnel_state);
+        self.session_log.on_tunnel_state(&tunnel_state);
         self.tunnel_state = tunnel_state.clone();
         self.management_interface
             .notifier()
             .notify_new_state(tunnel_state);
         self.fetch_am_i_mullvad();
+        #[cfg(not(target_os = "android"))]
+        self.update_relay_latency_prober();
+    }
+
+    /// Record how the connection attempt to the most recently selected relay ended.
+    fn update_relay_selection_outcome(&self, tunnel_state: &TunnelState) {
+        // Custom endpoints are not chosen by the relay selector
+        if !matches!(self.settings.relay_settings, RelaySettings::Normal(_)) {
+            return;
+        }
+        let outcome = match tunnel_state {
+            TunnelState::Connected { .. } => SelectionOutcome::Connected,
+            TunnelState::Error(error_state) => {
+                SelectionOutcome::Failed(error_state.cause().to_string())
+            }
+            TunnelState::Disconnected { .. } => SelectionOutcome::Cancelled,
+            TunnelState::Connecting { .. } | TunnelState::Disconnecting(_) => return,
+        };
+        self.relay_selector.set_selection_outcome(outcome);
+    }
+
+    /// Measure relay latencies only if the fastest relay is requested, and only while relays can
+    /// be reached: either through the tunnel, or while disconnected without the firewall
+    /// blocking traffic.
+    #[cfg(not(target_os = "android"))]
+    fn update_relay_latency_prober(&self) {
+        let wants_fastest = matches!(
+            self.settings.relay_settings,
+            RelaySettings::Normal(RelayConstraints {
+                strategy: Constraint::Only(
+                    mullvad_types::relay_constraints::RelayStrategy::Fastest
+                ),
+                ..
+            })
+        );
+        let can_reach_relays = match self.tunnel_state {
+            TunnelState::Connected { .. } => true,
+            TunnelState::Disconnected { locked_down, .. } => !locked_down,
+            _ => false,
+        };
+        self.relay_latency_prober
+            .set_enabled(wants_fastest && can_reach_relays);
     }
 
     /// Get the geographical location from am.i.mullvad.net. When it arrives,
@@ -1417,7 +1681,15 @@ impl Daemon {
                 ref mut location,
                 #[cfg(not(target_os = "android"))]
                     locked_down: _,
-            } => *location = Some(fetched_location),
+            } => {
+                if !fetched_location.mullvad_exit_ip {
+                    match Coordinates::new(fetched_location.latitude, fetched_location.longitude) {
+                        Ok(user_location) => self.relay_selector.set_user_location(user_location),
+                        Err(error) => log::warn!("Ignoring location of this device: {error}"),
+                    }
+                }
+                *location = Some(fetched_location)
+            }
             TunnelState::Connected {
                 ref mut location, ..
             } => {
@@ -1518,6 +1790,46 @@ impl Daemon {
         }
     }
 
+    /// Schedule a rotation of the exit relay, if there is a rotation policy. Any previously
+    /// scheduled rotation is cancelled.
+    fn schedule_relay_rotation(&mut self) {
+        self.unschedule_relay_rotation();
+        // Custom endpoints are not chosen by the relay selector
+        if !matches!(self.settings.relay_settings, RelaySettings::Normal(_)) {
+            return;
+        }
+        if let Some(policy) = self.settings.tunnel_options.relay_rotation.clone() {
+            self.relay_rotation_job = Some(relay_rotation::schedule(
+                policy,
+                self.commands(),
+                self.tx.to_specialized_sender(),
+            ));
+        }
+    }
+
+    fn unschedule_relay_rotation(&mut self) {
+        if let Some(job) = self.relay_rotation_job.take() {
+            job.abort();
+        }
+    }
+
+    /// Reconnect to a different exit relay than the current one.
+    fn handle_relay_rotation_due(&mut self, policy: RotationPolicy) {
+        self.relay_rotation_job = None;
+        if !self.tunnel_state.is_connected() {
+            return;
+        }
+        let Some(previous_exit) = self.relay_selector.rotate_exit() else {
+            return;
+        };
+        log::info!("Rotating away from exit relay {previous_exit} ({policy})");
+        self.pending_rotation = Some(Rotation {
+            previous_exit,
+            policy,
+        });
+        self.reconnect_tunnel();
+    }
+
     async fn handle_command(&mut self, command: DaemonCommand) {
         use self::DaemonCommand::*;
         if self.tunnel_state.is_disconnected() {
@@ -1528,11 +1840,18 @@ impl Daemon {
             SetTargetState(tx, state) => self.on_set_target_state(tx, state).await,
             Reconnect(tx) => self.on_reconnect(tx),
             GetState(tx) => self.on_get_state(tx),
+            GetTunnelStats(tx) => self.on_get_tunnel_stats(tx),
+            RunLeakCheck(tx) => self.on_run_leak_check(tx),
+            GetLeakCheckReport(tx) => self.on_get_leak_check_report(tx),
+            #[cfg(target_os = "macos")]
+            RunDnsLeakTest(tx) => self.on_run_dns_leak_test(tx),
             CreateNewAccount(tx) => self.on_create_new_account(tx),
             GetAccountData(tx, account_number) => self.on_get_account_data(tx, account_number),
             GetWwwAuthToken(tx) => self.on_get_www_auth_token(tx).await,
             SubmitVoucher(tx, voucher) => self.on_submit_voucher(tx, voucher),
             GetRelayLocations(tx) => self.on_get_relay_locations(tx),
+            GetRelayLatencies(tx) => self.on_get_relay_latencies(tx),
+            ExportWireguardConfig(tx) => self.on_export_wireguard_config(tx).await,
             UpdateRelayLocations => self.on_update_relay_locations().await,
             UpdateDefaultLocationCountry(tx) => self.on_update_default_location(tx).await,
             LoginAccount(tx, account_number) => self.on_login_account(tx, account_number),
@@ -1548,11 +1867,18 @@ impl Daemon {
             SetRelaySettings(tx, update) => self.on_set_relay_settings(tx, update).await,
             SetAllowLan(tx, allow_lan) => self.on_set_allow_lan(tx, allow_lan).await,
             SetShowBetaReleases(tx, enabled) => self.on_set_show_beta_releases(tx, enabled).await,
+            SetSessionLog(tx, enabled) => self.on_set_session_log(tx, enabled).await,
+            GetSessionLog(tx, since) => self.on_get_session_log(tx, since),
             #[cfg(not(target_os = "android"))]
             SetLockdownMode(tx, lockdown_mode) => {
                 self.on_set_lockdown_mode(tx, lockdown_mode).await
             }
             SetAutoConnect(tx, auto_connect) => self.on_set_auto_connect(tx, auto_connect).await,
+            SetTrustedNetworks(tx, trusted_networks) => {
+                self.on_set_trusted_networks(tx, trusted_networks).await
+            }
+            #[cfg(target_os = "linux")]
+            GetCurrentNetwork(tx) => self.on_get_current_network(tx),
             SetEnableIpv6(tx, enable_ipv6) => self.on_set_enable_ipv6(tx, enable_ipv6).await,
             SetUserspaceWireguard(tx, userspace) => {
                 self.on_set_userspace_wireguard(tx, userspace).await
@@ -1570,6 +1896,10 @@ impl Daemon {
                 self.on_set_relay_override(tx, relay_override).await
             }
             ClearAllRelayOverrides(tx) => self.on_clear_all_relay_overrides(tx).await,
+            SetRelayExclusions(tx, exclusions) => {
+                self.on_set_relay_exclusions(tx, exclusions).await
+            }
+            SetRelayRotation(tx, policy) => self.on_set_relay_rotation(tx, policy).await,
             SetWireguardMtu(tx, mtu) => self.on_set_wireguard_mtu(tx, mtu).await,
             SetWireguardAllowedIps(tx, allowed_ips) => {
                 self.on_set_wireguard_allowed_ips(tx, allowed_ips).await
@@ -1581,12 +1911,24 @@ impl Daemon {
             ResetSettings(tx, preserved) => self.on_reset_settings(tx, preserved).await,
             RotateWireguardKey(tx) => self.on_rotate_wireguard_key(tx),
             GetWireguardKey(tx) => self.on_get_wireguard_key(tx).await,
-            CreateCustomList(tx, name, locations) => {
-                self.on_create_custom_list(tx, name, locations).await
+            CreateCustomList(tx, name, locations, filter) => {
+                self.on_create_custom_list(tx, name, locations, filter)
+                    .await
             }
             DeleteCustomList(tx, id) => self.on_delete_custom_list(tx, id).await,
             UpdateCustomList(tx, update) => self.on_update_custom_list(tx, update).await,
             ClearCustomLists(tx) => self.on_clear_custom_lists(tx).await,
+            ImportCustomLists(tx, lists, mode) => {
+                self.on_import_custom_lists(tx, lists, mode).await
+            }
+            AddCustomEndpoint(tx, endpoint) => self.on_add_custom_endpoint(tx, endpoint).await,
+            UpdateCustomEndpoint(tx, endpoint) => {
+                self.on_update_custom_endpoint(tx, endpoint).await
+            }
+            RemoveCustomEndpoint(tx, name) => self.on_remove_custom_endpoint(tx, name).await,
+            MoveCustomEndpoint(tx, name, position) => {
+                self.on_move_custom_endpoint(tx, name, position).await
+            }
             GetVersionInfo(tx) => self.on_get_version_info(tx),
             AddApiAccessMethod(tx, name, enabled, access_method) => {
                 self.on_add_access_method(tx, name, enabled, access_method)
@@ -1614,14 +1956,12 @@ impl Daemon {
             RemoveSplitTunnelProcess(tx, pid) => self.on_remove_split_tunnel_process(tx, pid),
             #[cfg(target_os = "linux")]
             ClearSplitTunnelProcesses(tx) => self.on_clear_split_tunnel_processes(tx),
-            #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
             AddSplitTunnelApp(tx, app) => self.on_add_split_tunnel_app(tx, app),
-            #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
             RemoveSplitTunnelApp(tx, path) => self.on_remove_split_tunnel_app(tx, path),
-            #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
             ClearSplitTunnelApps(tx) => self.on_clear_split_tunnel_apps(tx),
-            #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
             SetSplitTunnelState(tx, enabled) => self.on_set_split_tunnel_state(tx, enabled),
+            #[cfg(target_os = "linux")]
+            SetSplitTunnelMode(tx, mode) => self.on_set_split_tunnel_mode(tx, mode).await,
             #[cfg(target_os = "windows")]
             GetSplitTunnelProcesses(tx) => self.on_get_split_tunnel_processes(tx),
             #[cfg(target_os = "windows")]
@@ -1774,6 +2114,7 @@ impl Daemon {
                 connection_mode,
                 ..
             } => {
+                self.metrics.on_access_method(&setting.name);
                 self.save_connection_mode_to_cache(connection_mode.clone());
                 // On android mullvad-api invokes protect on a socket to send requests
                 // outside the tunnel
@@ -1803,6 +2144,7 @@ impl Daemon {
                 connection_mode,
                 endpoint,
             } => {
+                self.metrics.on_access_method(&setting.name);
                 self.save_connection_mode_to_cache(connection_mode.clone());
                 // Update the firewall to exempt a new API endpoint.
                 let (completion_tx, completion_rx) = oneshot::channel();
@@ -1863,7 +2205,6 @@ impl Daemon {
         });
     }
 
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     async fn handle_new_excluded_paths(
         &mut self,
         update: ExcludedPathsUpdate,
@@ -1906,6 +2247,17 @@ impl Daemon {
                 .await
                 .map_err(Error::SettingsError),
         };
+        #[cfg(target_os = "linux")]
+        let save_result = save_result.and_then(|changed| {
+            self.apply_split_tunnel_paths().map_err(|error| {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to set excluded apps list")
+                );
+                Error::SplitTunnelError(error)
+            })?;
+            Ok(changed)
+        });
         let _ = tx.send(save_result.map(|_| ()));
     }
 
@@ -1932,12 +2284,45 @@ impl Daemon {
         Self::oneshot_send(tx, self.tunnel_state.clone(), "current state");
     }
 
+    fn on_get_tunnel_stats(&self, tx: oneshot::Sender<Option<Vec<PeerStats>>>) {
+        let stats = self.tunnel_state_machine_handle.tunnel_stats();
+        tokio::spawn(async move {
+            Self::oneshot_send(tx, stats.await, "tunnel stats");
+        });
+    }
+
+    fn on_run_leak_check(&mut self, tx: oneshot::Sender<Option<LeakCheckReport>>) {
+        let report = self.leak_checker.run_check();
+        tokio::spawn(async move {
+            Self::oneshot_send(tx, report.await, "leak check report");
+        });
+    }
+
+    fn on_get_leak_check_report(&mut self, tx: oneshot::Sender<Option<LeakCheckReport>>) {
+        let report = self.leak_checker.last_report();
+        tokio::spawn(async move {
+            Self::oneshot_send(tx, report.await, "last leak check report");
+        });
+    }
+
+    #[cfg(target_os = "macos")]
+    fn on_run_dns_leak_test(
+        &mut self,
+        tx: oneshot::Sender<Result<DnsLeakTestReport, DnsLeakTestError>>,
+    ) {
+        self.send_tunnel_command(TunnelCommand::DnsLeakTest(tx));
+    }
+
     fn on_is_performing_post_upgrade(&self, tx: oneshot::Sender<bool>) {
         let performing_post_upgrade = !self.migration_complete.is_complete();
         Self::oneshot_send(tx, performing_post_upgrade, "performing post upgrade");
     }
 
     fn on_create_new_account(&mut self, tx: ResponseTx<String, Error>) {
+        if let Err(error) = self.check_account_unlocked() {
+            Self::oneshot_send(tx, Err(error), "create new account");
+            return;
+        }
         let account_manager = self.account_manager.clone();
         tokio::spawn(async move {
             let result = async {
@@ -2022,6 +2407,33 @@ impl Daemon {
         Self::oneshot_send(tx, self.relay_selector.get_relays(), "relay locations");
     }
 
+    fn on_get_relay_latencies(&mut self, tx: oneshot::Sender<HashMap<String, Duration>>) {
+        Self::oneshot_send(tx, self.relay_selector.get_latencies(), "relay latencies");
+    }
+
+    /// Export the configuration of the current tunnel, if it is connected. Note that the
+    /// configuration of a tunnel to a Mullvad relay contains the private key of the device.
+    async fn on_export_wireguard_config(&mut self, tx: oneshot::Sender<Option<String>>) {
+        let config = if self.tunnel_state.is_connected() {
+            self.parameters_generator.last_connection_config().await
+        } else {
+            None
+        };
+        let config = config.and_then(|config| {
+            let dns = dns::config_from_settings(&self.settings)
+                .resolve(
+                    &[config.ipv4_gateway.into()],
+                    #[cfg(target_os = "macos")]
+                    53,
+                )
+                .addresses()
+                .collect();
+            WgQuickConfig::export(&config, self.settings.settings(), dns)
+                .map(|config| config.to_string())
+        });
+        Self::oneshot_send(tx, config, "exported WireGuard config");
+    }
+
     fn on_get_bridges(&mut self, tx: oneshot::Sender<BridgeList>) {
         Self::oneshot_send(tx, self.relay_selector.get_bridges(), "bridges");
     }
@@ -2065,6 +2477,10 @@ impl Daemon {
     }
 
     fn on_login_account(&mut self, tx: ResponseTx<(), Error>, account_number: String) {
+        if let Err(error) = self.check_account_unlocked() {
+            Self::oneshot_send(tx, Err(error), "login_account response");
+            return;
+        }
         let account_manager = self.account_manager.clone();
         let availability = self.api_runtime.availability_handle();
 
@@ -2087,6 +2503,10 @@ impl Daemon {
     }
 
     fn on_logout_account(&mut self, tx: ResponseTx<(), Error>) {
+        if let Err(error) = self.check_account_unlocked() {
+            Self::oneshot_send(tx, Err(error), "logout_account response");
+            return;
+        }
         let account_manager = self.account_manager.clone();
         tokio::spawn(async move {
             let result = async {
@@ -2099,8 +2519,20 @@ impl Daemon {
         });
     }
 
+    /// Fail if the administrator policy forbids logging in, logging out and creating accounts.
+    fn check_account_unlocked(&self) -> Result<(), Error> {
+        match self.settings.policy() {
+            Some(policy) if policy.lock_account() => Err(Error::AccountLocked),
+            _ => Ok(()),
+        }
+    }
+
     #[cfg(target_os = "android")]
     fn on_delete_account(&mut self, tx: ResponseTx<(), Error>) {
+        if let Err(error) = self.check_account_unlocked() {
+            Self::oneshot_send(tx, Err(error), "delete_account response");
+            return;
+        }
         let account_manager = self.account_manager.clone();
         tokio::spawn(async move {
             let result = account_manager.delete().await.map_err(|error| {
@@ -2161,6 +2593,11 @@ impl Daemon {
         account_number: AccountNumber,
         device_id: DeviceId,
     ) {
+        // This may remove the current device, which would log out
+        if let Err(error) = self.check_account_unlocked() {
+            Self::oneshot_send(tx, Err(error), "remove_device response");
+            return;
+        }
         let device_service = self.account_manager.device_service.clone();
         let notifier = self.management_interface.notifier().clone();
 
@@ -2233,6 +2670,10 @@ impl Daemon {
 
     #[cfg(not(target_os = "android"))]
     async fn on_factory_reset(&mut self, tx: ResponseTx<(), Error>) {
+        if let Err(error) = self.check_account_unlocked() {
+            Self::oneshot_send(tx, Err(error), "factory_reset response");
+            return;
+        }
         let mut last_error = None;
 
         if let Err(error) = self.account_manager.logout().await {
@@ -2395,6 +2836,40 @@ impl Daemon {
         }
     }
 
+    /// Update the split app paths in both the settings and the exclusion cgroup.
+    ///
+    /// The settings are persisted first. The exclusion cgroup is updated once that has succeeded,
+    /// in [Self::handle_new_excluded_paths].
+    #[cfg(target_os = "linux")]
+    fn set_split_tunnel_paths(&mut self, tx: ResponseTx<(), Error>, update: ExcludedPathsUpdate) {
+        let _ = self
+            .tx
+            .send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
+    }
+
+    /// Split processes running any of the split tunnel apps in the current settings, or none if
+    /// split tunneling is disabled, and update the split tunneling mode to match.
+    #[cfg(target_os = "linux")]
+    fn apply_split_tunnel_paths(&mut self) -> Result<(), split_tunnel::Error> {
+        let split_tunnel = &self.settings.split_tunnel;
+        let paths = if split_tunnel.enable_exclusions {
+            split_tunnel
+                .apps
+                .iter()
+                .map(|app| app.as_path().to_path_buf())
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        let mode = split_tunnel.effective_mode();
+        let result = self.exclude_pids.set_excluded_paths(paths);
+
+        let (tx, _rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::SplitTunnelMode(mode, tx));
+
+        result
+    }
+
     /// Update the split app paths in both the settings and tunnel
     #[cfg(target_os = "macos")]
     fn set_split_tunnel_paths(
@@ -2445,7 +2920,6 @@ impl Daemon {
         });
     }
 
-    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "android"))]
     fn on_add_split_tunnel_app(&mut self, tx: ResponseTx<(), Error>, app: SplitApp) {
         let settings = self.settings.to_settings();
 
@@ -2455,6 +2929,9 @@ impl Daemon {
             apps
         };
 
+        #[cfg(target_os = "linux")]
+        self.set_split_tunnel_paths(tx, ExcludedPathsUpdate::SetPaths(excluded_apps));
+        #[cfg(not(target_os = "linux"))]
         self.set_split_tunnel_paths(
             tx,
             "add_split_tunnel_app response",
@@ -2463,7 +2940,6 @@ impl Daemon {
         );
     }
 
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     fn on_remove_split_tunnel_app(&mut self, tx: ResponseTx<(), Error>, app: impl Into<SplitApp>) {
         let settings = self.settings.to_settings();
 
@@ -2473,6 +2949,9 @@ impl Daemon {
             apps
         };
 
+        #[cfg(target_os = "linux")]
+        self.set_split_tunnel_paths(tx, ExcludedPathsUpdate::SetPaths(excluded_apps));
+        #[cfg(not(target_os = "linux"))]
         self.set_split_tunnel_paths(
             tx,
             "remove_split_tunnel_app response",
@@ -2481,27 +2960,65 @@ impl Daemon {
         );
     }
 
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     fn on_clear_split_tunnel_apps(&mut self, tx: ResponseTx<(), Error>) {
-        let settings = self.settings.to_settings();
         let new_list = HashSet::new();
-        self.set_split_tunnel_paths(
-            tx,
-            "clear_split_tunnel_apps response",
-            settings,
-            ExcludedPathsUpdate::SetPaths(new_list),
-        );
+        #[cfg(target_os = "linux")]
+        self.set_split_tunnel_paths(tx, ExcludedPathsUpdate::SetPaths(new_list));
+        #[cfg(not(target_os = "linux"))]
+        {
+            let settings = self.settings.to_settings();
+            self.set_split_tunnel_paths(
+                tx,
+                "clear_split_tunnel_apps response",
+                settings,
+                ExcludedPathsUpdate::SetPaths(new_list),
+            );
+        }
     }
 
-    #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
     fn on_set_split_tunnel_state(&mut self, tx: ResponseTx<(), Error>, state: bool) {
-        let settings = self.settings.to_settings();
-        self.set_split_tunnel_paths(
-            tx,
-            "set_split_tunnel_state response",
-            settings,
-            ExcludedPathsUpdate::SetState(state),
-        );
+        #[cfg(target_os = "linux")]
+        self.set_split_tunnel_paths(tx, ExcludedPathsUpdate::SetState(state));
+        #[cfg(not(target_os = "linux"))]
+        {
+            let settings = self.settings.to_settings();
+            self.set_split_tunnel_paths(
+                tx,
+                "set_split_tunnel_state response",
+                settings,
+                ExcludedPathsUpdate::SetState(state),
+            );
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn on_set_split_tunnel_mode(&mut self, tx: ResponseTx<(), Error>, mode: SplitTunnelMode) {
+        match self
+            .settings
+            .update(move |settings| settings.split_tunnel.mode = mode)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::SplitTunnelMode(
+                        self.settings.split_tunnel.effective_mode(),
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_split_tunnel_mode response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_split_tunnel_mode response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_split_tunnel_mode response",
+                );
+            }
+        }
     }
 
     #[cfg(target_os = "windows")]
@@ -2538,6 +3055,10 @@ impl Daemon {
             Ok(settings_changed) => {
                 Self::oneshot_send(tx, Ok(()), "set_relay_settings response");
                 if settings_changed {
+                    // A custom relay may come with its own DNS servers
+                    let (dns_tx, _dns_rx) = oneshot::channel();
+                    let dns = dns::config_from_settings(&self.settings);
+                    self.send_tunnel_command(TunnelCommand::Dns(dns, dns_tx));
                     log::info!("Initiating tunnel restart because the relay settings changed");
                     self.reconnect_tunnel();
                 }
@@ -2615,6 +3136,35 @@ impl Daemon {
         }
     }
 
+    async fn on_set_session_log(&mut self, tx: ResponseTx<(), settings::Error>, enabled: bool) {
+        match self
+            .settings
+            .update(move |settings| settings.session_log = enabled)
+            .await
+        {
+            Ok(_) => {
+                self.session_log.set_enabled(enabled);
+                Self::oneshot_send(tx, Ok(()), "set_session_log response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_session_log response");
+            }
+        }
+    }
+
+    fn on_get_session_log(
+        &self,
+        tx: ResponseTx<Vec<SessionRecord>, Error>,
+        since: Option<DateTime<Utc>>,
+    ) {
+        let sessions = self.session_log.read(since);
+        tokio::spawn(async move {
+            let result = sessions.await.map_err(Error::SessionLog);
+            Self::oneshot_send(tx, result, "get_session_log response");
+        });
+    }
+
     #[cfg(not(target_os = "android"))]
     async fn on_set_lockdown_mode(
         &mut self,
@@ -2665,6 +3215,59 @@ impl Daemon {
         }
     }
 
+    async fn on_set_trusted_networks(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        trusted_networks: TrustedNetworks,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.trusted_networks = trusted_networks)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set trusted networks response");
+                if settings_changed {
+                    // Re-evaluate the current network against the new rules
+                    self.trusted_network.reset();
+                    self.apply_trusted_networks().await;
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set trusted networks response");
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn on_get_current_network(&self, tx: oneshot::Sender<NetworkInfo>) {
+        Self::oneshot_send(tx, self.current_network.clone(), "current network");
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn handle_network_change(&mut self, network: NetworkInfo) {
+        log::debug!("Current network: {network}");
+        self.current_network = network;
+        self.apply_trusted_networks().await;
+    }
+
+    /// Connect or disconnect if the host moved between trusted and untrusted networks.
+    async fn apply_trusted_networks(&mut self) {
+        #[cfg(not(target_os = "android"))]
+        let lockdown_mode = self.settings.lockdown_mode;
+        #[cfg(target_os = "android")]
+        let lockdown_mode = false;
+
+        if let Some(target_state) = self.trusted_network.evaluate(
+            &self.settings.trusted_networks,
+            &self.current_network,
+            lockdown_mode,
+        ) {
+            self.set_target_state(target_state).await;
+        }
+    }
+
     async fn on_set_obfuscation_settings(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
@@ -2672,7 +3275,11 @@ impl Daemon {
     ) {
         match self
             .settings
-            .update(move |settings| settings.obfuscation_settings = new_settings)
+            .try_update(move |settings| {
+                new_settings.custom_bridge.validate()?;
+                settings.obfuscation_settings = new_settings;
+                Ok::<_, InvalidCustomBridge>(())
+            })
             .await
         {
             Ok(settings_changed) => {
@@ -2803,7 +3410,10 @@ impl Daemon {
         match result {
             Ok(settings_changed) => {
                 Self::oneshot_send(tx, Ok(()), "set_daita_enabled response");
-                if let RelaySettings::CustomTunnelEndpoint(_) = &self.settings.relay_settings {
+                if matches!(
+                    self.settings.relay_settings,
+                    RelaySettings::CustomTunnelEndpoint(_) | RelaySettings::SavedCustomEndpoints
+                ) {
                     return; // DAITA is not supported for custom relays
                 }
 
@@ -2826,14 +3436,17 @@ impl Daemon {
     ) {
         match self
             .settings
-            .update(move |settings| settings.tunnel_options.dns_options = dns_options)
+            .try_update(move |settings| {
+                dns::check_supported(&dns_options)?;
+                settings.tunnel_options.dns_options = dns_options;
+                Ok::<_, dns::Error>(())
+            })
             .await
         {
             Ok(settings_changed) => {
                 if settings_changed {
                     let settings = self.settings.settings();
-                    let resolvers =
-                        dns::addresses_from_options(&settings.tunnel_options.dns_options);
+                    let resolvers = dns::config_from_settings(settings);
                     self.send_tunnel_command(TunnelCommand::Dns(
                         resolvers,
                         oneshot_map(tx, |tx, ()| {
@@ -2893,6 +3506,58 @@ impl Daemon {
         }
     }
 
+    async fn on_set_relay_exclusions(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        exclusions: RelayExclusions,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.relay_exclusions = exclusions)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_relay_exclusions response");
+                if settings_changed {
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_relay_exclusions response");
+            }
+        }
+    }
+
+    async fn on_set_relay_rotation(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        policy: Option<RotationPolicy>,
+    ) {
+        match self
+            .settings
+            .try_update(move |settings| {
+                if let Some(policy) = &policy {
+                    policy.validate()?;
+                }
+                settings.tunnel_options.relay_rotation = policy;
+                Ok::<_, InvalidRotationPolicy>(())
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_relay_rotation response");
+                if settings_changed && self.tunnel_state.is_connected() {
+                    self.schedule_relay_rotation();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_relay_rotation response");
+            }
+        }
+    }
+
     async fn on_set_wireguard_mtu(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
@@ -3005,8 +3670,9 @@ impl Daemon {
         tx: ResponseTx<mullvad_types::custom_list::Id, Error>,
         name: String,
         locations: BTreeSet<GeographicLocationConstraint>,
+        filter: Option<mullvad_types::custom_list::CustomListFilter>,
     ) {
-        let result = self.create_custom_list(name, locations).await;
+        let result = self.create_custom_list(name, locations, filter).await;
         Self::oneshot_send(tx, result, "create_custom_list response");
     }
 
@@ -3029,6 +3695,49 @@ impl Daemon {
         Self::oneshot_send(tx, result, "clear_custom_lists response");
     }
 
+    async fn on_import_custom_lists(
+        &mut self,
+        tx: ResponseTx<mullvad_types::custom_list::ImportSummary, Error>,
+        lists: Vec<mullvad_types::custom_list::ExportedCustomList>,
+        mode: mullvad_types::custom_list::ImportMode,
+    ) {
+        let result = self.import_custom_lists(lists, mode).await;
+        Self::oneshot_send(tx, result, "import_custom_lists response");
+    }
+
+    async fn on_add_custom_endpoint(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        endpoint: mullvad_types::custom_endpoint::SavedCustomEndpoint,
+    ) {
+        let result = self.add_custom_endpoint(endpoint).await;
+        Self::oneshot_send(tx, result, "add_custom_endpoint response");
+    }
+
+    async fn on_update_custom_endpoint(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        endpoint: mullvad_types::custom_endpoint::SavedCustomEndpoint,
+    ) {
+        let result = self.update_custom_endpoint(endpoint).await;
+        Self::oneshot_send(tx, result, "update_custom_endpoint response");
+    }
+
+    async fn on_remove_custom_endpoint(&mut self, tx: ResponseTx<(), Error>, name: String) {
+        let result = self.remove_custom_endpoint(name).await;
+        Self::oneshot_send(tx, result, "remove_custom_endpoint response");
+    }
+
+    async fn on_move_custom_endpoint(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        name: String,
+        position: usize,
+    ) {
+        let result = self.move_custom_endpoint(name, position).await;
+        Self::oneshot_send(tx, result, "move_custom_endpoint response");
+    }
+
     async fn on_add_access_method(
         &mut self,
         tx: ResponseTx<mullvad_types::access_method::Id, Error>,
@@ -3216,6 +3925,13 @@ impl Daemon {
             let (tx, _rx) = oneshot::channel();
             self.send_tunnel_command(TunnelCommand::SetExcludedApps(tx, vec![]));
         }
+        #[cfg(target_os = "linux")]
+        if let Err(error) = self.apply_split_tunnel_paths() {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to reset split tunnel paths")
+            );
+        }
 
         #[cfg(not(target_os = "android"))]
         {
@@ -3230,9 +3946,11 @@ impl Daemon {
         self.send_tunnel_command(TunnelCommand::AllowLan(self.settings.allow_lan, tx));
 
         let (tx, _rx) = oneshot::channel();
-        let dns = dns::addresses_from_options(&self.settings.tunnel_options.dns_options);
+        let dns = dns::config_from_settings(&self.settings);
         self.send_tunnel_command(TunnelCommand::Dns(dns, tx));
 
+        self.session_log.set_enabled(self.settings.session_log);
+
         let version_handle = self.version_handle.clone();
         let show_beta_releases = self.settings.show_beta_releases;
         tokio::spawn(async move {
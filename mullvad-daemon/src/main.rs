@@ -247,6 +247,7 @@ async fn create_daemon(
         cache_dir,
         rpc_socket_path,
         endpoint: mullvad_api::ApiEndpoint::from_env_vars(),
+        metrics_endpoint: cli::get_config().metrics_endpoint.clone(),
         log_handle,
     };
     Daemon::start(config, DaemonCommandChannel::new())
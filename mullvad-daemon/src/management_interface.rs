@@ -1,7 +1,8 @@
 use crate::{
     DaemonCommand, DaemonCommandSender, account_history, device,
-    migrations::multihop::scenario::Scenario,
+    migrations::multihop::scenario::Scenario, session_log, settings::policy::AdminPolicy,
 };
+use chrono::DateTime;
 use futures::{
     StreamExt,
     channel::{mpsc, oneshot},
@@ -10,20 +11,27 @@ use mullvad_api::{StatusCode, rest::Error as RestError};
 use mullvad_daemon_relay_selector::relay_selector::{
     RelaySelectorIO, grpc_service::RelaySelectorServer,
 };
+#[cfg(unix)]
+use mullvad_management_interface::authorization::{self, AccessPolicy};
 use mullvad_management_interface::types::FromProtobufTypeError;
 use mullvad_management_interface::{
     Code, Request, Response, ServerJoinHandle, Status,
     types::{self, daemon_event, management_service_server::ManagementService},
 };
 use mullvad_types::relay_constraints::GeographicLocationConstraint;
+#[cfg(target_os = "linux")]
+use mullvad_types::settings::SplitTunnelMode;
 use mullvad_types::{
     account::AccountNumber,
+    custom_list::{CustomListFilter, ExportedCustomList, ImportMode},
     relay_constraints::{
-        ObfuscationSettings, RelayOverride, RelaySettings, allowed_ip::AllowedIps,
+        ObfuscationSettings, RelayExclusions, RelayOverride, RelaySettings, allowed_ip::AllowedIps,
     },
     relay_list::RelayList,
-    settings::{DnsOptions, Settings, SettingsKeyList},
+    relay_rotation::RotationPolicy,
+    settings::{DnsOptions, Settings, SettingsKeyList, TrustedNetworks},
     states::{TargetState, TunnelState},
+    tunnel_stats::TunnelStats,
     version,
     wireguard::{RotationInterval, RotationIntervalError},
 };
@@ -32,13 +40,15 @@ use std::{
     path::PathBuf,
     str::FromStr,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
-use talpid_types::ErrorExt;
+use talpid_types::{ErrorExt, net::wireguard::PeerStats};
 use tokio::time::timeout;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 const RPC_SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+/// How often tunnel traffic statistics are sent to `TunnelStatsListen` subscribers.
+const TUNNEL_STATS_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -54,6 +64,7 @@ struct ManagementServiceImpl {
     subscriptions: Arc<Mutex<Vec<EventsListenerSender>>>,
     pub app_upgrade_broadcast: AppUpgradeBroadcast,
     log_reload_handle: crate::logging::LogHandle,
+    locked_settings: Option<types::LockedSettings>,
 }
 
 pub type ServiceResult<T> = std::result::Result<Response<T>, Status>;
@@ -72,6 +83,7 @@ impl ManagementService for ManagementServiceImpl {
     type EventsListenStream = EventsListenerReceiver;
     type AppUpgradeEventsListenStream = AppUpgradeEventListenerReceiver;
     type LogListenStream = UnboundedReceiverStream<Result<types::LogMessage, Status>>;
+    type TunnelStatsListenStream = UnboundedReceiverStream<Result<types::TunnelStats, Status>>;
 
     // Control and get the tunnel state
     //
@@ -111,12 +123,83 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(types::TunnelState::from(state)))
     }
 
+    async fn tunnel_stats_listen(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<Self::TunnelStatsListenStream> {
+        log::debug!("tunnel_stats_listen");
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let daemon_tx = self.daemon_tx.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(TUNNEL_STATS_INTERVAL);
+            let mut previous: Option<(Instant, Vec<PeerStats>)> = None;
+
+            while !tx.is_closed() {
+                interval.tick().await;
+
+                let (stats_tx, stats_rx) = oneshot::channel();
+                if daemon_tx
+                    .send(DaemonCommand::GetTunnelStats(stats_tx))
+                    .is_err()
+                {
+                    break;
+                }
+                let Ok(current) = stats_rx.await else {
+                    break;
+                };
+                let now = Instant::now();
+                let current = current.unwrap_or_default();
+
+                let (elapsed, previous_peers) = match &previous {
+                    Some((sampled_at, peers)) => (now.duration_since(*sampled_at), &peers[..]),
+                    None => (Duration::ZERO, &[][..]),
+                };
+                let stats = TunnelStats::from_samples(
+                    current.clone(),
+                    previous_peers,
+                    elapsed,
+                    SystemTime::now(),
+                );
+                previous = Some((now, current));
+
+                let stats = types::TunnelStats::try_from(stats).map_err(Status::from);
+                if tx.send(stats).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
     // Control the daemon and receive events
     //
 
-    async fn events_listen(&self, _: Request<()>) -> ServiceResult<Self::EventsListenStream> {
+    async fn events_listen(
+        &self,
+        #[cfg_attr(not(unix), expect(unused_variables))] request: Request<()>,
+    ) -> ServiceResult<Self::EventsListenStream> {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
+        #[cfg(unix)]
+        if authorization::is_unprivileged(&request) {
+            // Forward the events through a task that redacts them
+            let (redacted_tx, mut redacted_rx) = tokio::sync::mpsc::unbounded_channel();
+            self.subscriptions.lock().unwrap().push(redacted_tx);
+            tokio::spawn(async move {
+                while let Some(mut event) = redacted_rx.recv().await {
+                    if let Ok(event) = &mut event {
+                        authorization::redact_event(event);
+                    }
+                    if tx.send(event).is_err() {
+                        break;
+                    }
+                }
+            });
+            return Ok(Response::new(UnboundedReceiverStream::new(rx)));
+        }
+
         let mut subscriptions = self.subscriptions.lock().unwrap();
         subscriptions.push(tx);
 
@@ -216,6 +299,29 @@ impl ManagementService for ManagementServiceImpl {
             .map(|relays| Response::new(types::RelayList::from(relays)))
     }
 
+    async fn export_wireguard_config(&self, _: Request<()>) -> ServiceResult<String> {
+        log::debug!("export_wireguard_config");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ExportWireguardConfig(tx))?;
+        match self.wait_for_result(rx).await? {
+            Some(config) => Ok(Response::new(config)),
+            None => Err(Status::failed_precondition(
+                "the tunnel must be connected without multihop to export its configuration",
+            )),
+        }
+    }
+
+    async fn get_relay_latencies(&self, _: Request<()>) -> ServiceResult<types::RelayLatencies> {
+        log::debug!("get_relay_latencies");
+
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetRelayLatencies(tx))?;
+        let latencies = self.wait_for_result(rx).await?;
+        types::RelayLatencies::try_from(latencies)
+            .map(Response::new)
+            .map_err(Status::from)
+    }
+
     async fn get_bridges(&self, _: Request<()>) -> ServiceResult<types::BridgeList> {
         log::debug!("get_bridges");
 
@@ -243,13 +349,21 @@ impl ManagementService for ManagementServiceImpl {
     // Settings
     //
 
-    async fn get_settings(&self, _: Request<()>) -> ServiceResult<types::Settings> {
+    async fn get_settings(
+        &self,
+        #[cfg_attr(not(unix), expect(unused_variables))] request: Request<()>,
+    ) -> ServiceResult<types::Settings> {
         log::debug!("get_settings");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetSettings(tx))?;
-        self.wait_for_result(rx)
-            .await
-            .map(|settings| Response::new(types::Settings::from(&settings)))
+        let settings = self.wait_for_result(rx).await?;
+        #[cfg_attr(not(unix), expect(unused_mut))]
+        let mut settings = settings_to_proto(&settings, self.locked_settings.as_ref());
+        #[cfg(unix)]
+        if authorization::is_unprivileged(&request) {
+            authorization::redact_settings(&mut settings);
+        }
+        Ok(Response::new(settings))
     }
 
     async fn reset_settings(&self, request: Request<types::SettingsKeyList>) -> ServiceResult<()> {
@@ -279,6 +393,15 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn set_session_log(&self, request: Request<bool>) -> ServiceResult<()> {
+        let enabled = request.into_inner();
+        log::debug!("set_session_log({})", enabled);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetSessionLog(tx, enabled))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     #[cfg(not(target_os = "android"))]
     async fn set_lockdown_mode(&self, request: Request<bool>) -> ServiceResult<()> {
         let lockdown_mode = request.into_inner();
@@ -307,6 +430,41 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn set_trusted_networks(
+        &self,
+        request: Request<types::TrustedNetworkSettings>,
+    ) -> ServiceResult<()> {
+        let trusted_networks =
+            TrustedNetworks::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
+        log::debug!("set_trusted_networks({:?})", trusted_networks);
+        #[cfg(not(target_os = "linux"))]
+        if trusted_networks.enabled {
+            return Err(Status::unimplemented(
+                "Trusted networks are only supported on Linux",
+            ));
+        }
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetTrustedNetworks(tx, trusted_networks))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn get_current_network(&self, _: Request<()>) -> ServiceResult<types::NetworkInfo> {
+        log::debug!("get_current_network");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetCurrentNetwork(tx))?;
+        let network = self.wait_for_result(rx).await?;
+        Ok(Response::new(types::NetworkInfo::from(network)))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn get_current_network(&self, _: Request<()>) -> ServiceResult<types::NetworkInfo> {
+        Err(Status::unimplemented(
+            "Trusted networks are only supported on Linux",
+        ))
+    }
+
     async fn set_wireguard_mtu(&self, request: Request<u32>) -> ServiceResult<()> {
         let mtu = request.into_inner();
         let mtu = if mtu != 0 { Some(mtu as u16) } else { None };
@@ -398,6 +556,39 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn set_relay_exclusions(
+        &self,
+        request: Request<types::RelayExclusions>,
+    ) -> ServiceResult<()> {
+        let exclusions = RelayExclusions::from(request.into_inner());
+        log::debug!("set_relay_exclusions({:?})", exclusions);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetRelayExclusions(tx, exclusions))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_relay_rotation(
+        &self,
+        request: Request<types::RelayRotationPolicy>,
+    ) -> ServiceResult<()> {
+        let policy =
+            RotationPolicy::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
+        log::debug!("set_relay_rotation({:?})", policy);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetRelayRotation(tx, Some(policy)))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn clear_relay_rotation(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("clear_relay_rotation");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetRelayRotation(tx, None))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     // Account management
     //
 
@@ -438,11 +629,11 @@ impl ManagementService for ManagementServiceImpl {
         log::debug!("delete_account");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::DeleteAccount(tx))?;
-        let result = self
-            .wait_for_result(rx)
-            .await?
-            .map(Response::new)
-            .map_err(map_daemon_error);
+        let result = self.wait_for_result(rx).await?;
+        if let Err(crate::Error::AccountLocked) = result {
+            return Err(map_daemon_error(crate::Error::AccountLocked));
+        }
+        let result = result.map(Response::new).map_err(map_daemon_error);
         let (tx, _) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::ClearAccountHistory(tx))?;
         result
@@ -652,8 +843,14 @@ impl ManagementService for ManagementServiceImpl {
             .into_iter()
             .map(GeographicLocationConstraint::try_from)
             .collect::<Result<BTreeSet<_>, FromProtobufTypeError>>()?;
+        let filter = request.filter.map(CustomListFilter::try_from).transpose()?;
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::CreateCustomList(tx, request.name, locations))?;
+        self.send_command_to_daemon(DaemonCommand::CreateCustomList(
+            tx,
+            request.name,
+            locations,
+            filter,
+        ))?;
         self.wait_for_result(rx)
             .await?
             .map(|id| Response::new(id.to_string()))
@@ -695,6 +892,90 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
+    async fn import_custom_lists(
+        &self,
+        request: Request<types::CustomListImport>,
+    ) -> ServiceResult<types::CustomListImportResult> {
+        log::debug!("import_custom_lists");
+        let request = request.into_inner();
+        let mode = ImportMode::from(request.mode());
+        let lists = request
+            .custom_lists
+            .into_iter()
+            .map(ExportedCustomList::try_from)
+            .collect::<Result<Vec<_>, FromProtobufTypeError>>()?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ImportCustomLists(tx, lists, mode))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(|summary| Response::new(types::CustomListImportResult::from(summary)))
+            .map_err(map_daemon_error)
+    }
+
+    // Saved custom endpoints
+    //
+
+    async fn add_custom_endpoint(
+        &self,
+        request: Request<types::SavedCustomEndpoint>,
+    ) -> ServiceResult<()> {
+        log::debug!("add_custom_endpoint");
+        let endpoint =
+            mullvad_types::custom_endpoint::SavedCustomEndpoint::try_from(request.into_inner())?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::AddCustomEndpoint(tx, endpoint))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn update_custom_endpoint(
+        &self,
+        request: Request<types::SavedCustomEndpoint>,
+    ) -> ServiceResult<()> {
+        log::debug!("update_custom_endpoint");
+        let endpoint =
+            mullvad_types::custom_endpoint::SavedCustomEndpoint::try_from(request.into_inner())?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::UpdateCustomEndpoint(tx, endpoint))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn remove_custom_endpoint(&self, request: Request<String>) -> ServiceResult<()> {
+        log::debug!("remove_custom_endpoint");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::RemoveCustomEndpoint(
+            tx,
+            request.into_inner(),
+        ))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn move_custom_endpoint(
+        &self,
+        request: Request<types::CustomEndpointPosition>,
+    ) -> ServiceResult<()> {
+        log::debug!("move_custom_endpoint");
+        let request = request.into_inner();
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::MoveCustomEndpoint(
+            tx,
+            request.name,
+            request.position as usize,
+        ))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
     // Access Methods
 
     async fn add_api_access_method(
@@ -774,16 +1055,22 @@ impl ManagementService for ManagementServiceImpl {
     /// connect to the Mullvad API.
     async fn get_current_api_access_method(
         &self,
-        _: Request<()>,
+        #[cfg_attr(not(unix), expect(unused_variables))] request: Request<()>,
     ) -> ServiceResult<types::AccessMethodSetting> {
         log::debug!("get_current_api_access_method");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetCurrentAccessMethod(tx))?;
-        self.wait_for_result(rx)
+        #[cfg_attr(not(unix), expect(unused_mut))]
+        let mut access_method = self
+            .wait_for_result(rx)
             .await?
             .map(types::AccessMethodSetting::from)
-            .map(Response::new)
-            .map_err(map_daemon_error)
+            .map_err(map_daemon_error)?;
+        #[cfg(unix)]
+        if authorization::is_unprivileged(&request) {
+            authorization::redact_access_method(&mut access_method);
+        }
+        Ok(Response::new(access_method))
     }
 
     async fn test_custom_api_access_method(
@@ -925,7 +1212,6 @@ impl ManagementService for ManagementServiceImpl {
         }
     }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn add_split_tunnel_app(&self, request: Request<String>) -> ServiceResult<()> {
         use mullvad_types::settings::SplitApp;
         log::debug!("add_split_tunnel_app");
@@ -938,12 +1224,6 @@ impl ManagementService for ManagementServiceImpl {
             .map(Response::new)
     }
 
-    #[cfg(target_os = "linux")]
-    async fn add_split_tunnel_app(&self, _: Request<String>) -> ServiceResult<()> {
-        Ok(Response::new(()))
-    }
-
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn remove_split_tunnel_app(&self, request: Request<String>) -> ServiceResult<()> {
         use mullvad_types::settings::SplitApp;
         log::debug!("remove_split_tunnel_app");
@@ -955,12 +1235,7 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
             .map(Response::new)
     }
-    #[cfg(target_os = "linux")]
-    async fn remove_split_tunnel_app(&self, _: Request<String>) -> ServiceResult<()> {
-        Ok(Response::new(()))
-    }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn clear_split_tunnel_apps(&self, _: Request<()>) -> ServiceResult<()> {
         log::debug!("clear_split_tunnel_apps");
         let (tx, rx) = oneshot::channel();
@@ -970,12 +1245,7 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
             .map(Response::new)
     }
-    #[cfg(target_os = "linux")]
-    async fn clear_split_tunnel_apps(&self, _: Request<()>) -> ServiceResult<()> {
-        Ok(Response::new(()))
-    }
 
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn set_split_tunnel_state(&self, request: Request<bool>) -> ServiceResult<()> {
         log::debug!("set_split_tunnel_state");
         let enabled = request.into_inner();
@@ -986,9 +1256,27 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
             .map(Response::new)
     }
+
     #[cfg(target_os = "linux")]
-    async fn set_split_tunnel_state(&self, _: Request<bool>) -> ServiceResult<()> {
-        Ok(Response::new(()))
+    async fn set_split_tunnel_mode(
+        &self,
+        request: Request<types::SplitTunnelMode>,
+    ) -> ServiceResult<()> {
+        let mode = SplitTunnelMode::from(request.into_inner().mode());
+        log::debug!("set_split_tunnel_mode({mode})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetSplitTunnelMode(tx, mode))?;
+        self.wait_for_result(rx)
+            .await?
+            .map_err(map_daemon_error)
+            .map(Response::new)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_split_tunnel_mode(&self, _: Request<types::SplitTunnelMode>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Split tunneling modes are only supported on Linux",
+        ))
     }
 
     #[cfg(windows)]
@@ -1186,6 +1474,30 @@ impl ManagementService for ManagementServiceImpl {
 
         Ok(Response::new(UnboundedReceiverStream::new(rx)))
     }
+
+    async fn get_session_log(
+        &self,
+        request: Request<types::SessionLogQuery>,
+    ) -> ServiceResult<types::SessionLog> {
+        log::debug!("get_session_log");
+        let since = request
+            .into_inner()
+            .since
+            .map(|since| {
+                DateTime::from_timestamp(since.seconds, since.nanos as u32)
+                    .ok_or(Status::invalid_argument("invalid timestamp"))
+            })
+            .transpose()?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetSessionLog(tx, since))?;
+        let sessions = self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+        Ok(Response::new(types::SessionLog {
+            sessions: sessions
+                .into_iter()
+                .map(types::SessionRecord::from)
+                .collect(),
+        }))
+    }
     // Debug features
 
     async fn disable_relay(&self, relay: Request<String>) -> ServiceResult<()> {
@@ -1251,6 +1563,53 @@ impl ManagementService for ManagementServiceImpl {
         unreachable!("You should not call regenerate_rollout_threshold");
     }
 
+    async fn run_leak_check(&self, _: Request<()>) -> ServiceResult<types::LeakCheckReport> {
+        log::debug!("run_leak_check");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::RunLeakCheck(tx))?;
+        match self.wait_for_result(rx).await? {
+            Some(report) => Ok(Response::new(types::LeakCheckReport::from(report))),
+            None => Err(Status::failed_precondition(
+                "the tunnel must be connected to run a leak check",
+            )),
+        }
+    }
+
+    async fn get_leak_check_report(&self, _: Request<()>) -> ServiceResult<types::LeakCheckReport> {
+        log::debug!("get_leak_check_report");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetLeakCheckReport(tx))?;
+        match self.wait_for_result(rx).await? {
+            Some(report) => Ok(Response::new(types::LeakCheckReport::from(report))),
+            None => Err(Status::not_found("no leak check has completed")),
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    async fn run_dns_leak_test(&self, _: Request<()>) -> ServiceResult<types::DnsLeakTestReport> {
+        use talpid_types::net::dns::DnsLeakTestError;
+
+        log::debug!("run_dns_leak_test");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::RunDnsLeakTest(tx))?;
+        match self.wait_for_result(rx).await? {
+            Ok(report) => Ok(Response::new(types::DnsLeakTestReport::from(report))),
+            Err(error @ DnsLeakTestError::NotConnected) => {
+                Err(Status::failed_precondition(error.to_string()))
+            }
+            Err(error @ DnsLeakTestError::Unsupported) => {
+                Err(Status::unimplemented(error.to_string()))
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    async fn run_dns_leak_test(&self, _: Request<()>) -> ServiceResult<types::DnsLeakTestReport> {
+        Err(Status::unimplemented(
+            "DNS leak tests are only supported on macOS",
+        ))
+    }
+
     // App upgrade
 
     async fn app_upgrade(&self, _: Request<()>) -> ServiceResult<()> {
@@ -1394,8 +1753,14 @@ impl ManagementInterfaceServer {
         app_upgrade_broadcast: AppUpgradeBroadcast,
         log_reload_handle: crate::logging::LogHandle,
         relay_selector: RelaySelectorIO,
+        #[cfg(unix)] access_policy: Option<AccessPolicy>,
+        admin_policy: Option<&AdminPolicy>,
     ) -> Result<ManagementInterfaceServer, Error> {
         let subscriptions = Arc::<Mutex<Vec<EventsListenerSender>>>::default();
+        let locked_settings = admin_policy.map(|policy| types::LockedSettings {
+            settings: policy.locked_settings().map(str::to_owned).collect(),
+            account: policy.lock_account(),
+        });
 
         // NOTE: It is important that the channel buffer size is kept at 0. When sending a signal
         // to abort the gRPC server, the sender can be awaited to know when the gRPC server has
@@ -1407,6 +1772,7 @@ impl ManagementInterfaceServer {
             subscriptions: subscriptions.clone(),
             app_upgrade_broadcast,
             log_reload_handle,
+            locked_settings: locked_settings.clone(),
         };
 
         let relay_selector_service = RelaySelectorServer::new(relay_selector);
@@ -1418,6 +1784,8 @@ impl ManagementInterfaceServer {
                 StreamExt::into_future(server_abort_rx).await;
             },
             rpc_socket_path.clone(),
+            #[cfg(unix)]
+            access_policy,
         )
         .map_err(Error::SetupError)?;
 
@@ -1426,7 +1794,10 @@ impl ManagementInterfaceServer {
             rpc_socket_path.display()
         );
 
-        let broadcast = ManagementInterfaceEventBroadcaster { subscriptions };
+        let broadcast = ManagementInterfaceEventBroadcaster {
+            subscriptions,
+            locked_settings,
+        };
 
         Ok(ManagementInterfaceServer {
             rpc_server_join_handle,
@@ -1465,6 +1836,7 @@ impl ManagementInterfaceServer {
 #[derive(Clone)]
 pub struct ManagementInterfaceEventBroadcaster {
     subscriptions: Arc<Mutex<Vec<EventsListenerSender>>>,
+    locked_settings: Option<types::LockedSettings>,
 }
 
 impl ManagementInterfaceEventBroadcaster {
@@ -1490,8 +1862,9 @@ impl ManagementInterfaceEventBroadcaster {
     pub(crate) fn notify_settings(&self, settings: Settings) {
         log::debug!("Broadcasting new settings");
         self.notify(types::DaemonEvent {
-            event: Some(daemon_event::Event::Settings(types::Settings::from(
+            event: Some(daemon_event::Event::Settings(settings_to_proto(
                 &settings,
+                self.locked_settings.as_ref(),
             ))),
         })
     }
@@ -1592,6 +1965,18 @@ impl ManagementInterfaceEventBroadcaster {
     }
 }
 
+/// Converts settings to protobuf, including which settings are locked by the administrator
+/// policy.
+fn settings_to_proto(
+    settings: &Settings,
+    locked_settings: Option<&types::LockedSettings>,
+) -> types::Settings {
+    types::Settings {
+        locked_settings: locked_settings.cloned(),
+        ..types::Settings::from(settings)
+    }
+}
+
 /// Converts [`crate::Error`] into a tonic status.
 fn map_daemon_error(error: crate::Error) -> Status {
     use crate::Error as DaemonError;
@@ -1600,6 +1985,7 @@ fn map_daemon_error(error: crate::Error) -> Status {
         DaemonError::RestError(error) => map_rest_error(&error),
         DaemonError::SettingsError(error) => Status::from(error),
         DaemonError::AlreadyLoggedIn => Status::already_exists(error.to_string()),
+        DaemonError::AccountLocked => Status::permission_denied(error.to_string()),
         DaemonError::LoginError(error) => map_device_error(&error),
         DaemonError::LogoutError(error) => map_device_error(&error),
         DaemonError::DeleteAccountError(error) => map_device_error(&error),
@@ -1610,13 +1996,16 @@ fn map_daemon_error(error: crate::Error) -> Status {
         DaemonError::VoucherSubmission(error) => map_device_error(&error),
         #[cfg(target_os = "android")]
         DaemonError::VerifyPlayPurchase(error) => map_device_error(&error),
-        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        #[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
         DaemonError::SplitTunnelError(error) => map_split_tunnel_error(error),
         DaemonError::AccountHistory(error) => map_account_history_error(error),
         DaemonError::NoAccountNumber | DaemonError::NoAccountNumberHistory => {
             Status::unauthenticated(error.to_string())
         }
         DaemonError::VersionCheckError(error) => map_version_check_error(error),
+        DaemonError::SessionLog(error @ session_log::Error::NoLogDirectory) => {
+            Status::failed_precondition(error.to_string())
+        }
         error => Status::unknown(error.to_string()),
     }
 }
@@ -1638,7 +2027,7 @@ fn map_split_tunnel_error(error: talpid_core::split_tunnel::Error) -> Status {
     }
 }
 
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "linux"))]
 /// Converts [`talpid_core::split_tunnel::Error`] into a tonic status.
 fn map_split_tunnel_error(error: talpid_core::split_tunnel::Error) -> Status {
     Status::unknown(error.to_string())
@@ -1709,3 +2098,19 @@ fn map_protobuf_type_err(err: types::FromProtobufTypeError) -> Status {
         types::FromProtobufTypeError::InvalidArgument(err) => Status::invalid_argument(err),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_account_locked_is_permission_denied() {
+        let status = map_daemon_error(crate::Error::AccountLocked);
+        assert_eq!(status.code(), Code::PermissionDenied);
+
+        let status = map_daemon_error(crate::Error::SettingsError(crate::settings::Error::Locked(
+            "allow_lan".to_owned(),
+        )));
+        assert_eq!(status.code(), Code::PermissionDenied);
+    }
+}
@@ -0,0 +1,546 @@
+//! Optional OpenMetrics endpoint exposing statistics about the daemon, such as tunnel state
+//! transitions, connection attempts and API request latencies. It is only served if an endpoint
+//! is passed to the daemon, and it only ever listens on a loopback address or a Unix socket.
+
+use chrono::{DateTime, Utc};
+use http_body_util::Full;
+use hyper::{
+    Method, Request, Response, StatusCode,
+    body::{Bytes, Incoming},
+    header::{self, HeaderValue},
+    server::conn::http1,
+    service::service_fn,
+};
+use hyper_util::rt::{TokioIo, TokioTimer};
+use mullvad_api::stats::{ApiStats, LATENCY_BUCKETS};
+use mullvad_types::states::TunnelState;
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Write,
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use talpid_types::{ErrorExt, net::ObfuscationInfo};
+use tokio::io::{AsyncRead, AsyncWrite};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+/// Largest request that is accepted from a client.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+/// How long a client may take to send its request header.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+const TUNNEL_STATES: [&str; 5] = [
+    "disconnected",
+    "connecting",
+    "connected",
+    "disconnecting",
+    "error",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Metrics may only be served on a loopback address, not {0}")]
+    NotLoopback(SocketAddr),
+
+    #[error("Expected a loopback socket address or an absolute path to a Unix socket")]
+    InvalidEndpoint,
+
+    #[error("Failed to bind metrics endpoint")]
+    Bind(#[source] io::Error),
+}
+
+/// Where metrics are served.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricsEndpoint {
+    /// TCP socket bound to a loopback address
+    Tcp(SocketAddr),
+    /// Unix domain socket at the given path
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for MetricsEndpoint {
+    type Err = Error;
+
+    fn from_str(endpoint: &str) -> Result<Self, Self::Err> {
+        if let Ok(address) = endpoint.parse::<SocketAddr>() {
+            if !address.ip().is_loopback() {
+                return Err(Error::NotLoopback(address));
+            }
+            return Ok(MetricsEndpoint::Tcp(address));
+        }
+        #[cfg(unix)]
+        if std::path::Path::new(endpoint).is_absolute() {
+            return Ok(MetricsEndpoint::Unix(PathBuf::from(endpoint)));
+        }
+        Err(Error::InvalidEndpoint)
+    }
+}
+
+/// Collects the statistics exposed by the metrics endpoint. Cloning it yields a handle to the
+/// same statistics.
+#[derive(Clone)]
+pub struct Metrics {
+    state: Arc<Mutex<MetricsState>>,
+    api_stats: ApiStats,
+}
+
+struct MetricsState {
+    tunnel_state: &'static str,
+    state_entered: Instant,
+    time_in_state: BTreeMap<&'static str, Duration>,
+    transitions: BTreeMap<&'static str, u64>,
+    /// Number of connection attempts, by relay and obfuscation method
+    connect_attempts: BTreeMap<(String, &'static str), u64>,
+    access_method: Option<String>,
+    /// When the relay list in use was downloaded
+    relay_list_downloaded: Option<DateTime<Utc>>,
+}
+
+impl Metrics {
+    pub fn new(api_stats: ApiStats) -> Self {
+        let state = MetricsState {
+            tunnel_state: "disconnected",
+            state_entered: Instant::now(),
+            time_in_state: BTreeMap::new(),
+            transitions: BTreeMap::new(),
+            connect_attempts: BTreeMap::new(),
+            access_method: None,
+            relay_list_downloaded: None,
+        };
+        Metrics {
+            state: Arc::new(Mutex::new(state)),
+            api_stats,
+        }
+    }
+
+    /// Record that the daemon entered a new tunnel state.
+    pub fn on_tunnel_state(&self, tunnel_state: &TunnelState) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let previous_state = state.tunnel_state;
+        let elapsed = now.saturating_duration_since(state.state_entered);
+        *state.time_in_state.entry(previous_state).or_default() += elapsed;
+
+        let new_state = state_name(tunnel_state);
+        state.tunnel_state = new_state;
+        state.state_entered = now;
+        *state.transitions.entry(new_state).or_default() += 1;
+
+        if let TunnelState::Connecting {
+            endpoint, location, ..
+        } = tunnel_state
+        {
+            let relay = location
+                .as_ref()
+                .and_then(|location| location.hostname.clone())
+                .unwrap_or_else(|| endpoint.endpoint.address.ip().to_string());
+            let obfuscation = obfuscation_name(endpoint.obfuscation.as_ref());
+            *state
+                .connect_attempts
+                .entry((relay, obfuscation))
+                .or_default() += 1;
+        }
+    }
+
+    /// Record the name of the API access method in use.
+    pub fn on_access_method(&self, name: &str) {
+        self.state.lock().unwrap().access_method = Some(name.to_owned());
+    }
+
+    /// Record when the relay list in use was downloaded, if known.
+    pub fn on_relay_list(&self, downloaded: Option<DateTime<Utc>>) {
+        self.state.lock().unwrap().relay_list_downloaded = downloaded;
+    }
+
+    /// Render all metrics in the OpenMetrics text format.
+    fn render(&self, now: DateTime<Utc>) -> String {
+        let mut out = String::new();
+        // Writing to a `String` cannot fail
+        let _ = self.write_metrics(&mut out, now);
+        out
+    }
+
+    fn write_metrics(&self, out: &mut String, now: DateTime<Utc>) -> std::fmt::Result {
+        let state = self.state.lock().unwrap();
+        let api_stats = self.api_stats.snapshot();
+
+        writeln!(out, "# TYPE mullvad_tunnel_state gauge")?;
+        writeln!(out, "# HELP mullvad_tunnel_state Current tunnel state")?;
+        for name in TUNNEL_STATES {
+            let value = u8::from(name == state.tunnel_state);
+            writeln!(out, "mullvad_tunnel_state{{state=\"{name}\"}} {value}")?;
+        }
+
+        writeln!(out, "# TYPE mullvad_tunnel_state_transitions counter")?;
+        writeln!(
+            out,
+            "# HELP mullvad_tunnel_state_transitions Number of times each tunnel state was entered"
+        )?;
+        for name in TUNNEL_STATES {
+            let count = state.transitions.get(name).copied().unwrap_or(0);
+            writeln!(
+                out,
+                "mullvad_tunnel_state_transitions_total{{state=\"{name}\"}} {count}"
+            )?;
+        }
+
+        writeln!(out, "# TYPE mullvad_tunnel_state_seconds counter")?;
+        writeln!(out, "# UNIT mullvad_tunnel_state_seconds seconds")?;
+        writeln!(
+            out,
+            "# HELP mullvad_tunnel_state_seconds Time spent in each tunnel state"
+        )?;
+        for name in TUNNEL_STATES {
+            let mut time = state.time_in_state.get(name).copied().unwrap_or_default();
+            if name == state.tunnel_state {
+                time += state.state_entered.elapsed();
+            }
+            writeln!(
+                out,
+                "mullvad_tunnel_state_seconds_total{{state=\"{name}\"}} {:.3}",
+                time.as_secs_f64()
+            )?;
+        }
+
+        writeln!(out, "# TYPE mullvad_connect_attempts counter")?;
+        writeln!(
+            out,
+            "# HELP mullvad_connect_attempts Number of connection attempts by relay and obfuscation method"
+        )?;
+        for ((relay, obfuscation), count) in &state.connect_attempts {
+            writeln!(
+                out,
+                "mullvad_connect_attempts_total{{relay=\"{}\",obfuscation=\"{obfuscation}\"}} {count}",
+                escape_label(relay)
+            )?;
+        }
+
+        writeln!(out, "# TYPE mullvad_api_request_duration_seconds histogram")?;
+        writeln!(out, "# UNIT mullvad_api_request_duration_seconds seconds")?;
+        writeln!(
+            out,
+            "# HELP mullvad_api_request_duration_seconds Duration of API requests"
+        )?;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(api_stats.latency_buckets) {
+            writeln!(
+                out,
+                "mullvad_api_request_duration_seconds_bucket{{le=\"{bound}\"}} {count}"
+            )?;
+        }
+        writeln!(
+            out,
+            "mullvad_api_request_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            api_stats.requests
+        )?;
+        writeln!(
+            out,
+            "mullvad_api_request_duration_seconds_sum {:.3}",
+            api_stats.latency_sum.as_secs_f64()
+        )?;
+        writeln!(
+            out,
+            "mullvad_api_request_duration_seconds_count {}",
+            api_stats.requests
+        )?;
+
+        writeln!(out, "# TYPE mullvad_api_request_failures counter")?;
+        writeln!(
+            out,
+            "# HELP mullvad_api_request_failures Number of failed API requests by kind of failure"
+        )?;
+        for (kind, count) in &api_stats.failures {
+            writeln!(
+                out,
+                "mullvad_api_request_failures_total{{kind=\"{kind}\"}} {count}"
+            )?;
+        }
+
+        writeln!(out, "# TYPE mullvad_api_access_method info")?;
+        writeln!(
+            out,
+            "# HELP mullvad_api_access_method API access method currently in use"
+        )?;
+        if let Some(name) = &state.access_method {
+            writeln!(
+                out,
+                "mullvad_api_access_method_info{{name=\"{}\"}} 1",
+                escape_label(name)
+            )?;
+        }
+
+        writeln!(out, "# TYPE mullvad_relay_list_age_seconds gauge")?;
+        writeln!(out, "# UNIT mullvad_relay_list_age_seconds seconds")?;
+        writeln!(
+            out,
+            "# HELP mullvad_relay_list_age_seconds Time since the relay list was last downloaded"
+        )?;
+        if let Some(downloaded) = state.relay_list_downloaded {
+            let age = (now - downloaded).to_std().unwrap_or_default();
+            writeln!(
+                out,
+                "mullvad_relay_list_age_seconds {:.3}",
+                age.as_secs_f64()
+            )?;
+        }
+
+        writeln!(out, "# EOF")
+    }
+}
+
+fn state_name(state: &TunnelState) -> &'static str {
+    match state {
+        TunnelState::Disconnected { .. } => "disconnected",
+        TunnelState::Connecting { .. } => "connecting",
+        TunnelState::Connected { .. } => "connected",
+        TunnelState::Disconnecting(_) => "disconnecting",
+        TunnelState::Error(_) => "error",
+    }
+}
+
+fn obfuscation_name(obfuscation: Option<&ObfuscationInfo>) -> &'static str {
+    match obfuscation {
+        None => "none",
+        Some(ObfuscationInfo::Single(endpoint)) => match endpoint.obfuscation_type {
+            talpid_types::net::ObfuscationType::Udp2Tcp => "udp2tcp",
+            talpid_types::net::ObfuscationType::Shadowsocks => "shadowsocks",
+            talpid_types::net::ObfuscationType::Quic => "quic",
+            talpid_types::net::ObfuscationType::Lwo => "lwo",
+            talpid_types::net::ObfuscationType::CustomBridge => "custom_bridge",
+        },
+        Some(ObfuscationInfo::Multiplexer { .. }) => "multiplexer",
+    }
+}
+
+/// Escape a label value as required by the OpenMetrics text format.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+/// Bind `endpoint` and serve `metrics` on it until the daemon exits.
+pub async fn spawn_server(endpoint: MetricsEndpoint, metrics: Metrics) -> Result<(), Error> {
+    match endpoint {
+        MetricsEndpoint::Tcp(address) => {
+            let listener = tokio::net::TcpListener::bind(address)
+                .await
+                .map_err(Error::Bind)?;
+            log::info!("Serving metrics on {address}");
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            tokio::spawn(handle_connection(stream, metrics.clone()));
+                        }
+                        Err(error) => log_accept_error(error),
+                    }
+                }
+            });
+        }
+        #[cfg(unix)]
+        MetricsEndpoint::Unix(path) => {
+            // Remove any socket left behind by a previous instance
+            if let Err(error) = tokio::fs::remove_file(&path).await
+                && error.kind() != io::ErrorKind::NotFound
+            {
+                return Err(Error::Bind(error));
+            }
+            let listener = tokio::net::UnixListener::bind(&path).map_err(Error::Bind)?;
+            log::info!("Serving metrics on {}", path.display());
+            tokio::spawn(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            tokio::spawn(handle_connection(stream, metrics.clone()));
+                        }
+                        Err(error) => log_accept_error(error),
+                    }
+                }
+            });
+        }
+    }
+    Ok(())
+}
+
+fn log_accept_error(error: io::Error) {
+    log::error!(
+        "{}",
+        error.display_chain_with_msg("Failed to accept metrics connection")
+    );
+}
+
+/// Answer a single HTTP request, and then close the connection.
+async fn handle_connection(stream: impl AsyncRead + AsyncWrite + Unpin, metrics: Metrics) {
+    let service = service_fn(move |request| {
+        let response = respond(&request, &metrics);
+        async move { Ok::<_, Infallible>(response) }
+    });
+    let connection = http1::Builder::new()
+        .timer(TokioTimer::new())
+        .header_read_timeout(REQUEST_TIMEOUT)
+        .max_buf_size(MAX_REQUEST_SIZE)
+        .keep_alive(false)
+        .serve_connection(TokioIo::new(stream), service);
+    if let Err(error) = connection.await {
+        log::debug!("Failed to serve metrics request: {error}");
+    }
+}
+
+fn respond(request: &Request<Incoming>, metrics: &Metrics) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::default());
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/metrics") => {
+            *response.body_mut() = Full::from(metrics.render(Utc::now()));
+            response
+                .headers_mut()
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static(CONTENT_TYPE));
+        }
+        (&Method::GET, _) => *response.status_mut() = StatusCode::NOT_FOUND,
+        _ => {
+            *response.status_mut() = StatusCode::METHOD_NOT_ALLOWED;
+            response
+                .headers_mut()
+                .insert(header::ALLOW, HeaderValue::from_static("GET"));
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::{features::FeatureIndicators, location::GeoIpLocation};
+    use talpid_types::net::{
+        Endpoint, ObfuscationEndpoint, ObfuscationType, TransportProtocol, TunnelEndpoint,
+    };
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(
+            "127.0.0.1:9100".parse::<MetricsEndpoint>().unwrap(),
+            MetricsEndpoint::Tcp("127.0.0.1:9100".parse().unwrap())
+        );
+        assert_eq!(
+            "[::1]:9100".parse::<MetricsEndpoint>().unwrap(),
+            MetricsEndpoint::Tcp("[::1]:9100".parse().unwrap())
+        );
+        assert!(matches!(
+            "0.0.0.0:9100".parse::<MetricsEndpoint>(),
+            Err(Error::NotLoopback(_))
+        ));
+        assert!(matches!(
+            "metrics.sock".parse::<MetricsEndpoint>(),
+            Err(Error::InvalidEndpoint)
+        ));
+        #[cfg(unix)]
+        assert_eq!(
+            "/run/mullvad-metrics.sock"
+                .parse::<MetricsEndpoint>()
+                .unwrap(),
+            MetricsEndpoint::Unix(PathBuf::from("/run/mullvad-metrics.sock"))
+        );
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::new(ApiStats::default());
+
+        let endpoint = TunnelEndpoint {
+            endpoint: Endpoint::new(
+                std::net::Ipv4Addr::new(10, 0, 0, 1),
+                51820,
+                TransportProtocol::Udp,
+            ),
+            quantum_resistant: false,
+            obfuscation: Some(ObfuscationInfo::Single(ObfuscationEndpoint {
+                endpoint: Endpoint::new(
+                    std::net::Ipv4Addr::new(10, 0, 0, 1),
+                    443,
+                    TransportProtocol::Tcp,
+                ),
+                obfuscation_type: ObfuscationType::Udp2Tcp,
+            })),
+            entry_endpoint: None,
+            tunnel_interface: None,
+            daita: false,
+        };
+        let location = GeoIpLocation {
+            latitude: 57.7,
+            longitude: 11.97,
+            ipv4: None,
+            ipv6: None,
+            mullvad_exit_ip: true,
+            hostname: Some("se-got-wg-001".to_owned()),
+            city: Some("Gothenburg".to_owned()),
+            country: "Sweden".to_owned(),
+            entry_hostname: None,
+            entry_city: None,
+            entry_country: None,
+        };
+        for _ in 0..2 {
+            metrics.on_tunnel_state(&TunnelState::Connecting {
+                endpoint: endpoint.clone(),
+                location: Some(location.clone()),
+                feature_indicators: FeatureIndicators::default(),
+                rotation: None,
+            });
+        }
+        metrics.on_access_method("My \"proxy\"");
+
+        let now = Utc::now();
+        metrics.on_relay_list(Some(now - chrono::Duration::seconds(60)));
+        let output = metrics.render(now);
+
+        assert!(output.contains("mullvad_tunnel_state{state=\"connecting\"} 1\n"));
+        assert!(output.contains("mullvad_tunnel_state{state=\"disconnected\"} 0\n"));
+        assert!(
+            output.contains("mullvad_tunnel_state_transitions_total{state=\"connecting\"} 2\n")
+        );
+        assert!(output.contains(
+            "mullvad_connect_attempts_total{relay=\"se-got-wg-001\",obfuscation=\"udp2tcp\"} 2\n"
+        ));
+        assert!(output.contains("mullvad_api_request_duration_seconds_count 0\n"));
+        assert!(output.contains("mullvad_api_access_method_info{name=\"My \\\"proxy\\\"\"} 1\n"));
+        assert!(output.contains("mullvad_relay_list_age_seconds 60.000\n"));
+        assert!(output.ends_with("# EOF\n"));
+    }
+
+    #[tokio::test]
+    async fn test_serve_metrics() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        async fn request(metrics: &Metrics, request: &str) -> String {
+            let (mut client, server) = tokio::io::duplex(MAX_REQUEST_SIZE);
+            let server = tokio::spawn(handle_connection(server, metrics.clone()));
+            client.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            server.await.unwrap();
+            response
+        }
+
+        let metrics = Metrics::new(ApiStats::default());
+
+        let response = request(&metrics, "GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains(&format!("content-type: {CONTENT_TYPE}\r\n")));
+        assert!(response.ends_with("# EOF\n"));
+
+        let response = request(&metrics, "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+
+        let response = request(
+            &metrics,
+            "POST /metrics HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\n\r\n",
+        )
+        .await;
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    }
+}
@@ -56,6 +56,7 @@ mod v13;
 mod v14;
 mod v15;
 mod v16;
+mod v18;
 mod v2;
 mod v3;
 mod v4;
@@ -246,6 +247,8 @@ async fn migrate_settings(
         multihop::migrate_without_relay_selector(settings)?
     };
 
+    v18::migrate(settings)?;
+
     Ok(MigrationData {
         v5,
         multihop_split_filter_migration,
@@ -292,8 +295,9 @@ mod windows {
     type SECURITY_INFORMATION = u32;
 
     const MIGRATION_DIRNAME: &str = "windows.old";
-    const MIGRATE_FILES: [(&str, bool); 3] = [
+    const MIGRATE_FILES: [(&str, bool); 4] = [
         ("settings.json", true),
+        ("custom-relay-keys.json", false),
         ("device.json", true),
         ("account-history.json", false),
     ];
@@ -0,0 +1,165 @@
+use super::Result;
+use mullvad_types::settings::SettingsVersion;
+use serde_json::{Value, json};
+
+/// This migration handles:
+/// - Add a split tunneling mode, which determines whether the split tunneling apps are excluded
+///   from the tunnel or are the only apps that use it. Existing settings keep excluding apps.
+/// - Add an optional encrypted transport (DNS over TLS or HTTPS) for custom DNS servers. Existing
+///   custom DNS servers keep using plain DNS.
+/// - Add relay exclusions, i.e. relays and providers that are never selected. Nothing is excluded
+///   by default.
+/// - Add the session log setting, which is disabled by default.
+pub fn migrate(settings: &mut Value) -> Result<()> {
+    if !version_matches(settings) {
+        return Ok(());
+    }
+
+    log::info!("Migrating settings format to V19");
+
+    add_split_tunnel_mode(settings);
+    add_custom_dns_encryption(settings);
+    add_relay_exclusions(settings);
+    add_session_log(settings);
+
+    settings["settings_version"] = json!(SettingsVersion::V19);
+
+    Ok(())
+}
+
+/// { "split_tunnel": { "enable_exclusions": <bool>, "apps": [ .. ] } }
+///
+/// Add `"mode": "exclude"` to the `split_tunnel` object, if it exists.
+fn add_split_tunnel_mode(settings: &mut Value) -> Option<()> {
+    let split_tunnel = settings
+        .get_mut("split_tunnel")
+        .and_then(Value::as_object_mut)?;
+    split_tunnel
+        .entry("mode")
+        .or_insert_with(|| json!("exclude"));
+    Some(())
+}
+
+/// { "tunnel_options": { "dns_options": { "custom_options": { "addresses": [ .. ] } } } }
+///
+/// Add `"encryption": null` to the `custom_options` object, if it exists.
+fn add_custom_dns_encryption(settings: &mut Value) -> Option<()> {
+    let custom_options = settings
+        .get_mut("tunnel_options")?
+        .get_mut("dns_options")?
+        .get_mut("custom_options")
+        .and_then(Value::as_object_mut)?;
+    custom_options.entry("encryption").or_insert(Value::Null);
+    Some(())
+}
+
+/// Add `"relay_exclusions": { "relays": [], "providers": [] }` to the settings, unless present.
+fn add_relay_exclusions(settings: &mut Value) -> Option<()> {
+    settings
+        .as_object_mut()?
+        .entry("relay_exclusions")
+        .or_insert_with(|| json!({ "relays": [], "providers": [] }));
+    Some(())
+}
+
+/// Add `"session_log": false` to the settings, unless present.
+fn add_session_log(settings: &mut Value) -> Option<()> {
+    settings
+        .as_object_mut()?
+        .entry("session_log")
+        .or_insert(json!(false));
+    Some(())
+}
+
+fn version_matches(settings: &Value) -> bool {
+    settings
+        .get("settings_version")
+        .map(|version| version == SettingsVersion::V18 as u64)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_v18_to_v19_migration() {
+        let mut settings = json!({
+            "settings_version": 18,
+            "split_tunnel": {
+                "enable_exclusions": true,
+                "apps": ["/usr/bin/firefox"]
+            }
+        });
+        assert!(version_matches(&settings));
+
+        migrate(&mut settings).unwrap();
+
+        assert_eq!(
+            settings,
+            json!({
+                "settings_version": 19,
+                "split_tunnel": {
+                    "enable_exclusions": true,
+                    "apps": ["/usr/bin/firefox"],
+                    "mode": "exclude"
+                },
+                "relay_exclusions": { "relays": [], "providers": [] },
+                "session_log": false
+            })
+        );
+    }
+
+    #[test]
+    fn test_v18_to_v19_migration_custom_dns() {
+        let mut settings = json!({
+            "settings_version": 18,
+            "tunnel_options": {
+                "dns_options": {
+                    "state": "custom",
+                    "custom_options": {
+                        "addresses": ["1.1.1.1"]
+                    }
+                }
+            }
+        });
+
+        migrate(&mut settings).unwrap();
+
+        assert_eq!(
+            settings,
+            json!({
+                "settings_version": 19,
+                "tunnel_options": {
+                    "dns_options": {
+                        "state": "custom",
+                        "custom_options": {
+                            "addresses": ["1.1.1.1"],
+                            "encryption": null
+                        }
+                    }
+                },
+                "relay_exclusions": { "relays": [], "providers": [] },
+                "session_log": false
+            })
+        );
+    }
+
+    /// Settings without split tunneling or DNS settings should only get the new top-level
+    /// settings.
+    #[test]
+    fn test_v18_to_v19_migration_without_split_tunnel() {
+        let mut settings = json!({ "settings_version": 18 });
+
+        migrate(&mut settings).unwrap();
+
+        assert_eq!(
+            settings,
+            json!({
+                "settings_version": 19,
+                "relay_exclusions": { "relays": [], "providers": [] },
+                "session_log": false
+            })
+        );
+    }
+}
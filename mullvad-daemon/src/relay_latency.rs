@@ -0,0 +1,125 @@
+//! Measures the round-trip time to relays, so that the relay selector can prefer the fastest
+//! ones when [`RelayStrategy::Fastest`] is used. Probes are sent either directly while
+//! disconnected, or through the tunnel while connected. Probes sent through the tunnel also
+//! include the round-trip time to the current relay, which is the same for every relay probed.
+//!
+//! [`RelayStrategy::Fastest`]: mullvad_types::relay_constraints::RelayStrategy::Fastest
+
+use futures::{StreamExt, stream};
+use mullvad_daemon_relay_selector::relay_selector::RelaySelectorIO;
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
+use surge_ping::{Client, Config, ICMP, PingIdentifier, PingSequence};
+use tokio::{sync::watch, time::Instant};
+
+/// How often to measure the latency to all relays.
+const PROBE_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// How long to wait for each echo reply.
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// Number of echo requests sent to each relay. The lowest round-trip time is used.
+const PINGS_PER_RELAY: u16 = 3;
+/// Maximum number of relays to probe at the same time.
+const MAX_CONCURRENT_PROBES: usize = 32;
+
+/// Handle to a background task that periodically measures the latency to all active relays and
+/// hands the results to the relay selector. The task only runs while enabled.
+pub struct RelayLatencyProber {
+    enabled_tx: watch::Sender<bool>,
+}
+
+impl RelayLatencyProber {
+    pub fn spawn(relay_selector: RelaySelectorIO) -> Self {
+        let (enabled_tx, enabled_rx) = watch::channel(false);
+        tokio::spawn(run(relay_selector, enabled_rx));
+        Self { enabled_tx }
+    }
+
+    /// Start or stop probing relays. Probing is stopped immediately, and any measurements that
+    /// are in progress are discarded. Once enabled again, relays are probed as soon as
+    /// [`PROBE_INTERVAL`] has passed since the last completed measurement.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled_tx.send_if_modified(|current| {
+            let changed = *current != enabled;
+            *current = enabled;
+            changed
+        });
+    }
+}
+
+async fn run(relay_selector: RelaySelectorIO, mut enabled_rx: watch::Receiver<bool>) {
+    let mut next_probe = Instant::now();
+    loop {
+        if enabled_rx.wait_for(|enabled| *enabled).await.is_err() {
+            return;
+        }
+
+        let measure = async {
+            tokio::time::sleep_until(next_probe).await;
+            probe_relays(&relay_selector).await
+        };
+        let measured = tokio::select! {
+            measured = measure => measured,
+            _ = enabled_rx.wait_for(|enabled| !*enabled) => continue,
+        };
+        log::debug!("Measured latency to {} relays", measured.len());
+
+        // Keep the previous measurements of relays that did not respond this time
+        let mut latencies = relay_selector.get_latencies();
+        latencies.extend(measured);
+        relay_selector.set_latencies(latencies);
+
+        next_probe = Instant::now() + PROBE_INTERVAL;
+    }
+}
+
+async fn probe_relays(relay_selector: &RelaySelectorIO) -> Vec<(String, Duration)> {
+    let client = match Client::new(&Config::builder().kind(ICMP::V4).build()) {
+        Ok(client) => client,
+        Err(error) => {
+            log::error!("Failed to create ICMP socket for relay latency probing: {error}");
+            return vec![];
+        }
+    };
+
+    let relays: Vec<(String, Ipv4Addr)> = relay_selector
+        .get_relays()
+        .relays()
+        .filter(|relay| relay.active)
+        .map(|relay| (relay.hostname.clone(), relay.ipv4_addr_in))
+        .collect();
+
+    stream::iter(relays.into_iter().enumerate())
+        .map(|(index, (hostname, address))| {
+            let client = client.clone();
+            async move {
+                let identifier = PingIdentifier(index as u16);
+                let latency = probe_relay(&client, address, identifier).await?;
+                Some((hostname, latency))
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_PROBES)
+        .filter_map(|result| async move { result })
+        .collect()
+        .await
+}
+
+/// Return the lowest round-trip time to `address`, or `None` if it never replied.
+async fn probe_relay(
+    client: &Client,
+    address: Ipv4Addr,
+    identifier: PingIdentifier,
+) -> Option<Duration> {
+    let mut pinger = client.pinger(IpAddr::V4(address), identifier).await;
+    pinger.timeout(PING_TIMEOUT);
+
+    let mut lowest: Option<Duration> = None;
+    for sequence in 0..PINGS_PER_RELAY {
+        match pinger.ping(PingSequence(sequence), &[0; 8]).await {
+            Ok((_packet, rtt)) => lowest = Some(lowest.map_or(rtt, |lowest| lowest.min(rtt))),
+            Err(error) => log::trace!("No echo reply from {address}: {error}"),
+        }
+    }
+    lowest
+}
@@ -0,0 +1,61 @@
+//! Reconnects to a different exit relay on a schedule, as configured by the
+//! [`RotationPolicy`] in the tunnel options.
+
+use crate::{DaemonCommand, DaemonEventSender, tunnel_traffic};
+use futures::future::{AbortHandle, abortable};
+use mullvad_types::relay_rotation::{self, RotationPolicy};
+use std::{ops::ControlFlow, time::Duration};
+use talpid_core::mpsc::Sender;
+
+/// Longest time to sleep before checking the clock again for [`RotationPolicy::TimesOfDay`].
+/// Timers may not advance while the machine is suspended, so long sleeps would be late.
+const MAX_CLOCK_SLEEP: Duration = Duration::from_secs(60);
+
+/// Sent to the daemon when the current connection should be rotated according to the policy.
+pub(crate) struct RotationDue(pub RotationPolicy);
+
+/// Wait in the background until the current connection should be rotated according to
+/// `policy`, then notify the daemon. Abort the returned handle to cancel the rotation.
+pub(crate) fn schedule(
+    policy: RotationPolicy,
+    command_tx: DaemonEventSender<DaemonCommand>,
+    event_tx: DaemonEventSender<RotationDue>,
+) -> AbortHandle {
+    let (future, abort_handle) = abortable(async move {
+        wait_until_due(&policy, &command_tx).await;
+        let _ = event_tx.send(RotationDue(policy));
+    });
+    tokio::spawn(future);
+    abort_handle
+}
+
+async fn wait_until_due(policy: &RotationPolicy, command_tx: &DaemonEventSender<DaemonCommand>) {
+    match policy {
+        RotationPolicy::Interval(interval) => tokio::time::sleep(*interval).await,
+        RotationPolicy::Traffic(limit) => {
+            tunnel_traffic::poll(command_tx, |traffic| {
+                if traffic.total() >= *limit {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })
+            .await
+        }
+        RotationPolicy::TimesOfDay(times) => {
+            let Some(due) = relay_rotation::next_time_of_day(times, &chrono::Local::now()) else {
+                return std::future::pending().await;
+            };
+            log::debug!("Next relay rotation at {due}");
+            loop {
+                let Ok(remaining) = (due - chrono::Local::now()).to_std() else {
+                    return;
+                };
+                if remaining.is_zero() {
+                    return;
+                }
+                tokio::time::sleep(remaining.min(MAX_CLOCK_SLEEP)).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,537 @@
+//! Opt-in audit log of tunnel sessions. When enabled, a [`SessionRecord`] is appended to a JSON
+//! lines file in the log directory each time a connected tunnel is torn down. The current session
+//! is checkpointed periodically, so that it is recorded even if the daemon stops first.
+
+use crate::{DaemonCommand, DaemonEventSender, tunnel_traffic};
+use chrono::{DateTime, Utc};
+use futures::future::{AbortHandle, abortable};
+use mullvad_types::{
+    location::GeoIpLocation,
+    session_log::{SessionEnd, SessionRecord},
+    states::TunnelState,
+};
+use std::{
+    io,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use talpid_types::{ErrorExt, net::TunnelEndpoint, tunnel::ActionAfterDisconnect};
+use tokio::{io::AsyncWriteExt, sync::mpsc};
+
+pub const SESSION_LOG_FILENAME: &str = "sessions.jsonl";
+/// Name of the file in the log directory that the current session is checkpointed to.
+const CHECKPOINT_FILENAME: &str = "session-checkpoint.json";
+/// How often the current session is checkpointed. Traffic sent after the last checkpoint is not
+/// recorded if the daemon stops before the tunnel is torn down.
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("The daemon has no log directory, so there is no session log")]
+    NoLogDirectory,
+
+    #[error("Failed to read the session log")]
+    Read(#[source] io::Error),
+}
+
+pub(crate) struct SessionLog {
+    /// Path to the session log file, or `None` if the daemon has no log directory.
+    path: Option<PathBuf>,
+    /// Sends records to the task that writes them, or `None` if the daemon has no log directory.
+    writer: Option<mpsc::UnboundedSender<WriterCommand>>,
+    enabled: bool,
+    command_tx: DaemonEventSender<DaemonCommand>,
+    session: Option<ActiveSession>,
+    next_session_id: u64,
+}
+
+/// A session that has not been written to the log yet.
+struct ActiveSession {
+    id: u64,
+    /// The session as it would be recorded if it was interrupted now, apart from the traffic and
+    /// end time.
+    record: SessionRecord,
+    /// Most recent traffic sample.
+    traffic: Arc<Mutex<tunnel_traffic::Traffic>>,
+    traffic_poller: AbortHandle,
+    /// Set once the tunnel has been torn down, if the reason is not known yet.
+    end: Option<DateTime<Utc>>,
+}
+
+/// Writes to the session log, in the order they were sent. Session IDs are increasing.
+enum WriterCommand {
+    /// Replace the checkpoint with the current state of an unfinished session.
+    Checkpoint(u64, SessionRecord),
+    /// Append a finished session to the log, and remove its checkpoint.
+    Append(u64, SessionRecord),
+    /// Remove the checkpoint of a session that will not be recorded.
+    Discard(u64),
+}
+
+impl SessionLog {
+    /// Create a session log in `log_dir`. Any session that was checkpointed but never finished,
+    /// because the daemon stopped, is appended to the log as interrupted.
+    pub fn new(
+        log_dir: Option<&Path>,
+        enabled: bool,
+        command_tx: DaemonEventSender<DaemonCommand>,
+    ) -> Self {
+        let path = log_dir.map(|dir| dir.join(SESSION_LOG_FILENAME));
+        if enabled && path.is_none() {
+            log::warn!("The session log is enabled, but the daemon has no log directory");
+        }
+        let writer = log_dir.map(|dir| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(run_writer(
+                dir.join(SESSION_LOG_FILENAME),
+                dir.join(CHECKPOINT_FILENAME),
+                rx,
+            ));
+            tx
+        });
+        SessionLog {
+            path,
+            writer,
+            enabled,
+            command_tx,
+            session: None,
+            next_session_id: 0,
+        }
+    }
+
+    /// Enable or disable recording of sessions. Disabling discards the current session.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled && let Some(session) = self.session.take() {
+            session.traffic_poller.abort();
+            self.write(WriterCommand::Discard(session.id));
+        }
+    }
+
+    pub fn on_tunnel_state(&mut self, tunnel_state: &TunnelState) {
+        match tunnel_state {
+            TunnelState::Connected {
+                endpoint, location, ..
+            } => {
+                self.finish(SessionEnd::Reconnected);
+                self.start(endpoint, location.as_ref());
+            }
+            // Whether the daemon is entering the error state is not known until the next state
+            TunnelState::Disconnecting(ActionAfterDisconnect::Block) => {
+                if let Some(session) = &mut self.session {
+                    session.traffic_poller.abort();
+                    session.end.get_or_insert_with(Utc::now);
+                }
+            }
+            TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect)
+            | TunnelState::Connecting { .. } => self.finish(SessionEnd::Reconnected),
+            TunnelState::Disconnecting(ActionAfterDisconnect::Nothing)
+            | TunnelState::Disconnected { .. } => self.finish(SessionEnd::Disconnected),
+            TunnelState::Error(error_state) => {
+                self.finish(SessionEnd::Error(error_state.cause().clone()))
+            }
+        }
+    }
+
+    fn start(&mut self, endpoint: &TunnelEndpoint, location: Option<&GeoIpLocation>) {
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        if !self.enabled {
+            return;
+        }
+        let id = self.next_session_id;
+        self.next_session_id += 1;
+
+        let now = Utc::now();
+        let record = SessionRecord {
+            start: now,
+            end: now,
+            exit_hostname: location.and_then(|location| location.hostname.clone()),
+            entry_hostname: location.and_then(|location| location.entry_hostname.clone()),
+            endpoint: endpoint.clone(),
+            rx_bytes: 0,
+            tx_bytes: 0,
+            end_reason: SessionEnd::Interrupted,
+        };
+        let _ = writer.send(WriterCommand::Checkpoint(id, record.clone()));
+
+        let traffic = Arc::new(Mutex::new(tunnel_traffic::Traffic::default()));
+        let (poller, traffic_poller) = abortable(poll_traffic(
+            self.command_tx.clone(),
+            writer.clone(),
+            id,
+            record.clone(),
+            traffic.clone(),
+        ));
+        tokio::spawn(poller);
+
+        self.session = Some(ActiveSession {
+            id,
+            record,
+            traffic,
+            traffic_poller,
+            end: None,
+        });
+    }
+
+    fn finish(&mut self, end_reason: SessionEnd) {
+        let Some(session) = self.session.take() else {
+            return;
+        };
+        session.traffic_poller.abort();
+        let traffic = *session.traffic.lock().unwrap();
+        let record = SessionRecord {
+            end: session.end.unwrap_or_else(Utc::now),
+            rx_bytes: traffic.rx_bytes,
+            tx_bytes: traffic.tx_bytes,
+            end_reason,
+            ..session.record
+        };
+        self.write(WriterCommand::Append(session.id, record));
+    }
+
+    fn write(&self, command: WriterCommand) {
+        if let Some(writer) = &self.writer {
+            let _ = writer.send(command);
+        }
+    }
+
+    /// Return the recorded sessions that ended at or after `since`, oldest first.
+    pub fn read(
+        &self,
+        since: Option<DateTime<Utc>>,
+    ) -> impl Future<Output = Result<Vec<SessionRecord>, Error>> + use<> {
+        let path = self.path.clone();
+        async move {
+            let path = path.ok_or(Error::NoLogDirectory)?;
+            let contents = match tokio::fs::read_to_string(&path).await {
+                Ok(contents) => contents,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+                Err(error) => return Err(Error::Read(error)),
+            };
+            Ok(parse_records(&contents, since))
+        }
+    }
+}
+
+/// Store the traffic of the current tunnel in `traffic` whenever it is sampled, and checkpoint
+/// the session at most once per [`CHECKPOINT_INTERVAL`].
+async fn poll_traffic(
+    command_tx: DaemonEventSender<DaemonCommand>,
+    writer: mpsc::UnboundedSender<WriterCommand>,
+    id: u64,
+    record: SessionRecord,
+    traffic: Arc<Mutex<tunnel_traffic::Traffic>>,
+) {
+    let mut last_checkpoint = Instant::now();
+    tunnel_traffic::poll(&command_tx, |sample| {
+        *traffic.lock().unwrap() = sample;
+        if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            last_checkpoint = Instant::now();
+            let checkpoint = SessionRecord {
+                end: Utc::now(),
+                rx_bytes: sample.rx_bytes,
+                tx_bytes: sample.tx_bytes,
+                ..record.clone()
+            };
+            if writer
+                .send(WriterCommand::Checkpoint(id, checkpoint))
+                .is_err()
+            {
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    })
+    .await
+}
+
+/// Perform writes to the session log and the checkpoint file, after recovering any session that
+/// was interrupted.
+async fn run_writer(
+    log_path: PathBuf,
+    checkpoint_path: PathBuf,
+    mut rx: mpsc::UnboundedReceiver<WriterCommand>,
+) {
+    if let Err(error) = recover_checkpoint(&log_path, &checkpoint_path).await {
+        log::warn!(
+            "{}",
+            error.display_chain_with_msg("Failed to recover interrupted session")
+        );
+    }
+    // A checkpoint may be sent by the traffic poller after its session has finished
+    let mut finished = None;
+    while let Some(command) = rx.recv().await {
+        let result = match command {
+            WriterCommand::Checkpoint(id, _) if finished.is_some_and(|finished| id <= finished) => {
+                Ok(())
+            }
+            WriterCommand::Checkpoint(_, record) => {
+                write_checkpoint(&checkpoint_path, &record).await
+            }
+            WriterCommand::Append(id, record) => {
+                finished = Some(id);
+                let appended = append_record(&log_path, &record).await;
+                appended.and(remove_checkpoint(&checkpoint_path).await)
+            }
+            WriterCommand::Discard(id) => {
+                finished = Some(id);
+                remove_checkpoint(&checkpoint_path).await
+            }
+        };
+        if let Err(error) = result {
+            log::warn!(
+                "{}",
+                error.display_chain_with_msg("Failed to write to the session log")
+            );
+        }
+    }
+}
+
+/// Append the session in the checkpoint file to the log, if there is one.
+async fn recover_checkpoint(log_path: &Path, checkpoint_path: &Path) -> io::Result<()> {
+    let contents = match tokio::fs::read(checkpoint_path).await {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    match serde_json::from_slice::<SessionRecord>(&contents) {
+        Ok(record) => append_record(log_path, &record).await?,
+        Err(error) => log::warn!("Discarding malformed session checkpoint: {error}"),
+    }
+    remove_checkpoint(checkpoint_path).await
+}
+
+async fn write_checkpoint(path: &Path, record: &SessionRecord) -> io::Result<()> {
+    let contents = serde_json::to_vec(record).map_err(io::Error::other)?;
+    let mut file = mullvad_fs::AtomicFile::new(path).await?;
+    file.write_all(&contents).await?;
+    file.finalize().await
+}
+
+async fn remove_checkpoint(path: &Path) -> io::Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(error),
+        _ => Ok(()),
+    }
+}
+
+async fn append_record(path: &Path, record: &SessionRecord) -> io::Result<()> {
+    let mut line = serde_json::to_string(record).map_err(io::Error::other)?;
+    line.push('\n');
+    tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?
+        .write_all(line.as_bytes())
+        .await
+}
+
+/// Parse the records in a session log, skipping lines that cannot be parsed.
+fn parse_records(contents: &str, since: Option<DateTime<Utc>>) -> Vec<SessionRecord> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            serde_json::from_str::<SessionRecord>(line)
+                .inspect_err(|error| log::warn!("Skipping malformed session log entry: {error}"))
+                .ok()
+        })
+        .filter(|record| since.is_none_or(|since| record.end >= since))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Weak;
+    use talpid_types::{
+        net::{Endpoint, TransportProtocol},
+        tunnel::{ErrorState, ErrorStateCause},
+    };
+
+    fn endpoint() -> TunnelEndpoint {
+        TunnelEndpoint {
+            endpoint: Endpoint::new([10, 0, 0, 1], 51820, TransportProtocol::Udp),
+            quantum_resistant: false,
+            obfuscation: None,
+            entry_endpoint: None,
+            tunnel_interface: None,
+            daita: false,
+        }
+    }
+
+    fn record(end: DateTime<Utc>, end_reason: SessionEnd) -> SessionRecord {
+        SessionRecord {
+            start: end - chrono::Duration::minutes(5),
+            end,
+            exit_hostname: Some("se-got-wg-001".to_owned()),
+            entry_hostname: None,
+            endpoint: endpoint(),
+            rx_bytes: 1000,
+            tx_bytes: 500,
+            end_reason,
+        }
+    }
+
+    /// Create an enabled session log, and a receiver of the writes that it makes. The daemon is
+    /// not running, so no traffic is sampled.
+    fn session_log() -> (SessionLog, mpsc::UnboundedReceiver<WriterCommand>) {
+        let (writer, rx) = mpsc::unbounded_channel();
+        let session_log = SessionLog {
+            path: None,
+            writer: Some(writer),
+            enabled: true,
+            command_tx: DaemonEventSender::new(Weak::new()).to_specialized_sender(),
+            session: None,
+            next_session_id: 0,
+        };
+        (session_log, rx)
+    }
+
+    fn connecting() -> TunnelState {
+        TunnelState::Connecting {
+            endpoint: endpoint(),
+            location: None,
+            feature_indicators: Default::default(),
+            rotation: None,
+        }
+    }
+
+    fn connected() -> TunnelState {
+        TunnelState::Connected {
+            endpoint: endpoint(),
+            location: None,
+            feature_indicators: Default::default(),
+        }
+    }
+
+    fn disconnected() -> TunnelState {
+        TunnelState::Disconnected {
+            location: None,
+            #[cfg(not(target_os = "android"))]
+            locked_down: false,
+        }
+    }
+
+    /// Returns the next session that was appended to the log, skipping checkpoints.
+    fn next_appended(rx: &mut mpsc::UnboundedReceiver<WriterCommand>) -> Option<SessionRecord> {
+        while let Ok(command) = rx.try_recv() {
+            if let WriterCommand::Append(_, record) = command {
+                return Some(record);
+            }
+        }
+        None
+    }
+
+    /// A session starts when the tunnel is connected, is checkpointed immediately, and is
+    /// recorded when the tunnel is disconnected.
+    #[tokio::test]
+    async fn test_connect_and_disconnect() {
+        let (mut session_log, mut rx) = session_log();
+
+        session_log.on_tunnel_state(&connecting());
+        assert!(rx.try_recv().is_err());
+
+        session_log.on_tunnel_state(&connected());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(WriterCommand::Checkpoint(
+                0,
+                SessionRecord {
+                    end_reason: SessionEnd::Interrupted,
+                    ..
+                }
+            ))
+        ));
+
+        session_log.on_tunnel_state(&TunnelState::Disconnecting(ActionAfterDisconnect::Nothing));
+        session_log.on_tunnel_state(&disconnected());
+        let record = next_appended(&mut rx).unwrap();
+        assert!(matches!(record.end_reason, SessionEnd::Disconnected));
+        assert!(next_appended(&mut rx).is_none());
+    }
+
+    /// A session that ends in the error state is recorded with the cause of the error, and ends
+    /// when the tunnel started to be torn down.
+    #[tokio::test]
+    async fn test_error() {
+        let (mut session_log, mut rx) = session_log();
+
+        session_log.on_tunnel_state(&connecting());
+        session_log.on_tunnel_state(&connected());
+        session_log.on_tunnel_state(&TunnelState::Disconnecting(ActionAfterDisconnect::Block));
+        let torn_down = Utc::now();
+        session_log.on_tunnel_state(&TunnelState::Error(ErrorState::new(
+            ErrorStateCause::IsOffline,
+            None,
+        )));
+
+        let record = next_appended(&mut rx).unwrap();
+        assert!(matches!(
+            record.end_reason,
+            SessionEnd::Error(ErrorStateCause::IsOffline)
+        ));
+        assert!(record.end <= torn_down);
+    }
+
+    /// Reconnecting ends the current session and starts a new one.
+    #[tokio::test]
+    async fn test_reconnect() {
+        let (mut session_log, mut rx) = session_log();
+
+        session_log.on_tunnel_state(&connected());
+        session_log.on_tunnel_state(&connecting());
+        session_log.on_tunnel_state(&connected());
+        session_log.on_tunnel_state(&disconnected());
+
+        let first = next_appended(&mut rx).unwrap();
+        assert!(matches!(first.end_reason, SessionEnd::Reconnected));
+        let second = next_appended(&mut rx).unwrap();
+        assert!(matches!(second.end_reason, SessionEnd::Disconnected));
+    }
+
+    /// Nothing is written while the session log is disabled, and disabling it discards the
+    /// current session.
+    #[tokio::test]
+    async fn test_disabled() {
+        let (mut session_log, mut rx) = session_log();
+
+        session_log.on_tunnel_state(&connected());
+        session_log.set_enabled(false);
+        session_log.on_tunnel_state(&disconnected());
+        session_log.on_tunnel_state(&connected());
+        session_log.on_tunnel_state(&disconnected());
+
+        assert!(matches!(rx.try_recv(), Ok(WriterCommand::Checkpoint(0, _))));
+        assert!(matches!(rx.try_recv(), Ok(WriterCommand::Discard(0))));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_parse_records() {
+        let now = Utc::now();
+        let old = record(now - chrono::Duration::hours(2), SessionEnd::Disconnected);
+        let new = record(now, SessionEnd::Error(ErrorStateCause::IsOffline));
+        let contents = format!(
+            "{}\nnot a record\n\n{}\n",
+            serde_json::to_string(&old).unwrap(),
+            serde_json::to_string(&new).unwrap(),
+        );
+
+        let all = parse_records(&contents, None);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].end, old.end);
+
+        let recent = parse_records(&contents, Some(now - chrono::Duration::hours(1)));
+        assert_eq!(recent.len(), 1);
+        assert!(matches!(
+            recent[0].end_reason,
+            SessionEnd::Error(ErrorStateCause::IsOffline)
+        ));
+    }
+}
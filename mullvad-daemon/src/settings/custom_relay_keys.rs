@@ -0,0 +1,257 @@
+//! Private keys and preshared keys of custom relays.
+//!
+//! These are kept out of the settings file, and are instead stored in a separate file that only
+//! root can read, just like the private key of the device. The keys are removed from the
+//! serialized settings before they are written, and put back before the settings are parsed.
+
+use super::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs,
+    io::{self, AsyncWriteExt},
+};
+
+/// File that the keys are stored in, in the settings directory.
+const KEYS_FILE: &str = "custom-relay-keys.json";
+
+/// Path to the keys that belong to the settings file at `settings_path`.
+pub fn path(settings_path: &Path) -> PathBuf {
+    settings_path.with_file_name(KEYS_FILE)
+}
+
+/// Keys of all custom relays in the settings.
+#[derive(Default, Serialize, Deserialize)]
+pub struct CustomRelayKeys {
+    /// Keys of the custom relay in the relay settings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    relay: Option<RelayKeys>,
+    /// Keys of the saved custom relays, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    saved: BTreeMap<String, RelayKeys>,
+}
+
+/// Keys of a single custom relay, as serialized in the settings.
+#[derive(Serialize, Deserialize)]
+struct RelayKeys {
+    private_key: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    preshared_key: Option<Value>,
+}
+
+impl CustomRelayKeys {
+    /// Remove the keys of all custom relays from `settings`, which are serialized settings.
+    pub fn take(settings: &mut Value) -> Self {
+        let relay = settings
+            .pointer_mut("/relay_settings/custom_tunnel_endpoint")
+            .and_then(RelayKeys::take);
+        let mut saved = BTreeMap::new();
+        for saved_endpoint in saved_endpoints(settings) {
+            let Some(name) = saved_endpoint.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let name = name.to_owned();
+            if let Some(keys) = saved_endpoint.get_mut("endpoint").and_then(RelayKeys::take) {
+                saved.insert(name, keys);
+            }
+        }
+        Self { relay, saved }
+    }
+
+    /// Put the keys back into `settings`, which are serialized settings. Keys that are already
+    /// in `settings` are kept, so that settings files from before the keys were moved out can
+    /// still be read.
+    pub fn restore(&self, settings: &mut Value) {
+        if let Some(keys) = &self.relay
+            && let Some(endpoint) = settings.pointer_mut("/relay_settings/custom_tunnel_endpoint")
+        {
+            keys.restore(endpoint);
+        }
+        for saved_endpoint in saved_endpoints(settings) {
+            let keys = saved_endpoint
+                .get("name")
+                .and_then(Value::as_str)
+                .and_then(|name| self.saved.get(name));
+            if let Some(keys) = keys
+                && let Some(endpoint) = saved_endpoint.get_mut("endpoint")
+            {
+                keys.restore(endpoint);
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.relay.is_none() && self.saved.is_empty()
+    }
+
+    /// Read the keys stored at `path`. There are no keys if the file does not exist.
+    pub async fn read(path: &Path) -> Result<Self, Error> {
+        match fs::read(path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(Error::ParseError),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(Error::ReadError(path.display().to_string(), error)),
+        }
+    }
+
+    /// Write the keys to `path`, making the file readable only by its owner. The file is removed
+    /// if there are no keys.
+    pub async fn write(&self, path: &Path) -> Result<(), Error> {
+        if self.is_empty() {
+            return match fs::remove_file(path).await {
+                Ok(()) => Ok(()),
+                Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+                Err(error) => Err(Error::DeleteError(path.display().to_string(), error)),
+            };
+        }
+
+        let buffer = serde_json::to_vec_pretty(self).map_err(Error::SerializeError)?;
+        let write_err = |error| Error::WriteError(path.display().to_string(), error);
+        let mut file = mullvad_fs::AtomicFile::new(path).await.map_err(write_err)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))
+                .await
+                .map_err(write_err)?;
+        }
+        file.write_all(&buffer).await.map_err(write_err)?;
+        file.finalize().await.map_err(write_err)
+    }
+}
+
+impl RelayKeys {
+    /// Remove the keys from `endpoint`, which is a serialized custom relay.
+    fn take(endpoint: &mut Value) -> Option<Self> {
+        let private_key = endpoint
+            .pointer_mut("/config/tunnel")
+            .and_then(Value::as_object_mut)?
+            .remove("private_key")?;
+        let preshared_key = endpoint
+            .as_object_mut()
+            .and_then(|endpoint| endpoint.remove("preshared_key"));
+        Some(Self {
+            private_key,
+            preshared_key,
+        })
+    }
+
+    /// Put the keys back into `endpoint`, which is a serialized custom relay.
+    fn restore(&self, endpoint: &mut Value) {
+        if let Some(tunnel) = endpoint
+            .pointer_mut("/config/tunnel")
+            .and_then(Value::as_object_mut)
+        {
+            insert_missing(tunnel, "private_key", &self.private_key);
+        }
+        if let Some(preshared_key) = &self.preshared_key
+            && let Some(endpoint) = endpoint.as_object_mut()
+        {
+            insert_missing(endpoint, "preshared_key", preshared_key);
+        }
+    }
+}
+
+fn insert_missing(object: &mut Map<String, Value>, key: &str, value: &Value) {
+    object.entry(key).or_insert_with(|| value.clone());
+}
+
+/// Serialized saved custom relays in `settings`.
+fn saved_endpoints(settings: &mut Value) -> impl Iterator<Item = &mut Value> {
+    settings
+        .pointer_mut("/custom_endpoints/endpoints")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::{
+        CustomTunnelEndpoint, custom_endpoint::SavedCustomEndpoint,
+        relay_constraints::RelaySettings, settings::Settings,
+    };
+    use std::net::{Ipv4Addr, SocketAddr};
+    use talpid_types::net::wireguard;
+
+    fn custom_endpoint(host: &str) -> CustomTunnelEndpoint {
+        let private_key = wireguard::PrivateKey::new_from_random();
+        CustomTunnelEndpoint {
+            preshared_key: Some(wireguard::PresharedKey::from([7; 32])),
+            ..CustomTunnelEndpoint::new(
+                host.to_owned(),
+                wireguard::ConnectionConfig {
+                    tunnel: wireguard::TunnelConfig {
+                        private_key,
+                        addresses: vec![Ipv4Addr::new(10, 64, 0, 2).into()],
+                    },
+                    peer: wireguard::PeerConfig {
+                        public_key: wireguard::PrivateKey::new_from_random().public_key(),
+                        allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+                        endpoint: SocketAddr::from((Ipv4Addr::UNSPECIFIED, 51820)),
+                        psk: None,
+                        constant_packet_size: false,
+                    },
+                    exit_peer: None,
+                    ipv4_gateway: Ipv4Addr::new(10, 64, 0, 1),
+                    ipv6_gateway: None,
+                    #[cfg(target_os = "linux")]
+                    fwmark: None,
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_take_and_restore() {
+        let mut settings = Settings::default();
+        settings.relay_settings = RelaySettings::CustomTunnelEndpoint(custom_endpoint("a"));
+        settings.custom_endpoints =
+            vec![SavedCustomEndpoint::new("gateway".to_owned(), custom_endpoint("b")).unwrap()]
+                .into();
+
+        let mut value = serde_json::to_value(&settings).unwrap();
+        let keys = CustomRelayKeys::take(&mut value);
+
+        let serialized = value.to_string();
+        assert!(!serialized.contains("private_key"));
+        assert!(!serialized.contains("preshared_key"));
+        assert!(keys.relay.is_some());
+        assert_eq!(keys.saved.len(), 1);
+
+        // The keys survive being written to their own file
+        let keys: CustomRelayKeys =
+            serde_json::from_slice(&serde_json::to_vec(&keys).unwrap()).unwrap();
+        keys.restore(&mut value);
+        assert_eq!(serde_json::from_value::<Settings>(value).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_restore_keeps_existing_keys() {
+        let mut settings = Settings::default();
+        settings.relay_settings = RelaySettings::CustomTunnelEndpoint(custom_endpoint("a"));
+        let mut value = serde_json::to_value(&settings).unwrap();
+
+        let mut other = Settings::default();
+        other.relay_settings = RelaySettings::CustomTunnelEndpoint(custom_endpoint("a"));
+        let other_keys = CustomRelayKeys::take(&mut serde_json::to_value(&other).unwrap());
+
+        other_keys.restore(&mut value);
+        assert_eq!(serde_json::from_value::<Settings>(value).unwrap(), settings);
+    }
+
+    #[test]
+    fn test_no_keys() {
+        let mut value = serde_json::to_value(Settings::default()).unwrap();
+        let keys = CustomRelayKeys::take(&mut value);
+        assert!(keys.is_empty());
+        assert_eq!(
+            serde_json::from_value::<Settings>(value).unwrap(),
+            Settings::default()
+        );
+    }
+}
@@ -1,9 +1,12 @@
+use custom_relay_keys::CustomRelayKeys;
 use mullvad_types::{
     access_method::Error as ApiAccessMethodError,
+    custom_endpoint::Error as CustomEndpointError,
     custom_list::Error as CustomListError,
     relay_constraints::{Multihop, RelayConstraints, RelaySettings, WireguardConstraints},
-    settings::{DnsState, Settings, SettingsKey, SettingsKeyList},
+    settings::{DnsEncryption, DnsState, Settings, SettingsKey, SettingsKeyList},
 };
+use policy::AdminPolicy;
 use std::{
     fmt::{self, Display},
     ops::Deref,
@@ -17,7 +20,9 @@ use tokio::{
     io::{self, AsyncWriteExt},
 };
 
+mod custom_relay_keys;
 pub mod patch;
+pub mod policy;
 
 const SETTINGS_FILE: &str = "settings.json";
 
@@ -43,6 +48,9 @@ pub enum Error {
 
     #[error("Failed to parse IP network from string: {0}")]
     ParseIp(String),
+
+    #[error("The setting \"{0}\" is locked by the administrator policy")]
+    Locked(String),
 }
 
 /// Converts an [Error] to a management interface status
@@ -53,6 +61,7 @@ impl From<Error> for mullvad_management_interface::Status {
             Error::DeleteError(..) | Error::WriteError(..) | Error::ReadError(..) => {
                 Status::new(Code::FailedPrecondition, error.to_string())
             }
+            Error::Locked(..) => Status::new(Code::PermissionDenied, error.to_string()),
             Error::UpdateFailed(err)
                 if err
                     .downcast_ref::<mullvad_types::custom_list::Error>()
@@ -61,10 +70,17 @@ impl From<Error> for mullvad_management_interface::Status {
                 let custom_list_err = *err.downcast::<CustomListError>().unwrap();
                 handle_custom_list_error(custom_list_err)
             }
+            Error::UpdateFailed(err) if err.downcast_ref::<CustomEndpointError>().is_some() => {
+                let custom_endpoint_err = *err.downcast::<CustomEndpointError>().unwrap();
+                handle_custom_endpoint_error(custom_endpoint_err)
+            }
             Error::UpdateFailed(err) if err.downcast_ref::<ApiAccessMethodError>().is_some() => {
                 let api_access_method_err = *err.downcast::<ApiAccessMethodError>().unwrap();
                 handle_api_access_method_error(api_access_method_err)
             }
+            Error::UpdateFailed(err) if err.downcast_ref::<crate::dns::Error>().is_some() => {
+                Status::new(Code::Unimplemented, err.to_string())
+            }
             Error::SerializeError(..)
             | Error::ParseError(..)
             | Error::UpdateFailed(..)
@@ -87,6 +103,23 @@ fn handle_api_access_method_error(
     }
 }
 
+fn handle_custom_endpoint_error(
+    custom_endpoint_err: CustomEndpointError,
+) -> mullvad_management_interface::Status {
+    use mullvad_management_interface::{Code, Status};
+    match custom_endpoint_err {
+        error @ CustomEndpointError::DuplicateName => {
+            Status::new(Code::AlreadyExists, error.to_string())
+        }
+        error @ (CustomEndpointError::NameTooLong | CustomEndpointError::EmptyName) => {
+            Status::new(Code::InvalidArgument, error.to_string())
+        }
+        error @ CustomEndpointError::EndpointNotFound => {
+            Status::new(Code::NotFound, error.to_string())
+        }
+    }
+}
+
 fn handle_custom_list_error(
     custom_list_err: CustomListError,
 ) -> mullvad_management_interface::Status {
@@ -118,6 +151,7 @@ type ChangeListener =
 pub struct SettingsPersister {
     settings: Settings,
     path: PathBuf,
+    policy: Option<AdminPolicy>,
     on_change_listeners: Vec<ChangeListener>,
 }
 
@@ -129,13 +163,26 @@ impl SettingsPersister {
     pub async fn load(settings_dir: &Path) -> Self {
         let path = settings_dir.join(SETTINGS_FILE);
         let LoadSettingsResult {
-            settings,
-            should_save,
+            mut settings,
+            mut should_save,
         } = Self::load_inner(|| Self::load_from_file(&path)).await;
 
+        let policy = AdminPolicy::load(settings_dir, &settings);
+        if let Some(policy) = &policy {
+            log::info!(
+                "Loaded administrator policy. Locked settings: {}. Account locked: {}",
+                policy.locked_settings().collect::<Vec<_>>().join(", "),
+                policy.lock_account(),
+            );
+            let pinned = Self::apply_policy(policy, settings.clone());
+            should_save |= pinned != settings;
+            settings = pinned;
+        }
+
         let mut persister = SettingsPersister {
             settings,
             path,
+            policy,
             on_change_listeners: vec![],
         };
 
@@ -155,7 +202,26 @@ impl SettingsPersister {
         let path = settings_dir.join(SETTINGS_FILE);
         let LoadSettingsResult { settings, .. } =
             Self::load_inner(|| Self::load_from_file(&path)).await;
-        settings
+        match AdminPolicy::load(settings_dir, &settings) {
+            Some(policy) => Self::apply_policy(&policy, settings),
+            None => settings,
+        }
+    }
+
+    /// Return `settings` with the values pinned by `policy` applied.
+    fn apply_policy(policy: &AdminPolicy, settings: Settings) -> Settings {
+        policy.apply(&settings).unwrap_or_else(|error| {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to apply administrator policy")
+            );
+            settings
+        })
+    }
+
+    /// The administrator policy, if there is one.
+    pub const fn policy(&self) -> Option<&AdminPolicy> {
+        self.policy.as_ref()
     }
 
     /// Loads user settings, returning default settings if it should fail.
@@ -227,22 +293,31 @@ impl SettingsPersister {
         let settings_bytes = fs::read(path)
             .await
             .map_err(|error| Error::ReadError(display.as_ref().display().to_string(), error))?;
-        let settings = Self::load_from_bytes(&settings_bytes)?;
+        let keys = CustomRelayKeys::read(&custom_relay_keys::path(display.as_ref())).await?;
+        let settings = Self::load_from_bytes(&settings_bytes, &keys)?;
         Ok(settings)
     }
 
-    fn load_from_bytes(bytes: &[u8]) -> Result<Settings, Error> {
-        serde_json::from_slice(bytes).map_err(Error::ParseError)
+    /// Parse settings from `bytes`, putting back the `keys` of custom relays.
+    fn load_from_bytes(bytes: &[u8], keys: &CustomRelayKeys) -> Result<Settings, Error> {
+        let mut settings = serde_json::from_slice(bytes).map_err(Error::ParseError)?;
+        keys.restore(&mut settings);
+        serde_json::from_value(settings).map_err(Error::ParseError)
     }
 
     async fn save(&mut self) -> Result<(), Error> {
         Self::save_inner(&self.path, &self.settings).await
     }
 
-    /// Serializes the settings and saves them to the given file.
+    /// Serializes the settings and saves them to the given file. The keys of custom relays are
+    /// saved to a separate file.
     async fn save_inner(path: &Path, settings: &Settings) -> Result<(), Error> {
         log::debug!("Writing settings to {}", path.display());
-        let buffer = serde_json::to_string_pretty(settings).map_err(Error::SerializeError)?;
+        let mut settings = serde_json::to_value(settings).map_err(Error::SerializeError)?;
+        CustomRelayKeys::take(&mut settings)
+            .write(&custom_relay_keys::path(path))
+            .await?;
+        let buffer = serde_json::to_string_pretty(&settings).map_err(Error::SerializeError)?;
         Self::save_bytes(path, &buffer).await
     }
 
@@ -269,7 +344,8 @@ impl SettingsPersister {
         for key in preserved.keys {
             match key {
                 SettingsKey::RelaySettings => {
-                    self.settings.relay_settings = old_settings.relay_settings.clone()
+                    self.settings.relay_settings = old_settings.relay_settings.clone();
+                    self.settings.custom_endpoints = old_settings.custom_endpoints.clone();
                 }
                 SettingsKey::ObfuscationSettings => {
                     self.settings.obfuscation_settings = old_settings.obfuscation_settings.clone()
@@ -288,17 +364,22 @@ impl SettingsPersister {
                 SettingsKey::LockdownMode => {
                     self.settings.lockdown_mode = old_settings.lockdown_mode
                 }
-                SettingsKey::AutoConnect => self.settings.auto_connect = old_settings.auto_connect,
+                SettingsKey::AutoConnect => {
+                    self.settings.auto_connect = old_settings.auto_connect;
+                    self.settings.trusted_networks = old_settings.trusted_networks.clone();
+                }
                 SettingsKey::TunnelOptions => {
                     self.settings.tunnel_options = old_settings.tunnel_options.clone()
                 }
                 SettingsKey::RelayOverrides => {
                     self.settings.relay_overrides = old_settings.relay_overrides.clone()
                 }
+                SettingsKey::RelayExclusions => {
+                    self.settings.relay_exclusions = old_settings.relay_exclusions.clone()
+                }
                 SettingsKey::ShowBetaReleases => {
                     self.settings.show_beta_releases = old_settings.show_beta_releases
                 }
-                #[cfg(any(windows, target_os = "android", target_os = "macos"))]
                 SettingsKey::SplitTunnel => {
                     self.settings.split_tunnel = old_settings.split_tunnel.clone()
                 }
@@ -306,6 +387,10 @@ impl SettingsPersister {
             }
         }
 
+        if let Some(policy) = &self.policy {
+            self.settings = Self::apply_policy(policy, std::mem::take(&mut self.settings));
+        }
+
         #[cfg(not(test))]
         {
             use futures::TryFutureExt;
@@ -429,6 +514,14 @@ impl SettingsPersister {
             .map_err(Box::from)
             .map_err(Error::UpdateFailed)?;
 
+        if let Some(locked) = self
+            .policy
+            .as_ref()
+            .and_then(|policy| policy.violation(&new_settings))
+        {
+            return Err(Error::Locked(locked.to_owned()));
+        }
+
         if self.settings == new_settings {
             return Ok(false);
         }
@@ -593,8 +686,23 @@ impl Display for SettingsSummary<'_> {
                     (false, false) => f.write_str("custom, no addrs")?,
                     (false, true) => f.write_str("custom, local")?,
                 }
+                match &self
+                    .settings
+                    .tunnel_options
+                    .dns_options
+                    .custom_options
+                    .encryption
+                {
+                    Some(DnsEncryption::Tls { .. }) => f.write_str(", DoT")?,
+                    Some(DnsEncryption::Https { .. }) => f.write_str(", DoH")?,
+                    None => (),
+                }
             }
         }
+        let num_domain_rules = self.settings.tunnel_options.dns_options.domain_rules.len();
+        if num_domain_rules > 0 {
+            write!(f, ", {num_domain_rules} domain rules")?;
+        }
         Ok(())
     }
 }
@@ -723,7 +831,7 @@ mod test {
               }
             }"#;
 
-        let _ = SettingsPersister::load_from_bytes(settings).unwrap();
+        let _ = SettingsPersister::load_from_bytes(settings, &CustomRelayKeys::default()).unwrap();
     }
 
     /// The [`SettingsPersister`] should always succeed when deserializing a
@@ -772,7 +880,10 @@ mod test {
             should_save,
             settings,
         } = SettingsPersister::load_inner(|| async {
-            SettingsPersister::load_from_bytes(b"Not a valid settings file")
+            SettingsPersister::load_from_bytes(
+                b"Not a valid settings file",
+                &CustomRelayKeys::default(),
+            )
         })
         .await;
 
@@ -800,6 +911,32 @@ mod test {
         assert_eq!(disabled.recents, None);
     }
 
+    #[tokio::test]
+    async fn test_update_locked_setting() {
+        let policy = AdminPolicy::new(serde_json::json!({ "allow_lan": true }), true).unwrap();
+        let mut settings = SettingsPersister {
+            on_change_listeners: vec![],
+            path: PathBuf::new(),
+            settings: policy.apply(&Settings::default()).unwrap(),
+            policy: Some(policy),
+        };
+
+        let result = settings
+            .try_update(|settings| -> Result<(), Error> {
+                settings.allow_lan = false;
+                Ok(())
+            })
+            .await;
+        let Err(error @ Error::Locked(_)) = result else {
+            panic!("expected locked setting error, got {result:?}");
+        };
+        assert_eq!(
+            mullvad_management_interface::Status::from(error).code(),
+            mullvad_management_interface::Code::PermissionDenied
+        );
+        assert!(settings.settings.allow_lan);
+    }
+
     #[tokio::test]
     async fn test_full_reset() {
         // TODO: Make Settings::default() deterministic so that we can fully compare against a freshly generated settings struct
@@ -807,6 +944,7 @@ mod test {
         let mut settings = SettingsPersister {
             on_change_listeners: vec![],
             path: PathBuf::new(),
+            policy: None,
             settings: Settings::default(),
         };
         settings.settings.allow_lan = true;
@@ -828,6 +966,7 @@ mod test {
         let mut settings = SettingsPersister {
             on_change_listeners: vec![],
             path: PathBuf::new(),
+            policy: None,
             settings: Settings::default(),
         };
         settings.settings.allow_lan = true;
@@ -78,13 +78,13 @@ enum MergeStrategy {
 // TODO: Use Default trait when `const_trait_impl`` is available.
 const DEFAULT_MERGE_STRATEGY: MergeStrategy = MergeStrategy::Replace;
 
-struct PermittedKey {
-    key_type: PermittedKeyValue,
+pub(super) struct PermittedKey {
+    pub(super) key_type: PermittedKeyValue,
     merge_strategy: MergeStrategy,
 }
 
 impl PermittedKey {
-    const fn object(keys: &'static [(&'static str, PermittedKey)]) -> Self {
+    pub(super) const fn object(keys: &'static [(&'static str, PermittedKey)]) -> Self {
         Self {
             key_type: PermittedKeyValue::Object(keys),
             merge_strategy: DEFAULT_MERGE_STRATEGY,
@@ -98,7 +98,7 @@ impl PermittedKey {
         }
     }
 
-    const fn any() -> Self {
+    pub(super) const fn any() -> Self {
         Self {
             key_type: PermittedKeyValue::Any,
             merge_strategy: DEFAULT_MERGE_STRATEGY,
@@ -111,7 +111,7 @@ impl PermittedKey {
     }
 }
 
-enum PermittedKeyValue {
+pub(super) enum PermittedKeyValue {
     /// Select subkeys that can be modified at this level
     Object(&'static [(&'static str, PermittedKey)]),
     /// Array that can be modified at this level
@@ -261,7 +261,7 @@ fn merge_relay_overrides(
     Ok(serde_json::Value::Array(new_array))
 }
 
-fn merge_patch_to_value(
+pub(super) fn merge_patch_to_value(
     permitted_key: &'static PermittedKey,
     current_value: &mut serde_json::Value,
     patch_value: &serde_json::Value,
@@ -310,7 +310,7 @@ fn merge_patch_to_value(
     Ok(())
 }
 
-fn validate_patch_value(
+pub(super) fn validate_patch_value(
     permitted_key: &'static PermittedKey,
     json_value: &serde_json::Value,
     recurse_level: usize,
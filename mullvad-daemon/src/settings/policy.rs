@@ -0,0 +1,293 @@
+//! Administrator policy for managed deployments. The policy is a file in the settings directory
+//! that pins a subset of the settings to fixed values, and that may forbid changing the account.
+//!
+//! Pinned settings are written in the same format as the settings file, and are validated and
+//! merged using the same machinery as settings patches. See [super::patch]. Only settings listed in
+//! [PINNABLE_KEYS] may be pinned.
+//!
+//! This implementation must be kept in sync with the
+//! [spec](../../../docs/admin-policy.md).
+
+use super::patch::{self, PermittedKey, PermittedKeyValue};
+use mullvad_types::settings::Settings;
+use serde::Deserialize;
+use std::{fs, io, path::Path};
+use talpid_types::ErrorExt;
+
+/// Name of the administrator policy file, in the settings directory.
+pub const POLICY_FILENAME: &str = "admin-policy.json";
+
+/// Settings that may be pinned by the policy. A pinned value replaces the whole setting.
+const PINNABLE_KEYS: &PermittedKey = &PermittedKey::object(&[
+    ("relay_settings", PermittedKey::any()),
+    ("obfuscation_settings", PermittedKey::any()),
+    ("api_access_methods", PermittedKey::any()),
+    ("allow_lan", PermittedKey::any()),
+    ("lockdown_mode", PermittedKey::any()),
+    ("auto_connect", PermittedKey::any()),
+    (
+        "tunnel_options",
+        PermittedKey::object(&[("dns_options", PermittedKey::any())]),
+    ),
+]);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to read administrator policy")]
+    Read(#[source] io::Error),
+
+    #[cfg(unix)]
+    #[error(
+        "The administrator policy must be owned by root and must only be writable by its owner"
+    )]
+    Insecure,
+
+    #[error("Failed to parse administrator policy")]
+    Parse(#[source] serde_json::Error),
+
+    #[error("Invalid pinned settings in administrator policy")]
+    InvalidSettings(#[source] patch::Error),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    settings: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    lock_account: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdminPolicy {
+    /// Pinned settings, as a partial settings object.
+    pinned: serde_json::Value,
+    /// Pinned values as they are serialized in the settings, keyed by the dotted path of the
+    /// setting, e.g. `tunnel_options.dns_options`.
+    locked: Vec<(String, serde_json::Value)>,
+    /// Whether logging in, logging out and creating accounts is forbidden.
+    lock_account: bool,
+}
+
+impl AdminPolicy {
+    /// Load the administrator policy in `settings_dir`. Returns `None` if there is no policy. If
+    /// the policy exists but cannot be used, every setting that can be pinned is locked at its
+    /// current value in `settings`, and changing the account is forbidden.
+    pub fn load(settings_dir: &Path, settings: &Settings) -> Option<Self> {
+        match Self::read(&settings_dir.join(POLICY_FILENAME)) {
+            Ok(policy) => Some(policy),
+            Err(Error::Read(error)) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Locking all settings that a policy can pin")
+                );
+                Some(Self::lock_all(settings))
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, Error> {
+        let file = fs::File::open(path).map_err(Error::Read)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let metadata = file.metadata().map_err(Error::Read)?;
+            if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+                return Err(Error::Insecure);
+            }
+        }
+        let policy: PolicyFile = serde_json::from_reader(file).map_err(Error::Parse)?;
+        Self::new(
+            serde_json::Value::Object(policy.settings),
+            policy.lock_account,
+        )
+    }
+
+    pub(super) fn new(pinned: serde_json::Value, lock_account: bool) -> Result<Self, Error> {
+        patch::validate_patch_value(PINNABLE_KEYS, &pinned, 0).map_err(Error::InvalidSettings)?;
+
+        let mut policy = AdminPolicy {
+            locked: locked_values(PINNABLE_KEYS, &pinned, ""),
+            pinned,
+            lock_account,
+        };
+
+        // Fail early if the pinned values are not valid settings, and compare against the values
+        // as they are serialized rather than as they were written in the policy.
+        let normalized = policy
+            .apply(&Settings::default())
+            .and_then(|settings| {
+                serde_json::to_value(settings).map_err(patch::Error::SerializeSettings)
+            })
+            .map_err(Error::InvalidSettings)?;
+        for (path, value) in &mut policy.locked {
+            if let Some(normalized) = lookup(&normalized, path) {
+                *value = normalized.clone();
+            }
+        }
+
+        Ok(policy)
+    }
+
+    fn lock_all(settings: &Settings) -> Self {
+        let pinned = serde_json::to_value(settings)
+            .map(|settings| pinnable(PINNABLE_KEYS, &settings))
+            .unwrap_or_else(|_| serde_json::Value::Object(Default::default()));
+        AdminPolicy {
+            locked: locked_values(PINNABLE_KEYS, &pinned, ""),
+            pinned,
+            lock_account: true,
+        }
+    }
+
+    /// Paths of the settings that are locked, e.g. `tunnel_options.dns_options`.
+    pub fn locked_settings(&self) -> impl Iterator<Item = &str> {
+        self.locked.iter().map(|(path, _)| path.as_str())
+    }
+
+    /// Whether logging in, logging out and creating accounts is forbidden.
+    pub fn lock_account(&self) -> bool {
+        self.lock_account
+    }
+
+    /// Return `settings` with the pinned values applied.
+    pub(super) fn apply(&self, settings: &Settings) -> Result<Settings, patch::Error> {
+        let mut settings_value =
+            serde_json::to_value(settings).map_err(patch::Error::SerializeSettings)?;
+        patch::merge_patch_to_value(PINNABLE_KEYS, &mut settings_value, &self.pinned, 0)?;
+        serde_json::from_value(settings_value).map_err(patch::Error::DeserializePatched)
+    }
+
+    /// Return the path of a locked setting whose value in `settings` differs from the pinned
+    /// value, if any. If `settings` cannot be serialized, every locked setting is considered
+    /// violated, since the pinned values cannot be verified.
+    pub(super) fn violation(&self, settings: &Settings) -> Option<&str> {
+        let settings = match serde_json::to_value(settings) {
+            Ok(settings) => settings,
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to serialize settings to check policy")
+                );
+                return self.locked_settings().next();
+            }
+        };
+        self.locked
+            .iter()
+            .find(|(path, pinned)| {
+                // Settings that do not exist on this platform cannot be violated
+                lookup(&settings, path).is_some_and(|value| value != pinned)
+            })
+            .map(|(path, _)| path.as_str())
+    }
+}
+
+/// Return the parts of `value` that can be pinned.
+fn pinnable(permitted_key: &PermittedKey, value: &serde_json::Value) -> serde_json::Value {
+    match (&permitted_key.key_type, value) {
+        (PermittedKeyValue::Object(subkeys), serde_json::Value::Object(map)) => {
+            serde_json::Value::Object(
+                subkeys
+                    .iter()
+                    .filter_map(|(key, subkey)| {
+                        let value = map.get(*key)?;
+                        Some(((*key).to_owned(), pinnable(subkey, value)))
+                    })
+                    .collect(),
+            )
+        }
+        _ => value.clone(),
+    }
+}
+
+/// Return the pinned values in `pinned`, keyed by their dotted path.
+fn locked_values(
+    permitted_key: &PermittedKey,
+    pinned: &serde_json::Value,
+    path: &str,
+) -> Vec<(String, serde_json::Value)> {
+    match (&permitted_key.key_type, pinned) {
+        (PermittedKeyValue::Object(subkeys), serde_json::Value::Object(map)) => map
+            .iter()
+            .flat_map(|(key, value)| {
+                let Some((_, subkey)) = subkeys.iter().find(|(permitted, _)| key == permitted)
+                else {
+                    return vec![];
+                };
+                let path = if path.is_empty() {
+                    key.to_owned()
+                } else {
+                    format!("{path}.{key}")
+                };
+                locked_values(subkey, value, &path)
+            })
+            .collect(),
+        _ => vec![(path.to_owned(), pinned.clone())],
+    }
+}
+
+fn lookup<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.').try_fold(value, |value, key| value.get(key))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pinned_settings() {
+        let policy = AdminPolicy::new(
+            json!({
+                "allow_lan": true,
+                "tunnel_options": { "dns_options": { "state": "custom" } },
+            }),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            policy.locked_settings().collect::<Vec<_>>(),
+            ["allow_lan", "tunnel_options.dns_options"]
+        );
+
+        let mut settings = policy.apply(&Settings::default()).unwrap();
+        assert!(settings.allow_lan);
+        assert_eq!(policy.violation(&settings), None);
+
+        // Settings that are not pinned may be changed
+        settings.auto_connect = !settings.auto_connect;
+        assert_eq!(policy.violation(&settings), None);
+
+        settings.allow_lan = false;
+        assert_eq!(policy.violation(&settings), Some("allow_lan"));
+    }
+
+    #[test]
+    fn test_invalid_policy() {
+        // Only some settings may be pinned
+        assert!(AdminPolicy::new(json!({ "session_log": true }), false).is_err());
+        assert!(AdminPolicy::new(json!({ "tunnel_options": { "wireguard": {} } }), false).is_err());
+        // Pinned values must be valid settings
+        assert!(AdminPolicy::new(json!({ "allow_lan": "yes" }), false).is_err());
+        // Unknown keys are rejected
+        assert!(serde_json::from_str::<PolicyFile>(r#"{ "lock_acount": true }"#).is_err());
+    }
+
+    #[test]
+    fn test_lock_all() {
+        let settings = Settings::default();
+        let policy = AdminPolicy::lock_all(&settings);
+        assert!(policy.lock_account());
+        assert!(
+            policy
+                .locked_settings()
+                .any(|path| path == "relay_settings")
+        );
+        assert_eq!(policy.violation(&settings), None);
+
+        let mut settings = settings;
+        settings.allow_lan = !settings.allow_lan;
+        assert_eq!(policy.violation(&settings), Some("allow_lan"));
+    }
+}
@@ -0,0 +1,154 @@
+use mullvad_types::{settings::TrustedNetworks, states::TargetState};
+use talpid_types::net::NetworkInfo;
+
+/// Decides when trusted network rules should connect or disconnect the tunnel.
+///
+/// The tunnel is disconnected when the host moves to a trusted network, and connected when it
+/// moves to an untrusted network. Nothing happens while the network stays (un)trusted, so that
+/// the user can still connect or disconnect manually.
+#[derive(Debug, Default)]
+pub struct TrustedNetworkState {
+    /// Whether the current network was trusted when the rules were last applied.
+    trusted: Option<bool>,
+}
+
+impl TrustedNetworkState {
+    /// Forget the last decision, so that the rules are applied again on the next evaluation.
+    pub fn reset(&mut self) {
+        self.trusted = None;
+    }
+
+    /// Return the target state to switch to on `network`, if any.
+    pub fn evaluate(
+        &mut self,
+        trusted_networks: &TrustedNetworks,
+        network: &NetworkInfo,
+        lockdown_mode: bool,
+    ) -> Option<TargetState> {
+        // Ignore the absence of a network, so that brief outages do not cause reconnects
+        if !trusted_networks.enabled || network.interface.is_none() {
+            return None;
+        }
+
+        let trusted = trusted_networks.is_trusted(network);
+        if self.trusted.replace(trusted) == Some(trusted) {
+            return None;
+        }
+
+        if !trusted {
+            log::info!("Connecting since the current network is not trusted");
+            Some(TargetState::Secured)
+        } else if lockdown_mode {
+            log::info!("Not disconnecting on trusted network since lockdown mode is enabled");
+            None
+        } else {
+            log::info!("Disconnecting since the current network is trusted");
+            Some(TargetState::Unsecured)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TrustedNetworkState;
+    use mullvad_types::{
+        settings::{TrustedNetwork, TrustedNetworks},
+        states::TargetState,
+    };
+    use talpid_types::net::NetworkInfo;
+
+    fn trusted_networks() -> TrustedNetworks {
+        TrustedNetworks {
+            enabled: true,
+            rules: vec![TrustedNetwork::Ssid("home".to_owned())],
+        }
+    }
+
+    fn network(ssid: &str) -> NetworkInfo {
+        NetworkInfo {
+            interface: Some("wlan0".to_owned()),
+            ssid: Some(ssid.to_owned()),
+            gateway_mac: None,
+        }
+    }
+
+    /// Moving between trusted and untrusted networks disconnects and connects, but staying on the
+    /// same kind of network does nothing.
+    #[test]
+    fn test_network_changes() {
+        let rules = trusted_networks();
+        let mut state = TrustedNetworkState::default();
+
+        assert_eq!(
+            state.evaluate(&rules, &network("home"), false),
+            Some(TargetState::Unsecured)
+        );
+        assert_eq!(state.evaluate(&rules, &network("home"), false), None);
+        assert_eq!(
+            state.evaluate(&rules, &network("cafe"), false),
+            Some(TargetState::Secured)
+        );
+        assert_eq!(state.evaluate(&rules, &network("airport"), false), None);
+        assert_eq!(
+            state.evaluate(&rules, &network("home"), false),
+            Some(TargetState::Unsecured)
+        );
+    }
+
+    /// Losing the network does not count as moving to an untrusted network.
+    #[test]
+    fn test_no_network() {
+        let rules = trusted_networks();
+        let mut state = TrustedNetworkState::default();
+
+        assert_eq!(
+            state.evaluate(&rules, &network("home"), false),
+            Some(TargetState::Unsecured)
+        );
+        assert_eq!(state.evaluate(&rules, &NetworkInfo::default(), false), None);
+        assert_eq!(state.evaluate(&rules, &network("home"), false), None);
+    }
+
+    /// Disabled rules never change the target state.
+    #[test]
+    fn test_disabled() {
+        let rules = TrustedNetworks {
+            enabled: false,
+            ..trusted_networks()
+        };
+        let mut state = TrustedNetworkState::default();
+
+        assert_eq!(state.evaluate(&rules, &network("home"), false), None);
+        assert_eq!(state.evaluate(&rules, &network("cafe"), false), None);
+    }
+
+    /// Lockdown mode prevents disconnecting, but not connecting.
+    #[test]
+    fn test_lockdown_mode() {
+        let rules = trusted_networks();
+        let mut state = TrustedNetworkState::default();
+
+        assert_eq!(state.evaluate(&rules, &network("home"), true), None);
+        assert_eq!(
+            state.evaluate(&rules, &network("cafe"), true),
+            Some(TargetState::Secured)
+        );
+    }
+
+    /// Resetting the state applies the rules again, even if the network is unchanged.
+    #[test]
+    fn test_reset() {
+        let rules = trusted_networks();
+        let mut state = TrustedNetworkState::default();
+
+        assert_eq!(
+            state.evaluate(&rules, &network("cafe"), false),
+            Some(TargetState::Secured)
+        );
+        state.reset();
+        assert_eq!(
+            state.evaluate(&rules, &network("cafe"), false),
+            Some(TargetState::Secured)
+        );
+    }
+}
@@ -7,6 +7,7 @@ use tokio::sync::Mutex;
 use mullvad_daemon_relay_selector::relay_selector::RelaySelectorIO;
 use mullvad_relay_selector::{GetRelay, WireguardConfig};
 use mullvad_types::{
+    custom_endpoint::CustomEndpointsSettings,
     endpoint::MullvadEndpoint,
     location::GeoIpLocation,
     relay_constraints::RelaySettings,
@@ -29,6 +30,9 @@ pub enum Error {
     #[error("Failed to resolve hostname for custom relay")]
     ResolveCustomHostname,
 
+    #[error("No custom endpoints have been saved")]
+    NoCustomEndpoints,
+
     #[error("Failed to get device data")]
     Device(#[from] DeviceError),
 }
@@ -39,10 +43,13 @@ pub(crate) struct ParametersGenerator(Arc<Mutex<InnerParametersGenerator>>);
 struct InnerParametersGenerator {
     relay_selector: RelaySelectorIO,
     relay_settings: RelaySettings,
+    custom_endpoints: CustomEndpointsSettings,
     tunnel_options: TunnelOptions,
     account_manager: AccountManagerHandle,
 
     last_generated_relays: Option<LastSelectedRelays>,
+    /// Connection config of the most recently generated tunnel parameters.
+    last_connection_config: Option<wireguard::ConnectionConfig>,
 }
 
 impl ParametersGenerator {
@@ -51,14 +58,17 @@ impl ParametersGenerator {
         account_manager: AccountManagerHandle,
         relay_selector: RelaySelectorIO,
         relay_settings: RelaySettings,
+        custom_endpoints: CustomEndpointsSettings,
         tunnel_options: TunnelOptions,
     ) -> Self {
         Self(Arc::new(Mutex::new(InnerParametersGenerator {
             tunnel_options,
             relay_selector,
             relay_settings,
+            custom_endpoints,
             account_manager,
             last_generated_relays: None,
+            last_connection_config: None,
         })))
     }
 
@@ -71,6 +81,7 @@ impl ParametersGenerator {
     pub async fn set_settings(&self, settings: Settings) {
         let mut inner = self.0.lock().await;
         inner.relay_settings = settings.relay_settings.clone();
+        inner.custom_endpoints = settings.custom_endpoints.clone();
         inner.relay_selector.set_config(settings);
     }
 
@@ -82,6 +93,11 @@ impl ParametersGenerator {
         relays.server_override
     }
 
+    /// Returns the connection config of the most recently generated tunnel parameters.
+    pub async fn last_connection_config(&self) -> Option<wireguard::ConnectionConfig> {
+        self.0.lock().await.last_connection_config.clone()
+    }
+
     /// Gets the location associated with the last generated tunnel parameters.
     pub async fn get_last_location(&self) -> Option<GeoIpLocation> {
         let inner = self.0.lock().await;
@@ -126,6 +142,10 @@ impl InnerParametersGenerator {
                     Error::ResolveCustomHostname
                 });
         }
+        if let RelaySettings::SavedCustomEndpoints = self.relay_settings {
+            self.last_generated_relays = None;
+            return self.saved_custom_endpoint_parameters(retry_attempt);
+        }
 
         let data = self.device().await?;
         let selected_relay = self
@@ -136,6 +156,7 @@ impl InnerParametersGenerator {
             endpoint,
             obfuscator,
             inner,
+            ..
         } = selected_relay;
 
         let server_override = {
@@ -157,6 +178,40 @@ impl InnerParametersGenerator {
         Ok(self.create_wireguard_tunnel_parameters(endpoint, data, obfuscator))
     }
 
+    /// Generate tunnel parameters for one of the saved custom endpoints. The endpoints are tried
+    /// in priority order, moving on to the next one every time the tunnel state machine retries,
+    /// e.g. after the connectivity check has timed out. Endpoints whose host cannot be resolved
+    /// are skipped.
+    fn saved_custom_endpoint_parameters(
+        &self,
+        retry_attempt: u32,
+    ) -> Result<TunnelParameters, Error> {
+        let endpoints = &self.custom_endpoints;
+        if endpoints.is_empty() {
+            return Err(Error::NoCustomEndpoints);
+        }
+
+        for saved in endpoints.failover_order(retry_attempt) {
+            match saved
+                .endpoint
+                .to_tunnel_parameters(self.tunnel_options.clone())
+            {
+                Ok(parameters) => {
+                    log::info!("Using saved custom endpoint \"{}\"", saved.name);
+                    return Ok(parameters);
+                }
+                Err(error) => log::error!(
+                    "{}",
+                    error.display_chain_with_msg(&format!(
+                        "Failed to resolve hostname for custom endpoint \"{}\"",
+                        saved.name
+                    ))
+                ),
+            }
+        }
+        Err(Error::ResolveCustomHostname)
+    }
+
     fn create_wireguard_tunnel_parameters(
         &self,
         endpoint: MullvadEndpoint,
@@ -205,9 +260,12 @@ impl TunnelParametersGenerator for ParametersGenerator {
         let generator = self.0.clone();
         Box::pin(async move {
             let mut inner = generator.lock().await;
-            inner
-                .generate(retry_attempt, ip_availability)
-                .await
+            let parameters = inner.generate(retry_attempt, ip_availability).await;
+            inner.last_connection_config = parameters
+                .as_ref()
+                .ok()
+                .map(|parameters| parameters.connection.clone());
+            parameters
                 .inspect_err(|error| {
                     log::error!(
                         "{}",
@@ -237,9 +295,10 @@ impl From<Error> for ParameterGenerationError {
             Error::SelectRelay(mullvad_relay_selector::Error::NoRelayExit(_)) => {
                 ParameterGenerationError::NoMatchingRelayExit
             }
-            Error::NoAuthDetails | Error::SelectRelay(_) | Error::Device(_) => {
-                ParameterGenerationError::NoMatchingRelay
-            }
+            Error::NoAuthDetails
+            | Error::NoCustomEndpoints
+            | Error::SelectRelay(_)
+            | Error::Device(_) => ParameterGenerationError::NoMatchingRelay,
         }
     }
 }
@@ -0,0 +1,55 @@
+//! Periodic sampling of the traffic through the current tunnel.
+
+use crate::{DaemonCommand, DaemonEventSender};
+use futures::channel::oneshot;
+use std::{ops::ControlFlow, time::Duration};
+use talpid_core::mpsc::Sender;
+
+/// How often the tunnel traffic is sampled.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Bytes received and sent through the current tunnel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Traffic {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+impl Traffic {
+    pub fn total(&self) -> u64 {
+        self.rx_bytes.saturating_add(self.tx_bytes)
+    }
+}
+
+/// Sample the traffic through the current tunnel every [`POLL_INTERVAL`] and pass it to
+/// `on_sample`, until it breaks or the daemon shuts down. For multihop, the traffic is that of the
+/// busiest peer, so that it is not counted once per hop. Nothing is sampled while there is no
+/// tunnel.
+pub(crate) async fn poll(
+    command_tx: &DaemonEventSender<DaemonCommand>,
+    mut on_sample: impl FnMut(Traffic) -> ControlFlow<()>,
+) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let (tx, rx) = oneshot::channel();
+        if command_tx.send(DaemonCommand::GetTunnelStats(tx)).is_err() {
+            return;
+        }
+        let Ok(Some(peers)) = rx.await else {
+            continue;
+        };
+        let busiest = peers
+            .iter()
+            .map(|peer| Traffic {
+                rx_bytes: peer.rx_bytes,
+                tx_bytes: peer.tx_bytes,
+            })
+            .max_by_key(Traffic::total);
+        if let Some(traffic) = busiest
+            && on_sample(traffic).is_break()
+        {
+            return;
+        }
+    }
+}
@@ -70,6 +70,29 @@ pub async fn resolve_configs(
     resolvers: &[Nameserver],
     domain: &str,
 ) -> Result<Vec<config::ProxyConfig>, Error> {
+    resolve_config_with_resolverconfig(
+        doh_resolver_config(resolvers),
+        ResolverOpts::default(),
+        domain,
+        DEFAULT_TIMEOUT,
+    )
+    .await
+}
+
+/// Looks up the IP addresses of `domain` using known DoH resolvers provided by
+/// [default_resolvers]. Unlike the system resolver, this does not leak the looked up domain to
+/// the local network.
+pub async fn resolve_default_ips(domain: &str) -> Result<Vec<IpAddr>, Error> {
+    lookup_ip(
+        doh_resolver_config(&default_resolvers()),
+        ResolverOpts::default(),
+        domain,
+        DEFAULT_TIMEOUT,
+    )
+    .await
+}
+
+fn doh_resolver_config(resolvers: &[Nameserver]) -> ResolverConfig {
     let mut config = ResolverConfig::default();
     for resolver in resolvers.iter() {
         let servers = ServerGroup {
@@ -82,9 +105,7 @@ pub async fn resolve_configs(
             config.add_name_server(server);
         }
     }
-
-    resolve_config_with_resolverconfig(config, ResolverOpts::default(), domain, DEFAULT_TIMEOUT)
-        .await
+    config
 }
 
 pub async fn resolve_config_with_resolverconfig(
@@ -93,19 +114,8 @@ pub async fn resolve_config_with_resolverconfig(
     domain: &str,
     timeout: Duration,
 ) -> Result<Vec<config::ProxyConfig>, Error> {
-    let provider = TokioRuntimeProvider::default();
-    let resolver = TokioResolver::builder_with_config(resolver_config, provider)
-        .with_options(options)
-        .with_tls_config(client_config_tls12())
-        .build()
-        .map_err(Error::ProtocolError)?;
-
-    let lookup = tokio::time::timeout(timeout, resolver.lookup_ip(domain))
-        .await
-        .map_err(Error::Timeout)?
-        .map_err(Error::ProtocolError)?;
-
-    let addrs = lookup.iter().filter_map(|addr| match addr {
+    let addrs = lookup_ip(resolver_config, options, domain, timeout).await?;
+    let addrs = addrs.into_iter().filter_map(|addr| match addr {
         IpAddr::V4(_) => None,
         IpAddr::V6(addr) => Some(addr),
     });
@@ -125,6 +135,27 @@ pub async fn resolve_config_with_resolverconfig(
     Ok(proxy_configs)
 }
 
+async fn lookup_ip(
+    resolver_config: ResolverConfig,
+    options: ResolverOpts,
+    domain: &str,
+    timeout: Duration,
+) -> Result<Vec<IpAddr>, Error> {
+    let provider = TokioRuntimeProvider::default();
+    let resolver = TokioResolver::builder_with_config(resolver_config, provider)
+        .with_options(options)
+        .with_tls_config(client_config_tls12())
+        .build()
+        .map_err(Error::ProtocolError)?;
+
+    let lookup = tokio::time::timeout(timeout, resolver.lookup_ip(domain))
+        .await
+        .map_err(Error::Timeout)?
+        .map_err(Error::ProtocolError)?;
+
+    Ok(lookup.iter().collect())
+}
+
 fn client_config_tls12() -> ClientConfig {
     let root_store = rustls::RootCertStore {
         roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
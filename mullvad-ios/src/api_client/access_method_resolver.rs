@@ -91,12 +91,16 @@ impl AccessMethodResolver for SwiftAccessMethodResolver {
                 };
                 ApiConnectionMode::Proxied(ProxyConfig::from(edp))
             }
-            // AccessMethod::BuiltIn(BuiltInAccessMethod::DomainFronting) => {
-            //     mullvad_api::domain_fronting::resolve().await?
-            // }
+            AccessMethod::BuiltIn(BuiltInAccessMethod::DomainFronting) => {
+                mullvad_api::domain_fronting::resolve().await?
+            }
             AccessMethod::Custom(config) => {
                 ApiConnectionMode::Proxied(ProxyConfig::from(config.clone()))
             }
+            AccessMethod::CustomDomainFronting(config) => {
+                mullvad_api::domain_fronting::resolve_custom(&config.front, &config.proxy_host)
+                    .await?
+            }
         };
 
         let allowed_endpoint = {
@@ -111,7 +115,7 @@ impl AccessMethodResolver for SwiftAccessMethodResolver {
         };
         let provenance = match access_method {
             AccessMethod::BuiltIn(_) => "Built-in",
-            AccessMethod::Custom(_) => "Custom",
+            AccessMethod::Custom(_) | AccessMethod::CustomDomainFronting(_) => "Custom",
         };
         log::info!(
             "AccessMethodResolver: endpoint ({}): {:}, connection mode: {:}",
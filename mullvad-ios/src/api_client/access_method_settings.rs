@@ -6,7 +6,7 @@ use std::{
 
 use mullvad_types::access_method::{
     AccessMethod, AccessMethodSetting,
-    BuiltInAccessMethod::{Bridge, Direct, EncryptedDnsProxy},
+    BuiltInAccessMethod::{Bridge, Direct, DomainFronting, EncryptedDnsProxy},
     Id, Settings,
 };
 use talpid_types::net::proxy::{self, Shadowsocks, Socks5Remote};
@@ -147,7 +147,19 @@ pub unsafe extern "C" fn init_access_method_settings_wrapper(
     // SAFETY: custom_methods_raw must be a valid pointer to an AccessMethodSetting.
     let custom =
         unsafe { access_methods_from_raw_array(custom_methods_raw.cast(), custom_method_count) };
-    let settings = Settings::new(direct, mullvad_bridges, encrypted_dns_proxy, custom);
+    // Domain fronting is not offered by the iOS app yet
+    let domain_fronting = AccessMethodSetting::new(
+        DomainFronting.canonical_name(),
+        false,
+        AccessMethod::BuiltIn(DomainFronting),
+    );
+    let settings = Settings::new(
+        direct,
+        mullvad_bridges,
+        encrypted_dns_proxy,
+        domain_fronting,
+        custom,
+    );
     let context = SwiftAccessMethodSettingsContext { settings };
     SwiftAccessMethodSettingsWrapper::new(context)
 }
@@ -641,6 +641,7 @@ impl IosTunnelAdapter {
                     tunnel_obfuscation::shadowsocks::Settings {
                         shadowsocks_endpoint: ingress_endpoint,
                         wireguard_endpoint: wg_ep,
+                        credentials: None,
                     },
                 )
             }
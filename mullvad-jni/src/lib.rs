@@ -205,6 +205,7 @@ fn spawn_daemon(
         cache_dir,
         android_context,
         endpoint,
+        metrics_endpoint: None,
         log_handle,
     };
 
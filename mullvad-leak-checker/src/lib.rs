@@ -35,6 +35,25 @@ impl From<String> for Interface {
     }
 }
 
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Name(name) => f.write_str(name),
+
+            #[cfg(target_os = "windows")]
+            Self::Luid(luid) => write!(
+                f,
+                "LUID {}",
+                // SAFETY: u64 is valid for all bit patterns, so reading the union as a u64 is safe.
+                unsafe { luid.Value }
+            ),
+
+            #[cfg(target_os = "macos")]
+            Self::Index(index) => write!(f, "index {index}"),
+        }
+    }
+}
+
 impl fmt::Debug for Interface {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -34,6 +34,22 @@ pub struct TracerouteOpt {
     #[clap(long)]
     #[cfg(unix)]
     pub icmp: bool,
+
+    /// Highest TTL value of the probe packets. One probe packet is sent for each TTL value from 1
+    /// up to and including this value.
+    #[clap(
+        long,
+        default_value_t = DEFAULT_MAX_TTL,
+        value_parser = clap::value_parser!(u16).range(1..=i64::from(MAX_TTL)),
+    )]
+    pub max_ttl: u16,
+}
+
+impl TracerouteOpt {
+    /// Range of TTL values for the probe packets.
+    pub fn ttl_range(&self) -> Range<u16> {
+        1..self.max_ttl.saturating_add(1)
+    }
 }
 
 /// Timeout of the leak test as a whole. Should be more than [`SEND_TIMEOUT`] + `RECV_GRACE_TIME`.
@@ -49,8 +65,16 @@ const RECV_GRACE_TIME: Duration = Duration::from_millis(220);
 /// Time in-between send of each probe packet.
 const PROBE_INTERVAL: Duration = Duration::from_millis(100);
 
-/// Range of TTL values for the probe packets.
-const DEFAULT_TTL_RANGE: Range<u16> = 1..4;
+/// Default highest TTL value of the probe packets.
+///
+/// A leak is detected as soon as a single node outside the tunnel responds, and the first hops are
+/// almost always the local router and the ISP. Probing further only adds packets that leave the
+/// local network without making detection any more reliable.
+pub const DEFAULT_MAX_TTL: u16 = 3;
+
+/// Highest allowed TTL value of the probe packets. All probe packets must be sent within
+/// [`SEND_TIMEOUT`], with [`PROBE_INTERVAL`] in-between each one.
+pub const MAX_TTL: u16 = (SEND_TIMEOUT.as_millis() / PROBE_INTERVAL.as_millis()) as u16;
 
 /// [try_run_leak_test], but on an error, assume we aren't leaking.
 pub async fn run_leak_test(opt: &TracerouteOpt) -> LeakStatus {
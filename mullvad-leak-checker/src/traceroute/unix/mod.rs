@@ -9,7 +9,7 @@ use std::{
 
 use crate::{
     Interface, LeakStatus,
-    traceroute::{DEFAULT_TTL_RANGE, LEAK_TIMEOUT, PROBE_INTERVAL, SEND_TIMEOUT, TracerouteOpt},
+    traceroute::{LEAK_TIMEOUT, PROBE_INTERVAL, SEND_TIMEOUT, TracerouteOpt},
     util::{Ip, get_interface_ip},
 };
 
@@ -145,8 +145,8 @@ async fn send_icmp_probes<Impl: Traceroute>(
     opt: &TracerouteOpt,
     socket: &impl AsyncIcmpSocket,
 ) -> anyhow::Result<()> {
-    log::debug!("Sending probe packets (ttl={DEFAULT_TTL_RANGE:?})");
-    for ttl in DEFAULT_TTL_RANGE {
+    log::debug!("Sending probe packets (ttl={:?})", opt.ttl_range());
+    for ttl in opt.ttl_range() {
         log::trace!("Sending probe packet (ttl={ttl})");
 
         socket
@@ -270,8 +270,8 @@ async fn send_udp_probes(opt: &TracerouteOpt, socket: &mut AsyncUdpSocket) -> an
         // `opt.port` overrides the default port range
         .map(|port| opt.port.unwrap_or(port));
 
-    log::debug!("Sending probe packets (ttl={DEFAULT_TTL_RANGE:?})");
-    for (port, ttl) in ports.zip(DEFAULT_TTL_RANGE) {
+    log::debug!("Sending probe packets (ttl={:?})", opt.ttl_range());
+    for (port, ttl) in ports.zip(opt.ttl_range()) {
         log::trace!("Sending probe packet (ttl={ttl})");
 
         socket
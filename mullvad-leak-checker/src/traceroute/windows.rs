@@ -7,7 +7,7 @@ use tokio::time::sleep;
 
 use crate::{
     LeakInfo, LeakStatus,
-    traceroute::{DEFAULT_TTL_RANGE, LEAK_TIMEOUT, PROBE_INTERVAL, SEND_TIMEOUT, TracerouteOpt},
+    traceroute::{LEAK_TIMEOUT, PROBE_INTERVAL, SEND_TIMEOUT, TracerouteOpt},
     util::{Ip, get_interface_ip},
 };
 
@@ -27,7 +27,7 @@ pub async fn traceroute_using_ping(opt: &TracerouteOpt) -> anyhow::Result<LeakSt
 
     let mut ping_tasks = FuturesUnordered::new();
 
-    for (i, ttl) in DEFAULT_TTL_RANGE.enumerate() {
+    for (i, ttl) in opt.ttl_range().enumerate() {
         // Don't send all pings at once, wait a bit in between
         // each one to avoid sending more than necessary
         let probe_delay = PROBE_INTERVAL * i as u32;
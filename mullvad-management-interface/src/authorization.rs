@@ -0,0 +1,624 @@
+//! Per-RPC authorization of management interface clients, based on the credentials of the
+//! process on the other end of the Unix socket (`SO_PEERCRED`).
+//!
+//! Authorization is only enforced if an [`AccessPolicy`] file exists. Read-only RPCs may then be
+//! called by anyone who can connect to the socket, while all other RPCs may only be called by root
+//! and by members of the admin group of the policy. Read-only requests from other clients are
+//! marked as [`Unprivileged`], and private keys, passwords and account numbers must be redacted
+//! from the responses to them.
+//!
+//! Note that this fails open: if there is no policy file, which is the default, every client may
+//! call every RPC, just like when authorization was not supported.
+
+use crate::types;
+use nix::unistd::{Group, Uid, User};
+use serde::Deserialize;
+use std::{
+    fmt, fs, io,
+    os::unix::fs::MetadataExt,
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use talpid_types::ErrorExt;
+use tokio::net::unix::UCred;
+use tonic::{Request, Status, codegen::http, transport::server::UdsConnectInfo};
+use tower::{Layer, Service};
+
+/// Name of the access policy file, in the settings directory.
+pub const ACCESS_POLICY_FILENAME: &str = "management-access.json";
+
+/// RPCs that may be called by any client, as `(service, method)`.
+const READ_ONLY_METHODS: &[(&str, &str)] = &[
+    (MANAGEMENT_SERVICE, "GetTunnelState"),
+    (MANAGEMENT_SERVICE, "EventsListen"),
+    (MANAGEMENT_SERVICE, "TunnelStatsListen"),
+    (MANAGEMENT_SERVICE, "GetCurrentVersion"),
+    (MANAGEMENT_SERVICE, "GetVersionInfo"),
+    (MANAGEMENT_SERVICE, "IsPerformingPostUpgrade"),
+    (MANAGEMENT_SERVICE, "GetSettings"),
+    (MANAGEMENT_SERVICE, "GetRelayLocations"),
+    (MANAGEMENT_SERVICE, "GetRelayLatencies"),
+    (MANAGEMENT_SERVICE, "GetCurrentNetwork"),
+    (MANAGEMENT_SERVICE, "GetWireguardKey"),
+    (MANAGEMENT_SERVICE, "ShadowsocksCiphers"),
+    (MANAGEMENT_SERVICE, "GetBridges"),
+    (MANAGEMENT_SERVICE, "GetCurrentApiAccessMethod"),
+    (MANAGEMENT_SERVICE, "GetSplitTunnelProcesses"),
+    (MANAGEMENT_SERVICE, "SplitTunnelIsSupported"),
+    (MANAGEMENT_SERVICE, "GetExcludedProcesses"),
+    (MANAGEMENT_SERVICE, "NeedFullDiskPermissions"),
+    (MANAGEMENT_SERVICE, "GetFeatureIndicators"),
+    (MANAGEMENT_SERVICE, "GetLeakCheckReport"),
+    (MANAGEMENT_SERVICE, "AppUpgradeEventsListen"),
+    (MANAGEMENT_SERVICE, "GetMigrationEvent"),
+    (RELAY_SELECTOR_SERVICE, "PartitionRelays"),
+    (RELAY_SELECTOR_SERVICE, "GetSelectionHistory"),
+];
+
+const MANAGEMENT_SERVICE: &str = "mullvad_daemon.management_interface.ManagementService";
+const RELAY_SELECTOR_SERVICE: &str = "mullvad_daemon.relay_selector.RelaySelectorService";
+
+/// Replaces redacted passwords.
+const REDACTED_PASSWORD: &str = "[redacted]";
+/// Replaces redacted private and preshared keys.
+const REDACTED_KEY: [u8; 32] = [0; 32];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to read access policy")]
+    Read(#[source] io::Error),
+
+    #[error("The access policy must be owned by root and must only be writable by its owner")]
+    Insecure,
+
+    #[error("Failed to parse access policy")]
+    Parse(#[source] serde_json::Error),
+}
+
+/// Which clients may call RPCs that are not read-only. Root may always call every RPC.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccessPolicy {
+    /// Name of a group whose members may call every RPC.
+    admin_group: Option<String>,
+}
+
+impl AccessPolicy {
+    /// Load the access policy at `path`. Returns `None` if there is no policy, in which case every
+    /// client may call every RPC. If the policy exists but cannot be used, only root is allowed
+    /// to call RPCs that are not read-only.
+    pub fn load(path: &Path) -> Option<Self> {
+        match Self::read(path) {
+            Ok(policy) => Some(policy),
+            Err(Error::Read(error)) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Restricting the management interface to root")
+                );
+                Some(AccessPolicy::default())
+            }
+        }
+    }
+
+    fn read(path: &Path) -> Result<Self, Error> {
+        let file = fs::File::open(path).map_err(Error::Read)?;
+        let metadata = file.metadata().map_err(Error::Read)?;
+        if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+            return Err(Error::Insecure);
+        }
+        serde_json::from_reader(file).map_err(Error::Parse)
+    }
+
+    pub fn admin_group(&self) -> Option<&str> {
+        self.admin_group.as_deref()
+    }
+
+    /// Returns whether a client running as the user `uid` with the primary group `gid` may call
+    /// every RPC. This may block while looking up the members of the admin group.
+    fn is_admin(&self, uid: u32, gid: u32) -> bool {
+        uid == 0
+            || self
+                .admin_group
+                .as_deref()
+                .is_some_and(|group| is_member(uid, gid, group))
+    }
+}
+
+/// Marks read-only requests from clients that may not call every RPC.
+#[derive(Debug, Clone, Copy)]
+pub struct Unprivileged;
+
+/// Returns whether `request` was made by a client that may only call read-only RPCs. Private keys,
+/// passwords and account numbers must then be redacted from the response.
+pub fn is_unprivileged<T>(request: &Request<T>) -> bool {
+    request.extensions().get::<Unprivileged>().is_some()
+}
+
+/// Redact private keys, passwords and account numbers from `event`.
+pub fn redact_event(event: &mut types::DaemonEvent) {
+    use types::daemon_event::Event;
+
+    match &mut event.event {
+        Some(Event::Settings(settings)) => redact_settings(settings),
+        Some(Event::NewAccessMethod(access_method)) => redact_access_method(access_method),
+        Some(Event::Device(event)) => {
+            if let Some(device) = event
+                .new_state
+                .as_mut()
+                .and_then(|state| state.device.as_mut())
+            {
+                device.account_number.clear();
+            }
+        }
+        Some(Event::RemoveDevice(event)) => event.account_number.clear(),
+        _ => (),
+    }
+}
+
+/// Redact the keys of custom relays and the passwords of custom proxies from `settings`.
+pub fn redact_settings(settings: &mut types::Settings) {
+    if let Some(types::relay_settings::Endpoint::Custom(relay)) = settings
+        .relay_settings
+        .as_mut()
+        .and_then(|relay_settings| relay_settings.endpoint.as_mut())
+    {
+        redact_custom_relay(relay);
+    }
+    let saved_relays = settings
+        .custom_endpoints
+        .iter_mut()
+        .flat_map(|custom_endpoints| &mut custom_endpoints.endpoints)
+        .filter_map(|saved| saved.endpoint.as_mut());
+    for relay in saved_relays {
+        redact_custom_relay(relay);
+    }
+    if let Some(access_methods) = &mut settings.api_access_methods {
+        for access_method in &mut access_methods.custom {
+            redact_access_method(access_method);
+        }
+    }
+    if let Some(bridge) = settings
+        .obfuscation_settings
+        .as_mut()
+        .and_then(|obfuscation| obfuscation.custom_bridge.as_mut())
+        .and_then(|custom_bridge| custom_bridge.bridge.as_mut())
+    {
+        redact_proxy(bridge);
+    }
+}
+
+/// Redact the password of `access_method`, if it is a custom proxy.
+pub fn redact_access_method(access_method: &mut types::AccessMethodSetting) {
+    if let Some(types::access_method::AccessMethod::Custom(proxy)) = access_method
+        .access_method
+        .as_mut()
+        .and_then(|access_method| access_method.access_method.as_mut())
+    {
+        redact_proxy(proxy);
+    }
+}
+
+fn redact_custom_relay(relay: &mut types::CustomRelaySettings) {
+    if let Some(tunnel) = relay
+        .config
+        .as_mut()
+        .and_then(|config| config.tunnel.as_mut())
+    {
+        tunnel.private_key = REDACTED_KEY.to_vec();
+    }
+    if let Some(preshared_key) = &mut relay.preshared_key {
+        *preshared_key = REDACTED_KEY.to_vec();
+    }
+}
+
+fn redact_proxy(proxy: &mut types::CustomProxy) {
+    use types::custom_proxy::ProxyMethod;
+
+    match &mut proxy.proxy_method {
+        Some(ProxyMethod::Socks5remote(socks)) => {
+            if let Some(auth) = &mut socks.auth {
+                auth.password = REDACTED_PASSWORD.to_owned();
+            }
+        }
+        Some(ProxyMethod::Shadowsocks(shadowsocks)) => {
+            shadowsocks.password = REDACTED_PASSWORD.to_owned();
+        }
+        Some(ProxyMethod::HttpConnect(http)) => {
+            if let Some(auth) = &mut http.auth {
+                auth.password = REDACTED_PASSWORD.to_owned();
+            }
+        }
+        Some(ProxyMethod::Socks5local(_)) | None => (),
+    }
+}
+
+fn is_read_only(path: &str) -> bool {
+    let Some((service, method)) = path.trim_start_matches('/').split_once('/') else {
+        return false;
+    };
+    READ_ONLY_METHODS.contains(&(service, method))
+}
+
+/// Returns whether the client is a member of the group named `group_name`, either as its primary
+/// group or as a supplementary group.
+fn is_member(uid: u32, gid: u32, group_name: &str) -> bool {
+    let group = match Group::from_name(group_name) {
+        Ok(Some(group)) => group,
+        Ok(None) => {
+            log::warn!("Admin group \"{group_name}\" does not exist");
+            return false;
+        }
+        Err(error) => {
+            log::error!("Failed to look up admin group \"{group_name}\": {error}");
+            return false;
+        }
+    };
+    if gid == group.gid.as_raw() {
+        return true;
+    }
+    match User::from_uid(Uid::from_raw(uid)) {
+        Ok(Some(user)) => group.mem.contains(&user.name),
+        _ => false,
+    }
+}
+
+/// Rejects calls that are not allowed by the access policy, if there is one.
+#[derive(Debug, Clone)]
+pub(crate) struct AuthorizationLayer {
+    policy: Option<Arc<AccessPolicy>>,
+}
+
+impl AuthorizationLayer {
+    pub fn new(policy: Option<AccessPolicy>) -> Self {
+        AuthorizationLayer {
+            policy: policy.map(Arc::new),
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthorizationLayer {
+    type Service = Authorization<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Authorization {
+            inner,
+            policy: self.policy.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Authorization<S> {
+    inner: S,
+    policy: Option<Arc<AccessPolicy>>,
+}
+
+impl<S, B> Service<http::Request<B>> for Authorization<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<tonic::body::Body>>
+        + Clone
+        + Send
+        + 'static,
+    S::Error: Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let Some(policy) = self.policy.clone() else {
+            return Box::pin(self.inner.call(request));
+        };
+        let path = request.uri().path().to_owned();
+        let read_only = is_read_only(&path);
+        let peer = request
+            .extensions()
+            .get::<UdsConnectInfo>()
+            .and_then(|info| info.peer_cred);
+
+        // The service that was polled ready must be the one that is called
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let allowed = match peer {
+                Some(peer) => {
+                    tokio::task::spawn_blocking(move || policy.is_admin(peer.uid(), peer.gid()))
+                        .await
+                        .unwrap_or(false)
+                }
+                None => false,
+            };
+            if !allowed {
+                if !read_only {
+                    log::warn!("Denied {path} to {}", Peer(peer.as_ref()));
+                    return Ok(Status::permission_denied(
+                        "Only administrators may call this RPC. See the management access policy",
+                    )
+                    .into_http());
+                }
+                request.extensions_mut().insert(Unprivileged);
+            }
+            inner.call(request).await
+        })
+    }
+}
+
+struct Peer<'a>(Option<&'a UCred>);
+
+impl fmt::Display for Peer<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(peer) => {
+                write!(f, "uid {}", peer.uid())?;
+                if let Some(pid) = peer.pid() {
+                    write!(f, " (pid {pid})")?;
+                }
+                Ok(())
+            }
+            None => write!(f, "client with unknown credentials"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_read_only_methods() {
+        assert!(is_read_only(
+            "/mullvad_daemon.management_interface.ManagementService/GetTunnelState"
+        ));
+        assert!(is_read_only(
+            "/mullvad_daemon.relay_selector.RelaySelectorService/PartitionRelays"
+        ));
+        assert!(!is_read_only(
+            "/mullvad_daemon.management_interface.ManagementService/SetLockdownMode"
+        ));
+        assert!(is_read_only(
+            "/mullvad_daemon.management_interface.ManagementService/GetSettings"
+        ));
+        assert!(!is_read_only(
+            "/mullvad_daemon.management_interface.ManagementService/GetAccountHistory"
+        ));
+        assert!(!is_read_only(
+            "/mullvad_daemon.relay_selector.RelaySelectorService/GetTunnelState"
+        ));
+        assert!(!is_read_only("GetTunnelState"));
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy: AccessPolicy =
+            serde_json::from_str(r#"{ "admin_group": "vpnadmin" }"#).unwrap();
+        assert_eq!(policy.admin_group(), Some("vpnadmin"));
+        assert_eq!(
+            serde_json::from_str::<AccessPolicy>("{}").unwrap(),
+            AccessPolicy::default()
+        );
+        assert!(serde_json::from_str::<AccessPolicy>(r#"{ "admins": "vpnadmin" }"#).is_err());
+    }
+
+    /// Write an access policy with the permissions `mode` to a new file.
+    fn write_policy(name: &str, mode: u32) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!(
+            "mullvad-access-policy-{}-{name}.json",
+            std::process::id()
+        ));
+        fs::write(&path, r#"{ "admin_group": "vpnadmin" }"#).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_read_policy() {
+        let missing = std::env::temp_dir().join("mullvad-access-policy-missing.json");
+        assert_eq!(AccessPolicy::load(&missing), None);
+
+        // Files that are not owned by root are rejected
+        let path = write_policy("not-root", 0o644);
+        if Uid::effective().is_root() {
+            nix::unistd::chown(&path, Some(Uid::from_raw(65534)), None).unwrap();
+        }
+        assert!(matches!(AccessPolicy::read(&path), Err(Error::Insecure)));
+        // A policy that cannot be used restricts every RPC that is not read-only to root
+        assert_eq!(AccessPolicy::load(&path), Some(AccessPolicy::default()));
+        fs::remove_file(path).unwrap();
+
+        // So are files that others than the owner may write to
+        for (name, mode) in [("group-writable", 0o664), ("world-writable", 0o646)] {
+            let path = write_policy(name, mode);
+            assert!(matches!(AccessPolicy::read(&path), Err(Error::Insecure)));
+            fs::remove_file(path).unwrap();
+        }
+
+        // Only root can create a policy that is accepted
+        if Uid::effective().is_root() {
+            let path = write_policy("secure", 0o644);
+            assert_eq!(
+                AccessPolicy::read(&path).unwrap().admin_group(),
+                Some("vpnadmin")
+            );
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    /// Name of the primary group of this process, which it is a member of.
+    fn own_group() -> String {
+        Group::from_gid(nix::unistd::getegid())
+            .unwrap()
+            .expect("primary group exists")
+            .name
+    }
+
+    /// A policy whose admin group does not exist, so that only root is an admin.
+    fn root_only_policy() -> AccessPolicy {
+        AccessPolicy {
+            admin_group: Some("mullvad-test-no-such-group".to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_is_admin() {
+        let nobody = 65534;
+        assert!(AccessPolicy::default().is_admin(0, 0));
+        assert!(root_only_policy().is_admin(0, 0));
+        assert!(!AccessPolicy::default().is_admin(nobody, nobody));
+        assert!(!root_only_policy().is_admin(nobody, nobody));
+
+        let group = Group::from_name(&own_group()).unwrap().unwrap();
+        let policy = AccessPolicy {
+            admin_group: Some(group.name),
+        };
+        assert!(policy.is_admin(nobody, group.gid.as_raw()));
+    }
+
+    const SET_LOCKDOWN_MODE: &str =
+        "/mullvad_daemon.management_interface.ManagementService/SetLockdownMode";
+    const GET_SETTINGS: &str = "/mullvad_daemon.management_interface.ManagementService/GetSettings";
+
+    /// Call `path` through an [`Authorization`] service as a client with the credentials `peer`.
+    /// Returns the status code if the call was denied, and otherwise whether it was marked as
+    /// [`Unprivileged`].
+    async fn call(
+        policy: Option<AccessPolicy>,
+        peer: Option<UCred>,
+        path: &str,
+    ) -> Result<bool, tonic::Code> {
+        let inner = tower::service_fn(|request: http::Request<()>| async move {
+            let mut response = http::Response::new(tonic::body::Body::empty());
+            if request.extensions().get::<Unprivileged>().is_some() {
+                response.extensions_mut().insert(Unprivileged);
+            }
+            Ok::<_, std::convert::Infallible>(response)
+        });
+        let mut service = AuthorizationLayer::new(policy).layer(inner);
+
+        let mut request = http::Request::builder().uri(path).body(()).unwrap();
+        request.extensions_mut().insert(UdsConnectInfo {
+            peer_addr: None,
+            peer_cred: peer,
+        });
+        let response = service.call(request).await.unwrap();
+        match Status::from_header_map(response.headers()) {
+            Some(status) => Err(status.code()),
+            None => Ok(response.extensions().get::<Unprivileged>().is_some()),
+        }
+    }
+
+    #[test]
+    fn test_authorization() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (socket, _other) = tokio::net::UnixStream::pair().unwrap();
+            let peer = socket.peer_cred().unwrap();
+            let is_root = peer.uid() == 0;
+
+            // Every call is allowed without a policy
+            assert_eq!(call(None, Some(peer), SET_LOCKDOWN_MODE).await, Ok(false));
+
+            // Members of the admin group may call every RPC
+            let policy = AccessPolicy {
+                admin_group: Some(own_group()),
+            };
+            assert_eq!(
+                call(Some(policy.clone()), Some(peer), SET_LOCKDOWN_MODE).await,
+                Ok(false)
+            );
+            assert_eq!(
+                call(Some(policy), Some(peer), GET_SETTINGS).await,
+                Ok(false)
+            );
+
+            // Other clients may only call read-only RPCs, unless they are root
+            let policy = root_only_policy();
+            let result = call(Some(policy.clone()), Some(peer), SET_LOCKDOWN_MODE).await;
+            if is_root {
+                assert_eq!(result, Ok(false));
+            } else {
+                assert_eq!(result, Err(tonic::Code::PermissionDenied));
+            }
+            assert_eq!(
+                call(Some(policy.clone()), Some(peer), GET_SETTINGS).await,
+                Ok(!is_root)
+            );
+
+            // Clients with unknown credentials are never admins
+            assert_eq!(
+                call(Some(policy.clone()), None, SET_LOCKDOWN_MODE).await,
+                Err(tonic::Code::PermissionDenied)
+            );
+            assert_eq!(call(Some(policy), None, GET_SETTINGS).await, Ok(true));
+        });
+    }
+
+    #[test]
+    fn test_redact_settings() {
+        let mut settings = types::Settings {
+            relay_settings: Some(types::RelaySettings {
+                endpoint: Some(types::relay_settings::Endpoint::Custom(
+                    types::CustomRelaySettings {
+                        config: Some(types::WireguardConfig {
+                            tunnel: Some(types::wireguard_config::TunnelConfig {
+                                private_key: vec![1; 32],
+                                ..Default::default()
+                            }),
+                            ..Default::default()
+                        }),
+                        preshared_key: Some(vec![2; 32]),
+                        ..Default::default()
+                    },
+                )),
+            }),
+            api_access_methods: Some(types::ApiAccessMethodSettings {
+                custom: vec![types::AccessMethodSetting {
+                    access_method: Some(types::AccessMethod {
+                        access_method: Some(types::access_method::AccessMethod::Custom(
+                            types::CustomProxy {
+                                proxy_method: Some(types::custom_proxy::ProxyMethod::Shadowsocks(
+                                    types::Shadowsocks {
+                                        password: "hunter2".to_owned(),
+                                        ..Default::default()
+                                    },
+                                )),
+                            },
+                        )),
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        redact_settings(&mut settings);
+
+        let serialized = format!("{settings:?}");
+        assert!(!serialized.contains("hunter2"));
+        assert!(serialized.contains(REDACTED_PASSWORD));
+        let Some(types::relay_settings::Endpoint::Custom(relay)) =
+            settings.relay_settings.unwrap().endpoint
+        else {
+            unreachable!()
+        };
+        assert_eq!(
+            relay.config.unwrap().tunnel.unwrap().private_key,
+            REDACTED_KEY
+        );
+        assert_eq!(relay.preshared_key.unwrap(), REDACTED_KEY);
+    }
+}
@@ -1,7 +1,11 @@
 //! Client that returns and takes mullvad types as arguments instead of prost-generated types
 use crate::types;
 #[cfg(not(target_os = "android"))]
+use chrono::{DateTime, Utc};
+#[cfg(not(target_os = "android"))]
 use futures::{Stream, StreamExt};
+#[cfg(target_os = "linux")]
+use mullvad_types::settings::SplitTunnelMode;
 use mullvad_types::{
     access_method::AccessMethodSetting,
     device::{DeviceEvent, RemoveDeviceEvent},
@@ -14,17 +18,26 @@ use mullvad_types::{
 use mullvad_types::{
     access_method::{self, AccessMethod},
     account::{AccountData, AccountNumber, VoucherSubmission},
-    custom_list::{CustomList, Id},
+    custom_endpoint::SavedCustomEndpoint,
+    custom_list::{CustomList, ExportedCustomList, Id, ImportMode, ImportSummary},
     device::{Device, DeviceId, DeviceState},
     features::FeatureIndicators,
-    relay_constraints::{AllowedIps, ObfuscationSettings, RelayOverride, RelaySettings},
+    leak_check::LeakCheckReport,
+    relay_constraints::{
+        AllowedIps, ObfuscationSettings, RelayExclusions, RelayOverride, RelaySettings,
+    },
     relay_list::BridgeList,
-    settings::{DnsOptions, SettingsKeyList},
+    relay_rotation::RotationPolicy,
+    session_log::SessionRecord,
+    settings::{DnsOptions, SettingsKeyList, TrustedNetworks},
+    tunnel_stats::TunnelStats,
     wireguard::{PublicKey, QuantumResistantState, RotationInterval},
 };
 use std::net::IpAddr;
 #[cfg(not(target_os = "android"))]
-use std::{path::Path, str::FromStr};
+use std::{collections::HashMap, path::Path, str::FromStr, time::Duration};
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::{NetworkInfo, dns::DnsLeakTestReport};
 #[cfg(target_os = "windows")]
 use talpid_types::split_tunnel::ExcludedProcess;
 #[cfg(not(target_os = "android"))]
@@ -118,6 +131,12 @@ impl MullvadProxyClient {
         TunnelState::try_from(state).map_err(Error::InvalidResponse)
     }
 
+    pub async fn tunnel_stats_listen(&mut self) -> Result<impl Stream<Item = Result<TunnelStats>>> {
+        let listener = self.0.tunnel_stats_listen(()).await?.into_inner();
+
+        Ok(listener.map(|item| TunnelStats::try_from(item?).map_err(Error::InvalidResponse)))
+    }
+
     pub async fn events_listen<'a>(
         &mut self,
     ) -> Result<impl Stream<Item = Result<DaemonEvent>> + 'a> {
@@ -164,6 +183,15 @@ impl MullvadProxyClient {
         mullvad_types::relay_list::RelayList::try_from(list).map_err(Error::InvalidResponse)
     }
 
+    pub async fn export_wireguard_config(&mut self) -> Result<String> {
+        Ok(self.0.export_wireguard_config(()).await?.into_inner())
+    }
+
+    pub async fn get_relay_latencies(&mut self) -> Result<HashMap<String, Duration>> {
+        let latencies = self.0.get_relay_latencies(()).await?.into_inner();
+        HashMap::try_from(latencies).map_err(Error::InvalidResponse)
+    }
+
     pub async fn get_bridges(&mut self) -> Result<BridgeList> {
         let list = self.0.get_bridges(()).await?.into_inner();
         mullvad_types::relay_list::BridgeList::try_from(list).map_err(Error::InvalidResponse)
@@ -264,6 +292,11 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_session_log(&mut self, state: bool) -> Result<()> {
+        self.0.set_session_log(state).await?;
+        Ok(())
+    }
+
     pub async fn set_lockdown_mode(&mut self, state: bool) -> Result<()> {
         self.0.set_lockdown_mode(state).await?;
         Ok(())
@@ -274,6 +307,17 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_trusted_networks(&mut self, trusted_networks: TrustedNetworks) -> Result<()> {
+        let trusted_networks = types::TrustedNetworkSettings::from(&trusted_networks);
+        self.0.set_trusted_networks(trusted_networks).await?;
+        Ok(())
+    }
+
+    pub async fn get_current_network(&mut self) -> Result<NetworkInfo> {
+        let network = self.0.get_current_network(()).await?.into_inner();
+        Ok(NetworkInfo::from(network))
+    }
+
     pub async fn set_wireguard_mtu(&mut self, mtu: Option<u16>) -> Result<()> {
         self.0
             .set_wireguard_mtu(mtu.map(u32::from).unwrap_or(0))
@@ -328,6 +372,12 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_relay_exclusions(&mut self, exclusions: RelayExclusions) -> Result<()> {
+        let exclusions = types::RelayExclusions::from(exclusions);
+        self.0.set_relay_exclusions(exclusions).await?;
+        Ok(())
+    }
+
     pub async fn create_new_account(&mut self) -> Result<AccountNumber> {
         Ok(self
             .0
@@ -439,6 +489,17 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_relay_rotation(&mut self, policy: &RotationPolicy) -> Result<()> {
+        let policy = types::RelayRotationPolicy::from(policy);
+        self.0.set_relay_rotation(policy).await?;
+        Ok(())
+    }
+
+    pub async fn clear_relay_rotation(&mut self) -> Result<()> {
+        self.0.clear_relay_rotation(()).await?;
+        Ok(())
+    }
+
     pub async fn rotate_wireguard_key(&mut self) -> Result<()> {
         self.0.rotate_wireguard_key(()).await?;
         Ok(())
@@ -453,6 +514,7 @@ impl MullvadProxyClient {
         let request = types::NewCustomList {
             name,
             locations: Vec::new(),
+            filter: None,
         };
         let id = self
             .0
@@ -488,6 +550,58 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Create or update custom lists in bulk. Lists with the same name as an existing list are
+    /// merged into or replace that list, depending on `mode`.
+    pub async fn import_custom_lists(
+        &mut self,
+        custom_lists: Vec<ExportedCustomList>,
+        mode: ImportMode,
+    ) -> Result<ImportSummary> {
+        let request = types::CustomListImport {
+            custom_lists: custom_lists
+                .into_iter()
+                .map(types::NewCustomList::from)
+                .collect(),
+            mode: i32::from(types::custom_list_import::Mode::from(mode)),
+        };
+        let result = self
+            .0
+            .import_custom_lists(request)
+            .await
+            .map_err(map_custom_list_error)?
+            .into_inner();
+        ImportSummary::try_from(result).map_err(Error::InvalidResponse)
+    }
+
+    /// Save a custom endpoint with the lowest priority.
+    pub async fn add_custom_endpoint(&mut self, endpoint: SavedCustomEndpoint) -> Result<()> {
+        self.0
+            .add_custom_endpoint(types::SavedCustomEndpoint::from(endpoint))
+            .await?;
+        Ok(())
+    }
+
+    /// Replace the saved custom endpoint with the same name.
+    pub async fn update_custom_endpoint(&mut self, endpoint: SavedCustomEndpoint) -> Result<()> {
+        self.0
+            .update_custom_endpoint(types::SavedCustomEndpoint::from(endpoint))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn remove_custom_endpoint(&mut self, name: String) -> Result<()> {
+        self.0.remove_custom_endpoint(name).await?;
+        Ok(())
+    }
+
+    /// Move a saved custom endpoint to `position`, where 0 is the highest priority.
+    pub async fn move_custom_endpoint(&mut self, name: String, position: u32) -> Result<()> {
+        self.0
+            .move_custom_endpoint(types::CustomEndpointPosition { name, position })
+            .await?;
+        Ok(())
+    }
+
     pub async fn add_access_method(
         &mut self,
         name: String,
@@ -586,6 +700,17 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Set whether split tunneling apps are excluded from the tunnel, or are the only apps that
+    /// use it.
+    #[cfg(target_os = "linux")]
+    pub async fn set_split_tunnel_mode(&mut self, mode: SplitTunnelMode) -> Result<()> {
+        let mode = types::SplitTunnelMode {
+            mode: i32::from(types::SplitTunnelModeValue::from(mode)),
+        };
+        self.0.set_split_tunnel_mode(mode).await?;
+        Ok(())
+    }
+
     #[cfg(target_os = "windows")]
     pub async fn get_excluded_processes(&mut self) -> Result<Vec<ExcludedProcess>> {
         let procs = self.0.get_excluded_processes(()).await?.into_inner();
@@ -644,6 +769,24 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Run a leak check. Fails if the tunnel is not connected.
+    pub async fn run_leak_check(&mut self) -> Result<LeakCheckReport> {
+        let report = self.0.run_leak_check(()).await?.into_inner();
+        LeakCheckReport::try_from(report).map_err(Error::InvalidResponse)
+    }
+
+    /// Return the report from the most recent leak check.
+    pub async fn get_leak_check_report(&mut self) -> Result<LeakCheckReport> {
+        let report = self.0.get_leak_check_report(()).await?.into_inner();
+        LeakCheckReport::try_from(report).map_err(Error::InvalidResponse)
+    }
+
+    /// Run a DNS leak test. Fails if the tunnel is not connected. Only supported on macOS.
+    pub async fn run_dns_leak_test(&mut self) -> Result<DnsLeakTestReport> {
+        let report = self.0.run_dns_leak_test(()).await?.into_inner();
+        DnsLeakTestReport::try_from(report).map_err(Error::InvalidResponse)
+    }
+
     pub async fn set_wireguard_allowed_ips(&mut self, allowed_ips: AllowedIps) -> Result<()> {
         self.0
             .set_wireguard_allowed_ips(types::AllowedIpsList {
@@ -674,6 +817,25 @@ impl MullvadProxyClient {
 
         Ok(listener.map(|item| Ok(item?.message)))
     }
+
+    /// Return the sessions in the session log that ended at or after `since`, oldest first.
+    pub async fn get_session_log(
+        &mut self,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Vec<SessionRecord>> {
+        let since = since.map(|since| types::Timestamp {
+            seconds: since.timestamp(),
+            nanos: since.timestamp_subsec_nanos() as i32,
+        });
+        self.0
+            .get_session_log(types::SessionLogQuery { since })
+            .await?
+            .into_inner()
+            .sessions
+            .into_iter()
+            .map(|session| SessionRecord::try_from(session).map_err(Error::InvalidResponse))
+            .collect()
+    }
 }
 
 #[cfg(not(target_os = "android"))]
@@ -753,4 +915,12 @@ impl RelaySelectorClient {
         let result = self.0.partition_relays(predicate).await?.into_inner();
         Ok(result)
     }
+
+    pub async fn get_selection_history(
+        &mut self,
+    ) -> Result<mullvad_types::relay_selector::history::SelectionHistory> {
+        let history = self.0.get_selection_history(()).await?.into_inner();
+        mullvad_types::relay_selector::history::SelectionHistory::try_from(history)
+            .map_err(Error::InvalidResponse)
+    }
 }
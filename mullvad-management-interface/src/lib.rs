@@ -1,20 +1,25 @@
+#[cfg(unix)]
+pub mod authorization;
 pub mod client;
 pub mod types;
 
 #[cfg(unix)]
-use std::{env, fs, os::unix::fs::PermissionsExt};
+use std::{env, fs, os::unix::fs::PermissionsExt, path::Path};
+use std::{future::Future, io, path::PathBuf};
+#[cfg(not(unix))]
 use std::{
-    future::Future,
-    io,
-    path::PathBuf,
     pin::Pin,
     task::{Context, Poll},
 };
+#[cfg(not(target_os = "android"))]
 use tipsy::Endpoint as IpcEndpoint;
+#[cfg(not(unix))]
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tonic::transport::Server;
+#[cfg(not(unix))]
+use tonic::transport::server::Connected;
 #[cfg(not(target_os = "android"))]
 use tonic::transport::{Endpoint, Uri};
-use tonic::transport::{Server, server::Connected};
 #[cfg(not(target_os = "android"))]
 use tower::service_fn;
 
@@ -167,36 +172,28 @@ pub fn spawn_rpc_server(
     relay_selector_service: impl RelaySelectorService,
     abort_rx: impl Future<Output = ()> + Send + 'static,
     rpc_socket_path: PathBuf,
+    #[cfg(unix)] access_policy: Option<authorization::AccessPolicy>,
 ) -> std::result::Result<ServerJoinHandle, Error> {
-    use futures::TryStreamExt;
-
-    let endpoint = create_endpoint(rpc_socket_path)?;
-
-    // Extract the path first, since `incoming()` takes ownership.
-    // Note: `endpoint.clone()` is not safe to use due to a use-after-free in `tipsy`.
-    // See https://github.com/aschey/tipsy/issues/50.
     #[cfg(unix)]
-    let endpoint_path = endpoint.path().to_owned();
-
-    let incoming = endpoint
-        .incoming()
-        .map_err(Error::StartServerError)?
-        .map_ok(StreamBox);
-
-    #[cfg(unix)]
-    if let Some(group_name) = MULLVAD_MANAGEMENT_SOCKET_GROUP.as_ref() {
-        let group = nix::unistd::Group::from_name(group_name)
-            .map_err(Error::ObtainGidError)?
-            .ok_or(Error::NoGidError)?;
-        nix::unistd::chown(&endpoint_path, None, Some(group.gid)).map_err(Error::SetGidError)?;
-        fs::set_permissions(&endpoint_path, PermissionsExt::from_mode(0o760))
-            .map_err(Error::PermissionsError)?;
-    }
-
     let grpc_server = Server::builder()
+        .layer(authorization::AuthorizationLayer::new(access_policy))
         .add_service(ManagementServiceServer::new(management_service))
         .add_service(RelaySelectorServiceServer::new(relay_selector_service))
-        .serve_with_incoming_shutdown(incoming, abort_rx);
+        .serve_with_incoming_shutdown(bind_unix_socket(&rpc_socket_path)?, abort_rx);
+
+    #[cfg(not(unix))]
+    let grpc_server = {
+        use futures::TryStreamExt;
+
+        let incoming = create_endpoint(rpc_socket_path)?
+            .incoming()
+            .map_err(Error::StartServerError)?
+            .map_ok(StreamBox);
+        Server::builder()
+            .add_service(ManagementServiceServer::new(management_service))
+            .add_service(RelaySelectorServiceServer::new(relay_selector_service))
+            .serve_with_incoming_shutdown(incoming, abort_rx)
+    };
 
     let server_task = tokio::spawn(async move {
         if let Err(execution_error) = grpc_server.await.map_err(Error::GrpcTransportError) {
@@ -208,18 +205,43 @@ pub fn spawn_rpc_server(
     Ok(server_task)
 }
 
+/// Bind the management interface socket and return a stream of client connections. Unlike the
+/// named pipe used on Windows, a Unix socket lets us know the credentials of each client, which
+/// is needed for [`authorization`].
+#[cfg(unix)]
+fn bind_unix_socket(
+    rpc_socket_path: &Path,
+) -> Result<impl futures::Stream<Item = io::Result<tokio::net::UnixStream>>, Error> {
+    let listener =
+        tokio::net::UnixListener::bind(rpc_socket_path).map_err(Error::StartServerError)?;
+
+    if let Some(group_name) = MULLVAD_MANAGEMENT_SOCKET_GROUP.as_ref() {
+        // Explicitly start with strict permissions (`0o600`, root) before applying less
+        // restrictive permissions.
+        fs::set_permissions(rpc_socket_path, PermissionsExt::from_mode(0o600))
+            .map_err(Error::PermissionsError)?;
+        let group = nix::unistd::Group::from_name(group_name)
+            .map_err(Error::ObtainGidError)?
+            .ok_or(Error::NoGidError)?;
+        nix::unistd::chown(rpc_socket_path, None, Some(group.gid)).map_err(Error::SetGidError)?;
+        fs::set_permissions(rpc_socket_path, PermissionsExt::from_mode(0o760))
+            .map_err(Error::PermissionsError)?;
+    } else {
+        fs::set_permissions(rpc_socket_path, PermissionsExt::from_mode(0o766))
+            .map_err(Error::PermissionsError)?;
+    }
+
+    Ok(futures::stream::poll_fn(move |cx| {
+        listener
+            .poll_accept(cx)
+            .map(|result| Some(result.map(|(stream, _addr)| stream)))
+    }))
+}
+
+#[cfg(not(unix))]
 fn create_endpoint(rpc_socket_path: PathBuf) -> Result<IpcEndpoint, Error> {
     let endpoint = IpcEndpoint::new(rpc_socket_path, tipsy::OnConflict::Error)
         .map_err(Error::StartServerError)?;
-    #[cfg(unix)]
-    if MULLVAD_MANAGEMENT_SOCKET_GROUP.is_some() {
-        // Explicitly start with strict permissions (`0o600`, root) before applying less restrictive
-        // permissions.
-        let attr = tipsy::SecurityAttributes::empty()
-            .mode(0o600)
-            .map_err(Error::PermissionsError)?;
-        return Ok(endpoint.security_attributes(attr));
-    }
     let endpoint = endpoint.security_attributes(
         tipsy::SecurityAttributes::allow_everyone_create()
             .map_err(Error::SecurityAttributes)?
@@ -229,8 +251,10 @@ fn create_endpoint(rpc_socket_path: PathBuf) -> Result<IpcEndpoint, Error> {
     Ok(endpoint)
 }
 
+#[cfg(not(unix))]
 #[derive(Debug)]
 struct StreamBox<T: AsyncRead + AsyncWrite>(pub T);
+#[cfg(not(unix))]
 impl<T: AsyncRead + AsyncWrite> Connected for StreamBox<T> {
     type ConnectInfo = Option<()>;
 
@@ -238,6 +262,7 @@ impl<T: AsyncRead + AsyncWrite> Connected for StreamBox<T> {
         None
     }
 }
+#[cfg(not(unix))]
 impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRead for StreamBox<T> {
     fn poll_read(
         mut self: Pin<&mut Self>,
@@ -247,6 +272,7 @@ impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRead for StreamBox<T> {
         Pin::new(&mut self.0).poll_read(cx, buf)
     }
 }
+#[cfg(not(unix))]
 impl<T: AsyncRead + AsyncWrite + Unpin> AsyncWrite for StreamBox<T> {
     fn poll_write(
         mut self: Pin<&mut Self>,
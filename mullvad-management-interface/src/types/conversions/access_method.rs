@@ -11,7 +11,7 @@ mod settings {
                 direct: Some(settings.direct().clone().into()),
                 mullvad_bridges: Some(settings.mullvad_bridges().clone().into()),
                 encrypted_dns_proxy: Some(settings.encrypted_dns_proxy().clone().into()),
-                // domain_fronting: Some(settings.domain_fronting().clone().into()),
+                domain_fronting: Some(settings.domain_fronting().clone().into()),
                 custom: settings
                     .iter_custom()
                     .cloned()
@@ -46,12 +46,12 @@ mod settings {
                 ))
                 .and_then(access_method::AccessMethodSetting::try_from)?;
 
-            // let domain_fronting = settings
-            //     .domain_fronting
-            //     .ok_or(FromProtobufTypeError::invalid_argument(
-            //         "Could not deserialize Domain fronting Access Method from protobuf",
-            //     ))
-            //     .and_then(access_method::AccessMethodSetting::try_from)?;
+            let domain_fronting = settings
+                .domain_fronting
+                .ok_or(FromProtobufTypeError::invalid_argument(
+                    "Could not deserialize Domain fronting Access Method from protobuf",
+                ))
+                .and_then(access_method::AccessMethodSetting::try_from)?;
 
             let custom = settings
                 .custom
@@ -63,7 +63,7 @@ mod settings {
                 direct,
                 mullvad_bridges,
                 encrypted_dns_proxy,
-                // domain_fronting,
+                domain_fronting,
                 custom,
             ))
         }
@@ -76,7 +76,7 @@ mod settings {
 mod data {
     use crate::types::{FromProtobufTypeError, proto};
     use mullvad_types::access_method::{
-        AccessMethod, AccessMethodSetting, BuiltInAccessMethod, Id,
+        AccessMethod, AccessMethodSetting, BuiltInAccessMethod, CustomDomainFronting, Id,
     };
     use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
 
@@ -139,10 +139,13 @@ mod data {
                 proto::access_method::AccessMethod::EncryptedDnsProxy(proxy) => {
                     AccessMethod::from(proxy)
                 }
-                // proto::access_method::AccessMethod::DomainFronting(df) => AccessMethod::from(df),
+                proto::access_method::AccessMethod::DomainFronting(df) => AccessMethod::from(df),
                 proto::access_method::AccessMethod::Custom(custom) => {
                     CustomProxy::try_from(custom).map(AccessMethod::from)?
                 }
+                proto::access_method::AccessMethod::CustomDomainFronting(df) => {
+                    CustomDomainFronting::try_from(df).map(AccessMethod::from)?
+                }
             })
         }
     }
@@ -152,6 +155,11 @@ mod data {
             match value {
                 AccessMethod::Custom(value) => proto::AccessMethod::from(value),
                 AccessMethod::BuiltIn(value) => proto::AccessMethod::from(value),
+                AccessMethod::CustomDomainFronting(value) => proto::AccessMethod {
+                    access_method: Some(proto::access_method::AccessMethod::CustomDomainFronting(
+                        proto::CustomDomainFronting::from(value),
+                    )),
+                },
             }
         }
     }
@@ -174,11 +182,36 @@ mod data {
         }
     }
 
-    // impl From<proto::access_method::DomainFronting> for AccessMethod {
-    //     fn from(_value: proto::access_method::DomainFronting) -> Self {
-    //         AccessMethod::from(BuiltInAccessMethod::DomainFronting)
-    //     }
-    // }
+    impl From<proto::access_method::DomainFronting> for AccessMethod {
+        fn from(_value: proto::access_method::DomainFronting) -> Self {
+            AccessMethod::from(BuiltInAccessMethod::DomainFronting)
+        }
+    }
+
+    impl TryFrom<proto::CustomDomainFronting> for CustomDomainFronting {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::CustomDomainFronting) -> Result<Self, Self::Error> {
+            if value.front.is_empty() || value.proxy_host.is_empty() {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "Domain fronting requires a front domain and a proxy host",
+                ));
+            }
+            Ok(CustomDomainFronting {
+                front: value.front,
+                proxy_host: value.proxy_host,
+            })
+        }
+    }
+
+    impl From<CustomDomainFronting> for proto::CustomDomainFronting {
+        fn from(value: CustomDomainFronting) -> Self {
+            proto::CustomDomainFronting {
+                front: value.front,
+                proxy_host: value.proxy_host,
+            }
+        }
+    }
 
     impl TryFrom<proto::Socks5Local> for AccessMethod {
         type Error = FromProtobufTypeError;
@@ -225,11 +258,12 @@ mod data {
                     proto::access_method::AccessMethod::EncryptedDnsProxy(
                         proto::access_method::EncryptedDnsProxy {},
                     )
-                } // mullvad_types::access_method::BuiltInAccessMethod::DomainFronting => {
-                  //     proto::access_method::AccessMethod::DomainFronting(
-                  //         proto::access_method::DomainFronting {},
-                  //     )
-                  // }
+                }
+                mullvad_types::access_method::BuiltInAccessMethod::DomainFronting => {
+                    proto::access_method::AccessMethod::DomainFronting(
+                        proto::access_method::DomainFronting {},
+                    )
+                }
             }
         }
     }
@@ -1,9 +1,14 @@
 use std::{collections::BTreeSet, str::FromStr};
 
-use crate::types::{FromProtobufTypeError, proto};
+use crate::types::{
+    FromProtobufTypeError, proto, relay_constraints::try_ownership_constraint_from_i32,
+};
 use mullvad_types::{
-    custom_list::{CustomList, Id},
-    relay_constraints::GeographicLocationConstraint,
+    custom_list::{
+        CustomList, CustomListFilter, ExportedCustomList, ExportedLocation, Id, ImportMode,
+        ImportSummary,
+    },
+    relay_constraints::{GeographicLocationConstraint, Ownership},
 };
 
 impl From<mullvad_types::custom_list::CustomListsSettings> for proto::CustomListSettings {
@@ -40,6 +45,7 @@ impl From<mullvad_types::custom_list::CustomList> for proto::CustomList {
             id,
             name: custom_list.name,
             locations,
+            filter: custom_list.filter.map(proto::CustomListFilter::from),
         }
     }
 }
@@ -60,7 +66,188 @@ impl TryFrom<proto::CustomList> for mullvad_types::custom_list::CustomList {
         let mut inner = Self::with_id(id);
         inner.name = custom_list.name;
         inner.append(locations);
+        inner.filter = custom_list
+            .filter
+            .map(CustomListFilter::try_from)
+            .transpose()?;
 
         Ok(inner)
     }
 }
+
+impl From<CustomListFilter> for proto::CustomListFilter {
+    fn from(filter: CustomListFilter) -> Self {
+        let ownership = match filter.ownership {
+            None => proto::Ownership::Any,
+            Some(Ownership::MullvadOwned) => proto::Ownership::MullvadOwned,
+            Some(Ownership::Rented) => proto::Ownership::Rented,
+        };
+        let selected_obfuscation = if filter.quic {
+            proto::obfuscation_settings::SelectedObfuscation::Quic
+        } else {
+            proto::obfuscation_settings::SelectedObfuscation::Auto
+        };
+        Self {
+            countries: filter.countries.into_iter().collect(),
+            providers: filter.providers.into_iter().collect(),
+            ownership: i32::from(ownership),
+            daita_settings: Some(proto::DaitaSettings::from(filter.daita)),
+            obfuscation_settings: Some(proto::ObfuscationSettings {
+                selected_obfuscation: i32::from(selected_obfuscation),
+                ..Default::default()
+            }),
+            excluded_hostnames: filter.excluded_hostnames.into_iter().collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomListFilter> for CustomListFilter {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(filter: proto::CustomListFilter) -> Result<Self, Self::Error> {
+        use proto::obfuscation_settings::SelectedObfuscation;
+        let quic = match filter
+            .obfuscation_settings
+            .map(|settings| SelectedObfuscation::try_from(settings.selected_obfuscation))
+        {
+            None | Some(Ok(SelectedObfuscation::Auto)) => false,
+            Some(Ok(SelectedObfuscation::Quic)) => true,
+            Some(_) => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "custom list rules only support QUIC obfuscation",
+                ));
+            }
+        };
+        Ok(Self {
+            countries: filter.countries.into_iter().collect(),
+            providers: filter.providers.into_iter().collect(),
+            ownership: try_ownership_constraint_from_i32(filter.ownership)?.option(),
+            daita: filter
+                .daita_settings
+                .is_some_and(|settings| settings.enabled),
+            quic,
+            excluded_hostnames: filter.excluded_hostnames.into_iter().collect(),
+        })
+    }
+}
+
+impl From<ExportedCustomList> for proto::NewCustomList {
+    fn from(custom_list: ExportedCustomList) -> Self {
+        Self {
+            name: custom_list.name,
+            locations: custom_list
+                .locations
+                .into_iter()
+                .map(|location| proto::GeographicLocationConstraint::from(location.0))
+                .collect(),
+            filter: custom_list.filter.map(proto::CustomListFilter::from),
+        }
+    }
+}
+
+impl TryFrom<proto::NewCustomList> for ExportedCustomList {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(custom_list: proto::NewCustomList) -> Result<Self, Self::Error> {
+        let locations = custom_list
+            .locations
+            .into_iter()
+            .map(|location| GeographicLocationConstraint::try_from(location).map(ExportedLocation))
+            .collect::<Result<Vec<_>, Self::Error>>()?;
+        Ok(Self {
+            name: custom_list.name,
+            locations,
+            filter: custom_list
+                .filter
+                .map(CustomListFilter::try_from)
+                .transpose()?,
+        })
+    }
+}
+
+impl From<ImportMode> for proto::custom_list_import::Mode {
+    fn from(mode: ImportMode) -> Self {
+        match mode {
+            ImportMode::Merge => Self::Merge,
+            ImportMode::Replace => Self::Replace,
+        }
+    }
+}
+
+impl From<proto::custom_list_import::Mode> for ImportMode {
+    fn from(mode: proto::custom_list_import::Mode) -> Self {
+        match mode {
+            proto::custom_list_import::Mode::Merge => Self::Merge,
+            proto::custom_list_import::Mode::Replace => Self::Replace,
+        }
+    }
+}
+
+impl From<ImportSummary> for proto::CustomListImportResult {
+    fn from(summary: ImportSummary) -> Self {
+        Self {
+            created: summary.created,
+            updated: summary.updated,
+            unknown_locations: summary
+                .unknown_locations
+                .into_iter()
+                .map(
+                    |(custom_list, location)| proto::custom_list_import_result::UnknownLocation {
+                        custom_list,
+                        location: Some(proto::GeographicLocationConstraint::from(location)),
+                    },
+                )
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomListImportResult> for ImportSummary {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(result: proto::CustomListImportResult) -> Result<Self, Self::Error> {
+        let unknown_locations = result
+            .unknown_locations
+            .into_iter()
+            .map(|unknown| {
+                let location = unknown
+                    .location
+                    .ok_or(FromProtobufTypeError::invalid_argument(
+                        "missing unknown location",
+                    ))?;
+                Ok((
+                    unknown.custom_list,
+                    GeographicLocationConstraint::try_from(location)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, Self::Error>>()?;
+        Ok(Self {
+            created: result.created,
+            updated: result.updated,
+            unknown_locations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_filter_round_trip() {
+        let filter = CustomListFilter {
+            countries: BTreeSet::from(["se".to_owned()]),
+            providers: BTreeSet::from(["31173".to_owned()]),
+            ownership: Some(Ownership::MullvadOwned),
+            daita: true,
+            quic: true,
+            excluded_hostnames: BTreeSet::from(["se-got-wg-001".to_owned()]),
+        };
+        let converted = proto::CustomListFilter::from(filter.clone());
+        assert_eq!(CustomListFilter::try_from(converted).unwrap(), filter);
+
+        let filter = CustomListFilter::default();
+        let converted = proto::CustomListFilter::from(filter.clone());
+        assert_eq!(CustomListFilter::try_from(converted).unwrap(), filter);
+    }
+}
@@ -1,10 +1,105 @@
 use crate::types::{
     FromProtobufTypeError,
-    conversions::{bytes_to_privkey, bytes_to_pubkey},
+    conversions::{bytes_to_privkey, bytes_to_psk, bytes_to_pubkey},
     proto,
 };
+use mullvad_types::{
+    CustomTunnelEndpoint,
+    custom_endpoint::{CustomEndpointsSettings, SavedCustomEndpoint},
+};
 use talpid_types::net::wireguard;
 
+impl TryFrom<proto::CustomRelaySettings> for CustomTunnelEndpoint {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::CustomRelaySettings) -> Result<Self, Self::Error> {
+        let config = settings
+            .config
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "missing relay connection config",
+            ))?;
+        let config = wireguard::ConnectionConfig::try_from(config)?;
+        let preshared_key = settings
+            .preshared_key
+            .as_deref()
+            .map(bytes_to_psk)
+            .transpose()?;
+        let dns = settings
+            .dns_servers
+            .into_iter()
+            .map(|addr| {
+                addr.parse()
+                    .map_err(|_| FromProtobufTypeError::invalid_argument("invalid DNS server"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(CustomTunnelEndpoint {
+            host: settings.host,
+            config,
+            preshared_key,
+            dns,
+        })
+    }
+}
+
+impl From<CustomTunnelEndpoint> for proto::CustomRelaySettings {
+    fn from(endpoint: CustomTunnelEndpoint) -> Self {
+        proto::CustomRelaySettings {
+            host: endpoint.host,
+            config: Some(proto::WireguardConfig::from(endpoint.config)),
+            preshared_key: endpoint.preshared_key.map(|psk| psk.as_bytes().to_vec()),
+            dns_servers: endpoint.dns.iter().map(|addr| addr.to_string()).collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::SavedCustomEndpoint> for SavedCustomEndpoint {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(saved: proto::SavedCustomEndpoint) -> Result<Self, Self::Error> {
+        let endpoint = saved
+            .endpoint
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "missing custom endpoint",
+            ))?;
+        SavedCustomEndpoint::new(saved.name, CustomTunnelEndpoint::try_from(endpoint)?)
+            .map_err(|error| FromProtobufTypeError::invalid_argument(error.to_string()))
+    }
+}
+
+impl From<SavedCustomEndpoint> for proto::SavedCustomEndpoint {
+    fn from(saved: SavedCustomEndpoint) -> Self {
+        proto::SavedCustomEndpoint {
+            name: saved.name,
+            endpoint: Some(proto::CustomRelaySettings::from(saved.endpoint)),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomEndpointSettings> for CustomEndpointsSettings {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::CustomEndpointSettings) -> Result<Self, Self::Error> {
+        Ok(Self::from(
+            settings
+                .endpoints
+                .into_iter()
+                .map(SavedCustomEndpoint::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+        ))
+    }
+}
+
+impl From<CustomEndpointsSettings> for proto::CustomEndpointSettings {
+    fn from(settings: CustomEndpointsSettings) -> Self {
+        proto::CustomEndpointSettings {
+            endpoints: settings
+                .into_iter()
+                .map(proto::SavedCustomEndpoint::from)
+                .collect(),
+        }
+    }
+}
+
 impl TryFrom<proto::WireguardConfig> for wireguard::ConnectionConfig {
     type Error = FromProtobufTypeError;
 
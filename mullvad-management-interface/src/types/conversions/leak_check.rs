@@ -0,0 +1,152 @@
+use super::{from_timestamp, to_timestamp};
+use crate::types::{FromProtobufTypeError, proto};
+use mullvad_types::leak_check::{LeakCheckOutcome, LeakCheckReport};
+use std::net::IpAddr;
+use talpid_types::net::dns::{DnsLeakTestQuery, DnsLeakTestReport};
+
+impl From<LeakCheckReport> for proto::LeakCheckReport {
+    fn from(report: LeakCheckReport) -> Self {
+        let (outcome, error) = match report.outcome {
+            LeakCheckOutcome::NoLeak => (proto::leak_check_report::Outcome::NoLeak, None),
+            LeakCheckOutcome::LeakDetected => {
+                (proto::leak_check_report::Outcome::LeakDetected, None)
+            }
+            LeakCheckOutcome::Failed(error) => {
+                (proto::leak_check_report::Outcome::Failed, Some(error))
+            }
+        };
+
+        proto::LeakCheckReport {
+            interface: report.interface,
+            destination: report.destination.to_string(),
+            probe_ttls: report.probe_ttls.into_iter().map(u32::from).collect(),
+            reachable_nodes: report
+                .reachable_nodes
+                .iter()
+                .map(IpAddr::to_string)
+                .collect(),
+            started: Some(to_timestamp(report.started)),
+            finished: Some(to_timestamp(report.finished)),
+            outcome: i32::from(outcome),
+            error,
+        }
+    }
+}
+
+impl TryFrom<proto::LeakCheckReport> for LeakCheckReport {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(report: proto::LeakCheckReport) -> Result<Self, Self::Error> {
+        let outcome = match proto::leak_check_report::Outcome::try_from(report.outcome) {
+            Ok(proto::leak_check_report::Outcome::NoLeak) => LeakCheckOutcome::NoLeak,
+            Ok(proto::leak_check_report::Outcome::LeakDetected) => LeakCheckOutcome::LeakDetected,
+            Ok(proto::leak_check_report::Outcome::Failed) => {
+                LeakCheckOutcome::Failed(report.error.unwrap_or_default())
+            }
+            Err(_) => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "invalid leak check outcome",
+                ));
+            }
+        };
+
+        Ok(LeakCheckReport {
+            interface: report.interface,
+            destination: parse_ip(&report.destination)?,
+            probe_ttls: report
+                .probe_ttls
+                .into_iter()
+                .map(|ttl| {
+                    u16::try_from(ttl)
+                        .map_err(|_| FromProtobufTypeError::invalid_argument("invalid TTL"))
+                })
+                .collect::<Result<_, _>>()?,
+            reachable_nodes: report
+                .reachable_nodes
+                .iter()
+                .map(|node| parse_ip(node))
+                .collect::<Result<_, _>>()?,
+            started: from_timestamp(report.started)?,
+            finished: from_timestamp(report.finished)?,
+            outcome,
+        })
+    }
+}
+
+impl From<DnsLeakTestReport> for proto::DnsLeakTestReport {
+    fn from(report: DnsLeakTestReport) -> Self {
+        proto::DnsLeakTestReport {
+            expected_servers: report
+                .expected_servers
+                .iter()
+                .map(IpAddr::to_string)
+                .collect(),
+            queries: report
+                .queries
+                .into_iter()
+                .map(proto::dns_leak_test_report::Query::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<DnsLeakTestQuery> for proto::dns_leak_test_report::Query {
+    fn from(query: DnsLeakTestQuery) -> Self {
+        proto::dns_leak_test_report::Query {
+            name: query.name,
+            reached_resolver: query.upstream_servers.is_some(),
+            upstream_servers: query
+                .upstream_servers
+                .unwrap_or_default()
+                .iter()
+                .map(IpAddr::to_string)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::DnsLeakTestReport> for DnsLeakTestReport {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(report: proto::DnsLeakTestReport) -> Result<Self, Self::Error> {
+        Ok(DnsLeakTestReport {
+            expected_servers: report
+                .expected_servers
+                .iter()
+                .map(|server| parse_ip(server))
+                .collect::<Result<_, _>>()?,
+            queries: report
+                .queries
+                .into_iter()
+                .map(DnsLeakTestQuery::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl TryFrom<proto::dns_leak_test_report::Query> for DnsLeakTestQuery {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(query: proto::dns_leak_test_report::Query) -> Result<Self, Self::Error> {
+        let upstream_servers = if query.reached_resolver {
+            let servers = query
+                .upstream_servers
+                .iter()
+                .map(|server| parse_ip(server))
+                .collect::<Result<_, _>>()?;
+            Some(servers)
+        } else {
+            None
+        };
+
+        Ok(DnsLeakTestQuery {
+            name: query.name,
+            upstream_servers,
+        })
+    }
+}
+
+fn parse_ip(ip: &str) -> Result<IpAddr, FromProtobufTypeError> {
+    ip.parse()
+        .map_err(|_| FromProtobufTypeError::invalid_argument("invalid IP address"))
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use std::str::FromStr;
 
 mod access_method;
@@ -6,17 +7,20 @@ mod custom_list;
 mod custom_tunnel;
 mod device;
 mod features;
+mod leak_check;
 mod location;
 mod logging;
 mod net;
 pub mod relay_constraints;
 mod relay_list;
 mod relay_selector;
+mod session_log;
 mod settings;
 mod settings_key_list;
 #[cfg(target_os = "windows")]
 mod split_tunnel;
 mod states;
+mod tunnel_stats;
 mod version;
 mod wireguard;
 
@@ -50,6 +54,14 @@ fn bytes_to_privkey(
     ))
 }
 
+fn bytes_to_psk(
+    bytes: &[u8],
+) -> Result<talpid_types::net::wireguard::PresharedKey, FromProtobufTypeError> {
+    Ok(talpid_types::net::wireguard::PresharedKey::from(
+        *bytes_to_wg_key(bytes, "invalid preshared key")?,
+    ))
+}
+
 fn bytes_to_wg_key<'a>(
     bytes: &'a [u8],
     error_msg: &'static str,
@@ -64,6 +76,22 @@ fn arg_from_str<T: FromStr<Err = E>, E>(
     T::from_str(s).map_err(|_err| FromProtobufTypeError::invalid_argument(invalid_arg_msg))
 }
 
+fn to_timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+fn from_timestamp(
+    timestamp: Option<prost_types::Timestamp>,
+) -> Result<DateTime<Utc>, FromProtobufTypeError> {
+    let timestamp =
+        timestamp.ok_or(FromProtobufTypeError::invalid_argument("missing timestamp"))?;
+    DateTime::from_timestamp(timestamp.seconds, timestamp.nanos as u32)
+        .ok_or(FromProtobufTypeError::invalid_argument("invalid timestamp"))
+}
+
 impl From<FromProtobufTypeError> for crate::Status {
     fn from(err: FromProtobufTypeError) -> Self {
         match err {
@@ -80,6 +80,9 @@ impl From<talpid_types::net::ObfuscationEndpoint> for proto::ObfuscationEndpoint
                 talpid_types::net::ObfuscationType::Lwo => {
                     i32::from(proto::obfuscation_endpoint::ObfuscationType::Lwo)
                 }
+                talpid_types::net::ObfuscationType::CustomBridge => {
+                    i32::from(proto::obfuscation_endpoint::ObfuscationType::CustomBridge)
+                }
             },
         }
     }
@@ -110,6 +113,9 @@ impl TryFrom<proto::ObfuscationEndpoint> for talpid_types::net::ObfuscationEndpo
                 Ok(proto::obfuscation_endpoint::ObfuscationType::Lwo) => {
                     talpid_net::ObfuscationType::Lwo
                 }
+                Ok(proto::obfuscation_endpoint::ObfuscationType::CustomBridge) => {
+                    talpid_net::ObfuscationType::CustomBridge
+                }
                 Err(_) => {
                     return Err(FromProtobufTypeError::invalid_argument(
                         "unknown obfuscation type",
@@ -226,7 +232,8 @@ mod proxy {
 
     use crate::types::{FromProtobufTypeError, proto};
     use talpid_types::net::proxy::{
-        CustomProxy, Shadowsocks, ShadowsocksCipher, Socks5Local, Socks5Remote, SocksAuth,
+        CustomProxy, HttpAuth, HttpConnect, Shadowsocks, ShadowsocksCipher, Socks5Local,
+        Socks5Remote, SocksAuth,
     };
 
     impl TryFrom<proto::CustomProxy> for CustomProxy {
@@ -243,6 +250,9 @@ mod proxy {
                 Some(proto::custom_proxy::ProxyMethod::Shadowsocks(shadowsocks)) => {
                     CustomProxy::Shadowsocks(Shadowsocks::try_from(shadowsocks)?)
                 }
+                Some(proto::custom_proxy::ProxyMethod::HttpConnect(http)) => {
+                    CustomProxy::HttpConnect(HttpConnect::try_from(http)?)
+                }
                 None => {
                     return Err(FromProtobufTypeError::invalid_argument(
                         "CustomProxy missing proxy_method field",
@@ -292,6 +302,32 @@ mod proxy {
         }
     }
 
+    impl TryFrom<proto::HttpConnect> for HttpConnect {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpConnect) -> Result<Self, Self::Error> {
+            let ip = value.ip.parse::<IpAddr>().map_err(|_| {
+                FromProtobufTypeError::invalid_argument(
+                    "Could not parse HTTP proxy message from protobuf",
+                )
+            })?;
+            if value
+                .tls_server_name
+                .as_ref()
+                .is_some_and(|name| name.is_empty())
+            {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "HTTP proxy TLS server name must not be empty",
+                ));
+            }
+            Ok(HttpConnect {
+                endpoint: (ip, value.port as u16).into(),
+                auth: value.auth.map(HttpAuth::try_from).transpose()?,
+                tls_server_name: value.tls_server_name,
+            })
+        }
+    }
+
     impl TryFrom<proto::Shadowsocks> for Shadowsocks {
         type Error = FromProtobufTypeError;
 
@@ -367,6 +403,11 @@ mod proxy {
                             config,
                         ))
                     }
+                    CustomProxy::HttpConnect(config) => {
+                        proto::custom_proxy::ProxyMethod::HttpConnect(proto::HttpConnect::from(
+                            config,
+                        ))
+                    }
                 }),
             }
         }
@@ -418,6 +459,39 @@ mod proxy {
         }
     }
 
+    impl From<HttpConnect> for proto::HttpConnect {
+        fn from(value: HttpConnect) -> Self {
+            proto::HttpConnect {
+                ip: value.endpoint.ip().to_string(),
+                port: value.endpoint.port() as u32,
+                auth: value.auth.map(proto::HttpAuth::from),
+                tls_server_name: value.tls_server_name,
+            }
+        }
+    }
+
+    impl From<HttpAuth> for proto::HttpAuth {
+        fn from(value: HttpAuth) -> Self {
+            proto::HttpAuth {
+                username: value.username().to_string(),
+                password: value.password().to_string(),
+            }
+        }
+    }
+
+    impl TryFrom<proto::HttpAuth> for HttpAuth {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpAuth) -> Result<Self, Self::Error> {
+            HttpAuth::new(value.username, value.password).map_err(|_| {
+                FromProtobufTypeError::invalid_argument(
+                    "Failed to parse HTTP proxy authentication. \
+                     Make sure the credentials are valid.",
+                )
+            })
+        }
+    }
+
     impl TryFrom<proto::SocksAuth> for SocksAuth {
         type Error = FromProtobufTypeError;
 
@@ -431,3 +505,23 @@ mod proxy {
         }
     }
 }
+
+impl From<talpid_types::net::NetworkInfo> for proto::NetworkInfo {
+    fn from(network: talpid_types::net::NetworkInfo) -> Self {
+        proto::NetworkInfo {
+            interface: network.interface,
+            ssid: network.ssid,
+            gateway_mac: network.gateway_mac,
+        }
+    }
+}
+
+impl From<proto::NetworkInfo> for talpid_types::net::NetworkInfo {
+    fn from(network: proto::NetworkInfo) -> Self {
+        talpid_types::net::NetworkInfo {
+            interface: network.interface,
+            ssid: network.ssid,
+            gateway_mac: network.gateway_mac,
+        }
+    }
+}
@@ -2,13 +2,14 @@ use crate::types::{FromProtobufTypeError, proto};
 use mullvad_types::{
     constraints::Constraint,
     custom_list::Id,
+    location::Coordinates,
     relay_constraints::{
-        GeographicLocationConstraint, Multihop,
+        DistanceConstraint, DistanceOrigin, GeographicLocationConstraint, Multihop,
         allowed_ip::{self, AllowedIps},
     },
 };
 use std::str::FromStr;
-use talpid_types::net::wireguard::ConnectionConfig;
+use talpid_types::net::{obfuscation::CustomBridge, proxy::CustomProxy};
 
 impl TryFrom<&proto::WireguardConstraints>
     for mullvad_types::relay_constraints::WireguardConstraints
@@ -88,20 +89,15 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
 
         match update_value {
             proto::relay_settings::Endpoint::Custom(settings) => {
-                let config = settings
-                    .config
-                    .ok_or(FromProtobufTypeError::invalid_argument(
-                        "missing relay connection config",
-                    ))?;
-                let config = ConnectionConfig::try_from(config)?;
                 Ok(mullvad_constraints::RelaySettings::CustomTunnelEndpoint(
-                    CustomTunnelEndpoint {
-                        host: settings.host,
-                        config,
-                    },
+                    CustomTunnelEndpoint::try_from(settings)?,
                 ))
             }
 
+            proto::relay_settings::Endpoint::SavedCustomEndpoints(_) => {
+                Ok(mullvad_constraints::RelaySettings::SavedCustomEndpoints)
+            }
+
             proto::relay_settings::Endpoint::Normal(settings) => {
                 let location = settings
                     .location
@@ -111,6 +107,7 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
                     .into();
                 let providers = providers_constraint_from_proto(&settings.providers);
                 let ownership = try_ownership_constraint_from_i32(settings.ownership)?;
+                let strategy = try_strategy_constraint_from_i32(settings.strategy)?;
 
                 let wireguard_constraints = mullvad_constraints::WireguardConstraints::try_from(
                     &settings.wireguard_constraints.ok_or(
@@ -124,6 +121,7 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
                         providers,
                         ownership,
                         wireguard_constraints,
+                        strategy,
                     },
                 ))
             }
@@ -145,6 +143,9 @@ impl From<&mullvad_types::relay_constraints::ObfuscationSettings> for proto::Obf
             }
             SelectedObfuscation::Quic => proto::obfuscation_settings::SelectedObfuscation::Quic,
             SelectedObfuscation::Lwo => proto::obfuscation_settings::SelectedObfuscation::Lwo,
+            SelectedObfuscation::CustomBridge => {
+                proto::obfuscation_settings::SelectedObfuscation::CustomBridge
+            }
             SelectedObfuscation::WireguardPort => {
                 proto::obfuscation_settings::SelectedObfuscation::WireguardPort
             }
@@ -161,6 +162,9 @@ impl From<&mullvad_types::relay_constraints::ObfuscationSettings> for proto::Obf
                 &settings.wireguard_port,
             )),
             lwo: Some(proto::obfuscation_settings::Lwo::from(&settings.lwo)),
+            custom_bridge: Some(proto::obfuscation_settings::CustomBridge::from(
+                &settings.custom_bridge,
+            )),
         }
     }
 }
@@ -209,6 +213,19 @@ impl From<&mullvad_types::relay_constraints::LwoSettings> for proto::obfuscation
     }
 }
 
+impl From<&mullvad_types::relay_constraints::CustomBridgeSettings>
+    for proto::obfuscation_settings::CustomBridge
+{
+    fn from(settings: &mullvad_types::relay_constraints::CustomBridgeSettings) -> Self {
+        Self {
+            bridge: settings
+                .bridge
+                .clone()
+                .map(|bridge| proto::CustomProxy::from(CustomProxy::from(bridge))),
+        }
+    }
+}
+
 impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySettings {
     fn from(settings: mullvad_types::relay_constraints::RelaySettings) -> Self {
         use mullvad_types::relay_constraints::RelaySettings as MullvadRelaySettings;
@@ -216,10 +233,12 @@ impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySetti
 
         let endpoint = match settings {
             MullvadRelaySettings::CustomTunnelEndpoint(endpoint) => {
-                relay_settings::Endpoint::Custom(proto::CustomRelaySettings {
-                    host: endpoint.host,
-                    config: Some(proto::WireguardConfig::from(endpoint.config)),
-                })
+                relay_settings::Endpoint::Custom(proto::CustomRelaySettings::from(endpoint))
+            }
+            MullvadRelaySettings::SavedCustomEndpoints => {
+                relay_settings::Endpoint::SavedCustomEndpoints(
+                    proto::SavedCustomEndpointsRelaySettings {},
+                )
             }
             MullvadRelaySettings::Normal(constraints) => {
                 relay_settings::Endpoint::Normal(proto::NormalRelaySettings {
@@ -259,6 +278,7 @@ impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySetti
                             &constraints.wireguard_constraints.entry_ownership,
                         ) as i32,
                     }),
+                    strategy: convert_strategy_constraint(&constraints.strategy) as i32,
                 })
             }
         };
@@ -292,6 +312,11 @@ impl From<mullvad_types::relay_constraints::LocationConstraint> for proto::Locat
                     list_id.to_string(),
                 )),
             },
+            LocationConstraint::Distance(distance) => Self {
+                r#type: Some(proto::location_constraint::Type::Distance(
+                    proto::DistanceConstraint::from(distance),
+                )),
+            },
         }
     }
 }
@@ -318,10 +343,45 @@ impl TryFrom<proto::LocationConstraint> for mullvad_types::relay_constraints::Lo
                 };
                 Ok(location)
             }
+            proto::location_constraint::Type::Distance(distance) => Ok(
+                LocationConstraint::Distance(DistanceConstraint::try_from(distance)?),
+            ),
+        }
+    }
+}
+
+impl From<DistanceConstraint> for proto::DistanceConstraint {
+    fn from(distance: DistanceConstraint) -> Self {
+        Self {
+            origin: distance.origin.coordinates().map(|coordinates| {
+                proto::distance_constraint::Coordinates {
+                    latitude: coordinates.latitude(),
+                    longitude: coordinates.longitude(),
+                }
+            }),
+            max_distance_km: distance.max_distance_km,
         }
     }
 }
 
+impl TryFrom<proto::DistanceConstraint> for DistanceConstraint {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(distance: proto::DistanceConstraint) -> Result<Self, Self::Error> {
+        let origin = match distance.origin {
+            Some(origin) => DistanceOrigin::Coordinates(
+                Coordinates::new(origin.latitude, origin.longitude)
+                    .map_err(|_| FromProtobufTypeError::invalid_argument("Invalid coordinates"))?,
+            ),
+            None => DistanceOrigin::GeoIp,
+        };
+        Ok(DistanceConstraint {
+            origin,
+            max_distance_km: distance.max_distance_km,
+        })
+    }
+}
+
 impl From<GeographicLocationConstraint> for proto::GeographicLocationConstraint {
     fn from(location: mullvad_types::relay_constraints::GeographicLocationConstraint) -> Self {
         match location {
@@ -378,6 +438,7 @@ impl TryFrom<proto::ObfuscationSettings> for mullvad_types::relay_constraints::O
                 Ok(IpcSelectedObfuscation::Shadowsocks) => SelectedObfuscation::Shadowsocks,
                 Ok(IpcSelectedObfuscation::Quic) => SelectedObfuscation::Quic,
                 Ok(IpcSelectedObfuscation::Lwo) => SelectedObfuscation::Lwo,
+                Ok(IpcSelectedObfuscation::CustomBridge) => SelectedObfuscation::CustomBridge,
                 Ok(IpcSelectedObfuscation::WireguardPort) => SelectedObfuscation::WireguardPort,
                 Err(_) => {
                     return Err(FromProtobufTypeError::invalid_argument(
@@ -427,12 +488,20 @@ impl TryFrom<proto::ObfuscationSettings> for mullvad_types::relay_constraints::O
             }
         };
 
+        // Older clients do not know about custom bridges
+        let custom_bridge = settings
+            .custom_bridge
+            .map(mullvad_types::relay_constraints::CustomBridgeSettings::try_from)
+            .transpose()?
+            .unwrap_or_default();
+
         Ok(Self {
             selected_obfuscation,
             udp2tcp,
             shadowsocks,
             wireguard_port,
             lwo,
+            custom_bridge,
         })
     }
 }
@@ -486,6 +555,31 @@ impl TryFrom<&proto::obfuscation_settings::Lwo> for mullvad_types::relay_constra
     }
 }
 
+impl TryFrom<proto::obfuscation_settings::CustomBridge>
+    for mullvad_types::relay_constraints::CustomBridgeSettings
+{
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::obfuscation_settings::CustomBridge) -> Result<Self, Self::Error> {
+        let bridge = settings
+            .bridge
+            .map(CustomProxy::try_from)
+            .transpose()?
+            .map(|proxy| {
+                CustomBridge::try_from(proxy).map_err(|proxy| {
+                    FromProtobufTypeError::invalid_argument(match proxy {
+                        CustomProxy::HttpConnect(_) => {
+                            "HTTP proxies cannot be used as bridges, since they cannot relay UDP"
+                        }
+                        _ => "only Shadowsocks and remote SOCKS5 proxies can be used as bridges",
+                    })
+                })
+            })
+            .transpose()?;
+        Ok(Self { bridge })
+    }
+}
+
 impl TryFrom<proto::TransportPort> for mullvad_types::relay_constraints::TransportPort {
     type Error = FromProtobufTypeError;
 
@@ -535,6 +629,24 @@ impl TryFrom<proto::RelayOverride> for mullvad_types::relay_constraints::RelayOv
     }
 }
 
+impl From<mullvad_types::relay_constraints::RelayExclusions> for proto::RelayExclusions {
+    fn from(exclusions: mullvad_types::relay_constraints::RelayExclusions) -> Self {
+        proto::RelayExclusions {
+            relays: exclusions.relays.into_iter().collect(),
+            providers: exclusions.providers.into_iter().collect(),
+        }
+    }
+}
+
+impl From<proto::RelayExclusions> for mullvad_types::relay_constraints::RelayExclusions {
+    fn from(exclusions: proto::RelayExclusions) -> Self {
+        mullvad_types::relay_constraints::RelayExclusions {
+            relays: exclusions.relays.into_iter().collect(),
+            providers: exclusions.providers.into_iter().collect(),
+        }
+    }
+}
+
 impl From<proto::relay_selector::Provider> for mullvad_types::relay_constraints::Provider {
     fn from(provider: proto::relay_selector::Provider) -> Self {
         provider.name
@@ -574,6 +686,21 @@ pub fn ownership_constraint_from_proto(
     }
 }
 
+pub fn try_strategy_constraint_from_i32(
+    strategy: i32,
+) -> Result<Constraint<mullvad_types::relay_constraints::RelayStrategy>, FromProtobufTypeError> {
+    use mullvad_types::relay_constraints::RelayStrategy as MullvadRelayStrategy;
+
+    match proto::RelayStrategy::try_from(strategy) {
+        Ok(proto::RelayStrategy::Random) => Ok(Constraint::Any),
+        Ok(proto::RelayStrategy::Fastest) => Ok(Constraint::Only(MullvadRelayStrategy::Fastest)),
+        Ok(proto::RelayStrategy::Reliable) => Ok(Constraint::Only(MullvadRelayStrategy::Reliable)),
+        Err(_) => Err(FromProtobufTypeError::invalid_argument(
+            "invalid relay strategy argument",
+        )),
+    }
+}
+
 fn convert_providers_constraint(
     providers: &Constraint<mullvad_types::relay_constraints::Providers>,
 ) -> Vec<String> {
@@ -596,3 +723,15 @@ fn convert_ownership_constraint(
         },
     }
 }
+
+fn convert_strategy_constraint(
+    strategy: &Constraint<mullvad_types::relay_constraints::RelayStrategy>,
+) -> proto::RelayStrategy {
+    use mullvad_types::relay_constraints::RelayStrategy as MullvadRelayStrategy;
+
+    match strategy {
+        Constraint::Any => proto::RelayStrategy::Random,
+        Constraint::Only(MullvadRelayStrategy::Fastest) => proto::RelayStrategy::Fastest,
+        Constraint::Only(MullvadRelayStrategy::Reliable) => proto::RelayStrategy::Reliable,
+    }
+}
@@ -1,8 +1,9 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     ops::RangeInclusive,
     str::FromStr,
+    time::Duration,
 };
 
 use mullvad_types::{
@@ -515,3 +516,42 @@ impl TryFrom<proto::PortRange> for RangeInclusive<u16> {
         Ok(first..=last)
     }
 }
+
+impl TryFrom<HashMap<String, Duration>> for proto::RelayLatencies {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(latencies: HashMap<String, Duration>) -> Result<Self, Self::Error> {
+        let latencies = latencies
+            .into_iter()
+            .map(|(hostname, latency)| {
+                let latency = prost_types::Duration::try_from(latency)
+                    .map_err(|_| FromProtobufTypeError::invalid_argument("invalid latency"))?;
+                Ok(proto::RelayLatency {
+                    hostname,
+                    latency: Some(latency),
+                })
+            })
+            .collect::<Result<_, Self::Error>>()?;
+        Ok(proto::RelayLatencies { latencies })
+    }
+}
+
+impl TryFrom<proto::RelayLatencies> for HashMap<String, Duration> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(latencies: proto::RelayLatencies) -> Result<Self, Self::Error> {
+        latencies
+            .latencies
+            .into_iter()
+            .map(|relay| {
+                let latency = relay
+                    .latency
+                    .and_then(|latency| Duration::try_from(latency).ok())
+                    .ok_or(FromProtobufTypeError::invalid_argument(
+                        "invalid relay latency",
+                    ))?;
+                Ok((relay.hostname, latency))
+            })
+            .collect()
+    }
+}
@@ -5,9 +5,11 @@ use mullvad_types::{
     relay_selector::{
         EntryConstraints, EntrySpecificConstraints, ExitConstraints, MultihopConstraints,
         Predicate, Reason, RelayPartitions, ResolvedLocationConstraint,
+        history::{CandidateSummary, RelaySelection, SelectionHistory, SelectionOutcome},
     },
 };
 
+use super::{from_timestamp, to_timestamp};
 use crate::types::{
     FromProtobufTypeError, IpVersion, relay_constraints::try_ownership_constraint_from_i32,
 };
@@ -184,6 +186,7 @@ impl From<Vec<Reason>> for proto::IncompatibleConstraints {
         for reason in reasons {
             match reason {
                 Inactive => incompatible.inactive = true,
+                Excluded => incompatible.excluded = true,
                 Ownership => incompatible.ownership = true,
                 Location => incompatible.location = true,
                 Providers => incompatible.providers = true,
@@ -192,9 +195,172 @@ impl From<Vec<Reason>> for proto::IncompatibleConstraints {
                 Obfuscation => incompatible.obfuscation = true,
                 Port => incompatible.port = true,
                 Conflict => incompatible.conflict_with_other_hop = true,
-                IncludeInCountry => continue,
+                IncludeInCountry => incompatible.include_in_country = true,
             };
         }
         incompatible
     }
 }
+
+impl From<proto::IncompatibleConstraints> for Vec<Reason> {
+    fn from(incompatible: proto::IncompatibleConstraints) -> Self {
+        [
+            (incompatible.inactive, Reason::Inactive),
+            (incompatible.excluded, Reason::Excluded),
+            (incompatible.location, Reason::Location),
+            (incompatible.providers, Reason::Providers),
+            (incompatible.ownership, Reason::Ownership),
+            (incompatible.ip_version, Reason::IpVersion),
+            (incompatible.daita, Reason::Daita),
+            (incompatible.obfuscation, Reason::Obfuscation),
+            (incompatible.port, Reason::Port),
+            (incompatible.conflict_with_other_hop, Reason::Conflict),
+            (incompatible.include_in_country, Reason::IncludeInCountry),
+        ]
+        .into_iter()
+        .filter_map(|(incompatible, reason)| incompatible.then_some(reason))
+        .collect()
+    }
+}
+
+impl From<SelectionHistory> for proto::SelectionHistory {
+    fn from(history: SelectionHistory) -> Self {
+        Self {
+            selections: history
+                .into_iter()
+                .map(proto::RelaySelection::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::SelectionHistory> for SelectionHistory {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(history: proto::SelectionHistory) -> Result<Self, Self::Error> {
+        history
+            .selections
+            .into_iter()
+            .map(RelaySelection::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map(SelectionHistory::from)
+    }
+}
+
+impl From<RelaySelection> for proto::RelaySelection {
+    fn from(selection: RelaySelection) -> Self {
+        let relay = |hostname| proto::Relay { hostname };
+        Self {
+            time: Some(to_timestamp(selection.time)),
+            retry_attempt: selection.retry_attempt,
+            query: selection.query,
+            exit_candidates: Some(proto::CandidateSummary::from(selection.exit_candidates)),
+            entry_candidates: selection
+                .entry_candidates
+                .map(proto::CandidateSummary::from),
+            exit: Some(relay(selection.exit)),
+            entry: selection.entry.map(relay),
+            obfuscator: selection.obfuscator,
+            outcome: Some(proto::SelectionOutcome::from(selection.outcome)),
+        }
+    }
+}
+
+impl TryFrom<proto::RelaySelection> for RelaySelection {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(selection: proto::RelaySelection) -> Result<Self, Self::Error> {
+        let exit = selection
+            .exit
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "missing exit relay",
+            ))?;
+        let outcome = selection
+            .outcome
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "missing selection outcome",
+            ))?;
+        Ok(RelaySelection {
+            time: from_timestamp(selection.time)?,
+            retry_attempt: selection.retry_attempt,
+            query: selection.query,
+            exit_candidates: selection
+                .exit_candidates
+                .map(CandidateSummary::from)
+                .unwrap_or_default(),
+            entry_candidates: selection.entry_candidates.map(CandidateSummary::from),
+            exit: exit.hostname,
+            entry: selection.entry.map(|entry| entry.hostname),
+            obfuscator: selection.obfuscator,
+            outcome: SelectionOutcome::try_from(outcome)?,
+        })
+    }
+}
+
+impl From<CandidateSummary> for proto::CandidateSummary {
+    fn from(summary: CandidateSummary) -> Self {
+        let count = |count: usize| u32::try_from(count).unwrap_or(u32::MAX);
+        Self {
+            matches: count(summary.matches),
+            discards: summary
+                .discards
+                .into_iter()
+                .map(|(why, discarded)| proto::DiscardCount {
+                    why: Some(proto::IncompatibleConstraints::from(why)),
+                    count: count(discarded),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<proto::CandidateSummary> for CandidateSummary {
+    fn from(summary: proto::CandidateSummary) -> Self {
+        Self {
+            matches: summary.matches as usize,
+            discards: summary
+                .discards
+                .into_iter()
+                .map(|discard| {
+                    let why = discard.why.map(Vec::<Reason>::from).unwrap_or_default();
+                    (why, discard.count as usize)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<SelectionOutcome> for proto::SelectionOutcome {
+    fn from(outcome: SelectionOutcome) -> Self {
+        use proto::selection_outcome::Outcome;
+        let (outcome, error) = match outcome {
+            SelectionOutcome::Pending => (Outcome::Pending, None),
+            SelectionOutcome::Connected => (Outcome::Connected, None),
+            SelectionOutcome::Failed(error) => (Outcome::Failed, Some(error)),
+            SelectionOutcome::Superseded => (Outcome::Superseded, None),
+            SelectionOutcome::Cancelled => (Outcome::Cancelled, None),
+        };
+        Self {
+            outcome: i32::from(outcome),
+            error,
+        }
+    }
+}
+
+impl TryFrom<proto::SelectionOutcome> for SelectionOutcome {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(outcome: proto::SelectionOutcome) -> Result<Self, Self::Error> {
+        use proto::selection_outcome::Outcome;
+        match Outcome::try_from(outcome.outcome) {
+            Ok(Outcome::Pending) => Ok(SelectionOutcome::Pending),
+            Ok(Outcome::Connected) => Ok(SelectionOutcome::Connected),
+            Ok(Outcome::Failed) => Ok(SelectionOutcome::Failed(outcome.error.unwrap_or_default())),
+            Ok(Outcome::Superseded) => Ok(SelectionOutcome::Superseded),
+            Ok(Outcome::Cancelled) => Ok(SelectionOutcome::Cancelled),
+            Err(_) => Err(FromProtobufTypeError::invalid_argument(
+                "invalid selection outcome",
+            )),
+        }
+    }
+}
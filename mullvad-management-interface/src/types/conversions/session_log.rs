@@ -0,0 +1,72 @@
+use super::{from_timestamp, to_timestamp};
+use crate::types::{FromProtobufTypeError, proto};
+use mullvad_types::session_log::{SessionEnd, SessionRecord};
+use talpid_types::{net::TunnelEndpoint, tunnel::ErrorState};
+
+impl From<SessionRecord> for proto::SessionRecord {
+    fn from(record: SessionRecord) -> Self {
+        let (end_reason, error_state) = match record.end_reason {
+            SessionEnd::Disconnected => (proto::session_record::EndReason::Disconnected, None),
+            SessionEnd::Reconnected => (proto::session_record::EndReason::Reconnected, None),
+            SessionEnd::Error(cause) => (
+                proto::session_record::EndReason::Error,
+                Some(proto::ErrorState::from(&ErrorState::new(cause, None))),
+            ),
+            SessionEnd::Interrupted => (proto::session_record::EndReason::Interrupted, None),
+        };
+
+        proto::SessionRecord {
+            start: Some(to_timestamp(record.start)),
+            end: Some(to_timestamp(record.end)),
+            exit_hostname: record.exit_hostname,
+            entry_hostname: record.entry_hostname,
+            endpoint: Some(proto::TunnelEndpoint::from(record.endpoint)),
+            rx_bytes: record.rx_bytes,
+            tx_bytes: record.tx_bytes,
+            end_reason: i32::from(end_reason),
+            error_state,
+        }
+    }
+}
+
+impl TryFrom<proto::SessionRecord> for SessionRecord {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(record: proto::SessionRecord) -> Result<Self, Self::Error> {
+        let end_reason = match proto::session_record::EndReason::try_from(record.end_reason) {
+            Ok(proto::session_record::EndReason::Disconnected) => SessionEnd::Disconnected,
+            Ok(proto::session_record::EndReason::Reconnected) => SessionEnd::Reconnected,
+            Ok(proto::session_record::EndReason::Error) => {
+                let error_state =
+                    record
+                        .error_state
+                        .ok_or(FromProtobufTypeError::invalid_argument(
+                            "missing error state",
+                        ))?;
+                SessionEnd::Error(ErrorState::try_from(error_state)?.cause().clone())
+            }
+            Ok(proto::session_record::EndReason::Interrupted) => SessionEnd::Interrupted,
+            Err(_) => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "invalid session end reason",
+                ));
+            }
+        };
+        let endpoint = record
+            .endpoint
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "missing tunnel endpoint",
+            ))?;
+
+        Ok(SessionRecord {
+            start: from_timestamp(record.start)?,
+            end: from_timestamp(record.end)?,
+            exit_hostname: record.exit_hostname,
+            entry_hostname: record.entry_hostname,
+            endpoint: TunnelEndpoint::try_from(endpoint)?,
+            rx_bytes: record.rx_bytes,
+            tx_bytes: record.tx_bytes,
+            end_reason,
+        })
+    }
+}
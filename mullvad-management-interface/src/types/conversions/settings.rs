@@ -1,10 +1,13 @@
 use crate::types::{FromProtobufTypeError, proto};
-use mullvad_types::{constraints::Constraint, settings::CURRENT_SETTINGS_VERSION};
+use mullvad_types::{
+    constraints::Constraint,
+    relay_rotation::{MAX_ROTATION_INTERVAL, RotationPolicy, TimeOfDay},
+    settings::CURRENT_SETTINGS_VERSION,
+};
 use talpid_types::ErrorExt;
 
 impl From<&mullvad_types::settings::Settings> for proto::Settings {
     fn from(settings: &mullvad_types::settings::Settings) -> Self {
-        #[cfg(any(windows, target_os = "android", target_os = "macos"))]
         let split_tunnel = {
             let apps = settings
                 .split_tunnel
@@ -22,10 +25,11 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
             Some(proto::SplitTunnelSettings {
                 enable_exclusions: settings.split_tunnel.enable_exclusions,
                 apps,
+                mode: i32::from(proto::SplitTunnelModeValue::from(
+                    settings.split_tunnel.mode,
+                )),
             })
         };
-        #[cfg(target_os = "linux")]
-        let split_tunnel = None;
 
         Self {
             relay_settings: Some(proto::RelaySettings::from(settings.get_relay_settings())),
@@ -35,8 +39,13 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
             #[cfg(target_os = "android")]
             lockdown_mode: false,
             auto_connect: settings.auto_connect,
+            trusted_networks: Some(proto::TrustedNetworkSettings::from(
+                &settings.trusted_networks,
+            )),
             tunnel_options: Some(proto::TunnelOptions::from(&settings.tunnel_options)),
             show_beta_releases: settings.show_beta_releases,
+            session_log: settings.session_log,
+            locked_settings: None,
             obfuscation_settings: Some(proto::ObfuscationSettings::from(
                 &settings.obfuscation_settings,
             )),
@@ -44,6 +53,9 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
             custom_lists: Some(proto::CustomListSettings::from(
                 settings.custom_lists.clone(),
             )),
+            custom_endpoints: Some(proto::CustomEndpointSettings::from(
+                settings.custom_endpoints.clone(),
+            )),
             api_access_methods: Some(proto::ApiAccessMethodSettings::from(
                 settings.api_access_methods.clone(),
             )),
@@ -53,6 +65,9 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
                 .cloned()
                 .map(proto::RelayOverride::from)
                 .collect(),
+            relay_exclusions: Some(proto::RelayExclusions::from(
+                settings.relay_exclusions.clone(),
+            )),
             recents: settings.recents.clone().map(proto::Recents::from),
             update_default_location: settings.update_default_location,
         }
@@ -61,6 +76,7 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
 
 impl From<&mullvad_types::settings::DnsOptions> for proto::DnsOptions {
     fn from(options: &mullvad_types::settings::DnsOptions) -> Self {
+        use mullvad_types::settings::DnsEncryption;
         use proto::dns_options;
 
         proto::DnsOptions {
@@ -83,7 +99,30 @@ impl From<&mullvad_types::settings::DnsOptions> for proto::DnsOptions {
                     .iter()
                     .map(|addr| addr.to_string())
                     .collect(),
+                encryption: options
+                    .custom_options
+                    .encryption
+                    .as_ref()
+                    .map(|encryption| {
+                        use proto::custom_dns_options::Encryption;
+                        match encryption {
+                            DnsEncryption::Tls { server_name } => {
+                                Encryption::TlsServerName(server_name.clone())
+                            }
+                            DnsEncryption::Https { server_name, path } => {
+                                Encryption::HttpsUrl(format!("https://{server_name}{path}"))
+                            }
+                        }
+                    }),
             }),
+            domain_rules: options
+                .domain_rules
+                .iter()
+                .map(|rule| proto::DnsDomainRule {
+                    domain: rule.domain.clone(),
+                    servers: rule.servers.iter().map(|addr| addr.to_string()).collect(),
+                })
+                .collect(),
         }
     }
 }
@@ -101,6 +140,38 @@ impl From<&mullvad_types::settings::TunnelOptions> for proto::TunnelOptions {
             enable_ipv6: options.generic.enable_ipv6,
             dns_options: Some(proto::DnsOptions::from(&options.dns_options)),
             userspace: options.wireguard.userspace,
+            relay_rotation: options
+                .relay_rotation
+                .as_ref()
+                .map(proto::RelayRotationPolicy::from),
+        }
+    }
+}
+
+impl From<&RotationPolicy> for proto::RelayRotationPolicy {
+    fn from(policy: &RotationPolicy) -> Self {
+        use proto::relay_rotation_policy::{Policy, TimesOfDay};
+
+        let policy = match policy {
+            // Intervals are validated when they are set, but cap them in case the settings file
+            // was edited by hand
+            RotationPolicy::Interval(interval) => Policy::Interval(
+                prost_types::Duration::try_from((*interval).min(MAX_ROTATION_INTERVAL))
+                    .expect("Failed to convert std::time::Duration to prost_types::Duration for relay rotation interval"),
+            ),
+            RotationPolicy::Traffic(bytes) => Policy::TrafficBytes(*bytes),
+            RotationPolicy::TimesOfDay(times) => Policy::TimesOfDay(TimesOfDay {
+                times: times
+                    .iter()
+                    .map(|time| proto::TimeOfDay {
+                        hour: u32::from(time.hour()),
+                        minute: u32::from(time.minute()),
+                    })
+                    .collect(),
+            }),
+        };
+        proto::RelayRotationPolicy {
+            policy: Some(policy),
         }
     }
 }
@@ -133,18 +204,35 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
                 .ok_or(FromProtobufTypeError::invalid_argument(
                     "missing custom lists settings",
                 ))?;
+        let custom_endpoints =
+            settings
+                .custom_endpoints
+                .ok_or(FromProtobufTypeError::invalid_argument(
+                    "missing custom endpoint settings",
+                ))?;
         let api_access_methods_settings =
             settings
                 .api_access_methods
                 .ok_or(FromProtobufTypeError::invalid_argument(
                     "missing api access methods settings",
                 ))?;
-        #[cfg(any(windows, target_os = "android", target_os = "macos"))]
         let split_tunnel = settings
             .split_tunnel
             .ok_or(FromProtobufTypeError::invalid_argument(
                 "missing split tunnel options",
             ))?;
+        let trusted_networks =
+            settings
+                .trusted_networks
+                .ok_or(FromProtobufTypeError::invalid_argument(
+                    "missing trusted network settings",
+                ))?;
+        let relay_exclusions =
+            settings
+                .relay_exclusions
+                .ok_or(FromProtobufTypeError::invalid_argument(
+                    "missing relay exclusions",
+                ))?;
 
         Ok(Self {
             relay_settings: mullvad_types::relay_constraints::RelaySettings::try_from(
@@ -154,14 +242,18 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
             #[cfg(not(target_os = "android"))]
             lockdown_mode: settings.lockdown_mode,
             auto_connect: settings.auto_connect,
+            trusted_networks: mullvad_types::settings::TrustedNetworks::try_from(trusted_networks)?,
             tunnel_options: mullvad_types::settings::TunnelOptions::try_from(tunnel_options)?,
             relay_overrides: settings
                 .relay_overrides
                 .into_iter()
                 .map(mullvad_types::relay_constraints::RelayOverride::try_from)
                 .collect::<Result<Vec<_>, _>>()?,
+            relay_exclusions: mullvad_types::relay_constraints::RelayExclusions::from(
+                relay_exclusions,
+            ),
             show_beta_releases: settings.show_beta_releases,
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+            session_log: settings.session_log,
             split_tunnel: mullvad_types::settings::SplitTunnelSettings::from(split_tunnel),
             obfuscation_settings: mullvad_types::relay_constraints::ObfuscationSettings::try_from(
                 obfuscation_settings,
@@ -172,6 +264,9 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
             custom_lists: mullvad_types::custom_list::CustomListsSettings::try_from(
                 custom_lists_settings,
             )?,
+            custom_endpoints: mullvad_types::custom_endpoint::CustomEndpointsSettings::try_from(
+                custom_endpoints,
+            )?,
             api_access_methods: mullvad_types::access_method::Settings::try_from(
                 api_access_methods_settings,
             )?,
@@ -187,17 +282,89 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
     }
 }
 
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
+impl From<&mullvad_types::settings::TrustedNetworks> for proto::TrustedNetworkSettings {
+    fn from(trusted_networks: &mullvad_types::settings::TrustedNetworks) -> Self {
+        use mullvad_types::settings::TrustedNetwork;
+        use proto::trusted_network::Network;
+
+        proto::TrustedNetworkSettings {
+            enabled: trusted_networks.enabled,
+            rules: trusted_networks
+                .rules
+                .iter()
+                .map(|rule| proto::TrustedNetwork {
+                    network: Some(match rule.clone() {
+                        TrustedNetwork::Ssid(ssid) => Network::Ssid(ssid),
+                        TrustedNetwork::GatewayMac(mac) => Network::GatewayMac(mac),
+                        TrustedNetwork::Interface(interface) => Network::Interface(interface),
+                    }),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::TrustedNetworkSettings> for mullvad_types::settings::TrustedNetworks {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(trusted_networks: proto::TrustedNetworkSettings) -> Result<Self, Self::Error> {
+        use mullvad_types::settings::{TrustedNetwork, TrustedNetworks};
+        use proto::trusted_network::Network;
+
+        let rules = trusted_networks
+            .rules
+            .into_iter()
+            .map(|rule| {
+                match rule.network.ok_or(FromProtobufTypeError::invalid_argument(
+                    "missing trusted network",
+                ))? {
+                    Network::Ssid(ssid) => Ok(TrustedNetwork::Ssid(ssid)),
+                    Network::GatewayMac(mac) => TrustedNetwork::gateway_mac(&mac).map_err(|_| {
+                        FromProtobufTypeError::invalid_argument("invalid gateway MAC address")
+                    }),
+                    Network::Interface(interface) => Ok(TrustedNetwork::Interface(interface)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TrustedNetworks {
+            enabled: trusted_networks.enabled,
+            rules,
+        })
+    }
+}
+
 impl From<proto::SplitTunnelSettings> for mullvad_types::settings::SplitTunnelSettings {
     fn from(value: proto::SplitTunnelSettings) -> Self {
-        use mullvad_types::settings::{SplitApp, SplitTunnelSettings};
+        use mullvad_types::settings::{SplitApp, SplitTunnelMode, SplitTunnelSettings};
         SplitTunnelSettings {
             enable_exclusions: value.enable_exclusions,
+            mode: SplitTunnelMode::from(value.mode()),
             apps: value.apps.into_iter().map(SplitApp::from).collect(),
         }
     }
 }
 
+impl From<mullvad_types::settings::SplitTunnelMode> for proto::SplitTunnelModeValue {
+    fn from(mode: mullvad_types::settings::SplitTunnelMode) -> Self {
+        use mullvad_types::settings::SplitTunnelMode;
+        match mode {
+            SplitTunnelMode::Exclude => proto::SplitTunnelModeValue::Exclude,
+            SplitTunnelMode::Include => proto::SplitTunnelModeValue::Include,
+        }
+    }
+}
+
+impl From<proto::SplitTunnelModeValue> for mullvad_types::settings::SplitTunnelMode {
+    fn from(mode: proto::SplitTunnelModeValue) -> Self {
+        use mullvad_types::settings::SplitTunnelMode;
+        match mode {
+            proto::SplitTunnelModeValue::Exclude => SplitTunnelMode::Exclude,
+            proto::SplitTunnelModeValue::Include => SplitTunnelMode::Include,
+        }
+    }
+}
+
 impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
     type Error = FromProtobufTypeError;
 
@@ -242,17 +409,66 @@ impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
                 enable_ipv6: options.enable_ipv6,
             },
             dns_options: mullvad_types::settings::DnsOptions::try_from(dns_options)?,
+            relay_rotation: options
+                .relay_rotation
+                .map(RotationPolicy::try_from)
+                .transpose()?,
         })
     }
 }
 
+impl TryFrom<proto::RelayRotationPolicy> for RotationPolicy {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(policy: proto::RelayRotationPolicy) -> Result<Self, Self::Error> {
+        use proto::relay_rotation_policy::Policy;
+
+        let policy = match policy.policy {
+            Some(Policy::Interval(interval)) => RotationPolicy::Interval(
+                std::time::Duration::try_from(interval)
+                    .map_err(|_| FromProtobufTypeError::invalid_argument("invalid duration"))?,
+            ),
+            Some(Policy::TrafficBytes(bytes)) => RotationPolicy::Traffic(bytes),
+            Some(Policy::TimesOfDay(times)) => RotationPolicy::TimesOfDay(
+                times
+                    .times
+                    .into_iter()
+                    .map(|time| {
+                        let hour = u8::try_from(time.hour).ok();
+                        let minute = u8::try_from(time.minute).ok();
+                        hour.zip(minute)
+                            .and_then(|(hour, minute)| TimeOfDay::new(hour, minute).ok())
+                            .ok_or(FromProtobufTypeError::invalid_argument(
+                                "invalid time of day",
+                            ))
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            None => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "missing relay rotation policy",
+                ));
+            }
+        };
+        policy.validate().map_err(|error| {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Invalid relay rotation policy")
+            );
+            FromProtobufTypeError::invalid_argument("invalid relay rotation policy")
+        })?;
+        Ok(policy)
+    }
+}
+
 impl TryFrom<proto::DnsOptions> for mullvad_types::settings::DnsOptions {
     type Error = FromProtobufTypeError;
 
     fn try_from(options: proto::DnsOptions) -> Result<Self, Self::Error> {
         use mullvad_types::settings::{
             CustomDnsOptions as MullvadCustomDnsOptions,
-            DefaultDnsOptions as MullvadDefaultDnsOptions, DnsOptions as MullvadDnsOptions,
+            DefaultDnsOptions as MullvadDefaultDnsOptions, DnsDomainRule as MullvadDnsDomainRule,
+            DnsEncryption as MullvadDnsEncryption, DnsOptions as MullvadDnsOptions,
             DnsState as MullvadDnsState,
         };
 
@@ -299,7 +515,47 @@ impl TryFrom<proto::DnsOptions> for mullvad_types::settings::DnsOptions {
                         })
                     })
                     .collect::<Result<Vec<_>, _>>()?,
+                encryption: custom_options
+                    .encryption
+                    .map(|encryption| {
+                        use proto::custom_dns_options::Encryption;
+                        match encryption {
+                            Encryption::TlsServerName(server_name) => {
+                                MullvadDnsEncryption::tls(&server_name).map_err(|_| {
+                                    FromProtobufTypeError::invalid_argument(
+                                        "invalid DNS over TLS server name",
+                                    )
+                                })
+                            }
+                            Encryption::HttpsUrl(url) => {
+                                MullvadDnsEncryption::https(&url).map_err(|_| {
+                                    FromProtobufTypeError::invalid_argument(
+                                        "invalid DNS over HTTPS URL",
+                                    )
+                                })
+                            }
+                        }
+                    })
+                    .transpose()?,
             },
+            domain_rules: options
+                .domain_rules
+                .into_iter()
+                .map(|rule| {
+                    let servers = rule
+                        .servers
+                        .into_iter()
+                        .map(|addr| {
+                            addr.parse().map_err(|_| {
+                                FromProtobufTypeError::invalid_argument("invalid IP address")
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    MullvadDnsDomainRule::new(&rule.domain, servers).map_err(|_| {
+                        FromProtobufTypeError::invalid_argument("invalid DNS rule domain")
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
         })
     }
 }
@@ -15,8 +15,8 @@ impl From<mullvad_types::settings::SettingsKey> for proto::SettingsKey {
             mullvad_types::settings::SettingsKey::AutoConnect => AutoConnect,
             mullvad_types::settings::SettingsKey::TunnelOptions => TunnelOptions,
             mullvad_types::settings::SettingsKey::RelayOverrides => RelayOverrides,
+            mullvad_types::settings::SettingsKey::RelayExclusions => RelayExclusions,
             mullvad_types::settings::SettingsKey::ShowBetaReleases => ShowBetaReleases,
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             mullvad_types::settings::SettingsKey::SplitTunnel => SplitTunnel,
             mullvad_types::settings::SettingsKey::Recents => Recents,
         }
@@ -41,18 +41,12 @@ impl TryFrom<proto::SettingsKey> for mullvad_types::settings::SettingsKey {
             proto::SettingsKey::AutoConnect => Self::AutoConnect,
             proto::SettingsKey::TunnelOptions => Self::TunnelOptions,
             proto::SettingsKey::ShowBetaReleases => Self::ShowBetaReleases,
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             proto::SettingsKey::SplitTunnel => Self::SplitTunnel,
-            #[cfg(not(any(windows, target_os = "android", target_os = "macos")))]
-            proto::SettingsKey::SplitTunnel => {
-                return Err(FromProtobufTypeError::invalid_argument(
-                    "split tunnel not supported on this platform",
-                ));
-            }
             proto::SettingsKey::ObfuscationSettings => Self::ObfuscationSettings,
             proto::SettingsKey::CustomLists => Self::CustomLists,
             proto::SettingsKey::ApiAccessMethods => Self::ApiAccessMethods,
             proto::SettingsKey::RelayOverrides => Self::RelayOverrides,
+            proto::SettingsKey::RelayExclusions => Self::RelayExclusions,
             proto::SettingsKey::Recents => Self::Recents,
             proto::SettingsKey::UpdateDefaultLocation => Self::UpdateDefaultLocation,
         })
@@ -0,0 +1,95 @@
+use super::{FromProtobufTypeError, bytes_to_pubkey};
+use crate::types::proto;
+use mullvad_types::tunnel_stats::{DaitaTrafficStats, PeerTrafficStats, TunnelStats};
+
+impl TryFrom<TunnelStats> for proto::TunnelStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(stats: TunnelStats) -> Result<Self, Self::Error> {
+        let peers = stats
+            .peers
+            .into_iter()
+            .map(proto::PeerStats::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(proto::TunnelStats { peers })
+    }
+}
+
+impl TryFrom<PeerTrafficStats> for proto::PeerStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(stats: PeerTrafficStats) -> Result<Self, Self::Error> {
+        let last_handshake_age = stats
+            .last_handshake_age
+            .map(prost_types::Duration::try_from)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::invalid_argument("invalid handshake age"))?;
+
+        Ok(proto::PeerStats {
+            public_key: stats.public_key.as_bytes().to_vec(),
+            rx_bytes: stats.rx_bytes,
+            tx_bytes: stats.tx_bytes,
+            rx_bytes_per_second: stats.rx_bytes_per_second,
+            tx_bytes_per_second: stats.tx_bytes_per_second,
+            last_handshake_age,
+            daita: stats.daita.map(proto::DaitaStats::from),
+        })
+    }
+}
+
+impl From<DaitaTrafficStats> for proto::DaitaStats {
+    fn from(stats: DaitaTrafficStats) -> Self {
+        proto::DaitaStats {
+            tx_padding_bytes: stats.tx_padding_bytes,
+            tx_decoy_packet_bytes: stats.tx_decoy_packet_bytes,
+            rx_padding_bytes: stats.rx_padding_bytes,
+            rx_decoy_packet_bytes: stats.rx_decoy_packet_bytes,
+        }
+    }
+}
+
+impl TryFrom<proto::TunnelStats> for TunnelStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(stats: proto::TunnelStats) -> Result<Self, Self::Error> {
+        let peers = stats
+            .peers
+            .into_iter()
+            .map(PeerTrafficStats::try_from)
+            .collect::<Result<_, _>>()?;
+        Ok(TunnelStats { peers })
+    }
+}
+
+impl TryFrom<proto::PeerStats> for PeerTrafficStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(stats: proto::PeerStats) -> Result<Self, Self::Error> {
+        let last_handshake_age = stats
+            .last_handshake_age
+            .map(std::time::Duration::try_from)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::invalid_argument("invalid handshake age"))?;
+
+        Ok(PeerTrafficStats {
+            public_key: bytes_to_pubkey(&stats.public_key)?,
+            rx_bytes: stats.rx_bytes,
+            tx_bytes: stats.tx_bytes,
+            rx_bytes_per_second: stats.rx_bytes_per_second,
+            tx_bytes_per_second: stats.tx_bytes_per_second,
+            last_handshake_age,
+            daita: stats.daita.map(DaitaTrafficStats::from),
+        })
+    }
+}
+
+impl From<proto::DaitaStats> for DaitaTrafficStats {
+    fn from(stats: proto::DaitaStats) -> Self {
+        DaitaTrafficStats {
+            tx_padding_bytes: stats.tx_padding_bytes,
+            tx_decoy_packet_bytes: stats.tx_decoy_packet_bytes,
+            rx_padding_bytes: stats.rx_padding_bytes,
+            rx_decoy_packet_bytes: stats.rx_decoy_packet_bytes,
+        }
+    }
+}
@@ -22,6 +22,9 @@ pub enum Error {
     #[error("No relays matching current constraints: {0:?}")]
     NoRelay(Box<RelayQuery>),
 
+    #[error("Relays near this device were requested, but its location is not known")]
+    UnknownUserLocation,
+
     #[error("No bridges matching current constraints")]
     NoBridge,
 
@@ -44,6 +44,8 @@ pub enum Error {
     NoMatchingAddresses,
     #[error("The selected relay does not support the selected obfuscation method")]
     MissingSupport,
+    #[error("No custom bridge has been configured")]
+    NoCustomBridge,
 }
 
 // ---------------------------------------------------------------------------
@@ -211,6 +213,12 @@ impl RelayEndpointSet {
                     .supports_ip_version(*ip_version)
                     .if_false(Reason::IpVersion),
             ]),
+
+            // The bridge connects to the WireGuard endpoint on our behalf, and every relay has an
+            // IPv4 address, so any relay will do as long as there is a bridge.
+            Constraint::Only(ObfuscationMode::CustomBridge(settings)) => {
+                settings.bridge.is_some().if_false(Reason::Obfuscation)
+            }
         }
     }
 
@@ -224,11 +232,16 @@ impl RelayEndpointSet {
         } else {
             Constraint::Any
         };
-        // QUIC and Shadowsocks do not use the selected WireGuard endpoint, so it does not
-        // need to match the requested IP version. Use the same family when possible so IP
-        // overrides are derived correctly; otherwise, loosen the constraint.
+        // QUIC and Shadowsocks do not use the selected WireGuard endpoint, and a custom bridge
+        // connects to it on our behalf, so it does not need to match the requested IP version.
+        // Use the same family when possible so IP overrides are derived correctly; otherwise,
+        // loosen the constraint.
         let wireguard_ip_version = match query {
-            Constraint::Only(ObfuscationMode::Shadowsocks(_) | ObfuscationMode::Quic) => {
+            Constraint::Only(
+                ObfuscationMode::Shadowsocks(_)
+                | ObfuscationMode::Quic
+                | ObfuscationMode::CustomBridge(_),
+            ) => {
                 if self.wireguard.supports_ip_version(ip_version) {
                     ip_version
                 } else {
@@ -265,6 +278,13 @@ impl RelayEndpointSet {
             ObfuscationMode::Lwo(settings) => Some(Obfuscators::Single(
                 self.lwo_config(wireguard_endpoint.ip(), settings.port)?,
             )),
+            ObfuscationMode::CustomBridge(settings) => {
+                let bridge = settings.bridge.clone().ok_or(Error::NoCustomBridge)?;
+                Some(Obfuscators::Single(ObfuscatorConfig::CustomBridge {
+                    bridge,
+                    wireguard_endpoint,
+                }))
+            }
         };
         Ok((wireguard_endpoint, obfuscator_config))
     }
@@ -604,6 +624,8 @@ impl VerdictExt for bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use mullvad_types::relay_constraints::CustomBridgeSettings;
+    use talpid_types::net::{obfuscation::CustomBridge, proxy::Socks5Remote};
 
     /// Build a [`ShadowsocksEndpoints`] with no dedicated addresses, backed only by the
     /// WireGuard fallback with the given port ranges.
@@ -733,4 +755,71 @@ mod tests {
         assert_eq!(addr.ip(), IpAddr::V4(WG_IPV4));
         assert_eq!(addr.port(), 100);
     }
+
+    /// Build a [`RelayEndpointSet`] for a relay that only has an IPv4 WireGuard endpoint.
+    fn wg_only(ipv4: Ipv4Addr) -> RelayEndpointSet {
+        RelayEndpointSet {
+            wireguard: wg(ipv4),
+            shadowsocks: None,
+            udp2tcp_ports: None,
+            quic: None,
+            lwo: false,
+        }
+    }
+
+    fn custom_bridge(bridge: Option<CustomBridge>) -> Constraint<ObfuscationMode> {
+        Constraint::Only(ObfuscationMode::CustomBridge(CustomBridgeSettings {
+            bridge,
+        }))
+    }
+
+    /// The bridge connects to the WireGuard endpoint on our behalf, so a relay without an IPv6
+    /// address is still usable when IPv6 is requested.
+    #[test]
+    fn test_custom_bridge_loosens_ip_version() {
+        let endpoints = wg_only(WG_IPV4);
+        let bridge = CustomBridge::Socks5(Socks5Remote::new((Ipv4Addr::new(192, 0, 2, 1), 1080)));
+        let query = custom_bridge(Some(bridge.clone()));
+
+        let constraints = EntrySpecificConstraints {
+            obfuscation: query.clone(),
+            ip_version: Constraint::Only(IpVersion::V6),
+            ..Default::default()
+        };
+        assert_eq!(endpoints.obfuscation_verdict(&constraints), Verdict::Accept);
+
+        let (endpoint, obfuscator) = endpoints
+            .get_wireguard_obfuscator(&query, Constraint::Only(IpVersion::V6))
+            .expect("should fall back to the IPv4 WireGuard endpoint");
+        assert_eq!(endpoint.ip(), IpAddr::V4(WG_IPV4));
+        assert_eq!(
+            obfuscator,
+            Some(Obfuscators::Single(ObfuscatorConfig::CustomBridge {
+                bridge,
+                wireguard_endpoint: endpoint,
+            }))
+        );
+    }
+
+    /// Selecting the custom bridge mode without a configured bridge rejects every relay.
+    #[test]
+    fn test_custom_bridge_not_configured() {
+        let endpoints = wg_only(WG_IPV4);
+        let query = custom_bridge(None);
+
+        let constraints = EntrySpecificConstraints {
+            obfuscation: query.clone(),
+            ..Default::default()
+        };
+        assert_eq!(
+            endpoints.obfuscation_verdict(&constraints),
+            Verdict::reject(Reason::Obfuscation)
+        );
+
+        let result = endpoints.get_wireguard_obfuscator(&query, Constraint::Any);
+        assert!(
+            matches!(result, Err(Error::NoCustomBridge)),
+            "expected NoCustomBridge, got {result:?}"
+        );
+    }
 }
@@ -4,6 +4,8 @@ use super::{
 };
 use mullvad_types::{
     constraints::{Constraint, Match},
+    custom_list::CustomListFilter,
+    location::Coordinates,
     relay_list::{WireguardRelay, WireguardRelayEndpointData},
     relay_selector::{
         EntryConstraints, EntrySpecificConstraints, ExitConstraints, MultihopConstraints,
@@ -14,6 +16,12 @@ use mullvad_types::{
 use either::Either;
 use itertools::Itertools;
 
+/// Relays that are at most this many kilometers farther away than the nearest relay are also
+/// considered nearest, so that a distance constraint without a maximum distance does not narrow
+/// the selection down to a single relay or city.
+const NEAREST_MARGIN_KM: f64 = 50.0;
+
+#[derive(Clone)]
 pub(crate) struct MultiHopPartitions {
     pub(crate) entries: RelayPartitions,
     pub(crate) exits: RelayPartitions,
@@ -24,6 +32,7 @@ pub(crate) struct MultiHopPartitions {
 /// Contains both the singlehop and multihop partitions so that the caller can
 /// decide which configuration to use, or collapse the two with
 /// [`AutohopPartition::into_relay_partitions`].
+#[derive(Clone)]
 pub(super) struct AutohopPartition {
     pub(super) singlehop: RelayPartitions,
     pub(super) multihop: MultiHopPartitions,
@@ -97,7 +106,7 @@ impl RelaySelector {
 }
 
 // Evaluate a verdict function over every relay in the current relay list and partition the
-// results into matches and discards.
+// results into matches and discards. Relays excluded by the user are always discarded.
 fn partition_by_verdict(
     relays: &AnnotatedRelayList,
     f: impl Fn(&WireguardRelay, &RelayEndpointSet) -> Verdict,
@@ -106,7 +115,8 @@ fn partition_by_verdict(
         let set = relays
             .endpoint_set_for(relay)
             .expect("Relays in list always have an endpoint set");
-        match f(relay, set) {
+        let excluded = relays.exclusions.excludes(relay);
+        match f(relay, set).and((!excluded).if_false(Reason::Excluded)) {
             Verdict::Accept => Either::Left(relay.clone()),
             Verdict::Reject(reasons) => Either::Right((relay.clone(), reasons)),
         }
@@ -118,10 +128,16 @@ pub(super) fn partition_entry(
     relays: &AnnotatedRelayList,
     constraints: &EntryConstraints,
 ) -> RelayPartitions {
-    partition_by_verdict(relays, |relay, endpoint_set| {
-        usable_as_entry(relay, endpoint_set, &constraints.entry_specific)
-            .and(usable_as_exit(relay, &constraints.general))
-    })
+    let general = with_user_location(&constraints.general, relays.user_location);
+    let mut partitions = partition_by_verdict(relays, |relay, endpoint_set| {
+        usable_as_entry(relay, endpoint_set, &constraints.entry_specific).and(usable_as_exit(
+            relay,
+            endpoint_set,
+            &general,
+        ))
+    });
+    keep_nearest(&mut partitions, general.location.as_ref());
+    partitions
 }
 
 pub(super) fn partition_autohop(
@@ -150,9 +166,58 @@ pub(super) fn partition_exit(
     relays: &AnnotatedRelayList,
     constraints: &ExitConstraints,
 ) -> RelayPartitions {
-    partition_by_verdict(relays, |relay, _endpoint_set| {
-        usable_as_exit(relay, constraints)
-    })
+    let constraints = with_user_location(constraints, relays.user_location);
+    let mut partitions = partition_by_verdict(relays, |relay, endpoint_set| {
+        usable_as_exit(relay, endpoint_set, &constraints)
+    });
+    keep_nearest(&mut partitions, constraints.location.as_ref());
+    partitions
+}
+
+/// Measure a location constraint relative to this device from `user_location`.
+fn with_user_location(
+    constraints: &ExitConstraints,
+    user_location: Option<Coordinates>,
+) -> ExitConstraints {
+    ExitConstraints {
+        location: constraints
+            .location
+            .clone()
+            .map(|location| location.with_user_location(user_location)),
+        ..constraints.clone()
+    }
+}
+
+/// If `location` only accepts the relays nearest to an origin, discard every match that is more
+/// than [`NEAREST_MARGIN_KM`] farther away from the origin than the nearest match.
+fn keep_nearest(
+    partitions: &mut RelayPartitions,
+    location: Constraint<&ResolvedLocationConstraint>,
+) {
+    let Some(origin) = location
+        .option()
+        .and_then(|location| location.distance())
+        .filter(|distance| distance.max_distance_km.is_none())
+        .and_then(|distance| distance.origin.coordinates())
+    else {
+        return;
+    };
+    let distance = |relay: &WireguardRelay| relay.location.distance_from(origin);
+    let Some(nearest) = partitions
+        .matches
+        .iter()
+        .map(distance)
+        .min_by(f64::total_cmp)
+    else {
+        return;
+    };
+    let (matches, far): (Vec<_>, Vec<_>) = std::mem::take(&mut partitions.matches)
+        .into_iter()
+        .partition(|relay| distance(relay) <= nearest + NEAREST_MARGIN_KM);
+    partitions.matches = matches;
+    partitions
+        .discards
+        .extend(far.into_iter().map(|relay| (relay, vec![Reason::Location])));
 }
 
 /// Check that the relay satisfies the entry specific criteria. Note that this does not check exit constraints.
@@ -172,6 +237,7 @@ fn usable_as_entry(
 /// Check that the relay satisfies the exit criteria.
 fn usable_as_exit(
     relay: &WireguardRelay,
+    endpoint_set: &RelayEndpointSet,
     ExitConstraints {
         location,
         providers,
@@ -180,7 +246,7 @@ fn usable_as_exit(
 ) -> Verdict {
     let ownership = ownership.matches(relay).if_false(Reason::Ownership);
     let providers = providers.matches(relay).if_false(Reason::Providers);
-    let location = location_criteria(relay, location.as_ref());
+    let location = location_criteria(relay, endpoint_set, location.as_ref());
     let active = relay.active.if_false(Reason::Inactive);
 
     ownership.and(providers).and(location).and(active)
@@ -188,8 +254,16 @@ fn usable_as_exit(
 
 fn location_criteria(
     relay: &WireguardRelay,
+    endpoint_set: &RelayEndpointSet,
     location: Constraint<&ResolvedLocationConstraint>,
 ) -> Verdict {
+    let filter = location
+        .option()
+        .and_then(ResolvedLocationConstraint::filter);
+    if filter.is_some_and(|filter| in_custom_list_filter(relay, endpoint_set, filter)) {
+        return Verdict::Accept;
+    }
+
     if !location.matches(relay) {
         return Verdict::reject(Reason::Location);
     }
@@ -197,6 +271,25 @@ fn location_criteria(
     filter_include_in_country(relay, location)
 }
 
+/// Returns whether `relay` is included by the rules of a custom list. The rules are checked as
+/// relay constraints, except that relays excluded by hostname are never included.
+fn in_custom_list_filter(
+    relay: &WireguardRelay,
+    endpoint_set: &RelayEndpointSet,
+    filter: &CustomListFilter,
+) -> bool {
+    let EntryConstraints {
+        general,
+        entry_specific,
+    } = EntryConstraints::from(filter);
+    !filter.excluded_hostnames.contains(&relay.hostname)
+        && usable_as_entry(relay, endpoint_set, &entry_specific).and(usable_as_exit(
+            relay,
+            endpoint_set,
+            &general,
+        )) == Verdict::Accept
+}
+
 /// Ensure the same relay cannot be chosen as both entry and exit.
 ///
 /// If either side's `matches` contains a single relay that also appears in the other
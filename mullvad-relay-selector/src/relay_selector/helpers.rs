@@ -1,10 +1,20 @@
 //! This module contains various helper functions for the relay selector implementation.
 
-use std::ops::Deref;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ops::{Deref, DerefMut},
+    time::Duration,
+};
 
-use mullvad_types::relay_list::Relay;
+use chrono::{DateTime, Utc};
+use mullvad_types::{relay_list::Relay, relay_selector::penalties::RelayPenalties};
 use rand::{Rng, seq::IteratorRandom};
 
+/// Relays whose measured latency exceeds the lowest one by at most this much are considered to
+/// be equally fast.
+const LATENCY_TOLERANCE: Duration = Duration::from_millis(10);
+
 /// Picks a relay at random from `relays`, but don't pick `exclude`.
 pub fn pick_random_relay_excluding<'a, T>(relays: &'a [T], exclude: &'_ T) -> Option<&'a T>
 where
@@ -61,3 +71,48 @@ pub fn pick_random_relay_weighted<'a, RelayType>(
         )
     }
 }
+
+/// Returns the relays in `relays` whose measured latency is within [`LATENCY_TOLERANCE`] of the
+/// lowest measured latency. If none of the relays have been measured, all of them are returned.
+pub fn fastest_relays<'a, T>(relays: &'a [T], latencies: &HashMap<String, Duration>) -> Cow<'a, [T]>
+where
+    T: Deref<Target = Relay> + Clone,
+{
+    let latency = |relay: &T| latencies.get(&relay.hostname).copied();
+    let Some(lowest) = relays.iter().filter_map(latency).min() else {
+        return Cow::Borrowed(relays);
+    };
+    Cow::Owned(
+        relays
+            .iter()
+            .filter(|relay| latency(relay).is_some_and(|rtt| rtt <= lowest + LATENCY_TOLERANCE))
+            .cloned()
+            .collect(),
+    )
+}
+
+/// Returns copies of `relays` with their weights scaled down according to `penalties`, so that
+/// relays that recently failed to connect are less likely to be picked. If there are no
+/// penalties, `relays` is returned as is.
+pub fn penalized_relays<'a, T>(
+    relays: &'a [T],
+    penalties: &RelayPenalties,
+    now: DateTime<Utc>,
+) -> Cow<'a, [T]>
+where
+    T: DerefMut<Target = Relay> + Clone,
+{
+    if penalties.is_empty() {
+        return Cow::Borrowed(relays);
+    }
+    Cow::Owned(
+        relays
+            .iter()
+            .cloned()
+            .map(|mut relay| {
+                relay.weight = penalties.weight(&relay.hostname, relay.weight, now);
+                relay
+            })
+            .collect(),
+    )
+}
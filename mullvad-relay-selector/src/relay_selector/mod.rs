@@ -15,18 +15,24 @@ use crate::{
     query::{Hops, RelayQuery},
 };
 
+use chrono::Utc;
 pub use mullvad_types::relay_list::Relay;
-use mullvad_types::relay_selector::MultihopConstraints;
+use mullvad_types::relay_selector::{
+    MultihopConstraints, history::CandidateSummary, penalties::RelayPenalties,
+};
 use mullvad_types::{
     constraints::Constraint,
     endpoint::MullvadEndpoint,
     location::Coordinates,
+    relay_constraints::{RelayExclusions, RelayStrategy},
     relay_list::{Bridge, BridgeList, RelayList, WireguardRelay},
 };
 use std::ops::Deref;
 use std::{
+    borrow::Cow,
     collections::HashMap,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 use talpid_types::net::{obfuscation::Obfuscators, proxy::Shadowsocks};
 
@@ -38,6 +44,11 @@ struct AnnotatedRelayList {
     /// Maps relay hostname → pre-computed endpoint set.
     /// Relays whose WireGuard port ranges are empty are absent from this map.
     endpoint_sets: HashMap<String, endpoint_set::RelayEndpointSet>,
+    /// Relays that must never be selected. These are kept across relay list updates.
+    exclusions: RelayExclusions,
+    /// Location of this device, which distance constraints relative to it are measured from.
+    /// This is kept across relay list updates.
+    user_location: Option<Coordinates>,
 }
 
 impl AnnotatedRelayList {
@@ -53,6 +64,8 @@ impl AnnotatedRelayList {
         Self {
             inner: list,
             endpoint_sets,
+            exclusions: RelayExclusions::default(),
+            user_location: None,
         }
     }
 
@@ -61,12 +74,23 @@ impl AnnotatedRelayList {
     }
 }
 
+/// Locally observed properties of relays, which are used by some [`RelayStrategy`]s to choose
+/// among the relays that satisfy all constraints.
+#[derive(Debug, Default)]
+struct RelayMetrics {
+    /// Measured round-trip time to relays, by hostname.
+    latencies: HashMap<String, Duration>,
+    /// Penalties of relays that recently failed to connect.
+    penalties: RelayPenalties,
+}
+
 #[derive(Clone)]
 pub struct RelaySelector {
     // Relays are updated very infrequently, but might conceivably be accessed by multiple readers at
     // the same time.
     relays: Arc<RwLock<AnnotatedRelayList>>,
     bridges: Arc<RwLock<BridgeList>>,
+    metrics: Arc<RwLock<RelayMetrics>>,
 }
 
 /// The return type of [`RelaySelector::get_relay_by_query`].
@@ -75,6 +99,19 @@ pub struct GetRelay {
     pub endpoint: MullvadEndpoint,
     pub obfuscator: Option<Obfuscators>,
     pub inner: WireguardConfig,
+    /// The query that the relay was selected by.
+    pub query: RelayQuery,
+    /// The relays that were candidates for the query.
+    pub candidates: Candidates,
+}
+
+/// How many relays were candidates for a selection, and why the others were discarded.
+#[derive(Clone, Debug)]
+pub struct Candidates {
+    /// Candidates for the exit relay, or for the only relay when using singlehop.
+    pub exit: CandidateSummary,
+    /// Candidates for the entry relay, if an entry relay was selected.
+    pub entry: Option<CandidateSummary>,
 }
 
 impl RelaySelector {
@@ -82,6 +119,7 @@ impl RelaySelector {
         RelaySelector {
             relays: Arc::new(RwLock::new(AnnotatedRelayList::new(relays))),
             bridges: Arc::new(RwLock::new(bridges)),
+            metrics: Arc::new(RwLock::new(RelayMetrics::default())),
         }
     }
 
@@ -99,7 +137,26 @@ impl RelaySelector {
     /// Update the list of relays
     pub fn set_relays(&self, relays: RelayList) {
         log::trace!("Updating relay list");
-        *self.relays.write().unwrap() = AnnotatedRelayList::new(relays);
+        let mut annotated = self.relays.write().unwrap();
+        let exclusions = std::mem::take(&mut annotated.exclusions);
+        *annotated = AnnotatedRelayList {
+            exclusions,
+            user_location: annotated.user_location,
+            ..AnnotatedRelayList::new(relays)
+        };
+    }
+
+    /// Update the relays and providers that must never be selected.
+    pub fn set_exclusions(&self, exclusions: RelayExclusions) {
+        self.relays.write().unwrap().exclusions = exclusions;
+    }
+
+    /// Update the location of this device. Distance constraints with a
+    /// [`DistanceOrigin::GeoIp`] origin are measured from it.
+    ///
+    /// [`DistanceOrigin::GeoIp`]: mullvad_types::relay_constraints::DistanceOrigin::GeoIp
+    pub fn set_user_location(&self, user_location: Coordinates) {
+        self.relays.write().unwrap().user_location = Some(user_location);
     }
 
     /// Update the list of bridges
@@ -109,6 +166,28 @@ impl RelaySelector {
         *key = bridges;
     }
 
+    /// Update the measured round-trip times to relays, by hostname. These are used to pick the
+    /// fastest relays when [`RelayStrategy::Fastest`] is requested.
+    pub fn set_latencies(&self, latencies: HashMap<String, Duration>) {
+        self.metrics.write().unwrap().latencies = latencies;
+    }
+
+    /// Returns the measured round-trip times to relays, by hostname.
+    pub fn get_latencies(&self) -> HashMap<String, Duration> {
+        self.metrics.read().unwrap().latencies.clone()
+    }
+
+    /// Update the penalties of relays that recently failed to connect. These are used to avoid
+    /// those relays when [`RelayStrategy::Reliable`] is requested.
+    pub fn set_penalties(&self, penalties: RelayPenalties) {
+        self.metrics.write().unwrap().penalties = penalties;
+    }
+
+    /// Returns the penalties of relays that recently failed to connect.
+    pub fn get_penalties(&self) -> RelayPenalties {
+        self.metrics.read().unwrap().penalties.clone()
+    }
+
     /// Returns all countries and cities. The cities in the object returned does not have any
     /// relays in them.
     pub fn get_relays(&self) -> RelayList {
@@ -131,11 +210,23 @@ impl RelaySelector {
     /// Returns random relay and relay endpoint matching `query`.
     /// Note that this does not take custom config into consideration.
     pub fn get_relay_by_query(&self, query: RelayQuery) -> Result<GetRelay, Error> {
+        self.get_relay_by_query_avoiding(query, None)
+    }
+
+    /// Like [`Self::get_relay_by_query`], but never selects the exit relay with the hostname
+    /// `previous_exit` unless it is the only relay that matches `query`.
+    pub fn get_relay_by_query_avoiding(
+        &self,
+        query: RelayQuery,
+        previous_exit: Option<&str>,
+    ) -> Result<GetRelay, Error> {
         // Hold a single read lock for the whole call so the relay we choose during
         // partitioning is the same one we look up in `endpoint_sets` afterwards.
         let annotated = self.relays.read().unwrap();
+        let metrics = self.metrics.read().unwrap();
 
-        let inner = select_wireguard_relay(&annotated, &query)?;
+        let (inner, candidates) =
+            select_wireguard_relay(&annotated, &query, &metrics, previous_exit)?;
 
         let entry = match &inner {
             WireguardConfig::Singlehop { exit } => exit,
@@ -161,37 +252,133 @@ impl RelaySelector {
             endpoint,
             obfuscator,
             inner,
+            query,
+            candidates,
         })
     }
 }
 
 /// Select relay(s) matching the constraints, handling singlehop, autohop, and multihop routing.
+/// The candidates are summarized from the same partitions that the relays were selected from.
 fn select_wireguard_relay(
     relays: &AnnotatedRelayList,
     query: &RelayQuery,
-) -> Result<WireguardConfig, Error> {
+    metrics: &RelayMetrics,
+    previous_exit: Option<&str>,
+) -> Result<(WireguardConfig, Candidates), Error> {
+    if relays.user_location.is_none() && needs_user_location(query) {
+        return Err(Error::UnknownUserLocation);
+    }
     match &query.hops {
         Hops::Single(constraints) => {
             let partitions = filter::partition_entry(relays, constraints);
-            match helpers::pick_random_relay(&partitions.matches) {
-                Some(exit) => Ok(WireguardConfig::from(Singlehop::new(exit.clone()))),
-                None => Err(Error::NoRelay(Box::new(query.clone()))),
-            }
+            let exit = pick_exit(
+                &apply_strategy(&partitions.matches, query.strategy, metrics),
+                previous_exit,
+            )
+            .cloned()
+            .ok_or_else(|| Error::NoRelay(Box::new(query.clone())))?;
+            let candidates = Candidates {
+                exit: CandidateSummary::from(&partitions),
+                entry: None,
+            };
+            Ok((WireguardConfig::from(Singlehop::new(exit)), candidates))
         }
         Hops::Auto(constraints) => {
             let autohop = filter::partition_autohop(relays, constraints.clone());
             // Attempt to pick a single relay that matches all constraints
-            if let Some(exit) = helpers::pick_random_relay(&autohop.singlehop.matches) {
-                return Ok(WireguardConfig::from(Singlehop::new(exit.clone())));
+            let exit = pick_exit(
+                &apply_strategy(&autohop.singlehop.matches, query.strategy, metrics),
+                previous_exit,
+            )
+            .cloned();
+            if let Some(exit) = exit {
+                let candidates = Candidates {
+                    exit: CandidateSummary::from(&autohop.into_relay_partitions()),
+                    entry: None,
+                };
+                return Ok((WireguardConfig::from(Singlehop::new(exit)), candidates));
             }
             // Otherwise fall through to multihop using the pre-computed partition.
+            let candidates = Candidates {
+                exit: CandidateSummary::from(&autohop.clone().into_relay_partitions()),
+                entry: Some(CandidateSummary::from(&autohop.multihop.entries)),
+            };
             let multihop_constraints = constraints.clone().into_autohop();
-            select_from_multihop_partitions(autohop.multihop, multihop_constraints)
+            let config = select_from_multihop_partitions(
+                autohop.multihop,
+                multihop_constraints,
+                query.strategy,
+                metrics,
+                previous_exit,
+            )?;
+            Ok((config, candidates))
         }
         Hops::Multi(constraints) => {
             let partitions = filter::partition_multihop(relays, constraints);
-            select_from_multihop_partitions(partitions, constraints.clone())
+            let candidates = Candidates {
+                exit: CandidateSummary::from(&partitions.exits),
+                entry: Some(CandidateSummary::from(&partitions.entries)),
+            };
+            let config = select_from_multihop_partitions(
+                partitions,
+                constraints.clone(),
+                query.strategy,
+                metrics,
+                previous_exit,
+            )?;
+            Ok((config, candidates))
+        }
+    }
+}
+
+/// Returns whether any location constraint of `query` is relative to this device.
+fn needs_user_location(query: &RelayQuery) -> bool {
+    let locations = match &query.hops {
+        Hops::Single(constraints) | Hops::Auto(constraints) => {
+            vec![&constraints.general.location]
+        }
+        Hops::Multi(constraints) => vec![
+            &constraints.entry.general.location,
+            &constraints.exit.location,
+        ],
+    };
+    locations.into_iter().any(|location| {
+        location
+            .as_ref()
+            .is_only_and(|location| location.needs_user_location())
+    })
+}
+
+/// Pick a random exit relay from `relays`. The relay with the hostname `previous_exit` is only
+/// picked if there is no other option.
+fn pick_exit<'a>(
+    relays: &'a [WireguardRelay],
+    previous_exit: Option<&str>,
+) -> Option<&'a WireguardRelay> {
+    let previous =
+        previous_exit.and_then(|hostname| relays.iter().find(|relay| relay.hostname == hostname));
+    match previous {
+        Some(previous) => helpers::pick_random_relay_excluding(relays, previous).or(Some(previous)),
+        None => helpers::pick_random_relay(relays),
+    }
+}
+
+/// Narrow down, or reweight, the relays that the client connects to directly according to
+/// `strategy`.
+fn apply_strategy<'a>(
+    relays: &'a [WireguardRelay],
+    strategy: Constraint<RelayStrategy>,
+    metrics: &RelayMetrics,
+) -> Cow<'a, [WireguardRelay]> {
+    match strategy {
+        Constraint::Only(RelayStrategy::Fastest) => {
+            helpers::fastest_relays(relays, &metrics.latencies)
         }
+        Constraint::Only(RelayStrategy::Reliable) => {
+            helpers::penalized_relays(relays, &metrics.penalties, Utc::now())
+        }
+        Constraint::Any => Cow::Borrowed(relays),
     }
 }
 
@@ -203,13 +390,16 @@ fn select_wireguard_relay(
 fn select_from_multihop_partitions(
     partitions: filter::MultiHopPartitions,
     multihop_constraints: MultihopConstraints,
+    strategy: Constraint<RelayStrategy>,
+    metrics: &RelayMetrics,
+    previous_exit: Option<&str>,
 ) -> Result<WireguardConfig, Error> {
     let MultihopConstraints {
         entry: entry_constraints,
         exit: exit_constraints,
     } = multihop_constraints;
 
-    let exit = helpers::pick_random_relay(&partitions.exits.matches)
+    let exit = pick_exit(&partitions.exits.matches, previous_exit)
         .ok_or_else(|| Error::NoRelayExit(Box::new(exit_constraints)))?;
 
     let entry = if matches!(entry_constraints.general.location, Constraint::Any) {
@@ -232,7 +422,14 @@ fn select_from_multihop_partitions(
             .ok_or_else(|| Error::NoRelayEntry(Box::new(entry_constraints)))?
             .clone()
     } else {
-        helpers::pick_random_relay_excluding(&partitions.entries.matches, exit)
+        let entries: Vec<_> = partitions
+            .entries
+            .matches
+            .iter()
+            .filter(|&entry| entry.deref() != exit.deref())
+            .cloned()
+            .collect();
+        helpers::pick_random_relay(&apply_strategy(&entries, strategy, metrics))
             .ok_or_else(|| Error::NoRelayEntry(Box::new(entry_constraints)))?
             .clone()
     };
@@ -17,7 +17,7 @@ pub use mullvad_types::relay_constraints::{
 use mullvad_types::{
     Intersection,
     constraints::Constraint,
-    relay_constraints::{AllowedIps, Multihop, RelaySettings},
+    relay_constraints::{AllowedIps, Multihop, RelaySettings, RelayStrategy},
     relay_selector::{
         EntryConstraints, EntrySpecificConstraints, ExitConstraints, MultihopConstraints,
         ResolvedLocationConstraint,
@@ -25,6 +25,7 @@ use mullvad_types::{
     settings::Settings,
     wireguard::QuantumResistantState,
 };
+use std::fmt;
 use talpid_types::net::{IpAvailability, IpVersion};
 
 use crate::Error;
@@ -36,10 +37,12 @@ use crate::Error;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RelayQuery {
     /// Constraints depending on hop variant.
-    // Only this field affects relay selection.
+    // Only this field affects which relays may be selected.
     pub hops: Hops,
     pub allowed_ips: Constraint<AllowedIps>,
     pub quantum_resistant: Constraint<QuantumResistantState>,
+    /// How to choose among the relays that satisfy `hops`.
+    pub strategy: Constraint<RelayStrategy>,
 }
 
 /// The multihop variant and corresponding constraints on each hop.
@@ -140,6 +143,22 @@ impl RelayQuery {
     }
 }
 
+impl fmt::Display for RelayQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.hops {
+            Hops::Single(constraints) => write!(f, "singlehop: {constraints}")?,
+            Hops::Auto(constraints) => write!(f, "autohop: {constraints}")?,
+            Hops::Multi(MultihopConstraints { entry, exit }) => {
+                write!(f, "multihop: entry ({entry}), exit ({exit})")?
+            }
+        }
+        if let Constraint::Only(strategy) = self.strategy {
+            write!(f, "; strategy: {strategy}")?;
+        }
+        Ok(())
+    }
+}
+
 impl From<Settings> for RelayQuery {
     fn from(settings: Settings) -> Self {
         let RelaySettings::Normal(relay_settings) = settings.relay_settings else {
@@ -213,6 +232,7 @@ impl From<Settings> for RelayQuery {
             quantum_resistant: Constraint::Only(
                 settings.tunnel_options.wireguard.quantum_resistant,
             ),
+            strategy: relay_settings.strategy,
         }
     }
 }
@@ -225,6 +245,7 @@ impl Default for RelayQuery {
             hops: Hops::Single(EntryConstraints::default()),
             allowed_ips: Constraint::Any,
             quantum_resistant: Constraint::Any,
+            strategy: Constraint::Any,
         }
     }
 }
@@ -380,6 +401,7 @@ pub mod builder {
                 hops,
                 allowed_ips: self.allowed_ips,
                 quantum_resistant: self.quantum_resistant,
+                strategy: Constraint::Any,
             }
         }
     }
@@ -612,6 +634,7 @@ mod test {
                 },
                 wireguard_port: port1.into(),
                 lwo: LwoSettings { port: port1 },
+                custom_bridge: Default::default(),
             });
             assert_eq!(query, Constraint::Any);
         }
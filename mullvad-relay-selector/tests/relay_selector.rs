@@ -2,9 +2,10 @@
 
 use std::{
     assert_matches,
-    collections::HashSet,
+    collections::{BTreeSet, HashMap, HashSet},
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::LazyLock,
+    time::Duration,
 };
 use talpid_types::net::{
     IpVersion,
@@ -19,16 +20,20 @@ use mullvad_relay_selector::{
     query::{Hops, ObfuscationMode, builder::RelayQueryBuilder},
 };
 use mullvad_types::{
+    constraints::Constraint,
+    custom_list::{CustomList, CustomListFilter, CustomListsSettings},
     endpoint::MullvadEndpoint,
     location::Location,
     relay_constraints::{
-        GeographicLocationConstraint, LwoSettings, Ownership, Providers, RelayOverride,
+        GeographicLocationConstraint, LocationConstraint, LwoSettings, Ownership, Providers,
+        RelayExclusions, RelayOverride, RelayStrategy,
     },
     relay_list::{
         Bridge, BridgeEndpointData, BridgeList, EndpointData, Quic, Relay, RelayList,
         RelayListCity, RelayListCountry, ShadowsocksEndpointData, WireguardRelay,
         WireguardRelayEndpointData,
     },
+    relay_selector::{ResolvedLocationConstraint, penalties::RelayPenalties},
 };
 use vec1::vec1;
 
@@ -782,6 +787,136 @@ mod relay_selection {
         assert!(ips.len() > 1, "expected more than 1 server, got {ips:?}");
     }
 
+    /// Verify that only the relays with the lowest measured latency are picked when the fastest
+    /// relay is requested, and that unmeasured relays are ignored.
+    #[test]
+    fn test_fastest_relay() {
+        let relay_selector = default_relay_selector();
+        let location = GeographicLocationConstraint::country("se");
+        let mut query = RelayQueryBuilder::new().location(location).build();
+        query.strategy = Constraint::Only(RelayStrategy::Fastest);
+
+        // Without any measurements, any relay may be picked.
+        let relay = unwrap_relay(relay_selector.get_relay_by_query(query.clone()).unwrap());
+        assert!(relay.active);
+
+        relay_selector.set_latencies(HashMap::from([
+            ("se9-wireguard".to_string(), Duration::from_millis(40)),
+            ("se10-wireguard".to_string(), Duration::from_millis(5)),
+        ]));
+        for _ in 0..20 {
+            let relay = unwrap_relay(relay_selector.get_relay_by_query(query.clone()).unwrap());
+            assert_eq!(relay.hostname, "se10-wireguard");
+        }
+    }
+
+    /// Verify that relays that recently failed to connect are avoided, but not ruled out, when
+    /// the reliable strategy is used.
+    #[test]
+    fn test_reliable_relay() {
+        let relay_selector = default_relay_selector();
+        let location = GeographicLocationConstraint::country("se");
+        let mut query = RelayQueryBuilder::new().location(location).build();
+        query.strategy = Constraint::Only(RelayStrategy::Reliable);
+
+        let mut penalties = RelayPenalties::default();
+        for _ in 0..10 {
+            penalties.penalize("se9-wireguard", chrono::Utc::now());
+            penalties.penalize("se10-wireguard", chrono::Utc::now());
+        }
+        relay_selector.set_penalties(penalties);
+
+        let picked_unpenalized = (0..100)
+            .map(|_| unwrap_relay(relay_selector.get_relay_by_query(query.clone()).unwrap()))
+            .filter(|relay| relay.hostname == "se11-wireguard")
+            .count();
+        assert!(picked_unpenalized > 80);
+    }
+
+    /// Verify that a custom list with filter rules contains the relays that satisfy all rules, in
+    /// addition to its static locations.
+    #[test]
+    fn test_custom_list_filter() {
+        let relay_selector = default_relay_selector();
+        let mut custom_list = CustomList::new("filtered".to_string()).unwrap();
+        custom_list.filter = Some(CustomListFilter {
+            countries: BTreeSet::from(["se".to_string()]),
+            providers: BTreeSet::from(["provider1".to_string(), "provider2".to_string()]),
+            excluded_hostnames: BTreeSet::from(["se11-wireguard".to_string()]),
+            ..Default::default()
+        });
+        let list_id = custom_list.id();
+        let custom_lists = CustomListsSettings::from(vec![custom_list]);
+        let location = ResolvedLocationConstraint::from_constraint(
+            Constraint::Only(LocationConstraint::CustomList { list_id }),
+            &custom_lists,
+        );
+
+        let query = RelayQueryBuilder::new().location(location).build();
+        for _ in 0..20 {
+            let relay = unwrap_relay(relay_selector.get_relay_by_query(query.clone()).unwrap());
+            assert_eq!(relay.hostname, "se10-wireguard");
+        }
+    }
+
+    /// Verify that excluded relays, and relays hosted by excluded providers, are never picked and
+    /// are reported as excluded. The exclusions must survive relay list updates.
+    #[test]
+    fn test_relay_exclusions() {
+        let relay_selector = default_relay_selector();
+        relay_selector.set_exclusions(RelayExclusions {
+            relays: BTreeSet::from(["se9-wireguard".to_string(), "se10-wireguard".to_string()]),
+            providers: BTreeSet::from(["provider2".to_string()]),
+        });
+        relay_selector.set_relays(RELAYS.clone());
+
+        let query = RelayQueryBuilder::new().build();
+        for _ in 0..20 {
+            let relay = unwrap_relay(relay_selector.get_relay_by_query(query.clone()).unwrap());
+            assert_eq!(relay.provider, "provider0");
+            assert_ne!(relay.hostname, "se9-wireguard");
+        }
+
+        let partitions = relay_selector.partition_relays(Predicate::Singlehop(Default::default()));
+        let excluded: HashSet<&str> = partitions
+            .discards
+            .iter()
+            .filter(|(_, reasons)| reasons.contains(&Reason::Excluded))
+            .map(|(relay, _)| relay.hostname.as_str())
+            .collect();
+        assert_eq!(
+            excluded,
+            HashSet::from(["se9-wireguard", "se10-wireguard", "se11-wireguard"])
+        );
+    }
+
+    /// Rotating away from an exit relay must pick a different exit whenever another relay
+    /// matches, and fall back to the same exit when it is the only match.
+    #[test]
+    fn test_avoid_previous_exit() {
+        let relay_selector = default_relay_selector();
+
+        for query in [
+            RelayQueryBuilder::new().build(),
+            RelayQueryBuilder::new().multihop().build(),
+        ] {
+            for _ in 0..20 {
+                let relay = relay_selector
+                    .get_relay_by_query_avoiding(query.clone(), Some("se9-wireguard"))
+                    .unwrap();
+                assert_ne!(unwrap_relay(relay).hostname, "se9-wireguard");
+            }
+        }
+
+        let query = RelayQueryBuilder::new()
+            .location(DAITA_RELAY_LOCATION.clone())
+            .build();
+        let relay = relay_selector
+            .get_relay_by_query_avoiding(query, Some("se9-wireguard"))
+            .unwrap();
+        assert_eq!(unwrap_relay(relay).hostname, "se9-wireguard");
+    }
+
     /// `include_in_country = false` relays are excluded from country-level selection and
     /// only selectable via city or hostname constraints. There is no "use when necessary"
     /// fallback — country-level queries that would only be served by `=false` relays must
@@ -1091,7 +1226,10 @@ mod partition_relays {
     use itertools::Itertools;
     use mullvad_relay_selector::{EntryConstraints, ExitConstraints, RelayPartitions};
     use mullvad_types::constraints::Constraint;
-    use mullvad_types::relay_constraints::ShadowsocksSettings;
+    use mullvad_types::location::Coordinates;
+    use mullvad_types::relay_constraints::{
+        DistanceConstraint, DistanceOrigin, ShadowsocksSettings,
+    };
     use mullvad_types::relay_selector::ResolvedLocationConstraint;
     use std::collections::HashSet;
 
@@ -1435,6 +1573,88 @@ mod partition_relays {
         }
     }
 
+    /// Get the `country-city` codes of the matching relays.
+    fn matching_cities(RelayPartitions { matches, .. }: &RelayPartitions) -> HashSet<String> {
+        matches
+            .iter()
+            .map(|relay| {
+                format!(
+                    "{}-{}",
+                    relay.location.country_code, relay.location.city_code
+                )
+            })
+            .collect()
+    }
+
+    /// Check that a distance constraint selects relays by their distance from the origin.
+    #[test]
+    fn distance_constraint() {
+        let berlin = DistanceOrigin::Coordinates(Coordinates::new(52.52, 13.405).unwrap());
+        let partition = |distance: DistanceConstraint| {
+            let constraints =
+                EntryConstraints::default().general(ExitConstraints::default().location(distance));
+            relay_selector().partition_relays(Predicate::Singlehop(constraints))
+        };
+
+        // Prague is 281 km from Berlin, Malmö is 344 km and Copenhagen is 354 km
+        assert_eq!(
+            matching_cities(&partition(DistanceConstraint::within(berlin, 300))),
+            HashSet::from(["de-ber".to_owned(), "cz-prg".to_owned()])
+        );
+        assert_eq!(
+            matching_cities(&partition(DistanceConstraint::within(berlin, 400))),
+            HashSet::from([
+                "de-ber".to_owned(),
+                "cz-prg".to_owned(),
+                "se-mma".to_owned(),
+                "dk-cph".to_owned()
+            ])
+        );
+
+        let nearest = partition(DistanceConstraint::nearest(berlin));
+        assert_eq!(
+            matching_cities(&nearest),
+            HashSet::from(["de-ber".to_owned()])
+        );
+        let prague = nearest
+            .discards
+            .iter()
+            .find(|(relay, _)| relay.location.city_code == "prg")
+            .unwrap();
+        assert_eq!(prague.1, vec![Reason::Location]);
+    }
+
+    /// Check that a distance constraint relative to this device is measured from the location of
+    /// the device, and that no relay is selected while the location is unknown.
+    #[test]
+    fn distance_from_user_location() {
+        let (relay_list, bridge_list) = &*RELAYS;
+        let relay_selector = RelaySelector::new(relay_list.clone(), bridge_list.clone());
+        let constraints = EntryConstraints::default().general(
+            ExitConstraints::default().location(DistanceConstraint::nearest(DistanceOrigin::GeoIp)),
+        );
+
+        let unknown = relay_selector.partition_relays(Predicate::Singlehop(constraints.clone()));
+        assert!(unknown.matches.is_empty());
+        let query = RelayQueryBuilder::new()
+            .location(Constraint::Only(ResolvedLocationConstraint::from(
+                DistanceConstraint::nearest(DistanceOrigin::GeoIp),
+            )))
+            .build();
+        assert!(matches!(
+            relay_selector.get_relay_by_query(query),
+            Err(Error::UnknownUserLocation)
+        ));
+
+        let stockholm = Coordinates::new(59.3293, 18.0686).unwrap();
+        relay_selector.set_user_location(stockholm);
+        let nearest = relay_selector.partition_relays(Predicate::Singlehop(constraints));
+        assert_eq!(
+            matching_cities(&nearest),
+            HashSet::from(["se-sto".to_owned()])
+        );
+    }
+
     /// Check that if IPv4 is not available, a relay with an IPv6 endpoint is returned.
     #[test]
     fn runtime_ipv4_unavailable() {
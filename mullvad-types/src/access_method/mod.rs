@@ -6,4 +6,4 @@ mod types;
 pub use id::Id;
 pub use protobuf::AccessMethodSetting;
 pub use settings::{Error, Settings};
-pub use types::{AccessMethod, BuiltInAccessMethod};
+pub use types::{AccessMethod, BuiltInAccessMethod, CustomDomainFronting};
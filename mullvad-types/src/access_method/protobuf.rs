@@ -65,7 +65,7 @@ impl AccessMethodSetting {
     }
 
     pub fn is_builtin(&self) -> bool {
-        self.as_custom().is_none()
+        self.access_method.is_builtin()
     }
 
     pub fn is_direct(&self) -> bool {
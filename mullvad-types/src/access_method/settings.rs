@@ -21,8 +21,8 @@ pub struct Settings {
     mullvad_bridges: AccessMethodSetting,
     #[serde(default = "Settings::create_encrypted_dns_proxy")]
     encrypted_dns_proxy: AccessMethodSetting,
-    // #[serde(default = "Settings::create_domain_fronting")]
-    // domain_fronting: AccessMethodSetting,
+    #[serde(default = "Settings::create_domain_fronting")]
+    domain_fronting: AccessMethodSetting,
     /// Custom API access methods.
     custom: Vec<AccessMethodSetting>,
 }
@@ -32,14 +32,14 @@ impl Settings {
         direct: AccessMethodSetting,
         mullvad_bridges: AccessMethodSetting,
         encrypted_dns_proxy: AccessMethodSetting,
-        // domain_fronting: AccessMethodSetting,
+        domain_fronting: AccessMethodSetting,
         custom: Vec<AccessMethodSetting>,
     ) -> Settings {
         Settings {
             direct,
             mullvad_bridges,
             encrypted_dns_proxy,
-            // domain_fronting,
+            domain_fronting,
             custom,
         }
     }
@@ -68,7 +68,7 @@ impl Settings {
             AccessMethod::BuiltIn(ref built_in) => Err(Error::RemoveBuiltin {
                 attempted: built_in.clone(),
             }),
-            AccessMethod::Custom(_) => {
+            AccessMethod::Custom(_) | AccessMethod::CustomDomainFronting(_) => {
                 self.custom
                     .retain(|method| method.get_id() != *api_access_method);
                 self.ensure_consistent_state();
@@ -167,7 +167,7 @@ impl Settings {
         once(&self.direct)
             .chain(once(&self.mullvad_bridges))
             .chain(once(&self.encrypted_dns_proxy))
-            //.chain(once(&self.domain_fronting))
+            .chain(once(&self.domain_fronting))
             .chain(&self.custom)
     }
 
@@ -177,7 +177,7 @@ impl Settings {
         once(&mut self.direct)
             .chain(once(&mut self.mullvad_bridges))
             .chain(once(&mut self.encrypted_dns_proxy))
-            //.chain(once(&mut self.domain_fronting))
+            .chain(once(&mut self.domain_fronting))
             .chain(&mut self.custom)
     }
 
@@ -204,9 +204,9 @@ impl Settings {
         &self.encrypted_dns_proxy
     }
 
-    // pub fn domain_fronting(&self) -> &AccessMethodSetting {
-    //     &self.domain_fronting
-    // }
+    pub fn domain_fronting(&self) -> &AccessMethodSetting {
+        &self.domain_fronting
+    }
 
     fn create_direct() -> AccessMethodSetting {
         let method = BuiltInAccessMethod::Direct;
@@ -223,10 +223,11 @@ impl Settings {
         AccessMethodSetting::new(method.canonical_name(), true, AccessMethod::from(method))
     }
 
-    // fn create_domain_fronting() -> AccessMethodSetting {
-    //     let method = BuiltInAccessMethod::DomainFronting;
-    //     AccessMethodSetting::new(method.canonical_name(), true, AccessMethod::from(method))
-    // }
+    /// Unlike the other built-in access methods, domain fronting is disabled by default.
+    fn create_domain_fronting() -> AccessMethodSetting {
+        let method = BuiltInAccessMethod::DomainFronting;
+        AccessMethodSetting::new(method.canonical_name(), false, AccessMethod::from(method))
+    }
 }
 
 impl Default for Settings {
@@ -235,8 +236,41 @@ impl Default for Settings {
             direct: Settings::create_direct(),
             mullvad_bridges: Settings::create_mullvad_bridges(),
             encrypted_dns_proxy: Settings::create_encrypted_dns_proxy(),
-            // domain_fronting: Settings::create_domain_fronting(),
+            domain_fronting: Settings::create_domain_fronting(),
             custom: vec![],
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::access_method::CustomDomainFronting;
+
+    #[test]
+    fn test_domain_fronting() {
+        // Settings from before domain fronting existed
+        let settings: Settings = serde_json::from_str(r#"{ "custom": [] }"#).unwrap();
+        assert_eq!(
+            settings.domain_fronting().access_method,
+            AccessMethod::BuiltIn(BuiltInAccessMethod::DomainFronting)
+        );
+        assert!(!settings.domain_fronting().enabled());
+
+        let mut settings = settings;
+        let custom = AccessMethodSetting::new(
+            "cdn".to_owned(),
+            true,
+            AccessMethod::from(CustomDomainFronting {
+                front: "front.example.com".to_owned(),
+                proxy_host: "proxy.example.net".to_owned(),
+            }),
+        );
+        settings.append(custom.clone()).unwrap();
+        assert!(!custom.is_builtin());
+        assert_eq!(settings.cardinality(), 5);
+
+        settings.remove(&custom.get_id()).unwrap();
+        assert_eq!(settings.iter_custom().count(), 0);
+    }
+}
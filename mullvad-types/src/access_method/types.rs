@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
+use talpid_types::net::proxy::{CustomProxy, HttpConnect, Shadowsocks, Socks5Local, Socks5Remote};
 
 /// Access Method datastructure.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Hash)]
@@ -7,15 +7,20 @@ use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remo
 pub enum AccessMethod {
     BuiltIn(BuiltInAccessMethod),
     Custom(CustomProxy),
+    CustomDomainFronting(CustomDomainFronting),
 }
 
 impl AccessMethod {
     pub fn as_custom(&self) -> Option<&CustomProxy> {
         match self {
-            AccessMethod::BuiltIn(_) => None,
             AccessMethod::Custom(access_method) => Some(access_method),
+            AccessMethod::BuiltIn(_) | AccessMethod::CustomDomainFronting(_) => None,
         }
     }
+
+    pub fn is_builtin(&self) -> bool {
+        matches!(self, AccessMethod::BuiltIn(_))
+    }
 }
 
 impl From<CustomProxy> for AccessMethod {
@@ -42,6 +47,28 @@ impl From<Shadowsocks> for AccessMethod {
     }
 }
 
+impl From<HttpConnect> for AccessMethod {
+    fn from(value: HttpConnect) -> Self {
+        CustomProxy::HttpConnect(value).into()
+    }
+}
+
+impl From<CustomDomainFronting> for AccessMethod {
+    fn from(value: CustomDomainFronting) -> Self {
+        AccessMethod::CustomDomainFronting(value)
+    }
+}
+
+/// User-defined domain fronting. API requests are sent to a CDN, using `front` as the TLS server
+/// name, and forwarded by the CDN to the Mullvad API through `proxy_host`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct CustomDomainFronting {
+    /// Domain that is visible to observers of the connection.
+    pub front: String,
+    /// Host on the CDN that forwards requests to the Mullvad API.
+    pub proxy_host: String,
+}
+
 /// Built-In access method datastructure.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Hash)]
 #[serde(rename_all = "snake_case")]
@@ -49,7 +76,7 @@ pub enum BuiltInAccessMethod {
     Direct,
     Bridge,
     EncryptedDnsProxy,
-    // DomainFronting,
+    DomainFronting,
 }
 
 impl BuiltInAccessMethod {
@@ -58,7 +85,7 @@ impl BuiltInAccessMethod {
             BuiltInAccessMethod::Direct => "Direct".to_string(),
             BuiltInAccessMethod::Bridge => "Mullvad Bridges".to_string(),
             BuiltInAccessMethod::EncryptedDnsProxy => "Encrypted DNS proxy".to_string(),
-            // BuiltInAccessMethod::DomainFronting => "Domain fronting".to_string(),
+            BuiltInAccessMethod::DomainFronting => "Domain fronting".to_string(),
         }
     }
 }
@@ -0,0 +1,232 @@
+//! Named custom tunnel endpoints that are saved in the settings, so that they can be used
+//! without having to specify them again.
+
+use crate::CustomTunnelEndpoint;
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+const CUSTOM_ENDPOINT_NAME_MAX_SIZE: usize = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Custom endpoint name too long")]
+    NameTooLong,
+    #[error("Custom endpoint name must not be empty")]
+    EmptyName,
+    #[error("Custom endpoint with name already exists")]
+    DuplicateName,
+    #[error("Custom endpoint not found")]
+    EndpointNotFound,
+}
+
+/// A custom tunnel endpoint with a unique name.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SavedCustomEndpoint {
+    pub name: String,
+    pub endpoint: CustomTunnelEndpoint,
+}
+
+impl SavedCustomEndpoint {
+    /// Create a new [SavedCustomEndpoint]. This function will check that the name is appropriate
+    /// (see implementation for details).
+    pub fn new(name: String, endpoint: CustomTunnelEndpoint) -> Result<Self, Error> {
+        if name.is_empty() {
+            return Err(Error::EmptyName);
+        }
+        if name.chars().count() > CUSTOM_ENDPOINT_NAME_MAX_SIZE {
+            return Err(Error::NameTooLong);
+        }
+        Ok(Self { name, endpoint })
+    }
+}
+
+/// Saved custom tunnel endpoints, ordered by priority. When
+/// [`RelaySettings::SavedCustomEndpoints`] is selected, the first endpoint is tried first, and the
+/// following ones are used if connecting to it fails.
+///
+/// [`RelaySettings::SavedCustomEndpoints`]: crate::relay_constraints::RelaySettings::SavedCustomEndpoints
+#[derive(Default, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CustomEndpointsSettings {
+    endpoints: Vec<SavedCustomEndpoint>,
+}
+
+impl From<Vec<SavedCustomEndpoint>> for CustomEndpointsSettings {
+    fn from(endpoints: Vec<SavedCustomEndpoint>) -> Self {
+        Self { endpoints }
+    }
+}
+
+impl CustomEndpointsSettings {
+    /// Add an endpoint with the lowest priority.
+    pub fn add(&mut self, new_endpoint: SavedCustomEndpoint) -> Result<(), Error> {
+        if self.find_index(&new_endpoint.name).is_some() {
+            return Err(Error::DuplicateName);
+        }
+        self.endpoints.push(new_endpoint);
+        Ok(())
+    }
+
+    /// Replace the endpoint with the same name, keeping its priority.
+    pub fn update(&mut self, new_endpoint: SavedCustomEndpoint) -> Result<(), Error> {
+        let index = self
+            .find_index(&new_endpoint.name)
+            .ok_or(Error::EndpointNotFound)?;
+        self.endpoints[index] = new_endpoint;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), Error> {
+        let index = self.find_index(name).ok_or(Error::EndpointNotFound)?;
+        self.endpoints.remove(index);
+        Ok(())
+    }
+
+    /// Move an endpoint to `position`, where 0 is the highest priority. Positions past the end
+    /// move the endpoint to the lowest priority.
+    pub fn move_to(&mut self, name: &str, position: usize) -> Result<(), Error> {
+        let index = self.find_index(name).ok_or(Error::EndpointNotFound)?;
+        let endpoint = self.endpoints.remove(index);
+        let position = position.min(self.endpoints.len());
+        self.endpoints.insert(position, endpoint);
+        Ok(())
+    }
+
+    /// Returns the endpoints in the order to try them on connection attempt `retry_attempt`.
+    ///
+    /// The first attempt starts with the endpoint with the highest priority. Every failed attempt
+    /// moves on to the next endpoint, wrapping around after the one with the lowest priority.
+    pub fn failover_order(&self, retry_attempt: u32) -> impl Iterator<Item = &SavedCustomEndpoint> {
+        let first = match self.endpoints.len() {
+            0 => 0,
+            len => retry_attempt as usize % len,
+        };
+        self.endpoints[first..]
+            .iter()
+            .chain(self.endpoints[..first].iter())
+    }
+
+    /// Remove all saved endpoints
+    pub fn clear(&mut self) {
+        self.endpoints.clear();
+    }
+
+    fn find_index(&self, name: &str) -> Option<usize> {
+        self.endpoints
+            .iter()
+            .position(|endpoint| endpoint.name == name)
+    }
+}
+
+impl IntoIterator for CustomEndpointsSettings {
+    type Item = SavedCustomEndpoint;
+    type IntoIter = <Vec<SavedCustomEndpoint> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.endpoints.into_iter()
+    }
+}
+
+impl Deref for CustomEndpointsSettings {
+    type Target = [SavedCustomEndpoint];
+
+    fn deref(&self) -> &Self::Target {
+        &self.endpoints
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::net::wireguard::{ConnectionConfig, PeerConfig, PrivateKey, TunnelConfig};
+
+    fn endpoint(name: &str) -> SavedCustomEndpoint {
+        let config = ConnectionConfig {
+            tunnel: TunnelConfig {
+                private_key: PrivateKey::new_from_random(),
+                addresses: vec!["10.0.0.2".parse().unwrap()],
+            },
+            peer: PeerConfig {
+                public_key: PrivateKey::new_from_random().public_key(),
+                allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+                endpoint: "192.0.2.1:51820".parse().unwrap(),
+                psk: None,
+                constant_packet_size: false,
+            },
+            exit_peer: None,
+            ipv4_gateway: "10.0.0.1".parse().unwrap(),
+            ipv6_gateway: None,
+            #[cfg(target_os = "linux")]
+            fwmark: None,
+        };
+        SavedCustomEndpoint::new(
+            name.to_owned(),
+            CustomTunnelEndpoint::new("192.0.2.1".to_owned(), config),
+        )
+        .unwrap()
+    }
+
+    fn names(settings: &CustomEndpointsSettings) -> Vec<&str> {
+        settings
+            .iter()
+            .map(|endpoint| endpoint.name.as_str())
+            .collect()
+    }
+
+    fn failover_names(settings: &CustomEndpointsSettings, retry_attempt: u32) -> Vec<&str> {
+        settings
+            .failover_order(retry_attempt)
+            .map(|endpoint| endpoint.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_add_remove() {
+        let mut settings = CustomEndpointsSettings::default();
+        settings.add(endpoint("a")).unwrap();
+        settings.add(endpoint("b")).unwrap();
+        assert!(matches!(
+            settings.add(endpoint("a")),
+            Err(Error::DuplicateName)
+        ));
+        assert_eq!(names(&settings), ["a", "b"]);
+
+        settings.remove("a").unwrap();
+        assert_eq!(names(&settings), ["b"]);
+        assert!(matches!(settings.remove("a"), Err(Error::EndpointNotFound)));
+    }
+
+    #[test]
+    fn test_move_to() {
+        let mut settings = CustomEndpointsSettings::default();
+        for name in ["a", "b", "c"] {
+            settings.add(endpoint(name)).unwrap();
+        }
+
+        settings.move_to("c", 0).unwrap();
+        assert_eq!(names(&settings), ["c", "a", "b"]);
+        settings.move_to("c", 10).unwrap();
+        assert_eq!(names(&settings), ["a", "b", "c"]);
+        settings.move_to("a", 1).unwrap();
+        assert_eq!(names(&settings), ["b", "a", "c"]);
+    }
+
+    /// Endpoints are tried in priority order, moving on to the next one after every failed
+    /// connection attempt.
+    #[test]
+    fn test_failover_order() {
+        let mut settings = CustomEndpointsSettings::default();
+        assert_eq!(settings.failover_order(3).count(), 0);
+
+        for name in ["a", "b", "c"] {
+            settings.add(endpoint(name)).unwrap();
+        }
+        assert_eq!(failover_names(&settings, 0), ["a", "b", "c"]);
+        assert_eq!(failover_names(&settings, 1), ["b", "c", "a"]);
+        assert_eq!(failover_names(&settings, 2), ["c", "a", "b"]);
+        assert_eq!(failover_names(&settings, 3), ["a", "b", "c"]);
+
+        settings.move_to("c", 0).unwrap();
+        assert_eq!(failover_names(&settings, 0), ["c", "a", "b"]);
+        assert_eq!(failover_names(&settings, 1), ["a", "b", "c"]);
+    }
+}
@@ -1,7 +1,11 @@
-use crate::relay_constraints::GeographicLocationConstraint;
+use crate::{
+    location::{CityCode, CountryCode, Hostname},
+    relay_constraints::{GeographicLocationConstraint, Ownership, Provider},
+};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeSet,
+    fmt,
     ops::{Deref, DerefMut},
     str::FromStr,
 };
@@ -86,6 +90,54 @@ impl CustomListsSettings {
         Ok(())
     }
 
+    /// Add custom lists in bulk. An imported list whose name matches an existing list is merged
+    /// into or replaces that list, depending on `mode`. Either all lists are imported, or none
+    /// are.
+    pub fn import(
+        &mut self,
+        lists: Vec<ExportedCustomList>,
+        mode: ImportMode,
+    ) -> Result<ImportSummary, Error> {
+        let mut custom_lists = self.custom_lists.clone();
+        let mut summary = ImportSummary::default();
+        for list in lists {
+            let locations = list.locations.into_iter().collect();
+            match custom_lists
+                .iter_mut()
+                .find(|existing| existing.name == list.name)
+            {
+                Some(existing) => {
+                    match mode {
+                        ImportMode::Merge => {
+                            existing.append(locations);
+                            if list.filter.is_some() {
+                                existing.filter = list.filter;
+                            }
+                        }
+                        ImportMode::Replace => {
+                            existing.locations = locations;
+                            existing.filter = list.filter;
+                        }
+                    }
+                    if !summary.created.contains(&list.name)
+                        && !summary.updated.contains(&list.name)
+                    {
+                        summary.updated.push(list.name);
+                    }
+                }
+                None => {
+                    let mut new_list = CustomList::new(list.name.clone())?;
+                    new_list.append(locations);
+                    new_list.filter = list.filter;
+                    custom_lists.push(new_list);
+                    summary.created.push(list.name);
+                }
+            }
+        }
+        self.custom_lists = custom_lists;
+        Ok(summary)
+    }
+
     fn check_list_name_is_unique(&self, new_list: &CustomList) -> Result<(), Error> {
         if self
             .custom_lists
@@ -136,11 +188,128 @@ impl DerefMut for CustomListsSettings {
     }
 }
 
+/// How to import a custom list with the same name as an existing list.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Add the imported locations to the existing list.
+    #[default]
+    Merge,
+    /// Replace the locations of the existing list with the imported locations.
+    Replace,
+}
+
+/// The result of [`CustomListsSettings::import`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Names of the custom lists that were created.
+    pub created: Vec<String>,
+    /// Names of the existing custom lists that were merged into or replaced.
+    pub updated: Vec<String>,
+    /// Imported locations that do not match any relay, by custom list name. These are imported
+    /// regardless, but are likely to be typos or relays that have been removed.
+    pub unknown_locations: Vec<(String, GeographicLocationConstraint)>,
+}
+
+/// Custom lists in the format used for sharing them between devices. Unlike [`CustomList`], it
+/// contains no IDs, since these are local to each device. The format is described in
+/// `docs/custom-list-format.md`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExportedCustomLists {
+    pub custom_lists: Vec<ExportedCustomList>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExportedCustomList {
+    pub name: String,
+    #[serde(default)]
+    pub locations: Vec<ExportedLocation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<CustomListFilter>,
+}
+
+/// A location in [`ExportedCustomLists`]. A hostname must be accompanied by its city, and a city
+/// by its country.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "ExportedLocationFields", into = "ExportedLocationFields")]
+pub struct ExportedLocation(pub GeographicLocationConstraint);
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ExportedLocationFields {
+    country: CountryCode,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    city: Option<CityCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hostname: Option<Hostname>,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("A hostname must be accompanied by a city")]
+pub struct HostnameWithoutCity;
+
+impl TryFrom<ExportedLocationFields> for ExportedLocation {
+    type Error = HostnameWithoutCity;
+
+    fn try_from(fields: ExportedLocationFields) -> Result<Self, Self::Error> {
+        let constraint = match (fields.city, fields.hostname) {
+            (None, None) => GeographicLocationConstraint::Country(fields.country),
+            (Some(city), None) => GeographicLocationConstraint::City(fields.country, city),
+            (Some(city), Some(hostname)) => {
+                GeographicLocationConstraint::Hostname(fields.country, city, hostname)
+            }
+            (None, Some(_)) => return Err(HostnameWithoutCity),
+        };
+        Ok(ExportedLocation(constraint))
+    }
+}
+
+impl From<ExportedLocation> for ExportedLocationFields {
+    fn from(ExportedLocation(location): ExportedLocation) -> Self {
+        let (country, city, hostname) = match location {
+            GeographicLocationConstraint::Country(country) => (country, None, None),
+            GeographicLocationConstraint::City(country, city) => (country, Some(city), None),
+            GeographicLocationConstraint::Hostname(country, city, hostname) => {
+                (country, Some(city), Some(hostname))
+            }
+        };
+        Self {
+            country,
+            city,
+            hostname,
+        }
+    }
+}
+
+impl From<ExportedLocation> for GeographicLocationConstraint {
+    fn from(location: ExportedLocation) -> Self {
+        location.0
+    }
+}
+
+impl From<CustomListsSettings> for ExportedCustomLists {
+    fn from(settings: CustomListsSettings) -> Self {
+        let custom_lists = settings
+            .into_iter()
+            .map(|list| ExportedCustomList {
+                name: list.name,
+                locations: list.locations.into_iter().map(ExportedLocation).collect(),
+                filter: list.filter,
+            })
+            .collect();
+        Self { custom_lists }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CustomList {
     id: Id,
     pub name: String,
     pub locations: BTreeSet<GeographicLocationConstraint>,
+    /// Rules that select relays in addition to `locations`, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<CustomListFilter>,
 }
 
 impl CustomList {
@@ -165,6 +334,7 @@ impl CustomList {
             id,
             name: Default::default(),
             locations: Default::default(),
+            filter: Default::default(),
         }
     }
 
@@ -176,3 +346,144 @@ impl CustomList {
         self.locations.append(&mut locations);
     }
 }
+
+/// Rules that make a custom list contain every relay that satisfies all of them. The rules are
+/// evaluated against the current relay list, so that relays which are added later automatically
+/// become part of the list.
+///
+/// The rules are evaluated as [`EntryConstraints`][crate::relay_selector::EntryConstraints] by
+/// the relay selector, so they match relays exactly like the corresponding relay constraints.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CustomListFilter {
+    /// Only include relays in these countries. If empty, relays in any country are included.
+    pub countries: BTreeSet<CountryCode>,
+    /// Only include relays hosted by these providers. If empty, relays hosted by any provider
+    /// are included.
+    pub providers: BTreeSet<Provider>,
+    pub ownership: Option<Ownership>,
+    /// Only include relays that support DAITA.
+    pub daita: bool,
+    /// Only include relays that support QUIC obfuscation.
+    pub quic: bool,
+    /// Never include these relays, even if they satisfy all other rules.
+    pub excluded_hostnames: BTreeSet<Hostname>,
+}
+
+impl fmt::Display for CustomListFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rules = vec![];
+        if !self.countries.is_empty() {
+            rules.push(format!("in {}", join(&self.countries)));
+        }
+        if !self.providers.is_empty() {
+            rules.push(format!("hosted by {}", join(&self.providers)));
+        }
+        match self.ownership {
+            Some(Ownership::MullvadOwned) => rules.push("owned by Mullvad".to_owned()),
+            Some(Ownership::Rented) => rules.push("rented".to_owned()),
+            None => (),
+        }
+        if self.daita {
+            rules.push("with DAITA".to_owned());
+        }
+        if self.quic {
+            rules.push("with QUIC".to_owned());
+        }
+        if !self.excluded_hostnames.is_empty() {
+            rules.push(format!("except {}", join(&self.excluded_hostnames)));
+        }
+        if rules.is_empty() {
+            return write!(f, "all relays");
+        }
+        write!(f, "relays {}", rules.join(", "))
+    }
+}
+
+fn join(items: &BTreeSet<String>) -> String {
+    items
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn exported(name: &str, locations: &[GeographicLocationConstraint]) -> ExportedCustomList {
+        ExportedCustomList {
+            name: name.to_owned(),
+            locations: locations.iter().cloned().map(ExportedLocation).collect(),
+            filter: None,
+        }
+    }
+
+    #[test]
+    fn test_import_name_conflicts() {
+        let se = GeographicLocationConstraint::country("se");
+        let no = GeographicLocationConstraint::country("no");
+        let mut settings = CustomListsSettings::default();
+        let mut existing = CustomList::new("nordic".to_owned()).unwrap();
+        existing.append(BTreeSet::from([se.clone()]));
+        let existing_id = existing.id();
+        settings.add(existing).unwrap();
+
+        let summary = settings
+            .import(
+                vec![exported("nordic", &[no.clone()]), exported("new", &[])],
+                ImportMode::Merge,
+            )
+            .unwrap();
+        assert_eq!(summary.created, ["new"]);
+        assert_eq!(summary.updated, ["nordic"]);
+        assert_eq!(settings[0].id(), existing_id);
+        assert_eq!(settings[0].locations, BTreeSet::from([se, no.clone()]));
+
+        settings
+            .import(vec![exported("nordic", &[no.clone()])], ImportMode::Replace)
+            .unwrap();
+        assert_eq!(settings[0].locations, BTreeSet::from([no]));
+        assert_eq!(settings.len(), 2);
+
+        // Nothing is imported if any list is invalid
+        let too_long = "a".repeat(CUSTOM_LIST_NAME_MAX_SIZE + 1);
+        let result = settings.import(
+            vec![exported("other", &[]), exported(&too_long, &[])],
+            ImportMode::Merge,
+        );
+        assert!(matches!(result, Err(Error::NameTooLong)));
+        assert_eq!(settings.len(), 2);
+    }
+
+    #[test]
+    fn test_exported_location_format() {
+        let json = r#"{"custom_lists":[{"name":"a","locations":[
+            {"country":"se"},
+            {"country":"se","city":"got"},
+            {"country":"se","city":"got","hostname":"se-got-wg-001"}
+        ]}]}"#;
+        let lists: ExportedCustomLists = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            lists.custom_lists[0].locations,
+            [
+                ExportedLocation(GeographicLocationConstraint::country("se")),
+                ExportedLocation(GeographicLocationConstraint::city("se", "got")),
+                ExportedLocation(GeographicLocationConstraint::hostname(
+                    "se",
+                    "got",
+                    "se-got-wg-001"
+                )),
+            ]
+        );
+        let roundtrip = serde_json::to_string(&lists).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ExportedCustomLists>(&roundtrip).unwrap(),
+            lists
+        );
+
+        let hostname_without_city = r#"{"country":"se","hostname":"se-got-wg-001"}"#;
+        assert!(serde_json::from_str::<ExportedLocation>(hostname_without_city).is_err());
+    }
+}
@@ -4,7 +4,10 @@ use std::{
     fmt, io,
     net::{IpAddr, ToSocketAddrs},
 };
-use talpid_types::net::{Endpoint, wireguard::ConnectionConfig, wireguard::TunnelParameters};
+use talpid_types::net::{
+    Endpoint,
+    wireguard::{ConnectionConfig, PresharedKey, TunnelParameters},
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -19,11 +22,24 @@ pub enum Error {
 pub struct CustomTunnelEndpoint {
     pub host: String,
     pub config: ConnectionConfig,
+    /// Preshared key to use with the peer. This is stored separately from `config`, since the
+    /// PSK of a [`ConnectionConfig`] is ephemeral and never persisted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preshared_key: Option<PresharedKey>,
+    /// DNS servers to use while connected to this relay, unless custom DNS servers or content
+    /// blockers are enabled. These are not used for saved custom relays.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dns: Vec<IpAddr>,
 }
 
 impl CustomTunnelEndpoint {
     pub fn new(host: String, config: ConnectionConfig) -> Self {
-        Self { host, config }
+        Self {
+            host,
+            config,
+            preshared_key: None,
+            dns: vec![],
+        }
     }
 
     pub fn endpoint(&self) -> Endpoint {
@@ -37,6 +53,7 @@ impl CustomTunnelEndpoint {
         let ip = resolve_to_ip(&self.host)?;
         let mut connection = self.config.clone();
         connection.set_ip(ip);
+        connection.peer.psk = self.preshared_key.clone();
 
         let parameters = {
             let mut options = tunnel_options.wireguard.into_talpid_tunnel_options();
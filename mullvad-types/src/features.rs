@@ -129,9 +129,19 @@ pub fn compute_feature_indicators(
     endpoint: &TunnelEndpoint,
     server_ip_override: bool,
 ) -> FeatureIndicators {
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+    #[cfg(any(
+        windows,
+        target_os = "android",
+        target_os = "macos",
+        target_os = "linux"
+    ))]
     let split_tunneling = settings.split_tunnel.enable_exclusions;
-    #[cfg(not(any(windows, target_os = "android", target_os = "macos")))]
+    #[cfg(not(any(
+        windows,
+        target_os = "android",
+        target_os = "macos",
+        target_os = "linux"
+    )))]
     let split_tunneling = false;
 
     #[cfg(not(target_os = "android"))]
@@ -0,0 +1,33 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+/// Result of a single leak check.
+///
+/// A leak check sends probe packets with a very low TTL on the physical interface, towards the
+/// relay. If any network node responds, the packets were not blocked by the firewall and traffic
+/// is leaking outside the tunnel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeakCheckReport {
+    /// The physical interface that probe packets were sent on, if it could be determined.
+    pub interface: Option<String>,
+    /// Destination of the probe packets.
+    pub destination: IpAddr,
+    /// TTL values of the probe packets.
+    pub probe_ttls: Vec<u16>,
+    /// Network nodes that responded to any of the probe packets.
+    pub reachable_nodes: Vec<IpAddr>,
+    pub started: DateTime<Utc>,
+    pub finished: DateTime<Utc>,
+    pub outcome: LeakCheckOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LeakCheckOutcome {
+    /// None of the probe packets reached a network node.
+    NoLeak,
+    /// At least one network node was reachable outside the tunnel.
+    LeakDetected,
+    /// The leak check could not be completed.
+    Failed(String),
+}
@@ -2,17 +2,23 @@ pub mod access_method;
 pub mod account;
 pub mod auth_failed;
 pub mod constraints;
+pub mod custom_endpoint;
 pub mod custom_list;
 pub mod device;
 pub mod endpoint;
 pub mod features;
+pub mod leak_check;
 pub mod location;
 pub mod relay_constraints;
 pub mod relay_list;
+pub mod relay_rotation;
 pub mod relay_selector;
+pub mod session_log;
 pub mod settings;
 pub mod states;
+pub mod tunnel_stats;
 pub mod version;
+pub mod wg_quick;
 pub mod wireguard;
 
 mod custom_tunnel;
@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
 
 pub type CountryCode = String;
 pub type CityCode = String;
@@ -34,12 +37,33 @@ impl Location {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+/// A point on the surface of the earth, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CoordinatesFields")]
 pub struct Coordinates {
-    pub latitude: f64,
-    pub longitude: f64,
+    latitude: f64,
+    longitude: f64,
+}
+
+/// Unvalidated [`Coordinates`], which are validated when deserialized.
+#[derive(Deserialize)]
+struct CoordinatesFields {
+    latitude: f64,
+    longitude: f64,
+}
+
+impl TryFrom<CoordinatesFields> for Coordinates {
+    type Error = InvalidCoordinates;
+
+    fn try_from(fields: CoordinatesFields) -> Result<Self, Self::Error> {
+        Coordinates::new(fields.latitude, fields.longitude)
+    }
 }
 
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Latitude must be between -90 and 90 degrees, and longitude between -180 and 180 degrees")]
+pub struct InvalidCoordinates;
+
 impl From<&Location> for Coordinates {
     fn from(location: &Location) -> Self {
         Self {
@@ -70,7 +94,45 @@ impl From<GeoIpLocation> for Coordinates {
     }
 }
 
+impl fmt::Display for Coordinates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.4}, {:.4}", self.latitude, self.longitude)
+    }
+}
+
 impl Coordinates {
+    /// Create coordinates from a latitude and longitude in degrees, which must be within their
+    /// valid ranges.
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, InvalidCoordinates> {
+        if (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude) {
+            Ok(Coordinates {
+                latitude,
+                longitude,
+            })
+        } else {
+            Err(InvalidCoordinates)
+        }
+    }
+
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Returns the great-circle distance to `other`, in kilometers.
+    pub fn distance_from(&self, other: impl Into<Coordinates>) -> f64 {
+        let other: Coordinates = other.into();
+        haversine_dist_deg(
+            self.latitude,
+            self.longitude,
+            other.latitude,
+            other.longitude,
+        )
+    }
+
     /// Computes the approximate midpoint of a set of locations.
     ///
     /// This works by calculating the mean Cartesian coordinates, and converting them
@@ -244,6 +306,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_new_coordinates() {
+        assert!(Coordinates::new(52.52, 13.405).is_ok());
+        assert!(Coordinates::new(-90.0, 180.0).is_ok());
+        assert!(Coordinates::new(90.1, 0.0).is_err());
+        assert!(Coordinates::new(0.0, -180.1).is_err());
+        assert!(Coordinates::new(f64::NAN, 0.0).is_err());
+        assert!(
+            serde_json::from_str::<Coordinates>(r#"{"latitude":52.52,"longitude":13.405}"#).is_ok()
+        );
+        assert!(
+            serde_json::from_str::<Coordinates>(r#"{"latitude":91.0,"longitude":0.0}"#).is_err()
+        );
+    }
+
     #[test]
     fn test_midpoint() {
         assert!(
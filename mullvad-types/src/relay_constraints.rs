@@ -5,17 +5,19 @@ use crate::{
     CustomTunnelEndpoint, Intersection,
     constraints::{Constraint, Match},
     custom_list::{CustomListsSettings, Id},
-    location::{CityCode, CountryCode, Hostname},
+    location::{CityCode, Coordinates, CountryCode, Hostname},
     relay_list::WireguardRelay,
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    collections::{BTreeSet, HashSet},
     fmt,
     net::{Ipv4Addr, Ipv6Addr},
     str::FromStr,
 };
-use talpid_types::net::{IpVersion, TransportProtocol};
+use talpid_types::net::{
+    IpVersion, TransportProtocol, obfuscation::CustomBridge, proxy::ShadowsocksCipher,
+};
 
 /// Specifies a specific endpoint or [`RelayConstraints`] to use when `mullvad-daemon` selects a
 /// relay.
@@ -23,6 +25,10 @@ use talpid_types::net::{IpVersion, TransportProtocol};
 #[serde(rename_all = "snake_case")]
 pub enum RelaySettings {
     CustomTunnelEndpoint(CustomTunnelEndpoint),
+    /// Use the saved custom endpoints in [`Settings::custom_endpoints`], in priority order.
+    ///
+    /// [`Settings::custom_endpoints`]: crate::settings::Settings::custom_endpoints
+    SavedCustomEndpoints,
     Normal(RelayConstraints),
 }
 
@@ -49,6 +55,7 @@ impl fmt::Display for RelaySettingsFormatter<'_> {
             RelaySettings::CustomTunnelEndpoint(endpoint) => {
                 write!(f, "custom endpoint {endpoint}")
             }
+            RelaySettings::SavedCustomEndpoints => write!(f, "saved custom endpoints"),
             RelaySettings::Normal(constraints) => {
                 write!(
                     f,
@@ -68,6 +75,7 @@ impl fmt::Display for RelaySettingsFormatter<'_> {
 pub enum LocationConstraint {
     Location(GeographicLocationConstraint),
     CustomList { list_id: Id },
+    Distance(DistanceConstraint),
 }
 
 pub struct LocationConstraintFormatter<'a> {
@@ -91,6 +99,85 @@ impl fmt::Display for LocationConstraintFormatter<'_> {
                 .find(|list| list.id() == *list_id)
                 .map(|custom_list| write!(f, "{}", custom_list.name))
                 .unwrap_or_else(|| write!(f, "invalid custom list")),
+            LocationConstraint::Distance(distance) => write!(f, "{distance}"),
+        }
+    }
+}
+
+/// Selects relays by their great-circle distance from an origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DistanceConstraint {
+    pub origin: DistanceOrigin,
+    /// Only relays within this many kilometers of the origin match. If `None`, only the relays
+    /// nearest to the origin match.
+    pub max_distance_km: Option<u32>,
+}
+
+/// Where a [`DistanceConstraint`] is measured from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistanceOrigin {
+    /// The location of this device, as looked up from its IP address while disconnected.
+    GeoIp,
+    Coordinates(Coordinates),
+}
+
+// The coordinates of an origin are never NaN, since they are validated by `Coordinates::new`,
+// including when they are deserialized.
+impl Eq for DistanceOrigin {}
+
+impl DistanceConstraint {
+    /// Only the relays nearest to `origin`.
+    pub fn nearest(origin: DistanceOrigin) -> Self {
+        DistanceConstraint {
+            origin,
+            max_distance_km: None,
+        }
+    }
+
+    /// Every relay within `max_distance_km` kilometers of `origin`.
+    pub fn within(origin: DistanceOrigin, max_distance_km: u32) -> Self {
+        DistanceConstraint {
+            origin,
+            max_distance_km: Some(max_distance_km),
+        }
+    }
+}
+
+impl DistanceOrigin {
+    /// Returns the coordinates of the origin, or `None` if it is the location of this device.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        match self {
+            DistanceOrigin::GeoIp => None,
+            DistanceOrigin::Coordinates(coordinates) => Some(*coordinates),
+        }
+    }
+}
+
+/// Every relay within the maximum distance matches. No relay matches if the origin is not known.
+/// Narrowing down to the nearest relays is done by the relay selector, since it depends on which
+/// other relays match.
+impl Match<WireguardRelay> for DistanceConstraint {
+    fn matches(&self, relay: &WireguardRelay) -> bool {
+        match (self.origin.coordinates(), self.max_distance_km) {
+            (Some(origin), Some(max_distance_km)) => {
+                relay.location.distance_from(origin) <= f64::from(max_distance_km)
+            }
+            (Some(_), None) => true,
+            (None, _) => false,
+        }
+    }
+}
+
+impl fmt::Display for DistanceConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max_distance_km {
+            Some(max_distance_km) => write!(f, "within {max_distance_km} km of ")?,
+            None => write!(f, "nearest to ")?,
+        }
+        match self.origin {
+            DistanceOrigin::GeoIp => write!(f, "this device"),
+            DistanceOrigin::Coordinates(coordinates) => write!(f, "{coordinates}"),
         }
     }
 }
@@ -103,6 +190,7 @@ pub struct RelayConstraints {
     pub providers: Constraint<Providers>,
     pub ownership: Constraint<Ownership>,
     pub wireguard_constraints: WireguardConstraints,
+    pub strategy: Constraint<RelayStrategy>,
 }
 
 impl RelayConstraints {
@@ -152,7 +240,8 @@ impl fmt::Display for RelayConstraintsFormatter<'_> {
                 })
         )?;
         writeln!(f, "Provider(s): {}", self.constraints.providers)?;
-        write!(f, "Ownership: {}", self.constraints.ownership)
+        writeln!(f, "Ownership: {}", self.constraints.ownership)?;
+        write!(f, "Strategy: {}", self.constraints.strategy)
     }
 }
 
@@ -304,6 +393,44 @@ impl FromStr for Ownership {
 #[error("Not a valid ownership setting")]
 pub struct OwnershipParseError;
 
+/// Decides how a `RelaySelector` chooses among the relays that satisfy all other constraints.
+/// If unconstrained, a relay is picked at random, weighted by the relay list.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayStrategy {
+    /// Prefer the relays with the lowest measured round-trip time.
+    Fastest,
+    /// Avoid relays that recently failed to connect.
+    Reliable,
+}
+
+impl fmt::Display for RelayStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            RelayStrategy::Fastest => write!(f, "fastest relay"),
+            RelayStrategy::Reliable => write!(f, "reliable relay"),
+        }
+    }
+}
+
+impl FromStr for RelayStrategy {
+    type Err = RelayStrategyParseError;
+
+    fn from_str(s: &str) -> Result<RelayStrategy, Self::Err> {
+        match s {
+            "fastest" => Ok(RelayStrategy::Fastest),
+            "reliable" => Ok(RelayStrategy::Reliable),
+            _ => Err(RelayStrategyParseError),
+        }
+    }
+}
+
+/// Returned when `RelayStrategy::from_str` fails to convert a string into a
+/// [`RelayStrategy`] object.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Not a valid relay strategy")]
+pub struct RelayStrategyParseError;
+
 /// Limits the set of [`crate::relay_list::Relay`]s used by a `RelaySelector` based on
 /// provider.
 pub type Provider = String;
@@ -367,6 +494,28 @@ impl fmt::Display for Providers {
     }
 }
 
+/// Relays that are never selected, regardless of the relay constraints. This applies to both
+/// entry and exit relays.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RelayExclusions {
+    /// Hostnames of excluded relays.
+    pub relays: BTreeSet<Hostname>,
+    /// Providers whose relays are all excluded.
+    pub providers: BTreeSet<Provider>,
+}
+
+impl RelayExclusions {
+    /// Returns whether `relay`, or its provider, is excluded.
+    pub fn excludes(&self, relay: &WireguardRelay) -> bool {
+        self.relays.contains(&relay.hostname) || self.providers.contains(&relay.provider)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relays.is_empty() && self.providers.is_empty()
+    }
+}
+
 impl fmt::Display for GeographicLocationConstraint {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
@@ -607,6 +756,7 @@ pub enum SelectedObfuscation {
     Shadowsocks,
     Quic,
     Lwo,
+    CustomBridge,
 }
 
 impl Intersection for SelectedObfuscation {
@@ -633,6 +783,7 @@ impl fmt::Display for SelectedObfuscation {
             SelectedObfuscation::Shadowsocks => "shadowsocks".fmt(f),
             SelectedObfuscation::Quic => "quic".fmt(f),
             SelectedObfuscation::Lwo => "lwo".fmt(f),
+            SelectedObfuscation::CustomBridge => "custom bridge".fmt(f),
             SelectedObfuscation::WireguardPort => "wireguard port".fmt(f),
         }
     }
@@ -722,6 +873,44 @@ impl fmt::Display for LwoSettings {
     }
 }
 
+/// User-provided proxy to relay WireGuard traffic through.
+#[derive(Default, Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub struct CustomBridgeSettings {
+    pub bridge: Option<CustomBridge>,
+}
+
+impl fmt::Display for CustomBridgeSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.bridge {
+            None => write!(f, "no bridge"),
+            Some(CustomBridge::Shadowsocks(shadowsocks)) => {
+                write!(f, "shadowsocks {}", shadowsocks.endpoint)
+            }
+            Some(CustomBridge::Socks5(socks5)) => write!(f, "socks5 {}", socks5.endpoint),
+        }
+    }
+}
+
+impl CustomBridgeSettings {
+    /// Returns an error if the bridge cannot relay WireGuard traffic.
+    pub fn validate(&self) -> Result<(), InvalidCustomBridge> {
+        match &self.bridge {
+            // The packet overhead is only known for AEAD ciphers
+            Some(CustomBridge::Shadowsocks(shadowsocks)) if !shadowsocks.cipher.is_aead() => Err(
+                InvalidCustomBridge::UnsupportedCipher(shadowsocks.cipher.clone()),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvalidCustomBridge {
+    #[error("Shadowsocks cipher {0} is not supported by custom bridges, use an AEAD cipher")]
+    UnsupportedCipher(ShadowsocksCipher),
+}
+
 /// Contains obfuscation settings
 #[derive(Default, Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -732,6 +921,7 @@ pub struct ObfuscationSettings {
     pub shadowsocks: ShadowsocksSettings,
     pub wireguard_port: WireguardPortSettings,
     pub lwo: LwoSettings,
+    pub custom_bridge: CustomBridgeSettings,
 }
 
 /// Represents a specific obfuscation method (or explicit "off").
@@ -746,6 +936,21 @@ pub enum ObfuscationMode {
     Shadowsocks(ShadowsocksSettings),
     Quic,
     Lwo(LwoSettings),
+    CustomBridge(CustomBridgeSettings),
+}
+
+impl fmt::Display for ObfuscationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObfuscationMode::Off => write!(f, "off"),
+            ObfuscationMode::Port(settings) => write!(f, "wireguard port ({settings})"),
+            ObfuscationMode::Udp2tcp(settings) => write!(f, "udp2tcp ({settings})"),
+            ObfuscationMode::Shadowsocks(settings) => write!(f, "shadowsocks ({settings})"),
+            ObfuscationMode::Quic => write!(f, "quic"),
+            ObfuscationMode::Lwo(settings) => write!(f, "lwo ({settings})"),
+            ObfuscationMode::CustomBridge(settings) => write!(f, "custom bridge ({settings})"),
+        }
+    }
 }
 
 impl ObfuscationMode {
@@ -760,6 +965,13 @@ impl ObfuscationMode {
                     ..Default::default()
                 };
             }
+            ObfuscationMode::CustomBridge(custom_bridge) => {
+                return ObfuscationSettings {
+                    selected_obfuscation: SelectedObfuscation::CustomBridge,
+                    custom_bridge,
+                    ..Default::default()
+                };
+            }
             ObfuscationMode::Port(wireguard_port) => {
                 return ObfuscationSettings {
                     selected_obfuscation: SelectedObfuscation::WireguardPort,
@@ -817,6 +1029,7 @@ pub fn obfuscation_constraint_from_settings(
         Shadowsocks => Constraint::Only(ObfuscationMode::Shadowsocks(obfuscation.shadowsocks)),
         Quic => Constraint::Only(ObfuscationMode::Quic),
         Lwo => Constraint::Only(ObfuscationMode::Lwo(obfuscation.lwo)),
+        CustomBridge => Constraint::Only(ObfuscationMode::CustomBridge(obfuscation.custom_bridge)),
     }
 }
 
@@ -899,4 +1112,26 @@ mod tests {
             GeographicLocationConstraint::hostname("se", "got", "se-got-wg-101")
         );
     }
+
+    #[test]
+    fn test_validate_custom_bridge() {
+        use talpid_types::net::proxy::Shadowsocks;
+
+        let bridge = |cipher| CustomBridgeSettings {
+            bridge: Some(CustomBridge::Shadowsocks(Shadowsocks::new(
+                ([192, 0, 2, 1], 443),
+                ShadowsocksCipher::new(cipher).unwrap(),
+                "hunter2".to_owned(),
+            ))),
+        };
+
+        assert_eq!(CustomBridgeSettings::default().validate(), Ok(()));
+        assert_eq!(bridge("chacha20-ietf-poly1305").validate(), Ok(()));
+        assert_eq!(
+            bridge("aes-256-cfb").validate(),
+            Err(InvalidCustomBridge::UnsupportedCipher(
+                ShadowsocksCipher::new("aes-256-cfb").unwrap()
+            ))
+        );
+    }
 }
@@ -0,0 +1,214 @@
+//! Policies for periodically reconnecting to a different exit relay.
+
+use std::{collections::BTreeSet, fmt, str::FromStr, time::Duration};
+
+use chrono::{DateTime, Days, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// Shortest allowed interval between rotations.
+pub const MIN_ROTATION_INTERVAL: Duration = Duration::from_secs(60);
+/// Longest allowed interval between rotations.
+pub const MAX_ROTATION_INTERVAL: Duration = Duration::from_hours(30 * 24);
+
+/// When to reconnect to a different exit relay that matches the current relay constraints.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationPolicy {
+    /// Rotate after having been connected for this long.
+    Interval(Duration),
+    /// Rotate after this many bytes have been sent and received through the tunnel.
+    Traffic(u64),
+    /// Rotate at each of these local times of day.
+    TimesOfDay(BTreeSet<TimeOfDay>),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvalidRotationPolicy {
+    #[error("Rotation interval must be at least {} seconds", MIN_ROTATION_INTERVAL.as_secs())]
+    IntervalTooSmall,
+    #[error("Rotation interval must be at most {} hours", MAX_ROTATION_INTERVAL.as_secs() / 60 / 60)]
+    IntervalTooLarge,
+    #[error("Traffic limit must be greater than zero")]
+    NoTraffic,
+    #[error("At least one time of day must be given")]
+    NoTimesOfDay,
+}
+
+impl RotationPolicy {
+    /// Returns an error if the policy would rotate too often, too rarely, or never.
+    pub fn validate(&self) -> Result<(), InvalidRotationPolicy> {
+        match self {
+            RotationPolicy::Interval(interval) if *interval < MIN_ROTATION_INTERVAL => {
+                Err(InvalidRotationPolicy::IntervalTooSmall)
+            }
+            RotationPolicy::Interval(interval) if *interval > MAX_ROTATION_INTERVAL => {
+                Err(InvalidRotationPolicy::IntervalTooLarge)
+            }
+            RotationPolicy::Traffic(0) => Err(InvalidRotationPolicy::NoTraffic),
+            RotationPolicy::TimesOfDay(times) if times.is_empty() => {
+                Err(InvalidRotationPolicy::NoTimesOfDay)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for RotationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationPolicy::Interval(interval) => {
+                write!(f, "every {} minutes", interval.as_secs() / 60)
+            }
+            RotationPolicy::Traffic(bytes) => write!(f, "after {bytes} bytes"),
+            RotationPolicy::TimesOfDay(times) => {
+                write!(f, "at ")?;
+                for (i, time) in times.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{time}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A time of day, with minute precision. Serialized as `HH:MM`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay {
+    hour: u8,
+    minute: u8,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid time of day. Expected HH:MM, e.g. 08:30")]
+pub struct InvalidTimeOfDay;
+
+impl TimeOfDay {
+    pub fn new(hour: u8, minute: u8) -> Result<Self, InvalidTimeOfDay> {
+        if hour < 24 && minute < 60 {
+            Ok(TimeOfDay { hour, minute })
+        } else {
+            Err(InvalidTimeOfDay)
+        }
+    }
+
+    pub fn hour(&self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(&self) -> u8 {
+        self.minute
+    }
+
+    fn to_naive_time(self) -> NaiveTime {
+        NaiveTime::from_hms_opt(u32::from(self.hour), u32::from(self.minute), 0)
+            .expect("hour and minute are in range")
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = InvalidTimeOfDay;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hour, minute) = s.split_once(':').ok_or(InvalidTimeOfDay)?;
+        let hour = hour.parse().map_err(|_| InvalidTimeOfDay)?;
+        let minute = minute.parse().map_err(|_| InvalidTimeOfDay)?;
+        TimeOfDay::new(hour, minute)
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = InvalidTimeOfDay;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+/// Returns the first of `times` that comes strictly after `now`, in the time zone of `now`.
+/// Times of day that do not exist on a given date, due to daylight saving time, are skipped.
+pub fn next_time_of_day<Tz: TimeZone>(
+    times: &BTreeSet<TimeOfDay>,
+    now: &DateTime<Tz>,
+) -> Option<DateTime<Tz>> {
+    let today = now.date_naive();
+    // Looking two days ahead covers every time of day, even if one is skipped today
+    [today, today + Days::new(1), today + Days::new(2)]
+        .into_iter()
+        .flat_map(|date| {
+            times
+                .iter()
+                .map(move |time| date.and_time(time.to_naive_time()))
+        })
+        .filter_map(|naive| now.timezone().from_local_datetime(&naive).earliest())
+        .find(|time| time > now)
+}
+
+/// A reconnect caused by a [`RotationPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rotation {
+    /// Hostname of the exit relay that is being rotated away from.
+    pub previous_exit: String,
+    /// The policy that caused the rotation.
+    pub policy: RotationPolicy,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_parse_time_of_day() {
+        assert_eq!("08:30".parse(), TimeOfDay::new(8, 30));
+        assert_eq!("8:05".parse::<TimeOfDay>().unwrap().to_string(), "08:05");
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("12:60".parse::<TimeOfDay>().is_err());
+        assert!("1230".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn test_validate_interval() {
+        let interval = |duration| RotationPolicy::Interval(duration).validate();
+        assert_eq!(
+            interval(Duration::from_secs(59)),
+            Err(InvalidRotationPolicy::IntervalTooSmall)
+        );
+        assert_eq!(interval(MIN_ROTATION_INTERVAL), Ok(()));
+        assert_eq!(interval(MAX_ROTATION_INTERVAL), Ok(()));
+        assert_eq!(
+            interval(Duration::MAX),
+            Err(InvalidRotationPolicy::IntervalTooLarge)
+        );
+    }
+
+    #[test]
+    fn test_next_time_of_day() {
+        let times = BTreeSet::from([
+            TimeOfDay::new(6, 0).unwrap(),
+            TimeOfDay::new(18, 30).unwrap(),
+        ]);
+        let at = |day, hour, minute| Utc.with_ymd_and_hms(2025, 3, day, hour, minute, 0).unwrap();
+
+        assert_eq!(next_time_of_day(&times, &at(1, 5, 0)), Some(at(1, 6, 0)));
+        // A time that is now has already passed
+        assert_eq!(next_time_of_day(&times, &at(1, 6, 0)), Some(at(1, 18, 30)));
+        assert_eq!(next_time_of_day(&times, &at(1, 20, 0)), Some(at(2, 6, 0)));
+        assert_eq!(next_time_of_day(&BTreeSet::new(), &at(1, 20, 0)), None);
+    }
+}
@@ -0,0 +1,189 @@
+//! A record of the relays that were selected for recent connection attempts, and why.
+
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+
+use super::{Reason, RelayPartitions};
+
+/// Maximum number of selections kept in a [`SelectionHistory`].
+pub const MAX_SELECTIONS: usize = 50;
+
+/// The most recent relay selections, oldest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SelectionHistory {
+    selections: VecDeque<RelaySelection>,
+}
+
+/// A single decision made by the relay selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelaySelection {
+    pub time: DateTime<Utc>,
+    /// Connection attempt that the relay was selected for, starting at zero.
+    pub retry_attempt: u32,
+    /// The query that the relay was selected by, including any constraints added for this
+    /// particular retry attempt.
+    pub query: String,
+    /// Candidates for the exit relay, or for the only relay when using singlehop.
+    pub exit_candidates: CandidateSummary,
+    /// Candidates for the entry relay, if an entry relay was selected.
+    pub entry_candidates: Option<CandidateSummary>,
+    pub exit: String,
+    pub entry: Option<String>,
+    /// Description of the obfuscation method used to reach the first relay, if any.
+    pub obfuscator: Option<String>,
+    pub outcome: SelectionOutcome,
+}
+
+/// How many relays matched a query, and how many were discarded for which reasons.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CandidateSummary {
+    pub matches: usize,
+    /// The number of discarded relays for each distinct set of reasons, most common first.
+    pub discards: Vec<(Vec<Reason>, usize)>,
+}
+
+/// What happened after a relay was selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectionOutcome {
+    /// The tunnel is still connecting to the relay.
+    Pending,
+    /// The tunnel connected to the relay.
+    Connected,
+    /// The tunnel failed with an error, possibly unrelated to the relay.
+    Failed(String),
+    /// Another relay was selected before the tunnel connected, e.g. because the connection
+    /// attempt timed out or the settings changed.
+    Superseded,
+    /// The tunnel was disconnected before it connected.
+    Cancelled,
+}
+
+impl RelaySelection {
+    /// Hostname of the relay that the client connects to directly.
+    pub fn first_hop(&self) -> &str {
+        self.entry.as_deref().unwrap_or(&self.exit)
+    }
+}
+
+impl SelectionHistory {
+    /// Add a new selection, evicting the oldest one if the history is full. A previous
+    /// selection that never resolved is marked as [`SelectionOutcome::Superseded`].
+    pub fn push(&mut self, selection: RelaySelection) {
+        self.set_outcome(SelectionOutcome::Superseded);
+        if self.selections.len() == MAX_SELECTIONS {
+            self.selections.pop_front();
+        }
+        self.selections.push_back(selection);
+    }
+
+    /// Resolve the outcome of the most recent selection, unless it has already been resolved.
+    pub fn set_outcome(&mut self, outcome: SelectionOutcome) {
+        if let Some(last) = self.selections.back_mut()
+            && last.outcome == SelectionOutcome::Pending
+        {
+            last.outcome = outcome;
+        }
+    }
+
+    /// Returns the most recent selection.
+    pub fn latest(&self) -> Option<&RelaySelection> {
+        self.selections.back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RelaySelection> {
+        self.selections.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.selections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.selections.is_empty()
+    }
+}
+
+impl From<Vec<RelaySelection>> for SelectionHistory {
+    fn from(mut selections: Vec<RelaySelection>) -> Self {
+        let excess = selections.len().saturating_sub(MAX_SELECTIONS);
+        selections.drain(..excess);
+        Self {
+            selections: selections.into(),
+        }
+    }
+}
+
+impl IntoIterator for SelectionHistory {
+    type Item = RelaySelection;
+    type IntoIter = std::collections::vec_deque::IntoIter<RelaySelection>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.selections.into_iter()
+    }
+}
+
+impl From<&RelayPartitions> for CandidateSummary {
+    fn from(partitions: &RelayPartitions) -> Self {
+        let mut counts: HashMap<&[Reason], usize> = HashMap::new();
+        for (_relay, reasons) in &partitions.discards {
+            *counts.entry(reasons.as_slice()).or_default() += 1;
+        }
+        let mut discards: Vec<_> = counts
+            .into_iter()
+            .map(|(reasons, count)| (reasons.to_vec(), count))
+            .collect();
+        // Most common first. Ties are broken by the number of reasons to keep the order stable.
+        discards
+            .sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.len().cmp(&b.len())));
+        CandidateSummary {
+            matches: partitions.matches.len(),
+            discards,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn selection(exit: &str) -> RelaySelection {
+        RelaySelection {
+            time: Utc::now(),
+            retry_attempt: 0,
+            query: String::new(),
+            exit_candidates: CandidateSummary::default(),
+            entry_candidates: None,
+            exit: exit.to_owned(),
+            entry: None,
+            obfuscator: None,
+            outcome: SelectionOutcome::Pending,
+        }
+    }
+
+    #[test]
+    fn test_push_resolves_pending_selection() {
+        let mut history = SelectionHistory::default();
+        history.push(selection("se-got-wg-001"));
+        history.push(selection("se-got-wg-002"));
+        history.set_outcome(SelectionOutcome::Connected);
+        // Already resolved outcomes are left alone
+        history.set_outcome(SelectionOutcome::Cancelled);
+
+        let outcomes: Vec<_> = history.iter().map(|s| s.outcome.clone()).collect();
+        assert_eq!(
+            outcomes,
+            [SelectionOutcome::Superseded, SelectionOutcome::Connected]
+        );
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = SelectionHistory::default();
+        for i in 0..MAX_SELECTIONS + 5 {
+            history.push(selection(&format!("relay-{i}")));
+        }
+        assert_eq!(history.len(), MAX_SELECTIONS);
+        assert_eq!(history.iter().next().unwrap().exit, "relay-5");
+    }
+}
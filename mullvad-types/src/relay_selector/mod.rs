@@ -3,21 +3,35 @@
 //! Most types in this module are equivalent to the ones in `mullvad-management-interface\proto\management_interface.proto`.
 //! See the proto file for more documentation.
 
+pub mod history;
+pub mod penalties;
+
+use std::fmt;
+
 use talpid_types::net::IpVersion;
 
 use crate::{
     Intersection,
     constraints::{Constraint, Match},
-    custom_list::CustomListsSettings,
+    custom_list::{CustomListFilter, CustomListsSettings},
+    location::Coordinates,
     relay_constraints::{
-        GeographicLocationConstraint, LocationConstraint, LwoSettings, ObfuscationMode, Ownership,
-        Providers, ShadowsocksSettings, Udp2TcpObfuscationSettings,
+        DistanceConstraint, DistanceOrigin, GeographicLocationConstraint, LocationConstraint,
+        LwoSettings, ObfuscationMode, Ownership, Providers, ShadowsocksSettings,
+        Udp2TcpObfuscationSettings,
     },
     relay_list::WireguardRelay,
 };
 
+/// The locations of a [`LocationConstraint`], with custom lists resolved to their contents.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct ResolvedLocationConstraint(Vec<GeographicLocationConstraint>);
+pub struct ResolvedLocationConstraint {
+    locations: Vec<GeographicLocationConstraint>,
+    /// Rules of a custom list, which are matched against relays in addition to `locations`.
+    filter: Option<CustomListFilter>,
+    /// Relays near an origin, which are matched in addition to `locations`.
+    distance: Option<DistanceConstraint>,
+}
 
 impl ResolvedLocationConstraint {
     /// Define the mapping from a [location][`LocationConstraint`] and a set of
@@ -30,30 +44,83 @@ impl ResolvedLocationConstraint {
             Constraint::Any => Constraint::Any,
             Constraint::Only(location) => Constraint::Only(match location {
                 LocationConstraint::Location(location) => {
-                    ResolvedLocationConstraint(vec![location])
+                    ResolvedLocationConstraint::from(location)
                 }
                 LocationConstraint::CustomList { list_id } => custom_lists
                     .iter()
                     .find(|list| list.id() == list_id)
-                    .map(|custom_list| {
-                        ResolvedLocationConstraint(custom_list.locations.iter().cloned().collect())
+                    .map(|custom_list| ResolvedLocationConstraint {
+                        locations: custom_list.locations.iter().cloned().collect(),
+                        filter: custom_list.filter.clone(),
+                        distance: None,
                     })
                     .unwrap_or_else(|| {
                         log::warn!("Resolved non-existent custom list with id {list_id:?}");
-                        ResolvedLocationConstraint(vec![])
+                        ResolvedLocationConstraint {
+                            locations: vec![],
+                            filter: None,
+                            distance: None,
+                        }
                     }),
+                LocationConstraint::Distance(distance) => {
+                    ResolvedLocationConstraint::from(distance)
+                }
             }),
         }
     }
 
+    /// Returns the distance constraint, if relays are selected by their distance from an origin.
+    pub fn distance(&self) -> Option<&DistanceConstraint> {
+        self.distance.as_ref()
+    }
+
+    /// Returns whether relays are selected by their distance from this device.
+    pub fn needs_user_location(&self) -> bool {
+        self.distance
+            .is_some_and(|distance| distance.origin == DistanceOrigin::GeoIp)
+    }
+
+    /// Measure a distance constraint relative to this device from `user_location`. If the
+    /// location of this device is unknown, the distance constraint matches no relay.
+    pub fn with_user_location(mut self, user_location: Option<Coordinates>) -> Self {
+        if let Some(distance) = &mut self.distance
+            && distance.origin == DistanceOrigin::GeoIp
+            && let Some(user_location) = user_location
+        {
+            distance.origin = DistanceOrigin::Coordinates(user_location);
+        }
+        self
+    }
+
+    /// Returns the rules of a custom list, which relays may match in addition to the static
+    /// locations.
+    pub fn filter(&self) -> Option<&CustomListFilter> {
+        self.filter.as_ref()
+    }
+
+    /// Returns the static locations. Relays may also match the rules of a custom list.
     pub fn iter(&self) -> impl Iterator<Item = &GeographicLocationConstraint> {
-        self.0.iter()
+        self.locations.iter()
     }
 }
 
 impl From<GeographicLocationConstraint> for ResolvedLocationConstraint {
     fn from(value: GeographicLocationConstraint) -> Self {
-        Self(vec![value])
+        Self {
+            locations: vec![value],
+            filter: None,
+            distance: None,
+        }
+    }
+}
+
+impl From<DistanceConstraint> for ResolvedLocationConstraint {
+    fn from(value: DistanceConstraint) -> Self {
+        Self {
+            locations: vec![],
+            filter: None,
+            distance: Some(value),
+        }
     }
 }
 
@@ -63,9 +130,42 @@ impl From<GeographicLocationConstraint> for Constraint<ResolvedLocationConstrain
     }
 }
 
+impl fmt::Display for ResolvedLocationConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.locations.is_empty() && self.filter.is_none() && self.distance.is_none() {
+            return write!(f, "no locations");
+        }
+        for (i, location) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " or ")?;
+            }
+            write!(f, "{location}")?;
+        }
+        if let Some(filter) = &self.filter {
+            if !self.locations.is_empty() {
+                write!(f, " or ")?;
+            }
+            write!(f, "{filter}")?;
+        }
+        if let Some(distance) = &self.distance {
+            if !self.locations.is_empty() || self.filter.is_some() {
+                write!(f, " or ")?;
+            }
+            write!(f, "relays {distance}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Note that this does not match the rules of a custom list, since those depend on more than the
+/// location of a relay. See [`ResolvedLocationConstraint::filter`].
 impl Match<WireguardRelay> for &ResolvedLocationConstraint {
     fn matches(&self, relay: &WireguardRelay) -> bool {
         self.iter().any(|location| location.matches(relay))
+            || self
+                .distance
+                .as_ref()
+                .is_some_and(|distance| distance.matches(relay))
     }
 }
 
@@ -86,6 +186,48 @@ pub struct EntryConstraints {
     pub entry_specific: EntrySpecificConstraints,
 }
 
+/// The constraints that a relay must satisfy to be included by the rules of a custom list.
+impl From<&CustomListFilter> for EntryConstraints {
+    fn from(filter: &CustomListFilter) -> Self {
+        let countries: Vec<_> = filter
+            .countries
+            .iter()
+            .cloned()
+            .map(GeographicLocationConstraint::Country)
+            .collect();
+        let location = if countries.is_empty() {
+            Constraint::Any
+        } else {
+            Constraint::Only(ResolvedLocationConstraint {
+                locations: countries,
+                filter: None,
+                distance: None,
+            })
+        };
+        EntryConstraints {
+            general: ExitConstraints {
+                location,
+                providers: Providers::new(filter.providers.iter().cloned())
+                    .map_or(Constraint::Any, Constraint::Only),
+                ownership: Constraint::from(filter.ownership),
+            },
+            entry_specific: EntrySpecificConstraints {
+                obfuscation: if filter.quic {
+                    Constraint::Only(ObfuscationMode::Quic)
+                } else {
+                    Constraint::Any
+                },
+                daita: if filter.daita {
+                    Constraint::Only(true)
+                } else {
+                    Constraint::Any
+                },
+                ip_version: Constraint::Any,
+            },
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Intersection)]
 pub struct EntrySpecificConstraints {
     pub obfuscation: Constraint<ObfuscationMode>,
@@ -106,7 +248,7 @@ pub struct MultihopConstraints {
     pub exit: ExitConstraints,
 }
 
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct RelayPartitions {
     pub matches: Vec<WireguardRelay>,
     pub discards: Vec<(WireguardRelay, Vec<Reason>)>,
@@ -121,6 +263,8 @@ pub enum Reason {
     Daita,
     /// The relay is currently offline.
     Inactive,
+    /// The relay, or its provider, has been excluded by the user.
+    Excluded,
     /// The relay cannot be connected to with the requested ip version.
     IpVersion,
     /// The relay does not reside in the given location.
@@ -139,6 +283,60 @@ pub enum Reason {
     IncludeInCountry,
 }
 
+impl fmt::Display for EntryConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}; {}", self.general, self.entry_specific)
+    }
+}
+
+impl fmt::Display for EntrySpecificConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "obfuscation: {}; DAITA: ", self.obfuscation)?;
+        match self.daita {
+            Constraint::Any => write!(f, "any")?,
+            Constraint::Only(true) => write!(f, "on")?,
+            Constraint::Only(false) => write!(f, "off")?,
+        }
+        write!(f, "; IP version: {}", self.ip_version)
+    }
+}
+
+impl fmt::Display for ExitConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Constraint::Any => write!(f, "any location")?,
+            Constraint::Only(location) => write!(f, "{location}")?,
+        }
+        match &self.providers {
+            Constraint::Any => write!(f, "; any provider")?,
+            Constraint::Only(providers) => write!(f, "; {providers}")?,
+        }
+        match &self.ownership {
+            Constraint::Any => write!(f, "; any ownership"),
+            Constraint::Only(ownership) => write!(f, "; {ownership}"),
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            Reason::Conflict => "conflict with other hop",
+            Reason::Daita => "DAITA",
+            Reason::Inactive => "inactive",
+            Reason::Excluded => "excluded",
+            Reason::IpVersion => "IP version",
+            Reason::Location => "location",
+            Reason::Obfuscation => "obfuscation",
+            Reason::Ownership => "ownership",
+            Reason::Port => "port",
+            Reason::Providers => "providers",
+            Reason::IncludeInCountry => "not included in country",
+        };
+        f.write_str(reason)
+    }
+}
+
 // TODO: Should these be builders instead?
 
 impl EntryConstraints {
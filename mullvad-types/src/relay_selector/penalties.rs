@@ -0,0 +1,125 @@
+//! Penalties for relays that recently failed to connect. A penalty makes a relay less likely to
+//! be selected, and decays over time.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Time it takes for a penalty to decay to half its value.
+pub const PENALTY_HALF_LIFE: Duration = Duration::from_secs(30 * 60);
+/// Penalty added each time a relay fails to connect.
+const FAILURE_PENALTY: f64 = 1.0;
+/// Upper bound of a penalty, so that a relay that failed many times in a row recovers within a
+/// few hours.
+const MAX_PENALTY: f64 = 8.0;
+/// Penalties that have decayed below this are forgotten.
+const MIN_PENALTY: f64 = 0.01;
+
+/// Decaying penalties of relays, by hostname.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RelayPenalties {
+    relays: HashMap<String, Penalty>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Penalty {
+    value: f64,
+    /// When `value` was last updated.
+    updated: DateTime<Utc>,
+}
+
+impl Penalty {
+    fn at(&self, now: DateTime<Utc>) -> f64 {
+        let elapsed = (now - self.updated).to_std().unwrap_or_default();
+        self.value * 0.5f64.powf(elapsed.as_secs_f64() / PENALTY_HALF_LIFE.as_secs_f64())
+    }
+}
+
+impl RelayPenalties {
+    /// Penalize the relay with the hostname `hostname` for failing to connect.
+    pub fn penalize(&mut self, hostname: &str, now: DateTime<Utc>) {
+        let value = (self.penalty(hostname, now) + FAILURE_PENALTY).min(MAX_PENALTY);
+        self.relays.insert(
+            hostname.to_owned(),
+            Penalty {
+                value,
+                updated: now,
+            },
+        );
+    }
+
+    /// Remove the penalty of a relay, e.g. because it connected successfully. Returns whether the
+    /// relay had a penalty.
+    pub fn forgive(&mut self, hostname: &str) -> bool {
+        self.relays.remove(hostname).is_some()
+    }
+
+    /// Returns the current penalty of a relay, or zero if it has none.
+    pub fn penalty(&self, hostname: &str, now: DateTime<Utc>) -> f64 {
+        self.relays
+            .get(hostname)
+            .map(|penalty| penalty.at(now))
+            .unwrap_or(0.0)
+    }
+
+    /// Returns `weight` halved for every unit of penalty that the relay has. To keep the
+    /// precision of small weights, every weight is first multiplied by `2^MAX_PENALTY`, so only
+    /// the ratio between returned weights is meaningful. Relays with a non-zero weight keep a
+    /// weight of at least one, so that they are avoided rather than never selected.
+    pub fn weight(&self, hostname: &str, weight: u64, now: DateTime<Utc>) -> u64 {
+        let scaled = (weight as f64) * 2f64.powf(MAX_PENALTY - self.penalty(hostname, now));
+        if weight == 0 {
+            0
+        } else {
+            (scaled.round() as u64).max(1)
+        }
+    }
+
+    /// Forget penalties that have decayed to nearly nothing.
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        self.relays
+            .retain(|_, penalty| penalty.at(now) >= MIN_PENALTY);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relays.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_penalty_decays() {
+        let now = Utc::now();
+        let mut penalties = RelayPenalties::default();
+        penalties.penalize("se-got-wg-001", now);
+        penalties.penalize("se-got-wg-001", now);
+
+        assert_eq!(penalties.weight("se-got-wg-001", 100, now), 6400);
+        assert_eq!(penalties.weight("se-got-wg-002", 100, now), 25600);
+
+        let half_life = chrono::Duration::from_std(PENALTY_HALF_LIFE).unwrap();
+        let later = now + half_life;
+        assert_eq!(penalties.penalty("se-got-wg-001", later), 1.0);
+        assert_eq!(penalties.weight("se-got-wg-001", 100, later), 12800);
+
+        penalties.prune(now + half_life * 10);
+        assert!(penalties.is_empty());
+    }
+
+    #[test]
+    fn test_penalized_relays_remain_selectable() {
+        let now = Utc::now();
+        let mut penalties = RelayPenalties::default();
+        for _ in 0..20 {
+            penalties.penalize("se-got-wg-001", now);
+        }
+        assert_eq!(penalties.penalty("se-got-wg-001", now), MAX_PENALTY);
+        assert_eq!(penalties.weight("se-got-wg-001", 1, now), 1);
+        assert_eq!(penalties.weight("se-got-wg-002", 1, now), 256);
+        assert_eq!(penalties.weight("se-got-wg-001", 0, now), 0);
+    }
+}
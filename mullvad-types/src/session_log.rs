@@ -0,0 +1,50 @@
+//! Records of past tunnel sessions, kept in the opt-in session log.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use talpid_types::{net::TunnelEndpoint, tunnel::ErrorStateCause};
+
+/// A single tunnel session, from the time the tunnel was connected until it was torn down.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Hostname of the exit relay, if the tunnel went to a Mullvad relay.
+    pub exit_hostname: Option<String>,
+    /// Hostname of the entry relay, if multihop was used.
+    pub entry_hostname: Option<String>,
+    /// The endpoint of the tunnel, including the obfuscation method that was actually used.
+    pub endpoint: TunnelEndpoint,
+    /// Bytes received through the tunnel during the session.
+    pub rx_bytes: u64,
+    /// Bytes sent through the tunnel during the session.
+    pub tx_bytes: u64,
+    pub end_reason: SessionEnd,
+}
+
+/// Why a tunnel session ended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEnd {
+    /// The tunnel was disconnected.
+    Disconnected,
+    /// The tunnel was torn down to reconnect, e.g. because settings changed.
+    Reconnected,
+    /// The tunnel failed and the daemon entered the error state.
+    Error(ErrorStateCause),
+    /// The daemon stopped before the tunnel was torn down, e.g. because the machine lost power.
+    /// The session is known to have lasted at least until its end time.
+    Interrupted,
+}
+
+impl fmt::Display for SessionEnd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionEnd::Disconnected => write!(f, "disconnected"),
+            SessionEnd::Reconnected => write!(f, "reconnected"),
+            SessionEnd::Error(cause) => write!(f, "error: {cause}"),
+            SessionEnd::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
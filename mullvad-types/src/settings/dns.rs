@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::net::IpAddr;
+use std::{fmt, net::IpAddr};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 #[serde(rename_all = "snake_case")]
@@ -16,6 +16,9 @@ pub struct DnsOptions {
     pub state: DnsState,
     pub default_options: DefaultDnsOptions,
     pub custom_options: CustomDnsOptions,
+    /// Send queries for specific domains to specific DNS servers. These rules apply regardless
+    /// of `state`.
+    pub domain_rules: Vec<DnsDomainRule>,
 }
 
 /// Default DNS config
@@ -109,4 +112,189 @@ impl Default for DefaultDnsOptions {
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct CustomDnsOptions {
     pub addresses: Vec<IpAddr>,
+    /// Encrypt queries sent to `addresses`. If `None`, plain DNS is used.
+    #[serde(default)]
+    pub encryption: Option<DnsEncryption>,
+}
+
+/// Encrypted transport to use for custom DNS servers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsEncryption {
+    /// DNS over TLS, on port 853. The server certificate must be valid for `server_name`.
+    Tls { server_name: String },
+    /// DNS over HTTPS, on port 443. The server certificate must be valid for `server_name`.
+    Https { server_name: String, path: String },
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvalidDnsEncryptionError {
+    #[error("Invalid TLS server name")]
+    ServerName(#[from] InvalidDomainError),
+    #[error("Invalid DNS over HTTPS URL: {0}")]
+    Url(String),
+}
+
+impl DnsEncryption {
+    /// Path used for DNS over HTTPS if the URL does not specify one
+    pub const DEFAULT_HTTPS_PATH: &str = "/dns-query";
+
+    /// Use DNS over TLS, verifying the certificate against `server_name`.
+    pub fn tls(server_name: &str) -> Result<Self, InvalidDnsEncryptionError> {
+        Ok(DnsEncryption::Tls {
+            server_name: normalize_domain(server_name)?,
+        })
+    }
+
+    /// Use DNS over HTTPS with a URL such as `https://dns.example/dns-query`. The URL may not
+    /// specify a port, query or fragment.
+    pub fn https(url: &str) -> Result<Self, InvalidDnsEncryptionError> {
+        let invalid = || InvalidDnsEncryptionError::Url(url.to_owned());
+
+        let rest = url.strip_prefix("https://").ok_or_else(invalid)?;
+        let (host, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        if path.contains(['?', '#']) || path.chars().any(|c| c.is_whitespace()) {
+            return Err(invalid());
+        }
+        let server_name = normalize_domain(host).map_err(|_| invalid())?;
+        let path = match path {
+            "" | "/" => Self::DEFAULT_HTTPS_PATH.to_owned(),
+            path => path.to_owned(),
+        };
+
+        Ok(DnsEncryption::Https { server_name, path })
+    }
+
+    /// Name that the server certificate must be valid for
+    pub fn server_name(&self) -> &str {
+        match self {
+            DnsEncryption::Tls { server_name } | DnsEncryption::Https { server_name, .. } => {
+                server_name
+            }
+        }
+    }
+}
+
+impl fmt::Display for DnsEncryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsEncryption::Tls { server_name } => write!(f, "DNS over TLS ({server_name})"),
+            DnsEncryption::Https { server_name, path } => {
+                write!(f, "DNS over HTTPS (https://{server_name}{path})")
+            }
+        }
+    }
+}
+
+/// Send queries for a domain, and all of its subdomains, to specific DNS servers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub struct DnsDomainRule {
+    /// Domain that the rule applies to, such as `corp.example`
+    pub domain: String,
+    /// DNS servers to use for the domain. Servers with private IPs are reached outside the
+    /// tunnel, the same as for [`CustomDnsOptions`].
+    pub servers: Vec<IpAddr>,
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid domain name: {0}")]
+pub struct InvalidDomainError(String);
+
+impl DnsDomainRule {
+    /// Create a rule for `domain`. A leading `*.` and a trailing `.` are accepted but ignored,
+    /// since a rule always covers all subdomains.
+    pub fn new(domain: &str, servers: Vec<IpAddr>) -> Result<Self, InvalidDomainError> {
+        let domain = domain.strip_prefix("*.").unwrap_or(domain);
+        Ok(DnsDomainRule {
+            domain: normalize_domain(domain)?,
+            servers,
+        })
+    }
+}
+
+/// Lowercase `domain` and remove any trailing `.`, or fail if it is not a valid domain name
+fn normalize_domain(domain: &str) -> Result<String, InvalidDomainError> {
+    let invalid = || InvalidDomainError(domain.to_owned());
+
+    let normalized = domain.strip_suffix('.').unwrap_or(domain);
+    let normalized = normalized.to_ascii_lowercase();
+
+    if normalized.is_empty() || normalized.len() > 253 {
+        return Err(invalid());
+    }
+    let valid_label = |label: &str| {
+        !label.is_empty()
+            && label.len() <= 63
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if !normalized.split('.').all(valid_label) {
+        return Err(invalid());
+    }
+
+    Ok(normalized)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DnsDomainRule, DnsEncryption};
+
+    #[test]
+    fn test_domain_rule_normalization() {
+        for domain in [
+            "corp.example",
+            "*.corp.example",
+            "Corp.Example.",
+            "*.CORP.example.",
+        ] {
+            let rule = DnsDomainRule::new(domain, vec![]).unwrap();
+            assert_eq!(rule.domain, "corp.example");
+        }
+    }
+
+    #[test]
+    fn test_invalid_domain_rule() {
+        for domain in [
+            "",
+            ".",
+            "*.",
+            "corp..example",
+            "-corp.example",
+            "corp example",
+            "*",
+        ] {
+            DnsDomainRule::new(domain, vec![]).expect_err(domain);
+        }
+    }
+
+    #[test]
+    fn test_dns_over_https_url() {
+        let expected = DnsEncryption::Https {
+            server_name: "dns.example".to_owned(),
+            path: "/dns-query".to_owned(),
+        };
+        for url in [
+            "https://dns.example",
+            "https://dns.example/",
+            "https://DNS.example/dns-query",
+        ] {
+            assert_eq!(DnsEncryption::https(url).unwrap(), expected);
+        }
+
+        for url in [
+            "http://dns.example/dns-query",
+            "dns.example",
+            "https://dns.example:8443/dns-query",
+            "https://dns.example/dns-query?dns=1",
+            "https:///dns-query",
+        ] {
+            DnsEncryption::https(url).expect_err(url);
+        }
+    }
 }
@@ -1,20 +1,35 @@
 use crate::{
     access_method,
     constraints::Constraint,
+    custom_endpoint::CustomEndpointsSettings,
     custom_list::CustomListsSettings,
     relay_constraints::{
         GeographicLocationConstraint, LocationConstraint, ObfuscationSettings, RelayConstraints,
-        RelayOverride, RelaySettings, RelaySettingsFormatter, SelectedObfuscation,
+        RelayExclusions, RelayOverride, RelaySettings, RelaySettingsFormatter, SelectedObfuscation,
         WireguardConstraints,
     },
+    relay_rotation::RotationPolicy,
     wireguard,
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
+#[cfg(any(
+    windows,
+    target_os = "android",
+    target_os = "macos",
+    target_os = "linux"
+))]
 use std::collections::HashSet;
 use talpid_types::net::GenericTunnelOptions;
+#[cfg(any(
+    windows,
+    target_os = "android",
+    target_os = "macos",
+    target_os = "linux"
+))]
+pub use talpid_types::split_tunnel::SplitTunnelMode;
 
 mod dns;
+mod trusted_networks;
 
 /// Top level settings that can be controlled by the user. (i.e. not metadata or purely internal items)
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
@@ -30,8 +45,14 @@ pub enum SettingsKey {
     AutoConnect,
     TunnelOptions,
     RelayOverrides,
+    RelayExclusions,
     ShowBetaReleases,
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+    #[cfg(any(
+        windows,
+        target_os = "android",
+        target_os = "macos",
+        target_os = "linux"
+    ))]
     SplitTunnel,
     Recents,
 }
@@ -45,7 +66,7 @@ pub struct SettingsKeyList {
 /// latest version that exists in `SettingsVersion`.
 /// This should be bumped when a new version is introduced along with a migration
 /// being added to `mullvad-daemon`.
-pub const CURRENT_SETTINGS_VERSION: SettingsVersion = SettingsVersion::V18;
+pub const CURRENT_SETTINGS_VERSION: SettingsVersion = SettingsVersion::V19;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy)]
 #[repr(u32)]
@@ -67,6 +88,7 @@ pub enum SettingsVersion {
     V16 = 16,
     V17 = 17,
     V18 = 18,
+    V19 = 19,
 }
 
 impl<'de> Deserialize<'de> for SettingsVersion {
@@ -92,6 +114,7 @@ impl<'de> Deserialize<'de> for SettingsVersion {
             v if v == SettingsVersion::V16 as u32 => Ok(SettingsVersion::V16),
             v if v == SettingsVersion::V17 as u32 => Ok(SettingsVersion::V17),
             v if v == SettingsVersion::V18 as u32 => Ok(SettingsVersion::V18),
+            v if v == SettingsVersion::V19 as u32 => Ok(SettingsVersion::V19),
             v => Err(serde::de::Error::custom(format!(
                 "{v} is not a valid SettingsVersion"
             ))),
@@ -116,6 +139,8 @@ pub struct Settings {
     pub obfuscation_settings: ObfuscationSettings,
     /// All of the custom relay lists
     pub custom_lists: CustomListsSettings,
+    /// Saved custom tunnel endpoints
+    pub custom_endpoints: CustomEndpointsSettings,
     /// API access methods
     pub api_access_methods: access_method::Settings,
     // If the default location in `relay_settings` should be updated based on the user's geolocation.
@@ -128,15 +153,27 @@ pub struct Settings {
     pub lockdown_mode: bool,
     /// If the daemon should connect the VPN tunnel directly on start or not.
     pub auto_connect: bool,
+    /// Networks on which the tunnel is automatically disconnected. On other networks, the tunnel
+    /// is automatically connected.
+    pub trusted_networks: TrustedNetworks,
     /// Options that should be applied to tunnels of a specific type regardless of where the relays
     /// might be located.
     pub tunnel_options: TunnelOptions,
     /// Overrides for relays
     pub relay_overrides: Vec<RelayOverride>,
+    /// Relays and providers that are never selected
+    pub relay_exclusions: RelayExclusions,
     /// Whether to notify users of beta updates.
     pub show_beta_releases: bool,
+    /// Whether to record tunnel sessions in the session log.
+    pub session_log: bool,
     /// Split tunneling settings
-    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+    #[cfg(any(
+        windows,
+        target_os = "android",
+        target_os = "macos",
+        target_os = "linux"
+    ))]
     pub split_tunnel: SplitTunnelSettings,
     /// Specifies settings schema version
     pub settings_version: SettingsVersion,
@@ -161,17 +198,43 @@ pub struct Recents {
     pub entries: Vec<Constraint<LocationConstraint>>,
 }
 
-#[cfg(any(windows, target_os = "android", target_os = "macos"))]
+#[cfg(any(
+    windows,
+    target_os = "android",
+    target_os = "macos",
+    target_os = "linux"
+))]
 #[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 pub struct SplitTunnelSettings {
     /// Toggles split tunneling on or off
     pub enable_exclusions: bool,
     /// Set of applications to exclude from the tunnel.
     pub apps: HashSet<SplitApp>,
+    /// Whether `apps` are excluded from the tunnel, or are the only apps that use it.
+    /// Only [`SplitTunnelMode::Exclude`] is supported on platforms other than Linux.
+    pub mode: SplitTunnelMode,
+}
+
+#[cfg(any(
+    windows,
+    target_os = "android",
+    target_os = "macos",
+    target_os = "linux"
+))]
+impl SplitTunnelSettings {
+    /// Return the mode that should be enforced. While split tunneling is disabled, all traffic
+    /// uses the tunnel, regardless of `mode`.
+    pub fn effective_mode(&self) -> SplitTunnelMode {
+        if self.enable_exclusions {
+            self.mode
+        } else {
+            SplitTunnelMode::Exclude
+        }
+    }
 }
 
 /// An application whose traffic should be excluded from any active tunnel.
-#[cfg(any(windows, target_os = "macos"))]
+#[cfg(any(windows, target_os = "macos", target_os = "linux"))]
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct SplitApp(std::path::PathBuf);
 
@@ -180,7 +243,7 @@ pub struct SplitApp(std::path::PathBuf);
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub struct SplitApp(String);
 
-#[cfg(any(windows, target_os = "macos"))]
+#[cfg(any(windows, target_os = "macos", target_os = "linux"))]
 impl SplitApp {
     /// Convert the underlying path to a [`String`].
     /// This function will fail if the underlying path string is not valid UTF-8. See
@@ -197,6 +260,10 @@ impl SplitApp {
     pub fn display(&self) -> std::path::Display<'_> {
         self.0.display()
     }
+
+    pub fn as_path(&self) -> &std::path::Path {
+        &self.0
+    }
 }
 
 #[cfg(target_os = "android")]
@@ -216,14 +283,14 @@ impl SplitApp {
     }
 }
 
-#[cfg(any(windows, target_os = "macos"))]
+#[cfg(any(windows, target_os = "macos", target_os = "linux"))]
 impl From<String> for SplitApp {
     fn from(value: String) -> Self {
         SplitApp::from(std::path::PathBuf::from(value))
     }
 }
 
-#[cfg(any(windows, target_os = "macos"))]
+#[cfg(any(windows, target_os = "macos", target_os = "linux"))]
 impl From<std::path::PathBuf> for SplitApp {
     fn from(value: std::path::PathBuf) -> Self {
         SplitApp(value)
@@ -260,15 +327,24 @@ impl Default for Settings {
                 ..Default::default()
             },
             custom_lists: CustomListsSettings::default(),
+            custom_endpoints: CustomEndpointsSettings::default(),
             api_access_methods: access_method::Settings::default(),
             allow_lan: false,
             #[cfg(not(target_os = "android"))]
             lockdown_mode: false,
             auto_connect: false,
+            trusted_networks: TrustedNetworks::default(),
             tunnel_options: TunnelOptions::default(),
             relay_overrides: vec![],
+            relay_exclusions: RelayExclusions::default(),
             show_beta_releases: false,
-            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+            session_log: false,
+            #[cfg(any(
+                windows,
+                target_os = "android",
+                target_os = "macos",
+                target_os = "linux"
+            ))]
             split_tunnel: SplitTunnelSettings::default(),
             settings_version: CURRENT_SETTINGS_VERSION,
             recents: Some(Recents::default()),
@@ -380,9 +456,15 @@ pub struct TunnelOptions {
     pub generic: GenericTunnelOptions,
     /// DNS options.
     pub dns_options: DnsOptions,
+    /// When to reconnect to a different exit relay, if ever.
+    pub relay_rotation: Option<RotationPolicy>,
 }
 
-pub use dns::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
+pub use dns::{
+    CustomDnsOptions, DefaultDnsOptions, DnsDomainRule, DnsEncryption, DnsOptions, DnsState,
+    InvalidDnsEncryptionError, InvalidDomainError,
+};
+pub use trusted_networks::{InvalidMacAddressError, TrustedNetwork, TrustedNetworks};
 
 impl Default for TunnelOptions {
     fn default() -> Self {
@@ -393,6 +475,7 @@ impl Default for TunnelOptions {
                 enable_ipv6: cfg!(target_os = "android") || cfg!(target_os = "macos"),
             },
             dns_options: DnsOptions::default(),
+            relay_rotation: None,
         }
     }
 }
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use talpid_types::net::NetworkInfo;
+
+/// Networks on which the tunnel should not be used. When enabled, the daemon disconnects when
+/// the host joins a trusted network, and connects when it joins any other network.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(default)]
+pub struct TrustedNetworks {
+    pub enabled: bool,
+    pub rules: Vec<TrustedNetwork>,
+}
+
+impl TrustedNetworks {
+    /// Return whether `network` matches any of the rules. This is always false if the rules are
+    /// disabled.
+    pub fn is_trusted(&self, network: &NetworkInfo) -> bool {
+        self.enabled && self.rules.iter().any(|rule| rule.matches(network))
+    }
+}
+
+/// A rule that identifies a trusted network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustedNetwork {
+    /// Wi-Fi network with the given SSID
+    Ssid(String),
+    /// Network whose default gateway has the given MAC address
+    GatewayMac(String),
+    /// Network reached through the given interface
+    Interface(String),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid MAC address: {0}")]
+pub struct InvalidMacAddressError(String);
+
+impl TrustedNetwork {
+    /// Create a rule matching a gateway MAC address, such as `aa:bb:cc:dd:ee:ff`. The address is
+    /// normalized to lowercase and `-` separators are accepted.
+    pub fn gateway_mac(mac: &str) -> Result<Self, InvalidMacAddressError> {
+        let normalized = mac.replace('-', ":").to_ascii_lowercase();
+        let octets: Vec<&str> = normalized.split(':').collect();
+        let is_valid = octets.len() == 6
+            && octets
+                .iter()
+                .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()));
+        if !is_valid {
+            return Err(InvalidMacAddressError(mac.to_owned()));
+        }
+        Ok(TrustedNetwork::GatewayMac(normalized))
+    }
+
+    /// Return whether `network` is identified by this rule
+    pub fn matches(&self, network: &NetworkInfo) -> bool {
+        let matches = |expected: &str, actual: &Option<String>| actual.as_deref() == Some(expected);
+        match self {
+            TrustedNetwork::Ssid(ssid) => matches(ssid, &network.ssid),
+            TrustedNetwork::GatewayMac(mac) => matches(mac, &network.gateway_mac),
+            TrustedNetwork::Interface(interface) => matches(interface, &network.interface),
+        }
+    }
+}
+
+impl fmt::Display for TrustedNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrustedNetwork::Ssid(ssid) => write!(f, "SSID {ssid}"),
+            TrustedNetwork::GatewayMac(mac) => write!(f, "gateway MAC {mac}"),
+            TrustedNetwork::Interface(interface) => write!(f, "interface {interface}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TrustedNetwork, TrustedNetworks};
+    use talpid_types::net::NetworkInfo;
+
+    #[test]
+    fn test_trusted_network_rules() {
+        let office = NetworkInfo {
+            interface: Some("wlp3s0".to_owned()),
+            ssid: Some("Office".to_owned()),
+            gateway_mac: Some("aa:bb:cc:dd:ee:ff".to_owned()),
+        };
+        let hotel = NetworkInfo {
+            interface: Some("wlp3s0".to_owned()),
+            ssid: Some("Hotel".to_owned()),
+            gateway_mac: Some("11:22:33:44:55:66".to_owned()),
+        };
+
+        let mut trusted = TrustedNetworks {
+            enabled: true,
+            rules: vec![TrustedNetwork::Ssid("Office".to_owned())],
+        };
+        assert!(trusted.is_trusted(&office));
+        assert!(!trusted.is_trusted(&hotel));
+        assert!(!trusted.is_trusted(&NetworkInfo::default()));
+
+        trusted.rules = vec![TrustedNetwork::gateway_mac("AA-BB-CC-DD-EE-FF").unwrap()];
+        assert!(trusted.is_trusted(&office));
+        assert!(!trusted.is_trusted(&hotel));
+
+        trusted.enabled = false;
+        assert!(!trusted.is_trusted(&office));
+    }
+
+    #[test]
+    fn test_invalid_gateway_mac() {
+        for mac in ["", "aa:bb:cc:dd:ee", "aa:bb:cc:dd:ee:fg", "aabbccddeeff"] {
+            TrustedNetwork::gateway_mac(mac).expect_err(mac);
+        }
+    }
+}
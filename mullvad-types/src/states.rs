@@ -1,4 +1,4 @@
-use crate::{features::FeatureIndicators, location::GeoIpLocation};
+use crate::{features::FeatureIndicators, location::GeoIpLocation, relay_rotation::Rotation};
 use either::Either;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -79,6 +79,8 @@ pub enum TunnelState {
         endpoint: TunnelEndpoint,
         location: Option<GeoIpLocation>,
         feature_indicators: FeatureIndicators,
+        /// Set if the tunnel is reconnecting because of the relay rotation policy.
+        rotation: Option<Rotation>,
     },
     Connected {
         endpoint: TunnelEndpoint,
@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use talpid_types::net::wireguard::{DaitaStats, PeerStats, PublicKey};
+
+/// Traffic statistics for the active tunnel.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TunnelStats {
+    /// One entry per peer. Empty if there is no active tunnel.
+    pub peers: Vec<PeerTrafficStats>,
+}
+
+/// Traffic statistics for a single tunnel peer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerTrafficStats {
+    pub public_key: PublicKey,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// Average receive rate since the previous sample.
+    pub rx_bytes_per_second: u64,
+    /// Average transmit rate since the previous sample.
+    pub tx_bytes_per_second: u64,
+    /// Time elapsed since the last successful handshake.
+    pub last_handshake_age: Option<Duration>,
+    pub daita: Option<DaitaTrafficStats>,
+}
+
+/// Bytes added by DAITA to a peer's traffic.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaitaTrafficStats {
+    pub tx_padding_bytes: u64,
+    pub tx_decoy_packet_bytes: u64,
+    pub rx_padding_bytes: u64,
+    pub rx_decoy_packet_bytes: u64,
+}
+
+impl From<DaitaStats> for DaitaTrafficStats {
+    fn from(stats: DaitaStats) -> Self {
+        DaitaTrafficStats {
+            tx_padding_bytes: stats.tx_padding_bytes,
+            tx_decoy_packet_bytes: stats.tx_decoy_packet_bytes,
+            rx_padding_bytes: stats.rx_padding_bytes,
+            rx_decoy_packet_bytes: stats.rx_decoy_packet_bytes,
+        }
+    }
+}
+
+impl TunnelStats {
+    /// Build traffic statistics from the raw peer counters in `current`. Throughput is computed
+    /// from the difference to `previous`, which was sampled `elapsed` ago. Peers that are missing
+    /// from `previous` report a throughput of zero.
+    pub fn from_samples(
+        current: Vec<PeerStats>,
+        previous: &[PeerStats],
+        elapsed: Duration,
+        now: std::time::SystemTime,
+    ) -> Self {
+        let peers = current
+            .into_iter()
+            .map(|peer| {
+                let previous = previous
+                    .iter()
+                    .find(|prev| prev.public_key == peer.public_key);
+                let rate = |current: u64, previous: Option<u64>| {
+                    let millis = elapsed.as_millis();
+                    match previous {
+                        Some(previous) if millis > 0 => {
+                            let delta = u128::from(current.saturating_sub(previous));
+                            u64::try_from(delta * 1000 / millis).unwrap_or(u64::MAX)
+                        }
+                        _ => 0,
+                    }
+                };
+                PeerTrafficStats {
+                    rx_bytes_per_second: rate(peer.rx_bytes, previous.map(|p| p.rx_bytes)),
+                    tx_bytes_per_second: rate(peer.tx_bytes, previous.map(|p| p.tx_bytes)),
+                    last_handshake_age: peer
+                        .last_handshake
+                        .map(|time| now.duration_since(time).unwrap_or(Duration::ZERO)),
+                    daita: peer.daita.map(DaitaTrafficStats::from),
+                    public_key: peer.public_key,
+                    rx_bytes: peer.rx_bytes,
+                    tx_bytes: peer.tx_bytes,
+                }
+            })
+            .collect();
+        TunnelStats { peers }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn peer(rx_bytes: u64, tx_bytes: u64) -> PeerStats {
+        PeerStats {
+            public_key: PublicKey::from([1u8; 32]),
+            tx_bytes,
+            rx_bytes,
+            last_handshake: Some(SystemTime::UNIX_EPOCH),
+            daita: None,
+        }
+    }
+
+    #[test]
+    fn test_throughput_from_samples() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(5);
+        let stats = TunnelStats::from_samples(
+            vec![peer(3000, 1500)],
+            &[peer(1000, 500)],
+            Duration::from_secs(2),
+            now,
+        );
+
+        assert_eq!(stats.peers.len(), 1);
+        assert_eq!(stats.peers[0].rx_bytes_per_second, 1000);
+        assert_eq!(stats.peers[0].tx_bytes_per_second, 500);
+        assert_eq!(
+            stats.peers[0].last_handshake_age,
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn test_throughput_without_previous_sample() {
+        let stats = TunnelStats::from_samples(
+            vec![peer(3000, 1500)],
+            &[],
+            Duration::from_secs(1),
+            SystemTime::UNIX_EPOCH,
+        );
+
+        assert_eq!(stats.peers[0].rx_bytes_per_second, 0);
+        assert_eq!(stats.peers[0].tx_bytes_per_second, 0);
+    }
+}
@@ -0,0 +1,578 @@
+//! Parsing and formatting of WireGuard configuration files in the format used by `wg-quick`.
+
+use crate::{CustomTunnelEndpoint, relay_constraints::RelaySettings, settings::Settings};
+use ipnetwork::IpNetwork;
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+};
+use talpid_types::net::wireguard::{
+    ConnectionConfig, PeerConfig, PresharedKey, PrivateKey, PublicKey, TunnelConfig,
+};
+
+/// Keys that `wg-quick` accepts but which have no equivalent in the app. These are skipped when
+/// parsing, and reported in [`WgQuickConfig::ignored_keys`].
+const IGNORED_KEYS: &[&str] = &[
+    "ListenPort",
+    "FwMark",
+    "Table",
+    "SaveConfig",
+    "PreUp",
+    "PostUp",
+    "PreDown",
+    "PostDown",
+    "PersistentKeepalive",
+];
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("Line {0}: Expected a section header or a key-value pair")]
+    Syntax(usize),
+
+    #[error("Line {0}: Unknown section '{1}'")]
+    UnknownSection(usize, String),
+
+    #[error("Line {0}: Unknown key '{1}'")]
+    UnknownKey(usize, String),
+
+    #[error("Line {0}: Key is outside of a section")]
+    KeyOutsideSection(usize),
+
+    #[error("Line {line}: Invalid value for {key}: {value}")]
+    InvalidValue {
+        line: usize,
+        key: &'static str,
+        value: String,
+    },
+
+    #[error("Missing [{0}] section")]
+    MissingSection(&'static str),
+
+    #[error("Missing {1} in [{0}] section")]
+    MissingKey(&'static str, &'static str),
+
+    #[error("Only a single [Peer] section is supported")]
+    MultiplePeers,
+}
+
+/// A WireGuard configuration with a single peer, as read by `wg-quick`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgQuickConfig {
+    pub private_key: PrivateKey,
+    /// Addresses of the local tunnel interface.
+    pub addresses: Vec<IpNetwork>,
+    /// DNS servers to use while connected. Search domains are not supported and are skipped.
+    pub dns: Vec<IpAddr>,
+    pub mtu: Option<u16>,
+    pub peer: WgQuickPeer,
+    /// Recognized keys that were skipped since they have no equivalent in the app.
+    pub ignored_keys: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WgQuickPeer {
+    pub public_key: PublicKey,
+    pub preshared_key: Option<PresharedKey>,
+    pub allowed_ips: Vec<IpNetwork>,
+    /// Hostname or IP address of the peer.
+    pub host: String,
+    pub port: u16,
+}
+
+impl WgQuickConfig {
+    /// Create a configuration for the tunnel `config`, which was set up using `settings`. The
+    /// host name of custom relays is kept, while Mullvad relays are referred to by IP address.
+    ///
+    /// Returns `None` for multihop tunnels, which cannot be expressed in the wg-quick format.
+    pub fn export(
+        config: &ConnectionConfig,
+        settings: &Settings,
+        dns: Vec<IpAddr>,
+    ) -> Option<Self> {
+        if config.exit_peer.is_some() {
+            return None;
+        }
+        let custom_endpoint = match &settings.relay_settings {
+            RelaySettings::CustomTunnelEndpoint(endpoint) => Some(endpoint),
+            RelaySettings::SavedCustomEndpoints => settings
+                .custom_endpoints
+                .iter()
+                .map(|saved| &saved.endpoint)
+                .find(|endpoint| endpoint.config.peer.public_key == config.peer.public_key),
+            RelaySettings::Normal(_) => None,
+        };
+        let host = custom_endpoint
+            .map(|endpoint| endpoint.host.clone())
+            .unwrap_or_else(|| config.peer.endpoint.ip().to_string());
+        let mtu = settings.tunnel_options.wireguard.mtu;
+        Some(Self::from_connection_config(config, host, dns, mtu))
+    }
+
+    /// Create a configuration that connects to the peer of `config`. `host` is used as the
+    /// address of the peer.
+    pub fn from_connection_config(
+        config: &ConnectionConfig,
+        host: String,
+        dns: Vec<IpAddr>,
+        mtu: Option<u16>,
+    ) -> Self {
+        let addresses = config
+            .tunnel
+            .addresses
+            .iter()
+            .map(|address| IpNetwork::from(*address))
+            .collect();
+        WgQuickConfig {
+            private_key: config.tunnel.private_key.clone(),
+            addresses,
+            dns,
+            mtu,
+            peer: WgQuickPeer {
+                public_key: config.peer.public_key.clone(),
+                preshared_key: config.peer.psk.clone(),
+                allowed_ips: config.peer.allowed_ips.clone(),
+                host,
+                port: config.peer.endpoint.port(),
+            },
+            ignored_keys: vec![],
+        }
+    }
+
+    /// Convert the configuration to a custom tunnel endpoint. `wg-quick` configurations do not
+    /// specify any gateway, so it must be provided separately.
+    pub fn to_custom_tunnel_endpoint(
+        &self,
+        ipv4_gateway: Ipv4Addr,
+        ipv6_gateway: Option<Ipv6Addr>,
+    ) -> CustomTunnelEndpoint {
+        let config = ConnectionConfig {
+            tunnel: TunnelConfig {
+                private_key: self.private_key.clone(),
+                addresses: self.addresses.iter().map(IpNetwork::ip).collect(),
+            },
+            peer: PeerConfig {
+                public_key: self.peer.public_key.clone(),
+                allowed_ips: self.peer.allowed_ips.clone(),
+                endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), self.peer.port),
+                psk: None,
+                constant_packet_size: false,
+            },
+            exit_peer: None,
+            ipv4_gateway,
+            ipv6_gateway,
+            #[cfg(target_os = "linux")]
+            fwmark: None,
+        };
+        CustomTunnelEndpoint {
+            host: self.peer.host.clone(),
+            config,
+            preshared_key: self.peer.preshared_key.clone(),
+            dns: self.dns.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Interface,
+    Peer,
+}
+
+#[derive(Default)]
+struct PartialConfig {
+    private_key: Option<PrivateKey>,
+    addresses: Vec<IpNetwork>,
+    dns: Vec<IpAddr>,
+    mtu: Option<u16>,
+    public_key: Option<PublicKey>,
+    preshared_key: Option<PresharedKey>,
+    allowed_ips: Vec<IpNetwork>,
+    endpoint: Option<(String, u16)>,
+    ignored_keys: Vec<String>,
+}
+
+impl FromStr for WgQuickConfig {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut section = None;
+        let mut has_interface = false;
+        let mut has_peer = false;
+        let mut config = PartialConfig::default();
+
+        for (index, line) in s.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim();
+                if name.eq_ignore_ascii_case("Interface") {
+                    section = Some(Section::Interface);
+                    has_interface = true;
+                } else if name.eq_ignore_ascii_case("Peer") {
+                    if has_peer {
+                        return Err(Error::MultiplePeers);
+                    }
+                    section = Some(Section::Peer);
+                    has_peer = true;
+                } else {
+                    return Err(Error::UnknownSection(line_number, name.to_owned()));
+                }
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or(Error::Syntax(line_number))?;
+            let (key, value) = (key.trim(), value.trim());
+            let section = section.ok_or(Error::KeyOutsideSection(line_number))?;
+            config.parse_key(section, line_number, key, value)?;
+        }
+
+        if !has_interface {
+            return Err(Error::MissingSection("Interface"));
+        }
+        if !has_peer {
+            return Err(Error::MissingSection("Peer"));
+        }
+        let (host, port) = config
+            .endpoint
+            .ok_or(Error::MissingKey("Peer", "Endpoint"))?;
+
+        Ok(WgQuickConfig {
+            private_key: config
+                .private_key
+                .ok_or(Error::MissingKey("Interface", "PrivateKey"))?,
+            addresses: config.addresses,
+            dns: config.dns,
+            mtu: config.mtu,
+            peer: WgQuickPeer {
+                public_key: config
+                    .public_key
+                    .ok_or(Error::MissingKey("Peer", "PublicKey"))?,
+                preshared_key: config.preshared_key,
+                allowed_ips: config.allowed_ips,
+                host,
+                port,
+            },
+            ignored_keys: config.ignored_keys,
+        })
+    }
+}
+
+impl PartialConfig {
+    fn parse_key(
+        &mut self,
+        section: Section,
+        line: usize,
+        key: &str,
+        value: &str,
+    ) -> Result<(), Error> {
+        fn parse<T: FromStr>(line: usize, key: &'static str, value: &str) -> Result<T, Error> {
+            value.parse().map_err(|_| Error::InvalidValue {
+                line,
+                key,
+                value: value.to_owned(),
+            })
+        }
+        fn parse_list<T: FromStr>(
+            line: usize,
+            key: &'static str,
+            value: &str,
+        ) -> Result<Vec<T>, Error> {
+            list_items(value)
+                .map(|item| parse(line, key, item))
+                .collect()
+        }
+        fn parse_key_with<T, E>(
+            line: usize,
+            key: &'static str,
+            value: &str,
+            from_base64: impl FnOnce(&str) -> Result<T, E>,
+        ) -> Result<T, Error> {
+            from_base64(value).map_err(|_| Error::InvalidValue {
+                line,
+                key,
+                value: value.to_owned(),
+            })
+        }
+
+        match (section, key.to_ascii_lowercase().as_str()) {
+            (Section::Interface, "privatekey") => {
+                self.private_key = Some(parse_key_with(
+                    line,
+                    "PrivateKey",
+                    value,
+                    PrivateKey::from_base64,
+                )?);
+            }
+            (Section::Interface, "address") => {
+                self.addresses
+                    .extend(parse_list::<IpNetwork>(line, "Address", value)?);
+            }
+            (Section::Interface, "dns") => {
+                // Anything that is not an IP address is a search domain
+                self.dns
+                    .extend(list_items(value).filter_map(|item| item.parse::<IpAddr>().ok()));
+            }
+            (Section::Interface, "mtu") => {
+                self.mtu = Some(parse(line, "MTU", value)?);
+            }
+            (Section::Peer, "publickey") => {
+                self.public_key = Some(parse_key_with(
+                    line,
+                    "PublicKey",
+                    value,
+                    PublicKey::from_base64,
+                )?);
+            }
+            (Section::Peer, "presharedkey") => {
+                self.preshared_key = Some(parse_key_with(
+                    line,
+                    "PresharedKey",
+                    value,
+                    PresharedKey::from_base64,
+                )?);
+            }
+            (Section::Peer, "allowedips") => {
+                self.allowed_ips
+                    .extend(parse_list::<IpNetwork>(line, "AllowedIPs", value)?);
+            }
+            (Section::Peer, "endpoint") => {
+                self.endpoint = Some(parse_endpoint(value).ok_or_else(|| Error::InvalidValue {
+                    line,
+                    key: "Endpoint",
+                    value: value.to_owned(),
+                })?);
+            }
+            _ => {
+                let ignored = IGNORED_KEYS
+                    .iter()
+                    .find(|ignored| ignored.eq_ignore_ascii_case(key))
+                    .ok_or_else(|| Error::UnknownKey(line, key.to_owned()))?;
+                self.ignored_keys.push(ignored.to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn list_items(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Parse `host:port`, where `host` may be a bracketed IPv6 address.
+fn parse_endpoint(value: &str) -> Option<(String, u16)> {
+    let (host, port) = value.rsplit_once(':')?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_owned(), port.parse().ok()?))
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for WgQuickConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "[Interface]")?;
+        writeln!(f, "PrivateKey = {}", self.private_key.to_base64())?;
+        if !self.addresses.is_empty() {
+            writeln!(f, "Address = {}", join(&self.addresses))?;
+        }
+        if !self.dns.is_empty() {
+            writeln!(f, "DNS = {}", join(&self.dns))?;
+        }
+        if let Some(mtu) = self.mtu {
+            writeln!(f, "MTU = {mtu}")?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "[Peer]")?;
+        writeln!(f, "PublicKey = {}", self.peer.public_key)?;
+        if let Some(psk) = &self.peer.preshared_key {
+            writeln!(f, "PresharedKey = {}", psk.to_base64())?;
+        }
+        if !self.peer.allowed_ips.is_empty() {
+            writeln!(f, "AllowedIPs = {}", join(&self.peer.allowed_ips))?;
+        }
+        match self.peer.host.parse::<Ipv6Addr>() {
+            Ok(ip) => writeln!(f, "Endpoint = [{ip}]:{}", self.peer.port),
+            Err(_) => writeln!(f, "Endpoint = {}:{}", self.peer.host, self.peer.port),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: &str = "\
+[Interface]
+# Self-hosted gateway
+PrivateKey = mPsU8fB9aqGFVYPiNVlWd8TmXBlnA5xHBjjdsYvz4mw=
+Address = 10.64.0.2/32, fc00:bbbb:bbbb:bb01::2/128
+DNS = 10.64.0.1, corp.example
+MTU = 1380
+PostUp = iptables -A FORWARD -i %i -j ACCEPT
+
+[Peer]
+PublicKey = BLNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=
+PresharedKey = oHHfyPuCFFZm1I0JIzfLFNXQVNz4eVfQsVLu6DcaT4I=
+AllowedIPs = 0.0.0.0/0, ::/0
+Endpoint = gateway.example:51820
+PersistentKeepalive = 25
+";
+
+    #[test]
+    fn test_parse() {
+        let config: WgQuickConfig = CONFIG.parse().unwrap();
+        assert_eq!(
+            config.addresses,
+            vec![
+                "10.64.0.2/32".parse::<IpNetwork>().unwrap(),
+                "fc00:bbbb:bbbb:bb01::2/128".parse().unwrap()
+            ]
+        );
+        assert_eq!(config.dns, vec![IpAddr::from([10, 64, 0, 1])]);
+        assert_eq!(config.mtu, Some(1380));
+        assert_eq!(config.peer.host, "gateway.example");
+        assert_eq!(config.peer.port, 51820);
+        assert!(config.peer.preshared_key.is_some());
+        assert_eq!(config.peer.allowed_ips.len(), 2);
+        assert_eq!(config.ignored_keys, vec!["PostUp", "PersistentKeepalive"]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let mut config: WgQuickConfig = CONFIG.parse().unwrap();
+        config.ignored_keys.clear();
+        let formatted = config.to_string();
+        assert_eq!(formatted.parse::<WgQuickConfig>().unwrap(), config);
+    }
+
+    /// Connection config of a tunnel to the peer in [CONFIG].
+    fn connection_config() -> ConnectionConfig {
+        let config: WgQuickConfig = CONFIG.parse().unwrap();
+        let mut connection = config
+            .to_custom_tunnel_endpoint(Ipv4Addr::new(10, 64, 0, 1), None)
+            .config;
+        connection.set_ip(IpAddr::from([192, 0, 2, 1]));
+        connection
+    }
+
+    #[test]
+    fn test_from_connection_config() {
+        let mut connection = connection_config();
+        connection.peer.psk = Some(PresharedKey::from([7; 32]));
+        let dns = vec![IpAddr::from([10, 64, 0, 1])];
+
+        let config = WgQuickConfig::from_connection_config(
+            &connection,
+            "gateway.example".to_owned(),
+            dns.clone(),
+            Some(1380),
+        );
+
+        assert_eq!(config.private_key, connection.tunnel.private_key);
+        assert_eq!(
+            config.addresses,
+            vec![
+                "10.64.0.2/32".parse::<IpNetwork>().unwrap(),
+                "fc00:bbbb:bbbb:bb01::2/128".parse().unwrap()
+            ]
+        );
+        assert_eq!(config.dns, dns);
+        assert_eq!(config.mtu, Some(1380));
+        assert_eq!(config.peer.public_key, connection.peer.public_key);
+        assert_eq!(config.peer.preshared_key, connection.peer.psk);
+        assert_eq!(config.peer.allowed_ips, connection.peer.allowed_ips);
+        assert_eq!(config.peer.host, "gateway.example");
+        assert_eq!(config.peer.port, 51820);
+
+        // The exported file can be imported again
+        let imported: WgQuickConfig = config.to_string().parse().unwrap();
+        assert_eq!(imported, config);
+        let endpoint = imported.to_custom_tunnel_endpoint(connection.ipv4_gateway, None);
+        assert_eq!(endpoint.config.tunnel, connection.tunnel);
+        assert_eq!(endpoint.preshared_key, connection.peer.psk);
+        assert_eq!(endpoint.dns, dns);
+    }
+
+    #[test]
+    fn test_export() {
+        let connection = connection_config();
+        let config: WgQuickConfig = CONFIG.parse().unwrap();
+        let endpoint = config.to_custom_tunnel_endpoint(Ipv4Addr::new(10, 64, 0, 1), None);
+
+        // Mullvad relays are referred to by IP address
+        let mut settings = Settings::default();
+        settings.tunnel_options.wireguard.mtu = Some(1280);
+        let exported = WgQuickConfig::export(&connection, &settings, vec![]).unwrap();
+        assert_eq!(exported.peer.host, "192.0.2.1");
+        assert_eq!(exported.mtu, Some(1280));
+
+        // Custom relays keep their host name
+        settings.relay_settings = RelaySettings::CustomTunnelEndpoint(endpoint.clone());
+        let exported = WgQuickConfig::export(&connection, &settings, vec![]).unwrap();
+        assert_eq!(exported.peer.host, "gateway.example");
+
+        settings.relay_settings = RelaySettings::SavedCustomEndpoints;
+        settings.custom_endpoints = vec![
+            crate::custom_endpoint::SavedCustomEndpoint::new("gateway".to_owned(), endpoint)
+                .unwrap(),
+        ]
+        .into();
+        let exported = WgQuickConfig::export(&connection, &settings, vec![]).unwrap();
+        assert_eq!(exported.peer.host, "gateway.example");
+
+        // Multihop cannot be exported
+        let mut multihop = connection.clone();
+        multihop.exit_peer = Some(connection.peer.clone());
+        assert_eq!(WgQuickConfig::export(&multihop, &settings, vec![]), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let without_peer = "[Interface]\nPrivateKey = mPsU8fB9aqGFVYPiNVlWd8TmXBlnA5xHBjjdsYvz4mw=";
+        assert_eq!(
+            without_peer.parse::<WgQuickConfig>(),
+            Err(Error::MissingSection("Peer"))
+        );
+        assert_eq!(
+            "[Interface]\nFoo = bar".parse::<WgQuickConfig>(),
+            Err(Error::UnknownKey(2, "Foo".to_owned()))
+        );
+        assert_eq!(
+            "Address = 10.0.0.1/32".parse::<WgQuickConfig>(),
+            Err(Error::KeyOutsideSection(1))
+        );
+        assert_eq!(
+            format!("{CONFIG}\n[Peer]").parse::<WgQuickConfig>(),
+            Err(Error::MultiplePeers)
+        );
+    }
+
+    #[test]
+    fn test_parse_ipv6_endpoint() {
+        assert_eq!(
+            parse_endpoint("[2001:db8::1]:51820"),
+            Some(("2001:db8::1".to_owned(), 51820))
+        );
+        assert_eq!(parse_endpoint("gateway.example"), None);
+    }
+}
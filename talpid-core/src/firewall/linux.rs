@@ -15,9 +15,12 @@ use std::{
 };
 use talpid_cgroup::v2::CGroup2;
 use talpid_tunnel::TunnelMetadata;
-use talpid_types::net::{
-    ALLOWED_LAN_MULTICAST_NETS, ALLOWED_LAN_NETS, AllowedEndpoint, AllowedTunnelTraffic, Endpoint,
-    TransportProtocol,
+use talpid_types::{
+    net::{
+        ALLOWED_LAN_MULTICAST_NETS, ALLOWED_LAN_NETS, AllowedEndpoint, AllowedTunnelTraffic,
+        Endpoint, TransportProtocol,
+    },
+    split_tunnel::SplitTunnelMode,
 };
 
 /// Priority for rules that tag split tunneling packets. Equals NF_IP_PRI_MANGLE.
@@ -109,16 +112,21 @@ pub struct Firewall {
     /// The net_cls id of the v1 cgroup used for split tunneling.
     /// This is used as a fallback to [`Self::excluded_cgroup2`] since old kernels don't support cgroups v2.
     net_cls: Option<u32>,
+    /// Whether processes in the split tunneling cgroup are excluded from the tunnel, or are the
+    /// only processes that use it.
+    split_tunnel_mode: SplitTunnelMode,
 }
 
 impl Firewall {
     /// Create a `Firewall` from a `FirewallArguments`.
     pub fn from_args(args: FirewallArguments) -> Result<Self> {
-        Firewall::new(
+        let mut firewall = Firewall::new(
             args.linux_ids.fwmark,
             args.linux_ids.excluded_cgroup2,
             args.linux_ids.net_cls,
-        )
+        )?;
+        firewall.split_tunnel_mode = args.split_tunnel_mode;
+        Ok(firewall)
     }
 
     /// Create a `Firewall`.
@@ -138,9 +146,21 @@ impl Firewall {
             fwmark,
             excluded_cgroup2,
             net_cls,
+            split_tunnel_mode: SplitTunnelMode::default(),
         })
     }
 
+    /// Set how the split tunneling cgroup is treated. This takes effect the next time a policy is
+    /// applied. Returns whether the mode changed.
+    pub fn set_split_tunnel_mode(&mut self, mode: SplitTunnelMode) -> bool {
+        if self.split_tunnel_mode != mode {
+            self.split_tunnel_mode = mode;
+            true
+        } else {
+            false
+        }
+    }
+
     /// Apply a [`FirewallPolicy`] by setting up [`TABLE_NAME`] nftable.
     pub fn apply_policy(&mut self, policy: FirewallPolicy) -> Result<()> {
         let table = Table::new(TABLE_NAME, ProtoFamily::Inet);
@@ -346,11 +366,19 @@ impl<'a> PolicyBatch<'a> {
     }
 
     /// Allow split-tunneled traffic outside the tunnel.
+    ///
+    /// In [`SplitTunnelMode::Exclude`], traffic from the split tunneling cgroup is split. In
+    /// [`SplitTunnelMode::Include`], all traffic *except* that from the cgroup is split, but only
+    /// while connected.
     fn add_split_tunneling_rules(
         &mut self,
         policy: &FirewallPolicy,
         firewall: &Firewall,
     ) -> Result<()> {
+        let Some(cmp_op) = split_tunneling_cmp_op(policy, firewall.split_tunnel_mode) else {
+            return Ok(());
+        };
+
         if cfg!(feature = "cgroup2")
             && let Some(cgroup2) = &firewall.excluded_cgroup2
         {
@@ -371,7 +399,7 @@ impl<'a> PolicyBatch<'a> {
                 //
                 // Following from 1,2,3, `socket cgroupv2 level 1` should be fine here.
                 rule.add_expr(&nft_expr!(socket cgroupv2 level 1));
-                rule.add_expr(&nft_expr!(cmp == cgroup2.inode()));
+                rule.add_expr(&expr::Cmp::new(cmp_op, cgroup2.inode()));
             })?;
         } else if let Some(net_cls) = firewall.net_cls {
             self.add_actual_split_tunneling_rules(policy, firewall.fwmark, |rule| {
@@ -379,7 +407,7 @@ impl<'a> PolicyBatch<'a> {
                 // This causes all packets sent by that process to be marked with the                                                                                        ║
                 // cgroups classid (`net_cls`), which we can reference in nftables.
                 rule.add_expr(&nft_expr!(meta cgroup));
-                rule.add_expr(&nft_expr!(cmp == net_cls));
+                rule.add_expr(&expr::Cmp::new(cmp_op, net_cls));
             })?;
         } else {
             log::warn!("no cgroups, skipping add_split_tunneling_rules");
@@ -1163,6 +1191,19 @@ fn check_ct_established(rule: &mut Rule<'_>) {
     rule.add_expr(&nft_expr!(cmp != 0u32));
 }
 
+/// Return how the split tunneling cgroup should be compared against to match traffic that is
+/// split from the tunnel, or `None` if no traffic should be split under `policy`.
+fn split_tunneling_cmp_op(policy: &FirewallPolicy, mode: SplitTunnelMode) -> Option<expr::CmpOp> {
+    match mode {
+        SplitTunnelMode::Exclude => Some(expr::CmpOp::Eq),
+        // Only the connected state has a tunnel for the included processes to use. In any other
+        // state, splitting all other traffic would leak it.
+        SplitTunnelMode::Include => {
+            matches!(policy, FirewallPolicy::Connected { .. }).then_some(expr::CmpOp::Neq)
+        }
+    }
+}
+
 fn add_verdict(rule: &mut Rule<'_>, verdict: &expr::Verdict) {
     if *ADD_COUNTERS {
         rule.add_expr(&nft_expr!(counter));
@@ -1188,3 +1229,100 @@ fn lock_down_arp_ignore_sysctl() -> io::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_dns::DnsConfig;
+    use talpid_types::net::AllowedClients;
+
+    fn policies() -> Vec<FirewallPolicy> {
+        let tunnel = TunnelMetadata {
+            interface: "wg0-mullvad".to_owned(),
+            ips: vec![Ipv4Addr::new(10, 64, 0, 2).into()],
+            ipv4_gateway: Ipv4Addr::new(10, 64, 0, 1),
+            ipv6_gateway: None,
+        };
+        vec![
+            FirewallPolicy::Connecting {
+                peer_endpoints: vec![],
+                tunnel: Some(tunnel.clone()),
+                allow_lan: false,
+                allowed_endpoint: AllowedEndpoint {
+                    endpoint: Endpoint::new(Ipv4Addr::LOCALHOST, 443, TransportProtocol::Tcp),
+                    clients: AllowedClients::Root,
+                },
+                allowed_tunnel_traffic: AllowedTunnelTraffic::All,
+            },
+            FirewallPolicy::Connected {
+                peer_endpoints: vec![],
+                tunnel: tunnel.clone(),
+                allow_lan: false,
+                dns_config: DnsConfig::default().resolve(&[tunnel.ipv4_gateway.into()]),
+            },
+            FirewallPolicy::Disconnecting { allow_lan: false },
+            FirewallPolicy::Blocked {
+                allow_lan: false,
+                allowed_endpoint: None,
+            },
+        ]
+    }
+
+    /// Split apps are excluded from the tunnel in every state.
+    #[test]
+    fn test_split_tunneling_exclude() {
+        for policy in policies() {
+            assert_eq!(
+                split_tunneling_cmp_op(&policy, SplitTunnelMode::Exclude),
+                Some(expr::CmpOp::Eq),
+                "{policy}"
+            );
+        }
+    }
+
+    /// Traffic from all other apps is split only while connected, and blocked otherwise.
+    #[test]
+    fn test_split_tunneling_include() {
+        for policy in policies() {
+            let expected = match policy {
+                FirewallPolicy::Connected { .. } => Some(expr::CmpOp::Neq),
+                _ => None,
+            };
+            assert_eq!(
+                split_tunneling_cmp_op(&policy, SplitTunnelMode::Include),
+                expected,
+                "{policy}"
+            );
+        }
+    }
+
+    /// No split tunneling rules are generated for included apps unless connected.
+    #[test]
+    fn test_include_rules_only_when_connected() {
+        let rule_count = |policy: &FirewallPolicy, firewall: &Firewall| {
+            let table = Table::new(TABLE_NAME, ProtoFamily::Inet);
+            let batch = PolicyBatch::new(&table).finalize(policy, firewall).unwrap();
+            batch.sequence_numbers().len()
+        };
+        let firewall = |net_cls, split_tunnel_mode| Firewall {
+            fwmark: 0x6d6f6c65,
+            excluded_cgroup2: None,
+            net_cls,
+            split_tunnel_mode,
+        };
+
+        for policy in policies() {
+            let without_split_tunneling =
+                rule_count(&policy, &firewall(None, SplitTunnelMode::Include));
+            let include = rule_count(&policy, &firewall(Some(0x4d56), SplitTunnelMode::Include));
+            let exclude = rule_count(&policy, &firewall(Some(0x4d56), SplitTunnelMode::Exclude));
+
+            assert!(exclude > without_split_tunneling, "{policy}");
+            if matches!(policy, FirewallPolicy::Connected { .. }) {
+                assert_eq!(include, exclude, "{policy}");
+            } else {
+                assert_eq!(include, without_split_tunneling, "{policy}");
+            }
+        }
+    }
+}
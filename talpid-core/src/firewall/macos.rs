@@ -419,14 +419,15 @@ impl Firewall {
             } => {
                 let mut rules = vec![];
 
-                for server in dns_config.tunnel_config() {
+                for (server, port) in dns_config.all_tunnel_config() {
                     rules.append(
-                        &mut self.get_allow_tunnel_dns_rules_when_connected(tunnel, *server)?,
+                        &mut self
+                            .get_allow_tunnel_dns_rules_when_connected(tunnel, server, port)?,
                     );
                 }
-                for server in dns_config.non_tunnel_config() {
+                for (server, port) in dns_config.all_non_tunnel_config() {
                     rules.append(
-                        &mut self.get_allow_local_dns_rules_when_connected(tunnel, *server)?,
+                        &mut self.get_allow_local_dns_rules_when_connected(tunnel, server, port)?,
                     );
                 }
 
@@ -509,6 +510,7 @@ impl Firewall {
         &self,
         tunnel: &TunnelMetadata,
         server: IpAddr,
+        port: u16,
     ) -> Result<Vec<pfctl::FilterRule>> {
         let mut rules = Vec::with_capacity(4);
 
@@ -520,7 +522,7 @@ impl Firewall {
             .interface(&tunnel.interface)
             .proto(pfctl::Proto::Tcp)
             .keep_state(pfctl::StatePolicy::None)
-            .to(pfctl::Endpoint::new(server, 53))
+            .to(pfctl::Endpoint::new(server, port))
             .build()?;
         rules.push(block_tunnel_tcp);
         let block_tunnel_udp = self
@@ -530,7 +532,7 @@ impl Firewall {
             .interface(&tunnel.interface)
             .proto(pfctl::Proto::Udp)
             .keep_state(pfctl::StatePolicy::None)
-            .to(pfctl::Endpoint::new(server, 53))
+            .to(pfctl::Endpoint::new(server, port))
             .build()?;
         rules.push(block_tunnel_udp);
 
@@ -542,7 +544,7 @@ impl Firewall {
             .proto(pfctl::Proto::Tcp)
             .keep_state(pfctl::StatePolicy::Keep)
             .tcp_flags(Self::get_tcp_flags())
-            .to(pfctl::Endpoint::new(server, 53))
+            .to(pfctl::Endpoint::new(server, port))
             .build()?;
         rules.push(allow_nontunnel_tcp);
         let allow_nontunnel_udp = self
@@ -551,7 +553,7 @@ impl Firewall {
             .quick(true)
             .proto(pfctl::Proto::Udp)
             .keep_state(pfctl::StatePolicy::Keep)
-            .to(pfctl::Endpoint::new(server, 53))
+            .to(pfctl::Endpoint::new(server, port))
             .build()?;
         rules.push(allow_nontunnel_udp);
 
@@ -562,6 +564,7 @@ impl Firewall {
         &self,
         tunnel: &TunnelMetadata,
         server: IpAddr,
+        port: u16,
     ) -> Result<Vec<pfctl::FilterRule>> {
         let mut rules = Vec::with_capacity(2);
 
@@ -574,7 +577,7 @@ impl Firewall {
             .proto(pfctl::Proto::Tcp)
             .keep_state(pfctl::StatePolicy::Keep)
             .tcp_flags(Self::get_tcp_flags())
-            .to(pfctl::Endpoint::new(server, 53))
+            .to(pfctl::Endpoint::new(server, port))
             .build()?;
         rules.push(allow_tunnel_tcp);
         let allow_tunnel_udp = self
@@ -583,7 +586,7 @@ impl Firewall {
             .quick(true)
             .interface(&tunnel.interface)
             .proto(pfctl::Proto::Udp)
-            .to(pfctl::Endpoint::new(server, 53))
+            .to(pfctl::Endpoint::new(server, port))
             .build()?;
         rules.push(allow_tunnel_udp);
 
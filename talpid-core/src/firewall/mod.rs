@@ -32,6 +32,8 @@ cfg_if::cfg_if! {
 use crate::tunnel_state_machine::LinuxNetworkingIdentifiers;
 #[cfg(target_os = "linux")]
 use talpid_cgroup::v2::CGroup2;
+#[cfg(target_os = "linux")]
+use talpid_types::split_tunnel::SplitTunnelMode;
 
 pub use self::imp::Error;
 
@@ -329,6 +331,10 @@ pub struct FirewallArguments {
     /// excluded from the tunnel and _leaked_ during blocked states.
     #[cfg(target_os = "linux")]
     pub linux_ids: LinuxNetworkingIdentifiers,
+    /// Whether traffic identified by `linux_ids` is excluded from the tunnel, or is the only
+    /// traffic that uses it.
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_mode: SplitTunnelMode,
 }
 
 /// State to enter during firewall init.
@@ -378,6 +384,13 @@ impl Firewall {
         self.inner.reset_policy()
     }
 
+    /// Sets how split tunneled traffic is treated by the firewall. The change takes effect the next
+    /// time a policy is applied. Returns whether the mode changed.
+    #[cfg(target_os = "linux")]
+    pub fn set_split_tunnel_mode(&mut self, mode: SplitTunnelMode) -> bool {
+        self.inner.set_split_tunnel_mode(mode)
+    }
+
     /// Sets whether the firewall should persist the blocking rules across a reboot.
     #[cfg(target_os = "windows")]
     pub fn persist(&mut self, persist: bool) {
@@ -9,6 +9,10 @@ pub mod window;
 
 mod offline;
 
+/// Identify the network that the host is connected to, and monitor it for changes.
+#[cfg(target_os = "linux")]
+pub use offline::{current_network, spawn_network_monitor};
+
 /// Split tunneling
 pub mod split_tunnel;
 
@@ -32,6 +36,19 @@ mod linux;
 #[cfg(target_os = "macos")]
 pub(crate) mod resolver;
 
+/// Returns whether DNS queries are forwarded by the local DNS resolver while connected. Features
+/// that depend on it, such as per-domain DNS rules, are unavailable otherwise.
+pub fn local_dns_resolver_enabled() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        *resolver::LOCAL_DNS_RESOLVER
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        false
+    }
+}
+
 /// Connectivity monitor for Android
 #[cfg(target_os = "android")]
 pub mod connectivity_listener;
@@ -1,10 +1,16 @@
 use futures::{StreamExt, channel::mpsc::UnboundedSender};
 use std::{
+    fs,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::Arc,
+    time::Duration,
 };
+use talpid_dbus::network_manager::NetworkManager;
 use talpid_routing::RouteManagerHandle;
-use talpid_types::{ErrorExt, net::Connectivity};
+use talpid_types::{
+    ErrorExt,
+    net::{Connectivity, NetworkInfo},
+};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -26,6 +32,11 @@ const PUBLIC_INTERNET_ADDRESS_V4: IpAddr = IpAddr::V4(Ipv4Addr::new(193, 138, 21
 const PUBLIC_INTERNET_ADDRESS_V6: IpAddr =
     IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6));
 
+/// ARP table of the host. Used to find the MAC address of the default gateway.
+const ARP_TABLE_PATH: &str = "/proc/net/arp";
+/// How often to look up the MAC address of the default gateway in the ARP table.
+const ARP_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
 impl MonitorHandle {
     pub async fn connectivity(&self) -> Connectivity {
         check_connectivity(&self.route_manager, self.fwmark).await
@@ -103,3 +114,173 @@ async fn check_connectivity(handle: &RouteManagerHandle, fwmark: Option<u32>) ->
         }
     }
 }
+
+/// Send the current [NetworkInfo] on `notify_tx` once, and then whenever it changes. The monitor
+/// stops when `notify_tx` is closed.
+pub async fn spawn_network_monitor(
+    notify_tx: UnboundedSender<NetworkInfo>,
+    route_manager: RouteManagerHandle,
+    fwmark: Option<u32>,
+) -> Result<()> {
+    let mut listener = route_manager
+        .change_listener()
+        .await
+        .map_err(Error::RouteManagerError)?;
+
+    let (mut network, mut gateway) = network_and_gateway(&route_manager, fwmark).await;
+    if notify_tx.unbounded_send(network.clone()).is_err() {
+        return Ok(());
+    }
+
+    tokio::spawn(async move {
+        // The gateway may not be in the ARP table yet when the route changes, and it may be
+        // replaced without the route changing, so the ARP table is polled as well
+        let mut arp_interval = tokio::time::interval(ARP_POLL_INTERVAL);
+        arp_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            let new_network = tokio::select! {
+                event = listener.next() => {
+                    if event.is_none() {
+                        return;
+                    }
+                    let (new_network, new_gateway) =
+                        network_and_gateway(&route_manager, fwmark).await;
+                    gateway = new_gateway;
+                    new_network
+                }
+                _ = arp_interval.tick() => {
+                    // Keep the last known MAC if the ARP entry has expired
+                    match gateway.and_then(gateway_mac) {
+                        Some(mac) => NetworkInfo {
+                            gateway_mac: Some(mac),
+                            ..network.clone()
+                        },
+                        None => continue,
+                    }
+                }
+            };
+            if new_network != network {
+                network = new_network;
+                if notify_tx.unbounded_send(network.clone()).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Identify the network used by the default route outside the tunnel.
+pub async fn current_network(handle: &RouteManagerHandle, fwmark: Option<u32>) -> NetworkInfo {
+    network_and_gateway(handle, fwmark).await.0
+}
+
+/// Identify the network used by the default route outside the tunnel, and return it along with
+/// the address of its gateway.
+async fn network_and_gateway(
+    handle: &RouteManagerHandle,
+    fwmark: Option<u32>,
+) -> (NetworkInfo, Option<IpAddr>) {
+    let route = match handle
+        .get_destination_route(PUBLIC_INTERNET_ADDRESS_V4, fwmark)
+        .await
+    {
+        Ok(Some(route)) => route,
+        Ok(None) => return (NetworkInfo::default(), None),
+        Err(err) => {
+            log::error!(
+                "{}",
+                err.display_chain_with_msg("Failed to find the default route")
+            );
+            return (NetworkInfo::default(), None);
+        }
+    };
+
+    let node = route.get_node();
+    let interface = node.get_device().map(str::to_owned);
+    let gateway = node.get_address();
+    let gateway_mac = gateway.and_then(gateway_mac);
+    let ssid = match interface.clone() {
+        Some(interface) => tokio::task::spawn_blocking(move || wifi_ssid(&interface))
+            .await
+            .ok()
+            .flatten(),
+        None => None,
+    };
+
+    let network = NetworkInfo {
+        interface,
+        ssid,
+        gateway_mac,
+    };
+    (network, gateway)
+}
+
+/// Return the SSID of the Wi-Fi network that `interface` is connected to, if NetworkManager
+/// manages it.
+fn wifi_ssid(interface: &str) -> Option<String> {
+    let network_manager = NetworkManager::new()
+        .inspect_err(|err| {
+            log::trace!(
+                "{}",
+                err.display_chain_with_msg("NetworkManager is unavailable")
+            )
+        })
+        .ok()?;
+    network_manager
+        .wifi_ssid(interface)
+        .inspect_err(|err| {
+            log::trace!(
+                "{}",
+                err.display_chain_with_msg("Failed to obtain the SSID from NetworkManager")
+            )
+        })
+        .ok()
+        .flatten()
+}
+
+/// Look up the MAC address of `gateway` in the ARP table.
+fn gateway_mac(gateway: IpAddr) -> Option<String> {
+    let IpAddr::V4(gateway) = gateway else {
+        return None;
+    };
+    let arp_table = fs::read_to_string(ARP_TABLE_PATH).ok()?;
+    find_arp_entry(&arp_table, gateway)
+}
+
+/// Find the hardware address of `address` in the contents of `/proc/net/arp`.
+fn find_arp_entry(arp_table: &str, address: Ipv4Addr) -> Option<String> {
+    // Columns: IP address, HW type, Flags, HW address, Mask, Device
+    arp_table.lines().skip(1).find_map(|line| {
+        let mut columns = line.split_whitespace();
+        let entry_address: Ipv4Addr = columns.next()?.parse().ok()?;
+        let hw_address = columns.nth(2)?;
+        (entry_address == address && hw_address != "00:00:00:00:00:00")
+            .then(|| hw_address.to_ascii_lowercase())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::find_arp_entry;
+
+    #[test]
+    fn test_find_arp_entry() {
+        let arp_table = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         AA:BB:CC:DD:EE:FF     *        wlp3s0
+192.168.1.20     0x1         0x0         00:00:00:00:00:00     *        wlp3s0
+";
+        assert_eq!(
+            find_arp_entry(arp_table, "192.168.1.1".parse().unwrap()).as_deref(),
+            Some("aa:bb:cc:dd:ee:ff")
+        );
+        assert_eq!(
+            find_arp_entry(arp_table, "192.168.1.20".parse().unwrap()),
+            None
+        );
+        assert_eq!(find_arp_entry(arp_table, "10.0.0.1".parse().unwrap()), None);
+    }
+}
@@ -22,6 +22,9 @@ mod imp;
 #[path = "android.rs"]
 mod imp;
 
+#[cfg(target_os = "linux")]
+pub use imp::{current_network, spawn_network_monitor};
+
 /// Disables offline monitor
 static FORCE_DISABLE_OFFLINE_MONITOR: LazyLock<bool> = LazyLock::new(|| {
     std::env::var("TALPID_DISABLE_OFFLINE_MONITOR")
@@ -3,11 +3,16 @@
 //!   domains receive a spoofed answer. This fools the OS into thinking that it has connectivity.
 //! * In the `Forwarding` state, queries are forwarded to a set of configured DNS servers. This
 //!   lets us use the routing table to determine where to send them, instead of them being forced
-//!   out on the primary interface (in some cases).
+//!   out on the primary interface (in some cases). Queries for domains with a [DomainRule] are
+//!   forwarded to the servers of the most specific matching rule instead. Queries to the
+//!   configured servers may be encrypted using DNS over TLS or DNS over HTTPS.
+//!
+//! The resolver can also be used to run a DNS leak test. See [ResolverHandle::leak_test].
 //!
 //! See [start_resolver](crate::resolver::start_resolver).
 
 use std::{
+    collections::HashMap,
     io, iter,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     str::FromStr,
@@ -34,7 +39,7 @@ use hickory_server::{
     resolver::{
         //ResolveError, ResolveErrorKind, TokioResolver,
         TokioResolver,
-        config::{ConnectionConfig, NameServerConfig, ResolverConfig},
+        config::{ConnectionConfig, NameServerConfig, ResolverConfig, ServerGroup},
         lookup::Lookup,
         net::{NetError, runtime::TokioRuntimeProvider},
     },
@@ -44,11 +49,15 @@ use hickory_server::{
         UpdateRequest,
     },
 };
-use rand::random_range;
+use rand::{random, random_range};
 use socket2::{Domain, Protocol, Socket, Type};
 use std::sync::LazyLock;
+use talpid_dns::{DnsEncryption, DomainRule};
 use talpid_routing::data::RouteSocketMessage;
-use talpid_types::drop_guard::{OnDrop, on_drop};
+use talpid_types::{
+    drop_guard::{OnDrop, on_drop},
+    net::dns::{DnsLeakTestQuery, DnsLeakTestReport},
+};
 use tokio::{
     net::{self, UdpSocket},
     task::JoinHandle,
@@ -113,16 +122,29 @@ const TTL_SECONDS: u32 = 3;
 /// belongs to the documentation range so should never be reachable.
 const RESOLVED_ADDR: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 1);
 
+/// Zone that DNS leak test queries are sent to. Every query is prefixed by a random label.
+const LEAK_TEST_ZONE: &str = "dnsleaktest.mullvad.net.";
+/// Number of uniquely-named queries sent during a DNS leak test.
+const LEAK_TEST_QUERY_COUNT: usize = 4;
+/// How long to wait for each DNS leak test query to be resolved.
+const LEAK_TEST_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Port that plaintext queries are forwarded to on upstream DNS servers.
+const UPSTREAM_DNS_PORT: u16 = 53;
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct LocalResolverConfig {
     /// Try to bind to a random address in the `127/8` subnet.
     pub use_random_loopback: bool,
+    /// Port to forward plaintext queries to on upstream DNS servers.
+    pub upstream_port: u16,
 }
 
 impl Default for LocalResolverConfig {
     fn default() -> Self {
         Self {
             use_random_loopback: true,
+            upstream_port: UPSTREAM_DNS_PORT,
         }
     }
 }
@@ -157,6 +179,10 @@ struct LocalResolver {
     inner_resolver: Resolver,
     /// Which IP+port the local resolver is bound to.
     bound_to: SocketAddr,
+    /// Port to forward plaintext queries to on upstream DNS servers.
+    upstream_port: u16,
+    /// Channels to notify about queries in a given zone. Used by DNS leak tests.
+    observers: Vec<(LowerName, mpsc::UnboundedSender<ObservedQuery>)>,
 }
 
 /// A message to [LocalResolver]
@@ -177,6 +203,12 @@ enum ResolverMessage {
         response_tx: oneshot::Sender<Result<Box<AuthLookup>, NetError>>,
     },
 
+    /// Report all queries for names in `zone` to `observer_tx`, until it is closed
+    ObserveQueries {
+        zone: LowerName,
+        observer_tx: mpsc::UnboundedSender<ObservedQuery>,
+    },
+
     /// Gracefully stop resolver
     Stop {
         /// Channel for the query response
@@ -184,6 +216,13 @@ enum ResolverMessage {
     },
 }
 
+/// A query received by the local resolver
+struct ObservedQuery {
+    name: LowerName,
+    /// Servers that the query is forwarded to. Empty if the query is answered locally.
+    upstream_servers: Vec<IpAddr>,
+}
+
 /// Configuration for [Resolver]
 #[derive(Debug, Default, Clone)]
 enum Config {
@@ -195,6 +234,10 @@ enum Config {
     Forwarding {
         /// Remote DNS server to use
         dns_servers: Vec<IpAddr>,
+        /// Encrypted transport to use for `dns_servers`
+        encryption: Option<DnsEncryption>,
+        /// Remote DNS servers to use for specific domains
+        domain_rules: Vec<DomainRule>,
         /// Whether to give an empty response to AAAA queries
         filter_out_aaaa: bool,
     },
@@ -207,11 +250,49 @@ enum Resolver {
     /// Forward DNS queries to a configured server
     Forwarding {
         resolver: Box<TokioResolver>,
+        dns_servers: Vec<IpAddr>,
+        domain_resolvers: Vec<DomainResolver>,
         filter_out_aaaa: bool,
     },
 }
 
+/// Forwards queries for a domain, and all of its subdomains, to specific servers
+struct DomainResolver {
+    domain: LowerName,
+    resolver: TokioResolver,
+    dns_servers: Vec<IpAddr>,
+}
+
+impl DomainResolver {
+    /// Return the most specific resolver whose domain contains `name`, if any.
+    fn find<'a>(domain_resolvers: &'a [DomainResolver], name: &LowerName) -> Option<&'a Self> {
+        domain_resolvers
+            .iter()
+            .filter(|domain_resolver| domain_resolver.domain.zone_of(name))
+            .max_by_key(|domain_resolver| domain_resolver.domain.num_labels())
+    }
+}
+
 impl Resolver {
+    /// Return the servers that `query` will be forwarded to, if any.
+    fn upstream_servers(&self, query: &LowerQuery) -> Vec<IpAddr> {
+        match self {
+            Resolver::Blocking => vec![],
+            Resolver::Forwarding {
+                filter_out_aaaa: true,
+                ..
+            } if !*NEVER_FILTER_AAAA_QUERIES && query.query_type() == RecordType::AAAA => vec![],
+            Resolver::Forwarding {
+                dns_servers,
+                domain_resolvers,
+                ..
+            } => match DomainResolver::find(domain_resolvers, query.name()) {
+                Some(domain_resolver) => domain_resolver.dns_servers.clone(),
+                None => dns_servers.clone(),
+            },
+        }
+    }
+
     pub fn resolve(
         &self,
         query: LowerQuery,
@@ -223,12 +304,17 @@ impl Resolver {
             }
             Resolver::Forwarding {
                 resolver,
+                domain_resolvers,
                 filter_out_aaaa,
+                ..
             } => {
-                let resolver = resolver.clone();
+                let resolver = match DomainResolver::find(domain_resolvers, query.name()) {
+                    Some(domain_resolver) => domain_resolver.resolver.clone(),
+                    None => (**resolver).clone(),
+                };
                 let filter_out_aaaa = *filter_out_aaaa && !*NEVER_FILTER_AAAA_QUERIES;
                 tokio::spawn(async move {
-                    let lookup = Self::resolve_forward(*resolver, query, filter_out_aaaa)
+                    let lookup = Self::resolve_forward(resolver, query, filter_out_aaaa)
                         .await
                         .map(Box::new);
                     let _ = tx.send(lookup);
@@ -310,20 +396,32 @@ impl ResolverHandle {
         self.listening_addr
     }
 
-    /// Set the DNS server to forward queries to `dns_servers`
+    /// Set the DNS server to forward queries to `dns_servers`, except for queries matching any of
+    /// the `domain_rules`.
     ///
     /// # Arguments
     ///
+    /// `encryption`: Encrypted transport to use for `dns_servers`. Queries matching a domain rule
+    ///               are always sent using plain DNS.
+    ///
     /// `filter_out_aaaa`: This causes the resolver to always return empty responses for AAAA (IPv6)
     ///                    queries. This is useful on macOS when the primary interface has IPv6
     ///                    connectivity, but the VPN tunnel does not. When this is true, and the VPN
     ///                    tunnel lacks IPv6 connectivity, programs like Firefox will resolve IPv6
     ///                    addresses and may attempt to connect to them anyway (but fail).
-    pub async fn enable_forward(&self, dns_servers: Vec<IpAddr>, filter_out_aaaa: bool) {
+    pub async fn enable_forward(
+        &self,
+        dns_servers: Vec<IpAddr>,
+        encryption: Option<DnsEncryption>,
+        domain_rules: Vec<DomainRule>,
+        filter_out_aaaa: bool,
+    ) {
         let (response_tx, response_rx) = oneshot::channel();
         let _ = self.tx.unbounded_send(ResolverMessage::SetConfig {
             new_config: Config::Forwarding {
                 dns_servers,
+                encryption,
+                domain_rules,
                 filter_out_aaaa,
             },
             response_tx,
@@ -343,6 +441,82 @@ impl ResolverHandle {
         let _ = response_rx.await;
     }
 
+    /// Run a DNS leak test.
+    ///
+    /// This sends a few uniquely-named queries through the system resolver, and checks that all
+    /// of them reach the local resolver and are forwarded to `expected_servers` only. Any query
+    /// that is resolved without passing through the local resolver, or that is forwarded
+    /// elsewhere, is reported as a mismatch.
+    pub async fn leak_test(&self, expected_servers: Vec<IpAddr>) -> DnsLeakTestReport {
+        self.leak_test_with(expected_servers, |name| async move {
+            // The test names do not exist, so resolution is expected to fail
+            let host = name.to_ascii();
+            let _ = net::lookup_host((host.as_str(), 0)).await;
+        })
+        .await
+    }
+
+    /// Run a DNS leak test, using `lookup` to resolve the test names.
+    async fn leak_test_with<F, Fut>(
+        &self,
+        expected_servers: Vec<IpAddr>,
+        lookup: F,
+    ) -> DnsLeakTestReport
+    where
+        F: Fn(Name) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        let zone = Name::from_str(LEAK_TEST_ZONE).expect("invalid leak test zone");
+        let names: Vec<Name> = (0..LEAK_TEST_QUERY_COUNT)
+            .map(|_| {
+                let label = format!("{:016x}", random::<u64>());
+                Name::from_str(&label)
+                    .and_then(|label| label.append_domain(&zone))
+                    .expect("invalid leak test name")
+            })
+            .collect();
+
+        let (observer_tx, mut observer_rx) = mpsc::unbounded();
+        let _ = self.tx.unbounded_send(ResolverMessage::ObserveQueries {
+            zone: LowerName::from(&zone),
+            observer_tx,
+        });
+
+        log::debug!("Running DNS leak test against {expected_servers:?}");
+
+        futures::future::join_all(
+            names
+                .iter()
+                .map(|name| tokio::time::timeout(LEAK_TEST_QUERY_TIMEOUT, lookup(name.clone()))),
+        )
+        .await;
+
+        // Any query that passed through the local resolver has been observed by now
+        observer_rx.close();
+        let mut observed: HashMap<LowerName, Vec<IpAddr>> = HashMap::new();
+        while let Ok(Some(query)) = observer_rx.try_next() {
+            let servers = observed.entry(query.name).or_default();
+            for server in query.upstream_servers {
+                if !servers.contains(&server) {
+                    servers.push(server);
+                }
+            }
+        }
+
+        let queries = names
+            .into_iter()
+            .map(|name| DnsLeakTestQuery {
+                upstream_servers: observed.remove(&LowerName::from(&name)),
+                name: name.to_ascii(),
+            })
+            .collect();
+
+        DnsLeakTestReport {
+            expected_servers,
+            queries,
+        }
+    }
+
     /// Gracefully shut down resolver
     pub async fn stop(self) {
         let (response_tx, response_rx) = oneshot::channel();
@@ -407,7 +581,9 @@ impl LocalResolver {
             rx: command_rx,
             dns_server_task,
             bound_to: resolver_addr,
+            upstream_port: config.upstream_port,
             inner_resolver: Resolver::Blocking,
+            observers: vec![],
         };
 
         Ok((resolver, ResolverHandle::new(command_tx, resolver_addr)))
@@ -557,8 +733,12 @@ impl LocalResolver {
                     dns_query,
                     response_tx,
                 } => {
+                    self.notify_observers(&dns_query);
                     self.inner_resolver.resolve(dns_query, response_tx);
                 }
+                ResolverMessage::ObserveQueries { zone, observer_tx } => {
+                    self.observers.push((zone, observer_tx));
+                }
                 ResolverMessage::Stop { response_tx } => {
                     stop_tx = Some(response_tx);
                     break;
@@ -574,17 +754,43 @@ impl LocalResolver {
         }
     }
 
+    /// Tell observers about a query for a name in their zone. Closed observers are removed.
+    fn notify_observers(&mut self, query: &LowerQuery) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let upstream_servers = self.inner_resolver.upstream_servers(query);
+        self.observers.retain(|(zone, observer_tx)| {
+            if !zone.zone_of(query.name()) {
+                return !observer_tx.is_closed();
+            }
+            observer_tx
+                .unbounded_send(ObservedQuery {
+                    name: query.name().clone(),
+                    upstream_servers: upstream_servers.clone(),
+                })
+                .is_ok()
+        });
+    }
+
     /// Update the current DNS config.
     fn update_config(&mut self, config: Config) -> Result<(), NetError> {
         match config {
             Config::Blocking => self.blocking(),
             Config::Forwarding {
                 mut dns_servers,
+                encryption,
+                mut domain_rules,
                 filter_out_aaaa,
             } => {
                 // make sure not to accidentally forward queries to ourselves
-                dns_servers.retain(|addr| *addr != self.bound_to.ip());
-                self.forwarding(dns_servers, filter_out_aaaa)?;
+                let bound_to = self.bound_to.ip();
+                dns_servers.retain(|addr| *addr != bound_to);
+                for rule in &mut domain_rules {
+                    rule.tunnel_config.retain(|addr| *addr != bound_to);
+                    rule.non_tunnel_config.retain(|addr| *addr != bound_to);
+                }
+                self.forwarding(dns_servers, encryption, domain_rules, filter_out_aaaa)?;
             }
         };
         Ok(())
@@ -595,35 +801,99 @@ impl LocalResolver {
         self.inner_resolver = Resolver::Blocking;
     }
 
-    /// Turn into a forwarding resolver (forward DNS queries to `dns_servers`).
+    /// Turn into a forwarding resolver (forward DNS queries to `dns_servers`, or to the servers
+    /// of a matching domain rule).
     fn forwarding(
         &mut self,
         dns_servers: Vec<IpAddr>,
+        encryption: Option<DnsEncryption>,
+        domain_rules: Vec<DomainRule>,
         filter_out_aaaa: bool,
     ) -> Result<(), NetError> {
-        // TODO: Fix port
-        let forward_server_config = dns_servers
-            .into_iter()
-            .map(|ip| {
-                let mut udp = ConnectionConfig::udp();
-                udp.port = DNS_PORT;
-                let mut tcp = ConnectionConfig::tcp();
-                tcp.port = DNS_PORT;
-                NameServerConfig::new(ip, false, vec![udp, tcp])
-            })
-            .collect();
-
-        let forward_config = ResolverConfig::from_parts(None, vec![], forward_server_config);
         let resolver =
-            TokioResolver::builder_with_config(forward_config, TokioRuntimeProvider::default())
-                .build()?;
+            Self::forward_resolver(&dns_servers, encryption.as_ref(), self.upstream_port)?;
+
+        let mut domain_resolvers = vec![];
+        for rule in domain_rules {
+            let domain = match Name::from_str(&rule.domain) {
+                Ok(domain) => LowerName::from(domain),
+                Err(error) => {
+                    log::warn!(
+                        "Ignoring DNS rule for invalid domain {}: {error}",
+                        rule.domain
+                    );
+                    continue;
+                }
+            };
+            let dns_servers: Vec<IpAddr> = rule.addresses().collect();
+            domain_resolvers.push(DomainResolver {
+                domain,
+                resolver: Self::forward_resolver(&dns_servers, None, self.upstream_port)?,
+                dns_servers,
+            });
+        }
 
         self.inner_resolver = Resolver::Forwarding {
             resolver: Box::new(resolver),
+            dns_servers,
+            domain_resolvers,
             filter_out_aaaa,
         };
         Ok(())
     }
+
+    /// Create a resolver that forwards DNS queries to `dns_servers`, optionally over an encrypted
+    /// transport. Plaintext queries are sent to `port`.
+    fn forward_resolver(
+        dns_servers: &[IpAddr],
+        encryption: Option<&DnsEncryption>,
+        port: u16,
+    ) -> Result<TokioResolver, NetError> {
+        let forward_server_config = match encryption {
+            None => dns_servers
+                .iter()
+                .map(|&ip| plaintext_name_server(ip, port))
+                .collect(),
+            Some(DnsEncryption::Tls { server_name }) => ServerGroup {
+                ips: dns_servers,
+                server_name,
+                path: "",
+            }
+            .tls()
+            .collect(),
+            Some(DnsEncryption::Https { server_name, path }) => ServerGroup {
+                ips: dns_servers,
+                server_name,
+                path,
+            }
+            .https()
+            .collect(),
+        };
+
+        let forward_config = ResolverConfig::from_parts(None, vec![], forward_server_config);
+        TokioResolver::builder_with_config(forward_config, TokioRuntimeProvider::default())
+            .with_tls_config(tls_client_config())
+            .build()
+    }
+}
+
+/// Configuration for a DNS server that is reached using plain DNS over UDP or TCP on `port`.
+fn plaintext_name_server(ip: IpAddr, port: u16) -> NameServerConfig {
+    let mut udp = ConnectionConfig::udp();
+    udp.port = port;
+    let mut tcp = ConnectionConfig::tcp();
+    tcp.port = port;
+    NameServerConfig::new(ip, false, vec![udp, tcp])
+}
+
+/// TLS configuration used to verify encrypted DNS servers
+fn tls_client_config() -> rustls::ClientConfig {
+    let root_store = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    rustls::ClientConfig::builder()
+        .with_root_certificates(root_store)
+        .with_no_client_auth()
 }
 
 /// Flush the DNS cache.
@@ -803,7 +1073,11 @@ impl RequestHandler for ResolverImpl {
 mod test {
     use super::*;
     use hickory_server::resolver::config::{NameServerConfig, ResolverConfig};
-    use std::{net::UdpSocket, sync::Mutex, thread};
+    use std::{
+        net::{Ipv6Addr, UdpSocket},
+        sync::Mutex,
+        thread,
+    };
     use typed_builder::TypedBuilder;
 
     /// Can't have multiple local resolvers running at the same time, as they will try to bind to
@@ -811,10 +1085,15 @@ mod test {
     static LOCK: Mutex<()> = Mutex::new(());
 
     async fn start_resolver() -> ResolverHandle {
+        start_resolver_with_upstream_port(UPSTREAM_DNS_PORT).await
+    }
+
+    async fn start_resolver_with_upstream_port(upstream_port: u16) -> ResolverHandle {
         // NOTE: We're disabling lo0 aliases
         super::start_resolver(LocalResolverConfig {
             // Bind resolver to 127.0.0.1
             use_random_loopback: false,
+            upstream_port,
         })
         .await
         .unwrap()
@@ -938,6 +1217,7 @@ mod test {
         let config = LocalResolverConfig {
             // Bind resolver to 127.0.0.1 so that we can easily bind to the same address here.
             use_random_loopback: false,
+            upstream_port: UPSTREAM_DNS_PORT,
         };
         let handle = rt.block_on(super::start_resolver(config)).unwrap();
         let addr = handle.listening_addr();
@@ -947,6 +1227,211 @@ mod test {
         UdpSocket::bind(addr).expect("Failed to bind to a port that should have been removed");
     }
 
+    /// A DNS server on an ephemeral port on `[::1]` that answers every query with NXDOMAIN, and
+    /// records the raw queries it receives.
+    async fn start_stub_upstream() -> (SocketAddr, Arc<Mutex<Vec<Vec<u8>>>>) {
+        let socket = net::UdpSocket::bind(SocketAddr::from((Ipv6Addr::LOCALHOST, 0)))
+            .await
+            .unwrap();
+        let addr = socket.local_addr().unwrap();
+        let received = Arc::new(Mutex::new(vec![]));
+        let received_copy = received.clone();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, src)) = socket.recv_from(&mut buf).await {
+                let mut message = buf[..len].to_vec();
+                received_copy.lock().unwrap().push(message.clone());
+                if message.len() < 4 {
+                    continue;
+                }
+                // Turn the query into a response with RCODE=NXDOMAIN
+                message[2] |= 0x80;
+                message[3] = (message[3] & 0xf0) | 3;
+                let _ = socket.send_to(&message, src).await;
+            }
+        });
+
+        (addr, received)
+    }
+
+    fn stub_received_name(received: &Mutex<Vec<Vec<u8>>>, name: &str) -> bool {
+        let label = name.split('.').next().unwrap().as_bytes();
+        received
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|message| message.windows(label.len()).any(|window| window == label))
+    }
+
+    /// Query the local resolver directly, in place of the system resolver.
+    async fn leak_test(
+        handle: &ResolverHandle,
+        expected_servers: Vec<IpAddr>,
+    ) -> DnsLeakTestReport {
+        let test_resolver = get_test_resolver(handle.listening_addr());
+        handle
+            .leak_test_with(expected_servers, |name| {
+                let test_resolver = test_resolver.clone();
+                async move {
+                    let _ = test_resolver.lookup(name, RecordType::A).await;
+                }
+            })
+            .await
+    }
+
+    #[test_log::test]
+    fn test_leak_test_forwarded_to_expected_server() {
+        let _mutex = LOCK.lock().unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        rt.block_on(async move {
+            let (upstream_addr, received) = start_stub_upstream().await;
+            let upstream = upstream_addr.ip();
+            let handle = start_resolver_with_upstream_port(upstream_addr.port()).await;
+            handle
+                .enable_forward(vec![upstream], None, vec![], false)
+                .await;
+
+            let report = leak_test(&handle, vec![upstream]).await;
+
+            assert_eq!(report.queries.len(), LEAK_TEST_QUERY_COUNT);
+            assert!(!report.is_leaking(), "unexpected mismatch: {report:?}");
+            for query in &report.queries {
+                assert!(
+                    stub_received_name(&received, &query.name),
+                    "{} did not reach the upstream server",
+                    query.name
+                );
+            }
+            handle.stop().await;
+        });
+    }
+
+    #[test_log::test]
+    fn test_leak_test_unexpected_server() {
+        let _mutex = LOCK.lock().unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        rt.block_on(async move {
+            let (upstream_addr, _received) = start_stub_upstream().await;
+            let upstream = upstream_addr.ip();
+            let handle = start_resolver_with_upstream_port(upstream_addr.port()).await;
+            handle
+                .enable_forward(vec![upstream], None, vec![], false)
+                .await;
+
+            let expected = IpAddr::from(Ipv4Addr::new(10, 64, 0, 1));
+            let report = leak_test(&handle, vec![expected]).await;
+
+            assert_eq!(report.mismatches().count(), LEAK_TEST_QUERY_COUNT);
+            for query in &report.queries {
+                assert_eq!(query.upstream_servers, Some(vec![upstream]));
+            }
+            handle.stop().await;
+        });
+    }
+
+    #[test_log::test]
+    fn test_leak_test_bypassed_resolver() {
+        let _mutex = LOCK.lock().unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        rt.block_on(async move {
+            let (upstream_addr, _received) = start_stub_upstream().await;
+            let upstream = upstream_addr.ip();
+            let handle = start_resolver_with_upstream_port(upstream_addr.port()).await;
+            handle
+                .enable_forward(vec![upstream], None, vec![], false)
+                .await;
+
+            // Pretend that the queries were resolved without using the local resolver
+            let report = handle.leak_test_with(vec![upstream], |_| async {}).await;
+
+            assert_eq!(report.mismatches().count(), LEAK_TEST_QUERY_COUNT);
+            for query in &report.queries {
+                assert_eq!(query.upstream_servers, None);
+            }
+            handle.stop().await;
+        });
+    }
+
+    /// Test that queries matching a domain rule are forwarded to the servers of that rule only.
+    #[test_log::test]
+    fn test_domain_rule_forwarding() {
+        let _mutex = LOCK.lock().unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        rt.block_on(async move {
+            let (upstream_addr, received) = start_stub_upstream().await;
+            let upstream = upstream_addr.ip();
+            let handle = start_resolver_with_upstream_port(upstream_addr.port()).await;
+            // The default server is unreachable, so only the domain rule can resolve anything
+            let default_server = IpAddr::from(Ipv4Addr::new(192, 0, 2, 1));
+            let rule = DomainRule {
+                domain: "corp.example".to_owned(),
+                tunnel_config: vec![],
+                non_tunnel_config: vec![upstream],
+            };
+            handle
+                .enable_forward(vec![default_server], None, vec![rule], false)
+                .await;
+
+            let test_resolver = get_test_resolver(handle.listening_addr());
+            for name in ["intranet.corp.example.", "external.example."] {
+                let _ = tokio::time::timeout(
+                    Duration::from_secs(1),
+                    test_resolver.lookup(name, RecordType::A),
+                )
+                .await;
+            }
+
+            assert!(stub_received_name(&received, "intranet.corp.example."));
+            assert!(!stub_received_name(&received, "external.example."));
+            handle.stop().await;
+        });
+    }
+
+    /// Test that queries are never forwarded as plain DNS when encryption is enabled, even when
+    /// the encrypted DNS server cannot be reached.
+    #[test_log::test]
+    fn test_encrypted_forwarding() {
+        let _mutex = LOCK.lock().unwrap();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+
+        rt.block_on(async move {
+            let (upstream_addr, received) = start_stub_upstream().await;
+            let upstream = upstream_addr.ip();
+            let handle = start_resolver_with_upstream_port(upstream_addr.port()).await;
+
+            for encryption in [
+                DnsEncryption::Tls {
+                    server_name: "dns.example".to_owned(),
+                },
+                DnsEncryption::Https {
+                    server_name: "dns.example".to_owned(),
+                    path: "/dns-query".to_owned(),
+                },
+            ] {
+                handle
+                    .enable_forward(vec![upstream], Some(encryption.clone()), vec![], false)
+                    .await;
+
+                let report = leak_test(&handle, vec![upstream]).await;
+
+                assert!(!report.is_leaking(), "unexpected mismatch: {report:?}");
+                for query in &report.queries {
+                    assert!(
+                        !stub_received_name(&received, &query.name),
+                        "{} was forwarded as plain DNS with {encryption}",
+                        query.name
+                    );
+                }
+            }
+            handle.stop().await;
+        });
+    }
+
     #[derive(TypedBuilder)]
     struct BindParams {
         bind_addr: SocketAddr,
@@ -0,0 +1,578 @@
+//! Monitor for new processes, used to exclude applications from the tunnel by path.
+//!
+//! Process events are received from the kernel using the netlink proc connector. Whenever a
+//! process calls `exec`, the path of its executable is compared against the set of excluded
+//! paths, and matching processes are moved into the exclusion cgroup. Since child processes
+//! inherit the cgroup of their parent, only `exec` events need to be handled.
+//!
+//! <https://www.kernel.org/doc/html/latest/driver-api/connector.html>
+
+use std::{
+    collections::HashSet,
+    ffi::OsStr,
+    fs,
+    io::{self, Read},
+    mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::{ffi::OsStrExt, fs::OpenOptionsExt},
+    },
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
+    thread,
+    time::Duration,
+};
+
+use anyhow::Context;
+use libc::pid_t;
+use nix::unistd::Pid;
+
+use super::{Error, Inner};
+
+/// `CN_IDX_PROC` from `linux/connector.h`.
+const CN_IDX_PROC: u32 = 1;
+/// `CN_VAL_PROC` from `linux/connector.h`.
+const CN_VAL_PROC: u32 = 1;
+/// `PROC_CN_MCAST_LISTEN` from `linux/cn_proc.h`.
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+/// `PROC_EVENT_EXEC` from `linux/cn_proc.h`.
+const PROC_EVENT_EXEC: u32 = 0x00000002;
+
+const NLMSG_HDR_LEN: usize = 16;
+const CN_MSG_LEN: usize = 20;
+/// Offset of `proc_event.what` in a received message.
+const WHAT_OFFSET: usize = NLMSG_HDR_LEN + CN_MSG_LEN;
+/// Offset of `proc_event.event_data.exec.process_tgid` in a received message.
+const EXEC_TGID_OFFSET: usize = WHAT_OFFSET + 20;
+
+/// How often the monitor thread checks whether it should stop.
+const RECV_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Appended to `/proc/<pid>/exe` when the executable has been removed.
+const DELETED_SUFFIX: &[u8] = b" (deleted)";
+/// `BINPRM_BUF_SIZE` from `linux/binfmts.h`. The kernel does not read more than this of a shebang
+/// line.
+const SHEBANG_MAX_LEN: usize = 256;
+
+/// Moves processes that execute any of a set of paths into the exclusion cgroup.
+///
+/// The monitor stops when this value is dropped.
+pub(super) struct ExecMonitor {
+    paths: Arc<Mutex<HashSet<PathBuf>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl ExecMonitor {
+    /// Start monitoring new processes. Processes that execute any of `paths` are added to
+    /// `cgroup`.
+    pub fn start(cgroup: Inner, paths: HashSet<PathBuf>) -> Result<Self, Error> {
+        let socket = open_proc_connector().context("Failed to open proc connector")?;
+
+        let paths = Arc::new(Mutex::new(canonicalize_paths(paths)));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_paths = paths.clone();
+        let thread_stop = stop.clone();
+        thread::Builder::new()
+            .name("split-tunnel-exec-monitor".to_owned())
+            .spawn(move || run(socket, cgroup, thread_paths, thread_stop))
+            .context("Failed to spawn exec monitor thread")?;
+
+        Ok(ExecMonitor { paths, stop })
+    }
+
+    /// Replace the set of paths that are excluded.
+    pub fn set_paths(&self, paths: HashSet<PathBuf>) {
+        *self.paths.lock().unwrap() = canonicalize_paths(paths);
+    }
+}
+
+impl Drop for ExecMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn run(socket: OwnedFd, cgroup: Inner, paths: Arc<Mutex<HashSet<PathBuf>>>, stop: Arc<AtomicBool>) {
+    let mut buffer = [0u8; 4096];
+
+    while !stop.load(Ordering::Relaxed) {
+        let pid = match recv_exec_event(&socket, &mut buffer) {
+            Ok(Some(pid)) => pid,
+            Ok(None) => continue,
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+                ) =>
+            {
+                continue;
+            }
+            Err(error) => {
+                // ENOBUFS means that events were dropped. We cannot recover them, but we can keep
+                // listening for new ones.
+                if error.raw_os_error() == Some(libc::ENOBUFS) {
+                    log::warn!("Split tunneling exec monitor dropped process events");
+                    continue;
+                }
+                log::error!("Split tunneling exec monitor failed: {error}");
+                break;
+            }
+        };
+
+        let Some(exe) = matching_path(pid, &paths.lock().unwrap()) else {
+            continue;
+        };
+
+        log::debug!("Excluding process {pid} ({})", exe.display());
+        if let Err(error) = cgroup.add(Pid::from_raw(pid)) {
+            log::error!("Failed to exclude process {pid}: {error:?}");
+        }
+    }
+
+    log::trace!("Split tunneling exec monitor stopped");
+}
+
+/// Excludes and includes running processes by path on a background thread, so that the caller is
+/// not blocked while `/proc` is scanned. Scans are performed in the order they are requested.
+///
+/// The thread stops when this value is dropped.
+pub(super) struct ProcessScanner {
+    tx: mpsc::Sender<Scan>,
+}
+
+/// A request to move running processes in or out of the exclusion cgroup.
+pub(super) struct Scan {
+    /// Processes running any of these paths are excluded.
+    pub excluded: HashSet<PathBuf>,
+    /// Processes running any of these paths are included again, unless they are in
+    /// `manual_pids`.
+    pub included: HashSet<PathBuf>,
+    /// PIDs that were excluded explicitly, rather than because of their executable path.
+    pub manual_pids: HashSet<pid_t>,
+}
+
+impl ProcessScanner {
+    pub fn start(mut cgroup: Inner) -> Result<Self, Error> {
+        let (tx, rx) = mpsc::channel::<Scan>();
+        thread::Builder::new()
+            .name("split-tunnel-process-scanner".to_owned())
+            .spawn(move || {
+                for scan in rx {
+                    scan.run(&mut cgroup);
+                }
+            })
+            .context("Failed to spawn process scanner thread")?;
+        Ok(ProcessScanner { tx })
+    }
+
+    pub fn scan(&self, scan: Scan) {
+        let _ = self.tx.send(scan);
+    }
+}
+
+impl Scan {
+    fn run(self, cgroup: &mut Inner) {
+        if !self.included.is_empty() {
+            match cgroup.list() {
+                Ok(excluded_pids) => {
+                    for pid in find_processes(&self.included) {
+                        if excluded_pids.contains(&pid)
+                            && !self.manual_pids.contains(&pid)
+                            && let Err(error) = cgroup.remove(Pid::from_raw(pid))
+                        {
+                            log::error!("Failed to include process {pid}: {error:?}");
+                        }
+                    }
+                }
+                Err(error) => log::error!("Failed to list excluded processes: {error:?}"),
+            }
+        }
+        for pid in find_processes(&self.excluded) {
+            if let Err(error) = cgroup.add(Pid::from_raw(pid)) {
+                log::error!("Failed to exclude process {pid}: {error:?}");
+            }
+        }
+    }
+}
+
+/// Return the PIDs of all running processes whose executable is in `paths`.
+fn find_processes(paths: &HashSet<PathBuf>) -> Vec<pid_t> {
+    let paths = canonicalize_paths(paths.iter().cloned());
+    if paths.is_empty() {
+        return vec![];
+    }
+
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<pid_t>().ok())
+        .filter(|pid| matching_path(*pid, &paths).is_some())
+        .collect()
+}
+
+/// Return the path in `paths` that identifies the program running in process `pid`, if any.
+fn matching_path(pid: pid_t, paths: &HashSet<PathBuf>) -> Option<PathBuf> {
+    process_paths(pid)
+        .into_iter()
+        .find(|path| paths.contains(path))
+}
+
+/// Return the paths that identify the program running in process `pid`.
+///
+/// This is the executable of the process and, if the process is running a script that was started
+/// through a shebang line, the path of the script.
+fn process_paths(pid: pid_t) -> Vec<PathBuf> {
+    let mut paths = vec![];
+    if let Ok(exe) = fs::read_link(format!("/proc/{pid}/exe")) {
+        paths.push(strip_deleted_suffix(exe));
+    }
+    if let Some(script) = process_script(pid) {
+        paths.push(script);
+    }
+    paths
+}
+
+/// If the executable of a process is replaced or removed while it is running, the kernel appends
+/// ` (deleted)` to the `exe` link. Strip it so that the process can still be matched against the
+/// path it was started from.
+fn strip_deleted_suffix(exe: PathBuf) -> PathBuf {
+    match exe.as_os_str().as_bytes().strip_suffix(DELETED_SUFFIX) {
+        Some(path) => PathBuf::from(OsStr::from_bytes(path)),
+        None => exe,
+    }
+}
+
+/// Return the canonical path of the script that process `pid` is interpreting, if it was started
+/// by executing a script with a shebang line.
+///
+/// For such processes, the kernel sets the arguments to the interpreter, the optional argument on
+/// the shebang line, and the path of the script, in that order.
+fn process_script(pid: pid_t) -> Option<PathBuf> {
+    let cmdline = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    let mut args = cmdline.split(|&byte| byte == 0);
+    let interpreter = args.next()?;
+
+    // The script is either the first or the second argument
+    let cwd = fs::read_link(format!("/proc/{pid}/cwd")).ok()?;
+    args.take(2).find_map(|arg| {
+        if arg.is_empty() {
+            return None;
+        }
+        let script = fs::canonicalize(cwd.join(OsStr::from_bytes(arg))).ok()?;
+        let header = read_script_header(&script)?;
+        let shebang = parse_shebang(&header)?;
+        shebang.runs(interpreter).then_some(script)
+    })
+}
+
+/// Read the beginning of `script`, where the shebang line would be.
+///
+/// The arguments of a process are controlled by its user, so anything but a regular file is
+/// ignored. Reading a FIFO would block, and opening a device may have side effects.
+fn read_script_header(script: &Path) -> Option<Vec<u8>> {
+    if !fs::symlink_metadata(script).ok()?.is_file() {
+        return None;
+    }
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK | libc::O_NOFOLLOW)
+        .open(script)
+        .ok()?;
+    // The file may have been replaced since it was checked
+    if !file.metadata().ok()?.is_file() {
+        return None;
+    }
+    let mut header = vec![0u8; SHEBANG_MAX_LEN];
+    let len = file.read(&mut header).ok()?;
+    header.truncate(len);
+    Some(header)
+}
+
+/// A parsed shebang line.
+#[derive(Debug, PartialEq)]
+struct Shebang<'a> {
+    /// The interpreter, e.g. `/bin/sh` for `#!/bin/sh -e`.
+    interpreter: &'a [u8],
+    /// The optional argument to the interpreter, e.g. `-e` for `#!/bin/sh -e`.
+    argument: Option<&'a [u8]>,
+}
+
+impl Shebang<'_> {
+    /// Return whether a process whose first argument is `arg0` is running the script.
+    ///
+    /// For `#!/usr/bin/env python3`, `env` replaces itself with `python3`, so the first argument
+    /// is the program that `env` was asked to run rather than the interpreter.
+    fn runs(&self, arg0: &[u8]) -> bool {
+        if self.interpreter == arg0 {
+            return true;
+        }
+        if Path::new(OsStr::from_bytes(self.interpreter)).file_name() != Some(OsStr::new("env")) {
+            return false;
+        }
+        self.argument
+            .into_iter()
+            .flat_map(|argument| argument.split(|byte| byte.is_ascii_whitespace()))
+            // Skip options, e.g. `-S`, and variable assignments, e.g. `LANG=C`
+            .find(|word| !word.is_empty() && !word.starts_with(b"-") && !word.contains(&b'='))
+            .is_some_and(|program| program == arg0)
+    }
+}
+
+/// Parse a shebang line, e.g. `#!/bin/sh -e`. Like the kernel, everything after the interpreter
+/// is treated as a single argument.
+fn parse_shebang(header: &[u8]) -> Option<Shebang<'_>> {
+    let line = header.strip_prefix(b"#!")?;
+    let line = line.split(|&byte| byte == b'\n').next()?.trim_ascii();
+    let end = line
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .unwrap_or(line.len());
+    let (interpreter, argument) = line.split_at(end);
+    if interpreter.is_empty() {
+        return None;
+    }
+    let argument = argument.trim_ascii();
+    Some(Shebang {
+        interpreter,
+        argument: (!argument.is_empty()).then_some(argument),
+    })
+}
+
+fn canonicalize_paths(paths: impl IntoIterator<Item = PathBuf>) -> HashSet<PathBuf> {
+    paths
+        .into_iter()
+        .map(|path| canonicalize(&path).unwrap_or(path))
+        .collect()
+}
+
+fn canonicalize(path: &Path) -> Option<PathBuf> {
+    fs::canonicalize(path)
+        .inspect_err(|error| log::debug!("Failed to resolve {}: {error}", path.display()))
+        .ok()
+}
+
+/// Open a netlink socket and subscribe to process events.
+fn open_proc_connector() -> io::Result<OwnedFd> {
+    // SAFETY: `socket` has no memory safety preconditions.
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_NETLINK,
+            libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+            libc::NETLINK_CONNECTOR,
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `fd` is a valid file descriptor that is not owned by anything else.
+    let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    // SAFETY: `sockaddr_nl` is a plain C struct for which all zeroes is a valid value.
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+    addr.nl_groups = CN_IDX_PROC;
+    addr.nl_pid = 0;
+
+    // SAFETY: `addr` is a valid `sockaddr_nl` and the length matches its size.
+    let result = unsafe {
+        libc::bind(
+            socket.as_raw_fd(),
+            &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+            mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let timeout = libc::timeval {
+        tv_sec: RECV_TIMEOUT.as_secs() as libc::time_t,
+        tv_usec: 0,
+    };
+    // SAFETY: `timeout` is a valid `timeval` and the length matches its size.
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_RCVTIMEO,
+            &timeout as *const libc::timeval as *const libc::c_void,
+            mem::size_of::<libc::timeval>() as libc::socklen_t,
+        )
+    };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let message = subscribe_message();
+    // SAFETY: `message` is valid for reads of `message.len()` bytes.
+    let sent = unsafe {
+        libc::send(
+            socket.as_raw_fd(),
+            message.as_ptr() as *const libc::c_void,
+            message.len(),
+            0,
+        )
+    };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(socket)
+}
+
+/// Build a netlink message that subscribes to proc connector events.
+fn subscribe_message() -> [u8; NLMSG_HDR_LEN + CN_MSG_LEN + 4] {
+    const LEN: usize = NLMSG_HDR_LEN + CN_MSG_LEN + 4;
+    let mut message = [0u8; LEN];
+
+    // struct nlmsghdr
+    message[0..4].copy_from_slice(&(LEN as u32).to_ne_bytes());
+    message[4..6].copy_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+    message[12..16].copy_from_slice(&std::process::id().to_ne_bytes());
+
+    // struct cn_msg
+    message[16..20].copy_from_slice(&CN_IDX_PROC.to_ne_bytes());
+    message[20..24].copy_from_slice(&CN_VAL_PROC.to_ne_bytes());
+    message[32..34].copy_from_slice(&4u16.to_ne_bytes());
+
+    // enum proc_cn_mcast_op
+    message[36..40].copy_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+
+    message
+}
+
+/// Receive a single message from the proc connector. Returns the PID of the process if it is an
+/// `exec` event.
+fn recv_exec_event(socket: &OwnedFd, buffer: &mut [u8]) -> io::Result<Option<pid_t>> {
+    // SAFETY: `sockaddr_nl` is a plain C struct for which all zeroes is a valid value.
+    let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    let mut addr_len = mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t;
+
+    // SAFETY: `buffer` is valid for writes of `buffer.len()` bytes, and `addr` and `addr_len`
+    // describe a valid `sockaddr_nl`.
+    let received = unsafe {
+        libc::recvfrom(
+            socket.as_raw_fd(),
+            buffer.as_mut_ptr() as *mut libc::c_void,
+            buffer.len(),
+            0,
+            &mut addr as *mut libc::sockaddr_nl as *mut libc::sockaddr,
+            &mut addr_len,
+        )
+    };
+    if received < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // Ignore anything that was not sent by the kernel
+    if addr.nl_pid != 0 {
+        return Ok(None);
+    }
+
+    Ok(parse_exec_event(&buffer[..received as usize]))
+}
+
+fn parse_exec_event(message: &[u8]) -> Option<pid_t> {
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = message.get(offset..offset + 4)?;
+        Some(u32::from_ne_bytes(bytes.try_into().ok()?))
+    };
+
+    if read_u32(WHAT_OFFSET)? != PROC_EVENT_EXEC {
+        return None;
+    }
+    let tgid = read_u32(EXEC_TGID_OFFSET)?;
+    Some(tgid as pid_t)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_exec_event() {
+        let mut message = [0u8; EXEC_TGID_OFFSET + 4];
+        message[WHAT_OFFSET..WHAT_OFFSET + 4].copy_from_slice(&PROC_EVENT_EXEC.to_ne_bytes());
+        message[EXEC_TGID_OFFSET..].copy_from_slice(&1234u32.to_ne_bytes());
+        assert_eq!(parse_exec_event(&message), Some(1234));
+
+        // PROC_EVENT_FORK
+        message[WHAT_OFFSET..WHAT_OFFSET + 4].copy_from_slice(&1u32.to_ne_bytes());
+        assert_eq!(parse_exec_event(&message), None);
+
+        assert_eq!(parse_exec_event(&message[..WHAT_OFFSET]), None);
+    }
+
+    #[test]
+    fn test_strip_deleted_suffix() {
+        assert_eq!(
+            strip_deleted_suffix(PathBuf::from("/usr/bin/app (deleted)")),
+            PathBuf::from("/usr/bin/app")
+        );
+        assert_eq!(
+            strip_deleted_suffix(PathBuf::from("/usr/bin/app")),
+            PathBuf::from("/usr/bin/app")
+        );
+    }
+
+    #[test]
+    fn test_parse_shebang() {
+        assert_eq!(
+            parse_shebang(b"#!/bin/sh\necho"),
+            Some(Shebang {
+                interpreter: b"/bin/sh",
+                argument: None,
+            })
+        );
+        assert_eq!(
+            parse_shebang(b"#! /usr/bin/env python3 -u\n"),
+            Some(Shebang {
+                interpreter: b"/usr/bin/env",
+                argument: Some(b"python3 -u"),
+            })
+        );
+        assert_eq!(parse_shebang(b"\x7fELF"), None);
+        assert_eq!(parse_shebang(b"#!\n"), None);
+    }
+
+    #[test]
+    fn test_shebang_runs() {
+        let shebang = parse_shebang(b"#!/bin/sh -e\n").unwrap();
+        assert!(shebang.runs(b"/bin/sh"));
+        assert!(!shebang.runs(b"/bin/bash"));
+
+        let shebang = parse_shebang(b"#!/usr/bin/env python3\n").unwrap();
+        assert!(shebang.runs(b"/usr/bin/env"));
+        assert!(shebang.runs(b"python3"));
+        assert!(!shebang.runs(b"python2"));
+
+        let shebang = parse_shebang(b"#!/usr/bin/env -S LANG=C python3 -u\n").unwrap();
+        assert!(shebang.runs(b"python3"));
+        assert!(!shebang.runs(b"-u"));
+    }
+
+    #[test]
+    fn test_read_script_header_ignores_fifo() {
+        let dir = std::env::temp_dir().join(format!("exec-monitor-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let fifo = dir.join("fifo");
+        nix::unistd::mkfifo(&fifo, nix::sys::stat::Mode::S_IRWXU).unwrap();
+        let fifo_header = read_script_header(&fifo);
+
+        let script = dir.join("script");
+        fs::write(&script, b"#!/bin/sh\n").unwrap();
+        let script_header = read_script_header(&script);
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(fifo_header, None);
+        assert_eq!(script_header.as_deref(), Some(&b"#!/bin/sh\n"[..]));
+    }
+}
@@ -4,10 +4,12 @@
 //! <https://docs.kernel.org/admin-guide/cgroup-v2.html>
 
 use anyhow::Context;
+use exec_monitor::{ExecMonitor, ProcessScanner, Scan};
 use libc::pid_t;
 #[cfg(feature = "cgroup2")]
 use nftnl::{Batch, Chain, Hook, MsgType, Policy, ProtoFamily, Rule, Table, nft_expr};
 use nix::unistd::Pid;
+use std::{collections::HashSet, path::PathBuf};
 use talpid_cgroup::{
     SPLIT_TUNNEL_CGROUP_NAME,
     v1::{CGroup1, NET_CLS_CLASSID},
@@ -17,6 +19,8 @@ use talpid_cgroup::{
 #[cfg(feature = "cgroup2")]
 use crate::firewall;
 
+mod exec_monitor;
+
 /// Value used to mark packets and associated connections.
 /// This should be an arbitrary but unique integer.
 pub const MARK: u32 = 0xf41;
@@ -37,6 +41,12 @@ pub enum Error {
 /// <https://docs.kernel.org/admin-guide/cgroup-v2.html>
 pub struct PidManager {
     inner: Result<Inner, Error>,
+    /// Executables whose processes are excluded from the tunnel.
+    excluded_paths: HashSet<PathBuf>,
+    /// PIDs that were excluded explicitly, rather than because of their executable path.
+    manual_pids: HashSet<pid_t>,
+    exec_monitor: Option<ExecMonitor>,
+    process_scanner: Option<ProcessScanner>,
 }
 
 enum Inner {
@@ -65,7 +75,13 @@ impl PidManager {
             log::error!("Failed to initialize split-tunneling: {e:?}");
         };
 
-        PidManager { inner }
+        PidManager {
+            inner,
+            excluded_paths: HashSet::new(),
+            manual_pids: HashSet::new(),
+            exec_monitor: None,
+            process_scanner: None,
+        }
     }
 
     fn new_inner() -> Result<Inner, Error> {
@@ -124,15 +140,17 @@ impl PidManager {
     }
 
     /// Add a PID to the cgroup2 to have it excluded from the tunnel.
-    pub fn add(&self, pid: pid_t) -> Result<(), Error> {
-        let pid = Pid::from_raw(pid);
-        self.inner()?.add(pid)
+    pub fn add(&mut self, pid: pid_t) -> Result<(), Error> {
+        self.inner()?.add(Pid::from_raw(pid))?;
+        self.manual_pids.insert(pid);
+        Ok(())
     }
 
     /// Remove a PID from the cgroup2 to have it included in the tunnel.
-    pub fn remove(&self, pid: pid_t) -> Result<(), Error> {
-        let pid = Pid::from_raw(pid);
-        self.inner()?.remove(pid)
+    pub fn remove(&mut self, pid: pid_t) -> Result<(), Error> {
+        self.inner()?.remove(Pid::from_raw(pid))?;
+        self.manual_pids.remove(&pid);
+        Ok(())
     }
 
     /// Return a list of all PIDs currently in the Cgroup excluded from the tunnel.
@@ -142,7 +160,45 @@ impl PidManager {
 
     /// Removes all PIDs from the Cgroup.
     pub fn clear(&mut self) -> Result<(), Error> {
-        self.inner_mut()?.clear()
+        self.inner_mut()?.clear()?;
+        self.manual_pids.clear();
+        Ok(())
+    }
+
+    /// Set the executables whose processes should be excluded from the tunnel.
+    ///
+    /// Running processes that match any of the paths are excluded shortly after, on a background
+    /// thread, and new processes are excluded as they are started. Processes that were excluded
+    /// because of a path that is no longer in the set are included in the tunnel again, unless they
+    /// were also excluded using [Self::add].
+    pub fn set_excluded_paths(&mut self, paths: HashSet<PathBuf>) -> Result<(), Error> {
+        if paths == self.excluded_paths {
+            return Ok(());
+        }
+
+        if paths.is_empty() {
+            self.exec_monitor = None;
+        } else if let Some(monitor) = &self.exec_monitor {
+            monitor.set_paths(paths.clone());
+        } else {
+            let cgroup = self.inner()?.try_clone()?;
+            self.exec_monitor = Some(ExecMonitor::start(cgroup, paths.clone())?);
+        }
+
+        if self.process_scanner.is_none() {
+            let cgroup = self.inner()?.try_clone()?;
+            self.process_scanner = Some(ProcessScanner::start(cgroup)?);
+        }
+        if let Some(scanner) = &self.process_scanner {
+            scanner.scan(Scan {
+                excluded: paths.clone(),
+                included: self.excluded_paths.difference(&paths).cloned().collect(),
+                manual_pids: self.manual_pids.clone(),
+            });
+        }
+
+        self.excluded_paths = paths;
+        Ok(())
     }
 
     /// Return whether it is supported/available
@@ -225,6 +281,22 @@ impl Inner {
         Ok(())
     }
 
+    /// Return a new handle to the same cgroup.
+    fn try_clone(&self) -> Result<Inner, Error> {
+        Ok(match self {
+            Inner::CGroup1(inner) => Inner::CGroup1(InnerCGroup1 {
+                root_cgroup1: inner.root_cgroup1.try_clone()?,
+                excluded_cgroup1: inner.excluded_cgroup1.try_clone()?,
+                net_cls_classid: inner.net_cls_classid,
+            }),
+            #[cfg(feature = "cgroup2")]
+            Inner::CGroup2(inner) => Inner::CGroup2(InnerCGroup2 {
+                root_cgroup2: inner.root_cgroup2.try_clone()?,
+                excluded_cgroup2: inner.excluded_cgroup2.try_clone()?,
+            }),
+        })
+    }
+
     /// Get a handle to the [CGroup2] used for split-tunneling, if any.
     ///
     /// Returns an error if cloning the cgroup fails.
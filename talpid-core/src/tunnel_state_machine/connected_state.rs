@@ -22,6 +22,11 @@ use super::{
 pub(crate) type TunnelEventsReceiver =
     Fuse<mpsc::UnboundedReceiver<(TunnelEvent, oneshot::Sender<()>)>>;
 
+/// Encrypted DNS is configured, but the local DNS resolver that implements it is not in use.
+#[derive(thiserror::Error, Debug)]
+#[error("Encrypted DNS requires the local DNS resolver")]
+struct EncryptedDnsUnsupported;
+
 /// The tunnel is up and working.
 pub struct ConnectedState {
     metadata: TunnelMetadata,
@@ -157,6 +162,17 @@ impl ConnectedState {
     fn set_dns(&self, shared_values: &mut SharedTunnelStateValues) -> Result<(), BoxedError> {
         let dns_config: ResolvedDnsConfig = Self::resolve_dns(&self.metadata, shared_values);
 
+        // Encrypted DNS is only implemented by the local DNS resolver. Fail closed rather than
+        // fall back to plain DNS.
+        if dns_config.encryption().is_some() && !crate::local_dns_resolver_enabled() {
+            return Err(BoxedError::new(EncryptedDnsUnsupported));
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        if !dns_config.domain_rules().is_empty() {
+            log::warn!("Ignoring DNS domain rules, since they require the local DNS resolver");
+        }
+
         #[cfg(not(target_os = "macos"))]
         shared_values
             .dns_monitor
@@ -168,6 +184,9 @@ impl ConnectedState {
         // DNS resolver.
         if !*LOCAL_DNS_RESOLVER {
             log::debug!("Not enabling local DNS resolver");
+            if !dns_config.domain_rules().is_empty() {
+                log::warn!("Ignoring DNS domain rules, since they require the local DNS resolver");
+            }
             shared_values
                 .dns_monitor
                 .set(&self.metadata.interface, dns_config)
@@ -184,11 +203,16 @@ impl ConnectedState {
 
             // Tell local DNS resolver to start forwarding DNS queries to whatever `dns_config`
             // specifies as DNS.
-            shared_values.runtime.block_on(
-                shared_values
-                    .filtering_resolver
-                    .enable_forward(dns_config.addresses().collect(), filter_out_aaaa),
-            );
+            let encryption = dns_config.encryption().cloned();
+            let domain_rules = dns_config.domain_rules().to_vec();
+            shared_values
+                .runtime
+                .block_on(shared_values.filtering_resolver.enable_forward(
+                    dns_config.addresses().collect(),
+                    encryption,
+                    domain_rules,
+                    filter_out_aaaa,
+                ));
         }
 
         Ok(())
@@ -287,6 +311,40 @@ impl ConnectedState {
                 let _ = tx.send(());
                 SameState(self)
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_mode(mode) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "macos")]
+            Some(TunnelCommand::DnsLeakTest(result_tx)) => {
+                if *LOCAL_DNS_RESOLVER {
+                    let expected_servers = Self::resolve_dns(&self.metadata, shared_values)
+                        .addresses()
+                        .collect();
+                    let resolver = shared_values.filtering_resolver.clone();
+                    shared_values.runtime.spawn(async move {
+                        let report = resolver.leak_test(expected_servers).await;
+                        let _ = result_tx.send(Ok(report));
+                    });
+                } else {
+                    let _ =
+                        result_tx.send(Err(talpid_types::net::dns::DnsLeakTestError::Unsupported));
+                }
+                SameState(self)
+            }
             Some(TunnelCommand::Dns(servers, complete_tx)) => {
                 let consequence = if shared_values.set_dns_config(servers) {
                     #[cfg(target_os = "android")]
@@ -15,6 +15,7 @@ use talpid_types::net::{
     AllowedClients, AllowedEndpoint, AllowedTunnelTraffic, wireguard::TunnelParameters,
 };
 use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
+use talpid_wireguard::TunnelStatsHandle;
 
 use super::connected_state::TunnelEventsReceiver;
 use super::{
@@ -147,6 +148,7 @@ impl ConnectingState {
                         &shared_values.log_dir,
                         &shared_values.resource_dir,
                         shared_values.tun_provider.clone(),
+                        shared_values.tunnel_stats.clone(),
                         &shared_values.route_manager,
                         retry_attempt,
                     );
@@ -223,12 +225,14 @@ impl ConnectingState {
             })
     }
 
+    #[expect(clippy::too_many_arguments)]
     fn start_tunnel(
         runtime: tokio::runtime::Handle,
         parameters: TunnelParameters,
         log_dir: &Option<PathBuf>,
         resource_dir: &Path,
         tun_provider: Arc<Mutex<TunProvider>>,
+        tunnel_stats: Arc<Mutex<Option<TunnelStatsHandle>>>,
         route_manager: &RouteManagerHandle,
         retry_attempt: u32,
     ) -> Self {
@@ -275,7 +279,9 @@ impl ConnectingState {
 
             let block_reason = match TunnelMonitor::start(&tunnel_parameters, &log_dir, args) {
                 Ok(monitor) => {
+                    *tunnel_stats.lock().unwrap() = Some(monitor.stats_handle());
                     let reason = Self::wait_for_tunnel_monitor(monitor, retry_attempt);
+                    *tunnel_stats.lock().unwrap() = None;
                     log::debug!("Tunnel monitor exited with block reason: {:?}", reason);
                     reason
                 }
@@ -452,6 +458,21 @@ impl ConnectingState {
                 let _ = tx.send(());
                 SameState(self)
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_mode(mode) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "macos")]
+            Some(TunnelCommand::DnsLeakTest(result_tx)) => {
+                let _ = result_tx.send(Err(talpid_types::net::dns::DnsLeakTestError::NotConnected));
+                SameState(self)
+            }
             Some(TunnelCommand::Dns(servers, complete_tx)) => {
                 let consequence = if shared_values.set_dns_config(servers) {
                     #[cfg(target_os = "android")]
@@ -26,6 +26,8 @@ impl DisconnectingState {
         tunnel_close_event: TunnelCloseEvent,
         after_disconnect: AfterDisconnect,
     ) -> (Box<dyn TunnelState>, TunnelStateTransition) {
+        // Stop handing out statistics before the tunnel monitor is torn down.
+        *shared_values.tunnel_stats.lock().unwrap() = None;
         let _ = tunnel_close_tx.send(());
         let action_after_disconnect = after_disconnect.action();
 
@@ -66,6 +68,15 @@ impl DisconnectingState {
                 shared_values.allowed_endpoint = endpoint;
                 let _ = tx.send(());
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                let _ = shared_values.set_split_tunnel_mode(mode);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "macos")]
+            Some(TunnelCommand::DnsLeakTest(result_tx)) => {
+                let _ = result_tx.send(Err(talpid_types::net::dns::DnsLeakTestError::NotConnected));
+            }
             Some(TunnelCommand::Dns(servers, complete_tx)) => {
                 let _ = shared_values.set_dns_config(servers);
                 let _ = complete_tx.send(());
@@ -30,6 +30,10 @@ use talpid_tunnel::TunnelMetadata;
 use talpid_tunnel::{TunnelEvent, tun_provider::TunProvider};
 #[cfg(target_os = "macos")]
 use talpid_types::ErrorExt;
+#[cfg(target_os = "macos")]
+use talpid_types::net::dns::{DnsLeakTestError, DnsLeakTestReport};
+#[cfg(target_os = "linux")]
+use talpid_types::split_tunnel::SplitTunnelMode;
 
 use futures::{
     StreamExt,
@@ -49,9 +53,13 @@ use std::{
 #[cfg(target_os = "android")]
 use talpid_types::{ErrorExt, android::AndroidContext};
 use talpid_types::{
-    net::{AllowedEndpoint, Connectivity, IpAvailability, wireguard::TunnelParameters},
+    net::{
+        AllowedEndpoint, Connectivity, IpAvailability,
+        wireguard::{PeerStats, TunnelParameters},
+    },
     tunnel::{ErrorStateCause, ParameterGenerationError, TunnelStateTransition},
 };
+use talpid_wireguard::TunnelStatsHandle;
 
 #[cfg(target_os = "android")]
 use crate::connectivity_listener::ConnectivityListener;
@@ -112,6 +120,10 @@ pub struct InitialTunnelState {
     /// Apps to exclude from the tunnel.
     #[cfg(target_os = "android")]
     pub exclude_paths: Vec<String>,
+    /// Whether processes in the split tunneling cgroup are excluded from the tunnel, or are the
+    /// only ones that use it.
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_mode: SplitTunnelMode,
 }
 
 /// Identifiers for various network resources that should be unique to a given instance of a tunnel
@@ -164,10 +176,12 @@ pub async fn spawn(
     let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
     let weak_command_tx = Arc::downgrade(&command_tx);
+    let tunnel_stats = Arc::new(Mutex::new(None));
 
     let init_args = TunnelStateMachineInitArgs {
         settings: initial_settings,
         command_tx: weak_command_tx,
+        tunnel_stats: tunnel_stats.clone(),
         offline_state_tx: offline_state_listener,
         tunnel_parameters_generator,
         tun_provider,
@@ -198,6 +212,7 @@ pub async fn spawn(
     Ok(TunnelStateMachineHandle {
         command_tx,
         shutdown_rx,
+        tunnel_stats,
         #[cfg(windows)]
         split_tunnel,
     })
@@ -228,6 +243,13 @@ pub enum TunnelCommand {
     /// Bypass a socket, allowing traffic to flow through outside the tunnel.
     #[cfg(target_os = "android")]
     BypassSocket(RawFd, oneshot::Sender<()>),
+    /// Set whether split tunneled processes are excluded from the tunnel or are the only ones
+    /// using it.
+    #[cfg(target_os = "linux")]
+    SplitTunnelMode(SplitTunnelMode, oneshot::Sender<()>),
+    /// Run a DNS leak test using the local resolver.
+    #[cfg(target_os = "macos")]
+    DnsLeakTest(oneshot::Sender<Result<DnsLeakTestReport, DnsLeakTestError>>),
     /// Set applications that are allowed to send and receive traffic outside of the tunnel.
     #[cfg(any(target_os = "windows", target_os = "macos"))]
     SetExcludedApps(
@@ -342,6 +364,7 @@ struct TunnelStateMachine {
 struct TunnelStateMachineInitArgs<G: TunnelParametersGenerator> {
     settings: InitialTunnelState,
     command_tx: std::sync::Weak<mpsc::UnboundedSender<TunnelCommand>>,
+    tunnel_stats: Arc<Mutex<Option<TunnelStatsHandle>>>,
     offline_state_tx: mpsc::UnboundedSender<Connectivity>,
     tunnel_parameters_generator: G,
     tun_provider: TunProvider,
@@ -392,6 +415,8 @@ impl TunnelStateMachine {
             allow_lan: args.settings.allow_lan,
             #[cfg(target_os = "linux")]
             linux_ids: args.linux_ids,
+            #[cfg(target_os = "linux")]
+            split_tunnel_mode: args.settings.split_tunnel_mode,
         };
 
         let firewall = Firewall::from_args(fw_args).map_err(Error::InitFirewallError)?;
@@ -475,6 +500,7 @@ impl TunnelStateMachine {
             allowed_endpoint: args.settings.allowed_endpoint,
             tunnel_parameters_generator: Box::new(args.tunnel_parameters_generator),
             tun_provider: Arc::new(Mutex::new(args.tun_provider)),
+            tunnel_stats: args.tunnel_stats,
             log_dir: args.log_dir,
             resource_dir: args.resource_dir,
             #[cfg(target_os = "linux")]
@@ -575,6 +601,8 @@ struct SharedTunnelStateValues {
     tunnel_parameters_generator: Box<dyn TunnelParametersGenerator>,
     /// The provider of tunnel devices.
     tun_provider: Arc<Mutex<TunProvider>>,
+    /// Statistics handle for the tunnel that is currently running, if any.
+    tunnel_stats: Arc<Mutex<Option<TunnelStatsHandle>>>,
     /// Directory to store tunnel log file.
     log_dir: Option<PathBuf>,
     /// Resource directory path.
@@ -657,6 +685,11 @@ impl SharedTunnelStateValues {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    pub fn set_split_tunnel_mode(&mut self, mode: SplitTunnelMode) -> bool {
+        self.firewall.set_split_tunnel_mode(mode)
+    }
+
     pub fn set_dns_config(&mut self, dns_config: DnsConfig) -> bool {
         if self.dns_config != dns_config {
             self.dns_config = dns_config;
@@ -790,6 +823,7 @@ trait TunnelState: Send {
 pub struct TunnelStateMachineHandle {
     command_tx: Arc<mpsc::UnboundedSender<TunnelCommand>>,
     shutdown_rx: oneshot::Receiver<()>,
+    tunnel_stats: Arc<Mutex<Option<TunnelStatsHandle>>>,
     #[cfg(windows)]
     split_tunnel: split_tunnel::SplitTunnelHandle,
 }
@@ -811,6 +845,13 @@ impl TunnelStateMachineHandle {
         &self.command_tx
     }
 
+    /// Returns traffic statistics for each peer of the active tunnel, or `None` if there is no
+    /// running tunnel.
+    pub fn tunnel_stats(&self) -> impl Future<Output = Option<Vec<PeerStats>>> + Send + 'static {
+        let handle = self.tunnel_stats.lock().unwrap().clone();
+        async move { handle?.peer_stats().await }
+    }
+
     /// Returns split tunnel object handle.
     #[cfg(windows)]
     pub fn split_tunnel(&self) -> &split_tunnel::SplitTunnelHandle {
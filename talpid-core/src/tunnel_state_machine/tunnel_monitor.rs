@@ -6,7 +6,7 @@ use talpid_tunnel::TunnelArgs;
 use talpid_tunnel::tun_provider;
 use talpid_types::net::{wireguard as wireguard_types, wireguard::TunnelParameters};
 use talpid_types::tunnel::ErrorStateCause;
-use talpid_wireguard::WireguardMonitor;
+use talpid_wireguard::{TunnelStatsHandle, WireguardMonitor};
 
 const WIREGUARD_LOG_FILENAME: &str = "wireguard.log";
 
@@ -164,6 +164,11 @@ impl TunnelMonitor {
         }
     }
 
+    /// Returns a handle that can be used to read traffic statistics from the tunnel.
+    pub fn stats_handle(&self) -> TunnelStatsHandle {
+        self.monitor.stats_handle()
+    }
+
     /// Consumes the monitor and blocks until the tunnel exits or there is an error.
     pub fn wait(self) -> Result<()> {
         self.monitor.wait().map_err(Error::from)
@@ -25,6 +25,8 @@ const CONNECTIVITY_CHECK_KEY: &str = "ConnectivityCheckEnabled";
 const NM_DNS_MANAGER: &str = "org.freedesktop.NetworkManager.DnsManager";
 const NM_DNS_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager/DnsManager";
 const NM_DEVICE: &str = "org.freedesktop.NetworkManager.Device";
+const NM_DEVICE_WIRELESS: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const NM_ACCESS_POINT: &str = "org.freedesktop.NetworkManager.AccessPoint";
 
 const NM_IP4_CONFIG: &str = "org.freedesktop.NetworkManager.IP4Config";
 const NM_IP6_CONFIG: &str = "org.freedesktop.NetworkManager.IP6Config";
@@ -37,6 +39,8 @@ const NM_DEVICE_STATE_IP_CHECK: u32 = 80;
 const NM_DEVICE_STATE_SECONDARY: u32 = 90;
 const NM_DEVICE_STATE_ACTIVATED: u32 = 100;
 
+const NM_DEVICE_TYPE_WIFI: u32 = 2;
+
 const NM_SETTINGS_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings";
 const NM_SETTINGS_CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
 const NM_SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
@@ -645,6 +649,31 @@ impl NetworkManager {
         Err(Error::DeviceNotFound)
     }
 
+    /// Return the SSID of the Wi-Fi network that `interface_name` is connected to, or `None` if
+    /// the device is not a wireless device or is not associated with an access point.
+    pub fn wifi_ssid(&self, interface_name: &str) -> Result<Option<String>> {
+        let device_path = self.fetch_device(interface_name)?;
+        let device = self.as_path(&device_path);
+
+        let device_type: u32 = device.get(NM_DEVICE, "DeviceType").map_err(Error::Dbus)?;
+        if device_type != NM_DEVICE_TYPE_WIFI {
+            return Ok(None);
+        }
+
+        let access_point: dbus::Path<'static> = device
+            .get(NM_DEVICE_WIRELESS, "ActiveAccessPoint")
+            .map_err(Error::Dbus)?;
+        if &*access_point == "/" {
+            return Ok(None);
+        }
+
+        let ssid: Vec<u8> = self
+            .as_path(&access_point)
+            .get(NM_ACCESS_POINT, "Ssid")
+            .map_err(Error::Dbus)?;
+        Ok(Some(String::from_utf8_lossy(&ssid).into_owned()))
+    }
+
     pub fn convert_address_to_dbus(address: &IpAddr) -> VariantMap {
         let mut map: VariantMap = HashMap::new();
         map.insert(
@@ -26,16 +26,23 @@ mod imp;
 
 pub use self::imp::Error;
 
+/// Port used by unencrypted DNS
+const PLAIN_DNS_PORT: u16 = 53;
+
 /// DNS configuration
 #[derive(Debug, Clone, PartialEq)]
 pub struct DnsConfig {
     config: InnerDnsConfig,
+    domain_rules: Vec<DomainRule>,
+    encryption: Option<DnsEncryption>,
 }
 
 impl Default for DnsConfig {
     fn default() -> Self {
         Self {
             config: InnerDnsConfig::Default,
+            domain_rules: vec![],
+            encryption: None,
         }
     }
 }
@@ -48,8 +55,89 @@ impl DnsConfig {
                 tunnel_config: tunnel_config.to_owned(),
                 non_tunnel_config: non_tunnel_config.to_owned(),
             },
+            domain_rules: vec![],
+            encryption: None,
         }
     }
+
+    /// Use specific DNS servers for some domains.
+    ///
+    /// Note that these are only honored by the local DNS resolver on macOS.
+    pub fn with_domain_rules(mut self, domain_rules: Vec<DomainRule>) -> Self {
+        self.domain_rules = domain_rules;
+        self
+    }
+
+    /// Encrypt queries sent to the default DNS servers. Servers used by domain rules are not
+    /// affected.
+    ///
+    /// Note that this is only honored by the local DNS resolver on macOS.
+    pub fn with_encryption(mut self, encryption: Option<DnsEncryption>) -> Self {
+        self.encryption = encryption;
+        self
+    }
+
+    /// Encrypted transport to use for the default DNS servers, if any
+    pub fn encryption(&self) -> Option<&DnsEncryption> {
+        self.encryption.as_ref()
+    }
+}
+
+/// Encrypted transport to use when forwarding DNS queries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsEncryption {
+    /// DNS over TLS
+    Tls {
+        /// Name to verify the server certificate against
+        server_name: String,
+    },
+    /// DNS over HTTPS
+    Https {
+        /// Name to verify the server certificate against
+        server_name: String,
+        /// Path of the DNS endpoint, such as `/dns-query`
+        path: String,
+    },
+}
+
+impl DnsEncryption {
+    /// Port that the DNS servers are reached on
+    pub fn port(&self) -> u16 {
+        match self {
+            DnsEncryption::Tls { .. } => 853,
+            DnsEncryption::Https { .. } => 443,
+        }
+    }
+}
+
+impl fmt::Display for DnsEncryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsEncryption::Tls { server_name } => write!(f, "tls://{server_name}"),
+            DnsEncryption::Https { server_name, path } => write!(f, "https://{server_name}{path}"),
+        }
+    }
+}
+
+/// DNS servers to use for a domain and all of its subdomains
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DomainRule {
+    /// Domain that the rule applies to
+    pub domain: String,
+    /// Addresses to reach through the tunnel
+    pub tunnel_config: Vec<IpAddr>,
+    /// Addresses to reach outside the tunnel. These are allowed in the firewall.
+    pub non_tunnel_config: Vec<IpAddr>,
+}
+
+impl DomainRule {
+    /// Return all addresses used by this rule
+    pub fn addresses(&self) -> impl Iterator<Item = IpAddr> + '_ {
+        self.non_tunnel_config
+            .iter()
+            .chain(&self.tunnel_config)
+            .copied()
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -77,6 +165,8 @@ impl DnsConfig {
             InnerDnsConfig::Default => ResolvedDnsConfig {
                 tunnel_config: default_tun_config.to_owned(),
                 non_tunnel_config: vec![],
+                domain_rules: self.domain_rules.clone(),
+                encryption: self.encryption.clone(),
                 #[cfg(target_os = "macos")]
                 port,
             },
@@ -86,6 +176,8 @@ impl DnsConfig {
             } => ResolvedDnsConfig {
                 tunnel_config: tunnel_config.to_owned(),
                 non_tunnel_config: non_tunnel_config.to_owned(),
+                domain_rules: self.domain_rules.clone(),
+                encryption: self.encryption.clone(),
                 #[cfg(target_os = "macos")]
                 port,
             },
@@ -102,6 +194,10 @@ pub struct ResolvedDnsConfig {
     /// For the most part, the tunnel state machine will not handle any of this configuration
     /// on non-tunnel interface, only allow them in the firewall.
     non_tunnel_config: Vec<IpAddr>,
+    /// DNS servers to use for specific domains
+    domain_rules: Vec<DomainRule>,
+    /// Encrypted transport to use for `tunnel_config` and `non_tunnel_config`
+    encryption: Option<DnsEncryption>,
     /// Port to use
     #[cfg(target_os = "macos")]
     port: u16,
@@ -115,6 +211,15 @@ impl fmt::Display for ResolvedDnsConfig {
         f.write_str(" Non-tunnel DNS: ")?;
         Self::fmt_addr_set(f, &self.non_tunnel_config)?;
 
+        for rule in &self.domain_rules {
+            write!(f, " {}: ", rule.domain)?;
+            Self::fmt_addr_set(f, &rule.addresses().collect::<Vec<_>>())?;
+        }
+
+        if let Some(encryption) = &self.encryption {
+            write!(f, " Encryption: {encryption}")?;
+        }
+
         #[cfg(target_os = "macos")]
         write!(f, " Port: {}", self.port)?;
 
@@ -146,7 +251,52 @@ impl ResolvedDnsConfig {
         &self.non_tunnel_config
     }
 
-    /// Consume `self` and return a vector of all addresses
+    /// DNS servers to use for specific domains
+    pub fn domain_rules(&self) -> &[DomainRule] {
+        &self.domain_rules
+    }
+
+    /// Encrypted transport to use for the default DNS servers, if any
+    pub fn encryption(&self) -> Option<&DnsEncryption> {
+        self.encryption.as_ref()
+    }
+
+    /// Addresses and ports to allow on the tunnel interface, including those used by domain
+    /// rules
+    pub fn all_tunnel_config(&self) -> impl Iterator<Item = (IpAddr, u16)> + '_ {
+        let port = self
+            .encryption
+            .as_ref()
+            .map_or(PLAIN_DNS_PORT, |e| e.port());
+        let rule_servers = self
+            .domain_rules
+            .iter()
+            .flat_map(|rule| &rule.tunnel_config);
+        self.tunnel_config
+            .iter()
+            .map(move |addr| (*addr, port))
+            .chain(rule_servers.map(|addr| (*addr, PLAIN_DNS_PORT)))
+    }
+
+    /// Addresses and ports to allow on non-tunnel interface, including those used by domain
+    /// rules
+    pub fn all_non_tunnel_config(&self) -> impl Iterator<Item = (IpAddr, u16)> + '_ {
+        let port = self
+            .encryption
+            .as_ref()
+            .map_or(PLAIN_DNS_PORT, |e| e.port());
+        let rule_servers = self
+            .domain_rules
+            .iter()
+            .flat_map(|rule| &rule.non_tunnel_config);
+        self.non_tunnel_config
+            .iter()
+            .map(move |addr| (*addr, port))
+            .chain(rule_servers.map(|addr| (*addr, PLAIN_DNS_PORT)))
+    }
+
+    /// Consume `self` and return a vector of all default addresses, i.e. excluding those only used
+    /// by domain rules
     pub fn addresses(self) -> impl Iterator<Item = IpAddr> {
         self.non_tunnel_config.into_iter().chain(self.tunnel_config)
     }
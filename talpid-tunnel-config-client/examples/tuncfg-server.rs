@@ -71,7 +71,7 @@ impl EphemeralPeer for EphemeralPeerImpl {
             }
 
             let psk = PresharedKey::from(psk_data);
-            println!("psk: {psk:?}");
+            println!("psk: {}", psk.to_base64());
             println!("==============================================");
 
             Some(PostQuantumResponseV1 { ciphertexts })
@@ -0,0 +1,61 @@
+use std::net::IpAddr;
+
+/// Result of a DNS leak test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsLeakTestReport {
+    /// DNS servers that the tunnel is configured to use.
+    pub expected_servers: Vec<IpAddr>,
+    /// Uniquely-named queries that were sent through the system resolver.
+    pub queries: Vec<DnsLeakTestQuery>,
+}
+
+impl DnsLeakTestReport {
+    /// Returns all queries that did not arrive at the expected DNS servers only.
+    pub fn mismatches(&self) -> impl Iterator<Item = &DnsLeakTestQuery> {
+        self.queries
+            .iter()
+            .filter(|query| !query.arrived_only_at(&self.expected_servers))
+    }
+
+    /// Returns whether any query did not arrive at the expected DNS servers only.
+    pub fn is_leaking(&self) -> bool {
+        self.mismatches().next().is_some()
+    }
+}
+
+/// Reasons why a DNS leak test could not be run.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsLeakTestError {
+    /// The tunnel is not connected.
+    #[error("The tunnel must be connected to run a DNS leak test")]
+    NotConnected,
+    /// The local DNS resolver, which observes the test queries, is not in use.
+    #[error("DNS leak tests require the local DNS resolver")]
+    Unsupported,
+}
+
+/// A single query sent during a DNS leak test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsLeakTestQuery {
+    /// Domain name that was queried.
+    pub name: String,
+    /// DNS servers that the query was forwarded to, or `None` if the query never reached the
+    /// local resolver.
+    pub upstream_servers: Option<Vec<IpAddr>>,
+}
+
+impl DnsLeakTestQuery {
+    /// Returns whether the query was forwarded to at least one server, and only to servers in
+    /// `expected_servers`.
+    pub fn arrived_only_at(&self, expected_servers: &[IpAddr]) -> bool {
+        match &self.upstream_servers {
+            Some(servers) => {
+                !servers.is_empty()
+                    && servers
+                        .iter()
+                        .all(|server| expected_servers.contains(server))
+            }
+            None => false,
+        }
+    }
+}
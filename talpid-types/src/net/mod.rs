@@ -12,6 +12,7 @@ use std::{
     str::FromStr,
 };
 
+pub mod dns;
 pub mod obfuscation;
 pub mod proxy;
 pub mod wireguard;
@@ -58,6 +59,7 @@ pub enum ObfuscationType {
     Shadowsocks,
     Quic,
     Lwo,
+    CustomBridge,
 }
 
 impl fmt::Display for ObfuscationType {
@@ -67,6 +69,7 @@ impl fmt::Display for ObfuscationType {
             ObfuscationType::Shadowsocks => "Shadowsocks".fmt(f),
             ObfuscationType::Quic => "QUIC".fmt(f),
             ObfuscationType::Lwo => "LWO".fmt(f),
+            ObfuscationType::CustomBridge => "custom bridge".fmt(f),
         }
     }
 }
@@ -164,6 +167,7 @@ impl From<&ObfuscatorConfig> for ObfuscationEndpoint {
             ObfuscatorConfig::Shadowsocks { .. } => ObfuscationType::Shadowsocks,
             ObfuscatorConfig::Quic { .. } => ObfuscationType::Quic,
             ObfuscatorConfig::Lwo { .. } => ObfuscationType::Lwo,
+            ObfuscatorConfig::CustomBridge { .. } => ObfuscationType::CustomBridge,
         };
 
         ObfuscationEndpoint {
@@ -531,6 +535,29 @@ impl fmt::Display for Connectivity {
     }
 }
 
+/// Properties that identify the physical network that the host is connected to.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct NetworkInfo {
+    /// Interface used by the default route
+    pub interface: Option<String>,
+    /// SSID of the Wi-Fi network, if `interface` is a wireless device
+    pub ssid: Option<String>,
+    /// MAC address of the default gateway, formatted as `aa:bb:cc:dd:ee:ff`
+    pub gateway_mac: Option<String>,
+}
+
+impl fmt::Display for NetworkInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "interface: {}, SSID: {}, gateway MAC: {}",
+            self.interface.as_deref().unwrap_or("-"),
+            self.ssid.as_deref().unwrap_or("-"),
+            self.gateway_mac.as_deref().unwrap_or("-"),
+        )
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(target_os = "android", derive(FromJava))]
 #[cfg_attr(target_os = "android", jnix(package = "net.mullvad.talpid.model"))]
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::{fmt, net::SocketAddr};
 
-use super::{Endpoint, TransportProtocol};
+use super::{
+    Endpoint, TransportProtocol,
+    proxy::{CustomProxy, Shadowsocks, Socks5Remote},
+};
 
 /// Available obfuscation configuration types.
 #[derive(Clone, Eq, PartialEq, Deserialize, Serialize, Debug)]
@@ -36,6 +39,47 @@ pub enum ObfuscatorConfig {
     Lwo {
         endpoint: SocketAddr,
     },
+    /// Relay WireGuard traffic through a user-provided proxy.
+    CustomBridge {
+        bridge: CustomBridge,
+        /// Public WireGuard endpoint of the relay.
+        wireguard_endpoint: SocketAddr,
+    },
+}
+
+/// User-provided proxy that WireGuard traffic can be relayed through. Only proxies that can relay
+/// UDP, and whose traffic can be excluded from the tunnel, are supported.
+#[derive(Clone, Eq, PartialEq, Hash, Deserialize, Serialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomBridge {
+    Shadowsocks(Shadowsocks),
+    /// A SOCKS5 proxy that supports the UDP ASSOCIATE command. It must relay UDP on the same
+    /// address and port as it accepts TCP connections on.
+    Socks5(Socks5Remote),
+}
+
+impl TryFrom<CustomProxy> for CustomBridge {
+    type Error = CustomProxy;
+
+    fn try_from(proxy: CustomProxy) -> Result<Self, Self::Error> {
+        match proxy {
+            CustomProxy::Shadowsocks(shadowsocks) => Ok(CustomBridge::Shadowsocks(shadowsocks)),
+            CustomProxy::Socks5Remote(socks5) => Ok(CustomBridge::Socks5(socks5)),
+            // A local SOCKS5 proxy cannot be excluded from the tunnel
+            proxy @ CustomProxy::Socks5Local(_) => Err(proxy),
+            // HTTP CONNECT only tunnels TCP, so it cannot relay WireGuard
+            proxy @ CustomProxy::HttpConnect(_) => Err(proxy),
+        }
+    }
+}
+
+impl From<CustomBridge> for CustomProxy {
+    fn from(bridge: CustomBridge) -> Self {
+        match bridge {
+            CustomBridge::Shadowsocks(shadowsocks) => CustomProxy::Shadowsocks(shadowsocks),
+            CustomBridge::Socks5(socks5) => CustomProxy::Socks5Remote(socks5),
+        }
+    }
 }
 
 impl Obfuscators {
@@ -70,7 +114,7 @@ impl Obfuscators {
     /// might use, with duplicates removed.
     pub fn endpoints(&self) -> Vec<Endpoint> {
         match self {
-            Obfuscators::Single(config) => vec![config.endpoint()],
+            Obfuscators::Single(config) => config.endpoints(),
             Obfuscators::Multiplexer {
                 direct,
                 configs: (first_config, remaining_configs),
@@ -82,8 +126,8 @@ impl Obfuscators {
                         protocol: TransportProtocol::Udp,
                     });
                 }
-                endpoints.push(first_config.endpoint());
-                endpoints.extend(remaining_configs.iter().map(|cfg| cfg.endpoint()));
+                endpoints.extend(first_config.endpoints());
+                endpoints.extend(remaining_configs.iter().flat_map(|cfg| cfg.endpoints()));
 
                 endpoints.sort();
                 endpoints.dedup();
@@ -114,6 +158,73 @@ impl ObfuscatorConfig {
                 address: *endpoint,
                 protocol: TransportProtocol::Udp,
             },
+            ObfuscatorConfig::CustomBridge { bridge, .. } => match bridge {
+                CustomBridge::Shadowsocks(shadowsocks) => Endpoint {
+                    address: shadowsocks.endpoint,
+                    protocol: TransportProtocol::Udp,
+                },
+                CustomBridge::Socks5(socks5) => Endpoint {
+                    address: socks5.endpoint,
+                    protocol: TransportProtocol::Tcp,
+                },
+            },
+        }
+    }
+
+    /// Return all endpoints that this obfuscator connects to. This is [Self::endpoint], except
+    /// for SOCKS5 bridges, which are also sent UDP on the same address and port.
+    pub fn endpoints(&self) -> Vec<Endpoint> {
+        let endpoint = self.endpoint();
+        match self {
+            ObfuscatorConfig::CustomBridge {
+                bridge: CustomBridge::Socks5(_),
+                ..
+            } => vec![
+                endpoint,
+                Endpoint {
+                    protocol: TransportProtocol::Udp,
+                    ..endpoint
+                },
+            ],
+            _ => vec![endpoint],
+        }
+    }
+}
+
+impl fmt::Display for Obfuscators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Obfuscators::Single(config) => config.fmt(f),
+            Obfuscators::Multiplexer {
+                direct,
+                configs: (first_config, remaining_configs),
+            } => {
+                write!(f, "multiplexer of ")?;
+                if let Some(direct) = direct {
+                    write!(f, "direct via {direct}, ")?;
+                }
+                write!(f, "{first_config}")?;
+                for config in remaining_configs {
+                    write!(f, ", {config}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for ObfuscatorConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObfuscatorConfig::Udp2Tcp { endpoint } => write!(f, "udp2tcp via {endpoint}"),
+            ObfuscatorConfig::Shadowsocks { endpoint } => write!(f, "shadowsocks via {endpoint}"),
+            ObfuscatorConfig::Quic {
+                hostname, endpoint, ..
+            } => write!(f, "quic via {endpoint} ({hostname})"),
+            ObfuscatorConfig::Lwo { endpoint } => write!(f, "lwo via {endpoint}"),
+            ObfuscatorConfig::CustomBridge { .. } => {
+                write!(f, "custom bridge via {}", self.endpoint().address)
+            }
         }
     }
 }
@@ -10,6 +10,10 @@ pub enum Error {
     /// Validation of SOCKS5 username or password failed.
     #[error("Invalid SOCKS5 authentication credentials: {0}")]
     InvalidSocksAuthValues(&'static str),
+
+    /// Validation of HTTP proxy username or password failed.
+    #[error("Invalid HTTP proxy authentication credentials: {0}")]
+    InvalidHttpAuthValues(&'static str),
 }
 
 /// Types of bridges that can be used to proxy a connection to a tunnel
@@ -45,6 +49,7 @@ pub enum CustomProxy {
     Shadowsocks(Shadowsocks),
     Socks5Local(Socks5Local),
     Socks5Remote(Socks5Remote),
+    HttpConnect(HttpConnect),
 }
 
 impl CustomProxy {
@@ -62,6 +67,10 @@ impl CustomProxy {
                 endpoint: Endpoint::from_socket_address(settings.endpoint, TransportProtocol::Tcp),
                 proxy_type: ProxyType::Shadowsocks,
             },
+            CustomProxy::HttpConnect(settings) => ProxyEndpoint {
+                endpoint: Endpoint::from_socket_address(settings.endpoint, TransportProtocol::Tcp),
+                proxy_type: ProxyType::Custom,
+            },
         }
     }
 }
@@ -84,6 +93,12 @@ impl From<Shadowsocks> for CustomProxy {
     }
 }
 
+impl From<HttpConnect> for CustomProxy {
+    fn from(value: HttpConnect) -> Self {
+        CustomProxy::HttpConnect(value)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Shadowsocks {
     pub endpoint: SocketAddr,
@@ -112,6 +127,11 @@ impl ShadowsocksCipher {
         shadowsocks_crypto::CipherKind::from_str(&self.0).unwrap()
     }
 
+    /// Returns whether this is an AEAD cipher, as opposed to a stream cipher.
+    pub fn is_aead(&self) -> bool {
+        self.clone().kind().is_aead()
+    }
+
     pub fn list() -> &'static [&'static str] {
         shadowsocks_crypto::available_ciphers()
     }
@@ -276,6 +296,84 @@ impl SocksAuth {
     }
 }
 
+/// HTTP proxy that tunnels TCP connections using the `CONNECT` method. Since it cannot relay UDP,
+/// it can only be used to reach the API, and not as a bridge for WireGuard traffic.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct HttpConnect {
+    pub endpoint: SocketAddr,
+    pub auth: Option<HttpAuth>,
+    /// If set, the connection to the proxy is made over TLS, and the certificate of the proxy
+    /// must be valid for this name.
+    pub tls_server_name: Option<String>,
+}
+
+/// A valid HTTP basic authentication according to
+/// RFC 7617: <https://datatracker.ietf.org/doc/html/rfc7617>.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct HttpAuth {
+    username: Sensitive<String>,
+    password: Sensitive<String>,
+}
+
+impl HttpAuth {
+    /// Validate an HTTP basic authentication.
+    ///
+    /// # Examples
+    ///
+    /// The username must not be empty and must not contain a colon.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::HttpAuth;
+    ///
+    /// assert!(HttpAuth::new("FooBar".to_string(), "hunter2".to_string()).is_ok());
+    /// assert!(HttpAuth::new("".to_string(), "hunter2".to_string()).is_err());
+    /// assert!(HttpAuth::new("Foo:Bar".to_string(), "hunter2".to_string()).is_err());
+    /// ```
+    ///
+    /// Neither the username nor the password may contain control characters, since they would
+    /// end up in a request header.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::HttpAuth;
+    ///
+    /// assert!(HttpAuth::new("FooBar".to_string(), "hunter2\r\n".to_string()).is_err());
+    /// ```
+    pub fn new(username: String, password: String) -> Result<Self, Error> {
+        if username.is_empty() {
+            return Err(Error::InvalidHttpAuthValues("Username must not be empty"));
+        }
+        if username.contains(':') {
+            return Err(Error::InvalidHttpAuthValues(
+                "Username must not contain a colon",
+            ));
+        }
+        if username
+            .chars()
+            .chain(password.chars())
+            .any(char::is_control)
+        {
+            return Err(Error::InvalidHttpAuthValues(
+                "Username and password must not contain control characters",
+            ));
+        }
+
+        Ok(HttpAuth {
+            username: username.into(),
+            password: password.into(),
+        })
+    }
+
+    /// Read the username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Read the password.
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
 impl Shadowsocks {
     pub fn new<I: Into<SocketAddr>>(
         endpoint: I,
@@ -336,6 +434,16 @@ impl Socks5Remote {
     }
 }
 
+impl HttpConnect {
+    pub fn new<I: Into<SocketAddr>>(endpoint: I) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            auth: None,
+            tls_server_name: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -8,6 +8,7 @@ use std::{
     cmp, fmt,
     hash::{Hash, Hasher},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::SystemTime,
 };
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -121,6 +122,34 @@ pub struct TunnelConfig {
     pub addresses: Vec<IpAddr>,
 }
 
+/// Traffic counters for a single peer of an active WireGuard tunnel.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct PeerStats {
+    /// Peer's public key.
+    pub public_key: PublicKey,
+    /// Total number of bytes sent to the peer.
+    pub tx_bytes: u64,
+    /// Total number of bytes received from the peer.
+    pub rx_bytes: u64,
+    /// Time of the most recent successful handshake, if any.
+    pub last_handshake: Option<SystemTime>,
+    /// DAITA overhead counters. Only available when DAITA is in use.
+    pub daita: Option<DaitaStats>,
+}
+
+/// Bytes added by DAITA to a peer's traffic.
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub struct DaitaStats {
+    /// Extra bytes added due to constant-size padding of data packets
+    pub tx_padding_bytes: u64,
+    /// Bytes of standalone decoy packets transmitted
+    pub tx_decoy_packet_bytes: u64,
+    /// Extra bytes removed due to constant-size padding of data packets
+    pub rx_padding_bytes: u64,
+    /// Bytes of standalone decoy packets received
+    pub rx_decoy_packet_bytes: u64,
+}
+
 /// Options in [`TunnelParameters`] that apply to any WireGuard connection.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TunnelOptions {
@@ -318,6 +347,14 @@ impl PresharedKey {
     pub fn as_bytes_mut(&mut self) -> &mut [u8; 32] {
         &mut self.0
     }
+
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.as_bytes())
+    }
+
+    pub fn from_base64(key: &str) -> Result<Self, InvalidKey> {
+        key_from_base64(key)
+    }
 }
 
 impl From<Box<[u8; 32]>> for PresharedKey {
@@ -326,9 +363,35 @@ impl From<Box<[u8; 32]>> for PresharedKey {
     }
 }
 
+impl From<[u8; 32]> for PresharedKey {
+    fn from(key: [u8; 32]) -> PresharedKey {
+        PresharedKey(Box::new(key))
+    }
+}
+
+impl Serialize for PresharedKey {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_key(self.as_bytes(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PresharedKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_key(deserializer)
+    }
+}
+
+/// The key is redacted, since preshared keys of custom relays are stored in the settings, which
+/// may end up in logs.
 impl fmt::Debug for PresharedKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", STANDARD.encode(self.as_bytes()))
+        f.write_str("PresharedKey(<redacted>)")
     }
 }
 
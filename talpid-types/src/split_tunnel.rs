@@ -1,4 +1,5 @@
-use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::PathBuf};
 
 /// A process that is being excluded from the tunnel.
 #[derive(Debug, Clone)]
@@ -11,3 +12,23 @@ pub struct ExcludedProcess {
     /// not due to its path being in the config.
     pub inherited: bool,
 }
+
+/// Determines how the split tunneling app list is applied.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitTunnelMode {
+    /// Split apps are excluded from the tunnel. All other traffic uses the tunnel.
+    #[default]
+    Exclude,
+    /// Only split apps use the tunnel. All other traffic is excluded from the tunnel.
+    Include,
+}
+
+impl fmt::Display for SplitTunnelMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitTunnelMode::Exclude => f.write_str("exclude"),
+            SplitTunnelMode::Include => f.write_str("include"),
+        }
+    }
+}
@@ -17,7 +17,7 @@ use std::{
     net::IpAddr,
     path::Path,
     pin::Pin,
-    sync::{Arc, LazyLock, mpsc as sync_mpsc},
+    sync::{Arc, LazyLock, Weak, mpsc as sync_mpsc},
 };
 #[cfg(all(not(target_os = "android"), not(target_os = "linux")))]
 use talpid_routing::RouteManagerHandle;
@@ -35,7 +35,10 @@ use talpid_tunnel_config_client::DaitaSettings;
 use talpid_types::net::obfuscation::Obfuscators;
 use talpid_types::{
     BoxedError, ErrorExt,
-    net::{AllowedTunnelTraffic, Endpoint, TransportProtocol, wireguard::TunnelParameters},
+    net::{
+        AllowedTunnelTraffic, Endpoint, TransportProtocol,
+        wireguard::{PeerStats, PublicKey, TunnelParameters},
+    },
 };
 use tokio::sync::Mutex as AsyncMutex;
 
@@ -152,6 +155,41 @@ pub struct WireguardMonitor {
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
 }
 
+/// Handle used to read traffic statistics from a running tunnel.
+///
+/// The handle does not keep the tunnel alive. Once the tunnel has been stopped, no statistics
+/// are returned.
+#[derive(Clone)]
+pub struct TunnelStatsHandle {
+    tunnel: Weak<AsyncMutex<Option<TunnelType>>>,
+}
+
+impl TunnelStatsHandle {
+    /// Returns the current statistics for each peer of the tunnel, or `None` if the tunnel is
+    /// down or the statistics could not be obtained.
+    pub async fn peer_stats(&self) -> Option<Vec<PeerStats>> {
+        let tunnel = self.tunnel.upgrade()?;
+        let tunnel = tunnel.lock().await;
+        let stats = tunnel
+            .as_ref()?
+            .get_tunnel_stats()
+            .await
+            .inspect_err(|error| {
+                log::debug!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to obtain tunnel stats")
+                );
+            })
+            .ok()?;
+        Some(
+            stats
+                .into_iter()
+                .map(|(pubkey, stats)| stats.into_peer_stats(PublicKey::from(pubkey)))
+                .collect(),
+        )
+    }
+}
+
 #[cfg(not(target_os = "android"))]
 /// Overrides the preference for the kernel module for WireGuard.
 static FORCE_USERSPACE_WIREGUARD: LazyLock<bool> = LazyLock::new(|| {
@@ -797,6 +835,13 @@ impl WireguardMonitor {
         }
     }
 
+    /// Returns a handle that can be used to read traffic statistics from the tunnel.
+    pub fn stats_handle(&self) -> TunnelStatsHandle {
+        TunnelStatsHandle {
+            tunnel: Arc::downgrade(&self.tunnel),
+        }
+    }
+
     /// Blocks the current thread until tunnel disconnects
     pub fn wait(mut self) -> Result<()> {
         let wait_result = {
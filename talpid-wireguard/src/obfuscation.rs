@@ -15,7 +15,8 @@ use talpid_tunnel::tun_provider::TunProvider;
 use talpid_types::{
     ErrorExt,
     net::{
-        obfuscation::{ObfuscatorConfig, Obfuscators},
+        obfuscation::{CustomBridge, ObfuscatorConfig, Obfuscators},
+        proxy::{Shadowsocks, Socks5Remote},
         wireguard::{PeerConfig, PublicKey},
     },
 };
@@ -23,7 +24,7 @@ use tokio::sync::oneshot;
 use tunnel_obfuscation::{
     LocalSocketObfuscator, create_local_socket_obfuscator_with_bypass, lwo,
     multiplexer::{self, Transport},
-    quic, shadowsocks, udp2tcp,
+    quic, shadowsocks, socks5, udp2tcp,
 };
 
 /// Settings for the local socket obfuscator to run: either a single obfuscator or a multiplexer.
@@ -184,6 +185,7 @@ fn settings_from_single_config(
                 } else {
                     SocketAddr::from((Ipv6Addr::LOCALHOST, 51820))
                 },
+                credentials: None,
             })
         }
         ObfuscatorConfig::Quic {
@@ -206,6 +208,25 @@ fn settings_from_single_config(
             client_public_key,
             server_public_key,
         }),
+        ObfuscatorConfig::CustomBridge {
+            bridge: CustomBridge::Shadowsocks(bridge),
+            wireguard_endpoint,
+        } => tunnel_obfuscation::Settings::Shadowsocks(shadowsocks::Settings {
+            shadowsocks_endpoint: bridge.endpoint,
+            wireguard_endpoint: *wireguard_endpoint,
+            credentials: Some(shadowsocks::Credentials {
+                cipher: bridge.cipher.clone().kind(),
+                password: bridge.plaintext_password().to_owned(),
+            }),
+        }),
+        ObfuscatorConfig::CustomBridge {
+            bridge: CustomBridge::Socks5(bridge),
+            wireguard_endpoint,
+        } => tunnel_obfuscation::Settings::Socks5(socks5::Settings {
+            proxy_endpoint: bridge.endpoint,
+            auth: bridge.auth.clone(),
+            wireguard_endpoint: *wireguard_endpoint,
+        }),
     }
 }
 
@@ -218,8 +239,18 @@ pub fn config_from_single_settings(settings: &tunnel_obfuscation::Settings) -> O
         tunnel_obfuscation::Settings::Udp2Tcp(settings) => ObfuscatorConfig::Udp2Tcp {
             endpoint: settings.peer,
         },
-        tunnel_obfuscation::Settings::Shadowsocks(settings) => ObfuscatorConfig::Shadowsocks {
-            endpoint: settings.shadowsocks_endpoint,
+        tunnel_obfuscation::Settings::Shadowsocks(settings) => match &settings.credentials {
+            None => ObfuscatorConfig::Shadowsocks {
+                endpoint: settings.shadowsocks_endpoint,
+            },
+            Some(credentials) => ObfuscatorConfig::CustomBridge {
+                bridge: CustomBridge::Shadowsocks(Shadowsocks::new(
+                    settings.shadowsocks_endpoint,
+                    credentials.cipher.into(),
+                    credentials.password.clone(),
+                )),
+                wireguard_endpoint: settings.wireguard_endpoint,
+            },
         },
         tunnel_obfuscation::Settings::Quic(settings) => ObfuscatorConfig::Quic {
             hostname: settings.hostname().to_owned(),
@@ -229,6 +260,13 @@ pub fn config_from_single_settings(settings: &tunnel_obfuscation::Settings) -> O
         tunnel_obfuscation::Settings::Lwo(settings) => ObfuscatorConfig::Lwo {
             endpoint: settings.server_addr,
         },
+        tunnel_obfuscation::Settings::Socks5(settings) => ObfuscatorConfig::CustomBridge {
+            bridge: CustomBridge::Socks5(Socks5Remote {
+                endpoint: settings.proxy_endpoint,
+                auth: settings.auth.clone(),
+            }),
+            wireguard_endpoint: settings.wireguard_endpoint,
+        },
     }
 }
 
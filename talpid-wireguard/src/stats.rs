@@ -1,5 +1,6 @@
 use std::fmt;
 use std::time::{Duration, SystemTime};
+use talpid_types::net::wireguard::{self, PeerStats, PublicKey};
 
 /// Contains bytes sent and received through a tunnel
 #[derive(Default, PartialEq, Eq, Clone)]
@@ -27,6 +28,23 @@ pub struct DaitaStats {
     pub rx_decoy_packet_bytes: u64,
 }
 
+impl Stats {
+    pub fn into_peer_stats(self, public_key: PublicKey) -> PeerStats {
+        PeerStats {
+            public_key,
+            tx_bytes: self.tx_bytes,
+            rx_bytes: self.rx_bytes,
+            last_handshake: self.last_handshake_time,
+            daita: self.daita.map(|daita| wireguard::DaitaStats {
+                tx_padding_bytes: daita.tx_padding_bytes,
+                tx_decoy_packet_bytes: daita.tx_decoy_packet_bytes,
+                rx_padding_bytes: daita.rx_padding_bytes,
+                rx_decoy_packet_bytes: daita.rx_decoy_packet_bytes,
+            }),
+        }
+    }
+}
+
 impl fmt::Debug for Stats {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let stats = StatsDebug {
@@ -80,8 +80,10 @@ pub async fn test_dns_leak_custom_public_ip(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![CONFIG_IP],
+                encryption: None,
             },
             state: settings::DnsState::Custom,
+            domain_rules: vec![],
         })
         .await
         .expect("failed to configure DNS server");
@@ -112,8 +114,10 @@ pub async fn test_dns_leak_custom_private_ip(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![CONFIG_IP],
+                encryption: None,
             },
             state: settings::DnsState::Custom,
+            domain_rules: vec![],
         })
         .await
         .expect("failed to configure DNS server");
@@ -363,8 +367,10 @@ pub async fn test_dns_config_custom_private(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![IpAddr::V4(TEST_CONFIG.host_bridge_ip)],
+                encryption: None,
             },
             state: settings::DnsState::Custom,
+            domain_rules: vec![],
         })
         .await
         .context("failed to configure DNS server")?;
@@ -397,8 +403,10 @@ pub async fn test_dns_config_custom_public(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![custom_ip],
+                encryption: None,
             },
             state: settings::DnsState::Custom,
+            domain_rules: vec![],
         })
         .await
         .context("failed to configure DNS server")?;
@@ -503,6 +511,7 @@ pub async fn test_content_blockers(
                 default_options: test_opts,
                 custom_options: settings::CustomDnsOptions::default(),
                 state: settings::DnsState::Default,
+                domain_rules: vec![],
             })
             .await
             .context("failed to configure DNS server")?;
@@ -669,6 +678,8 @@ async fn connect_local_wg_relay(mullvad_client: &mut MullvadProxyClient) -> Resu
             fwmark: None,
             ipv6_gateway: None,
         },
+        preshared_key: None,
+        dns: vec![],
     };
     set_custom_endpoint(mullvad_client, custom_tunnel_endpoint)
         .await
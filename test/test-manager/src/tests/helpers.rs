@@ -1368,6 +1368,7 @@ pub fn into_settings(
         hops,
         allowed_ips,
         quantum_resistant: _,
+        strategy,
     }: RelayQuery,
 ) -> (RelayConstraints, ObfuscationSettings) {
     let location_constraint = |exit: &ExitConstraints| {
@@ -1393,6 +1394,7 @@ pub fn into_settings(
                     entry_providers: Constraint::Any,
                     entry_ownership: Constraint::Any,
                 },
+                strategy,
             };
             let obfuscation = obfuscation_to_settings(entry.entry_specific.obfuscation);
             (constraints, obfuscation)
@@ -1410,6 +1412,7 @@ pub fn into_settings(
                     entry_providers: Constraint::Any,
                     entry_ownership: Constraint::Any,
                 },
+                strategy,
             };
             let obfuscation = obfuscation_to_settings(entry.entry_specific.obfuscation);
             (constraints, obfuscation)
@@ -1427,6 +1430,7 @@ pub fn into_settings(
                     entry_providers: entry.general.providers,
                     entry_ownership: entry.general.ownership,
                 },
+                strategy,
             };
             let obfuscation = obfuscation_to_settings(entry.entry_specific.obfuscation);
             (constraints, obfuscation)
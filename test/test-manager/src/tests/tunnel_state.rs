@@ -181,10 +181,7 @@ pub async fn test_connecting_state(
 
     set_custom_endpoint(
         &mut mullvad_client,
-        CustomTunnelEndpoint {
-            host: "1.3.3.7".to_owned(),
-            config: unreachable_wireguard_tunnel(),
-        },
+        CustomTunnelEndpoint::new("1.3.3.7".to_owned(), unreachable_wireguard_tunnel()),
     )
     .await
     .expect("failed to update relay settings");
@@ -8,6 +8,7 @@ pub mod multiplexer;
 pub mod quic;
 pub mod shadowsocks;
 pub mod socket;
+pub mod socks5;
 pub mod udp2tcp;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -38,6 +39,12 @@ pub enum Error {
     #[error("Failed to run LWO")]
     RunLwoObfuscator(#[source] lwo::Error),
 
+    #[error("Failed to initialize SOCKS5")]
+    CreateSocks5Obfuscator(#[source] socks5::Error),
+
+    #[error("Failed to run SOCKS5")]
+    RunSocks5Obfuscator(#[source] socks5::Error),
+
     #[error("Failed to bind socket")]
     BindRemoteUdp(#[source] io::Error),
 
@@ -75,6 +82,7 @@ pub enum Settings {
     Shadowsocks(shadowsocks::Settings),
     Quic(quic::Settings),
     Lwo(lwo::Settings),
+    Socks5(socks5::Settings),
 }
 
 pub async fn create_local_socket_obfuscator(
@@ -96,6 +104,7 @@ pub async fn create_local_socket_obfuscator_with_bypass(
             .await
             .map(box_obfuscator),
         Settings::Lwo(s) => lwo::Lwo::new(bypass, s).await.map(box_obfuscator),
+        Settings::Socks5(s) => socks5::Socks5::new(bypass, s).await.map(box_obfuscator),
     }
 }
 
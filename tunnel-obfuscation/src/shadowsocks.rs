@@ -17,7 +17,7 @@ use shadowsocks::{
         udprelay::proxy_socket::{ProxySocketError, UdpSocketType},
     },
 };
-use std::{io, net::SocketAddr, sync::Arc};
+use std::{fmt, io, net::SocketAddr, sync::Arc};
 use talpid_net::bypass::{BypassSocket, SocketBypass};
 use tokio::{net::UdpSocket, sync::oneshot};
 
@@ -42,11 +42,15 @@ pub enum Error {
     /// Server config error
     #[error("Server config error")]
     ServerConfig(#[from] ServerConfigError),
+    /// Only AEAD ciphers are supported
+    #[error("Unsupported cipher: {0}")]
+    UnsupportedCipher(CipherKind),
 }
 
 pub struct Shadowsocks {
     udp_client_addr: SocketAddr,
     wireguard_endpoint: SocketAddr,
+    cipher: CipherKind,
     server: tokio::task::JoinHandle<Result<()>>,
     // The receiver will implicitly shut down when this is dropped
     _shutdown_tx: oneshot::Sender<()>,
@@ -58,6 +62,38 @@ pub struct Settings {
     pub shadowsocks_endpoint: SocketAddr,
     /// Remote WireGuard endpoint
     pub wireguard_endpoint: SocketAddr,
+    /// Credentials for a server that is not run by Mullvad. If `None`, the credentials used by
+    /// Mullvad's servers are used.
+    pub credentials: Option<Credentials>,
+}
+
+/// Cipher and password of a Shadowsocks server.
+#[derive(Clone)]
+pub struct Credentials {
+    pub cipher: CipherKind,
+    pub password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("cipher", &self.cipher)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Settings {
+    fn cipher_and_password(&self) -> Result<(CipherKind, &str)> {
+        let (cipher, password) = match &self.credentials {
+            Some(credentials) => (credentials.cipher, credentials.password.as_str()),
+            None => (SHADOWSOCKS_CIPHER, SHADOWSOCKS_PASSWORD),
+        };
+        // The packet overhead is only known for AEAD ciphers
+        if !cipher.is_aead() {
+            return Err(Error::UnsupportedCipher(cipher));
+        }
+        Ok((cipher, password))
+    }
 }
 
 impl Shadowsocks {
@@ -65,6 +101,10 @@ impl Shadowsocks {
         bypass: Arc<dyn SocketBypass>,
         settings: &Settings,
     ) -> crate::Result<Self> {
+        let (cipher, password) = settings
+            .cipher_and_password()
+            .map_err(crate::Error::CreateShadowsocksObfuscator)?;
+
         let (local_udp_socket, udp_client_addr) =
             create_local_udp_socket(settings.shadowsocks_endpoint.is_ipv4())
                 .await
@@ -75,8 +115,12 @@ impl Shadowsocks {
         let remote_socket =
             create_remote_socket(&bypass, settings.shadowsocks_endpoint.is_ipv4()).await?;
 
+        let ss_config = ServerConfig::new(settings.shadowsocks_endpoint, password, cipher)
+            .map_err(|error| crate::Error::CreateShadowsocksObfuscator(error.into()))?;
+
         let server = tokio::spawn(run_forwarding(
             settings.shadowsocks_endpoint,
+            ss_config,
             remote_socket,
             local_udp_socket,
             settings.wireguard_endpoint,
@@ -86,6 +130,7 @@ impl Shadowsocks {
         Ok(Shadowsocks {
             udp_client_addr,
             wireguard_endpoint: settings.wireguard_endpoint,
+            cipher,
             server,
             _shutdown_tx: shutdown_tx,
         })
@@ -94,6 +139,7 @@ impl Shadowsocks {
 
 async fn run_forwarding(
     shadowsocks_endpoint: SocketAddr,
+    ss_config: ServerConfig,
     remote_socket: BypassSocket<UdpSocket>,
     local_udp_socket: UdpSocket,
     wireguard_endpoint: SocketAddr,
@@ -103,7 +149,7 @@ async fn run_forwarding(
         .await
         .map_err(Error::WaitForUdpClient)?;
 
-    let shadowsocks = connect_shadowsocks(remote_socket, shadowsocks_endpoint)?;
+    let shadowsocks = connect_shadowsocks(remote_socket, &ss_config);
     let shadowsocks = Arc::new(shadowsocks);
 
     let local_udp = Arc::new(local_udp_socket);
@@ -139,23 +185,18 @@ async fn run_forwarding(
 
 fn connect_shadowsocks(
     remote_socket: BypassSocket<UdpSocket>,
-    shadowsocks_endpoint: SocketAddr,
-) -> Result<ShadowSocket> {
+    ss_config: &ServerConfig,
+) -> ShadowSocket {
     let ss_context = Context::new_shared(ServerType::Local);
-    let ss_config = ServerConfig::new(
-        shadowsocks_endpoint,
-        SHADOWSOCKS_PASSWORD,
-        SHADOWSOCKS_CIPHER,
-    )?;
     let guard = remote_socket.guard;
     let socket = ProxySocket::from_socket(
         UdpSocketType::Client,
         ss_context,
-        &ss_config,
+        ss_config,
         // wrap the tokio socket
         shadowsocks::net::UdpSocket::from(remote_socket.socket),
     );
-    Ok(BypassSocket { socket, guard })
+    BypassSocket { socket, guard }
 }
 
 async fn create_local_udp_socket(ipv4: bool) -> Result<(UdpSocket, SocketAddr)> {
@@ -266,11 +307,11 @@ impl LocalSocketObfuscator for Shadowsocks {
         // This math relies on the packet structure of Shadowsocks AEAD UDP packets.
         // https://shadowsocks.org/doc/aead.html
         // Those packets look like this: [salt][address][payload][tag]
-        debug_assert!(SHADOWSOCKS_CIPHER.is_aead());
+        debug_assert!(self.cipher.is_aead());
 
-        let overhead = SHADOWSOCKS_CIPHER.salt_len()
+        let overhead = self.cipher.salt_len()
             + Address::from(self.wireguard_endpoint).serialized_len()
-            + SHADOWSOCKS_CIPHER.tag_len();
+            + self.cipher.tag_len();
 
         u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
     }
@@ -291,3 +332,49 @@ fn is_fatal_socket_io_error(error: &io::Error) -> bool {
             | io::ErrorKind::BrokenPipe
     )
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn settings(credentials: Option<Credentials>) -> Settings {
+        Settings {
+            shadowsocks_endpoint: "192.0.2.1:443".parse().unwrap(),
+            wireguard_endpoint: "192.0.2.2:51820".parse().unwrap(),
+            credentials,
+        }
+    }
+
+    #[test]
+    fn test_cipher_and_password() {
+        let (cipher, password) = settings(None).cipher_and_password().unwrap();
+        assert_eq!(cipher, SHADOWSOCKS_CIPHER);
+        assert_eq!(password, SHADOWSOCKS_PASSWORD);
+
+        let credentials = Credentials {
+            cipher: CipherKind::CHACHA20_POLY1305,
+            password: "hunter2".to_owned(),
+        };
+        let settings = settings(Some(credentials));
+        let (cipher, password) = settings.cipher_and_password().unwrap();
+        assert_eq!(cipher, CipherKind::CHACHA20_POLY1305);
+        assert_eq!(password, "hunter2");
+    }
+
+    /// User bridges may use stream ciphers, whose packet overhead is unknown.
+    #[test]
+    fn test_non_aead_cipher_rejected() {
+        let credentials = Credentials {
+            cipher: CipherKind::AES_256_CFB,
+            password: "hunter2".to_owned(),
+        };
+        let result = settings(Some(credentials)).cipher_and_password();
+        assert!(
+            matches!(
+                result,
+                Err(Error::UnsupportedCipher(CipherKind::AES_256_CFB))
+            ),
+            "expected UnsupportedCipher, got {result:?}"
+        );
+    }
+}
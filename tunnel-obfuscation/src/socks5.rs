@@ -0,0 +1,474 @@
+//! SOCKS5 obfuscation. Relays WireGuard traffic through a SOCKS5 proxy using the UDP ASSOCIATE
+//! command. See RFC 1928: <https://datatracker.ietf.org/doc/html/rfc1928>.
+//!
+//! The proxy must relay UDP on the same address and port as it accepts TCP connections on, since
+//! that is the only endpoint that is allowed by the firewall.
+
+use crate::{LocalSocketObfuscator, socket::create_remote_socket};
+use async_trait::async_trait;
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
+use talpid_net::bypass::{BypassSocket, SocketBypass};
+use talpid_types::net::proxy::SocksAuth;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpSocket, TcpStream, UdpSocket},
+};
+
+const SOCKS_VERSION: u8 = 5;
+const AUTH_NONE: u8 = 0x00;
+const AUTH_PASSWORD: u8 = 0x02;
+const AUTH_PASSWORD_VERSION: u8 = 1;
+const CMD_UDP_ASSOCIATE: u8 = 0x03;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_IPV6: u8 = 0x04;
+const REPLY_SUCCEEDED: u8 = 0x00;
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// Remote SOCKS5 proxy
+    pub proxy_endpoint: SocketAddr,
+    /// Username and password, if the proxy requires authentication
+    pub auth: Option<SocksAuth>,
+    /// Remote WireGuard endpoint
+    pub wireguard_endpoint: SocketAddr,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to bind the local UDP socket
+    #[error("Failed to bind the local UDP socket")]
+    BindLocalUdp(#[source] io::Error),
+
+    /// Failed to create the TCP socket
+    #[error("Failed to create the TCP socket")]
+    CreateTcpSocket(#[source] io::Error),
+
+    /// Failed to exclude the TCP socket from tunnel traffic
+    #[error("Failed to exclude the TCP socket from tunnel traffic")]
+    Bypass(#[source] io::Error),
+
+    /// Failed to accept the local WireGuard instance
+    #[error("Failed to accept the local WireGuard instance")]
+    ConnectLocalUdp(#[source] io::Error),
+
+    /// Failed to connect to the proxy
+    #[error("Failed to connect to the proxy")]
+    ConnectTcp(#[source] io::Error),
+
+    /// Failed to talk to the proxy
+    #[error("Failed to set up UDP relaying with the proxy")]
+    Handshake(#[source] io::Error),
+
+    /// The proxy does not speak SOCKS5 as expected
+    #[error("Unexpected response from the proxy: {0}")]
+    Protocol(&'static str),
+
+    /// The proxy did not accept the authentication method
+    #[error("The proxy did not accept the authentication method")]
+    AuthMethodRejected,
+
+    /// The proxy rejected the username or password
+    #[error("The proxy rejected the username or password")]
+    AuthenticationFailed,
+
+    /// The proxy refused to relay UDP
+    #[error("The proxy refused to relay UDP, reply code {0}")]
+    AssociateFailed(u8),
+
+    /// The proxy relays UDP on an endpoint that is not allowed by the firewall
+    #[error("The proxy relays UDP on {0}, but only the address and port of the proxy are allowed")]
+    UnexpectedRelayAddress(SocketAddr),
+
+    /// Failed to connect the remote UDP socket to the relay address of the proxy
+    #[error("Failed to connect to the UDP relay of the proxy")]
+    ConnectRemoteUdp(#[source] io::Error),
+}
+
+/// Forwards datagrams through a SOCKS5 proxy.
+pub struct Socks5 {
+    local_udp_socket: UdpSocket,
+    tcp_socket: BypassSocket<TcpSocket>,
+    remote_udp_socket: BypassSocket<UdpSocket>,
+    settings: Settings,
+}
+
+impl Socks5 {
+    pub(crate) async fn new(
+        bypass: Arc<dyn SocketBypass>,
+        settings: &Settings,
+    ) -> crate::Result<Self> {
+        let local_udp_socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .await
+            .map_err(|error| crate::Error::CreateSocks5Obfuscator(Error::BindLocalUdp(error)))?;
+
+        let tcp_socket = match settings.proxy_endpoint {
+            SocketAddr::V4(..) => TcpSocket::new_v4(),
+            SocketAddr::V6(..) => TcpSocket::new_v6(),
+        }
+        .map_err(|error| crate::Error::CreateSocks5Obfuscator(Error::CreateTcpSocket(error)))?;
+        let tcp_socket = BypassSocket::new(bypass.clone(), tcp_socket)
+            .map_err(|error| crate::Error::CreateSocks5Obfuscator(Error::Bypass(error)))?;
+
+        let remote_udp_socket =
+            create_remote_socket(&bypass, settings.proxy_endpoint.is_ipv4()).await?;
+
+        Ok(Socks5 {
+            local_udp_socket,
+            tcp_socket,
+            remote_udp_socket,
+            settings: settings.clone(),
+        })
+    }
+
+    /// Wait for the local WireGuard instance, then ask the proxy to relay UDP and shuttle
+    /// datagrams until either side fails or the proxy closes the TCP connection.
+    async fn forward(self: Box<Self>) -> Result<(), Error> {
+        let Self {
+            local_udp_socket,
+            tcp_socket,
+            remote_udp_socket,
+            settings,
+        } = *self;
+
+        let wg_addr = local_udp_socket
+            .peek_sender()
+            .await
+            .map_err(Error::ConnectLocalUdp)?;
+        local_udp_socket
+            .connect(wg_addr)
+            .await
+            .map_err(Error::ConnectLocalUdp)?;
+
+        let (tcp_socket, _tcp_bypass) = (tcp_socket.socket, tcp_socket.guard);
+        let mut control = tcp_socket
+            .connect(settings.proxy_endpoint)
+            .await
+            .map_err(Error::ConnectTcp)?;
+
+        let mut relay_addr = associate(&mut control, settings.auth.as_ref()).await?;
+        if relay_addr.ip().is_unspecified() {
+            relay_addr.set_ip(settings.proxy_endpoint.ip());
+        }
+        if relay_addr != settings.proxy_endpoint {
+            return Err(Error::UnexpectedRelayAddress(relay_addr));
+        }
+        remote_udp_socket
+            .connect(relay_addr)
+            .await
+            .map_err(Error::ConnectRemoteUdp)?;
+        log::debug!("Relaying UDP through {relay_addr}");
+
+        let local_udp_socket = Arc::new(local_udp_socket);
+        let (remote_udp_socket, _udp_bypass) =
+            (Arc::new(remote_udp_socket.socket), remote_udp_socket.guard);
+
+        let mut outgoing = tokio::spawn(handle_outgoing(
+            local_udp_socket.clone(),
+            remote_udp_socket.clone(),
+            settings.wireguard_endpoint,
+        ));
+        let mut incoming = tokio::spawn(handle_incoming(
+            remote_udp_socket,
+            local_udp_socket,
+            settings.wireguard_endpoint,
+        ));
+
+        // The association lasts for as long as the TCP connection is open
+        tokio::select! {
+            _ = wait_for_close(&mut control) => log::trace!("SOCKS5 control connection closed"),
+            _ = &mut outgoing => log::trace!("Local UDP client closed"),
+            _ = &mut incoming => log::trace!("SOCKS5 UDP relay closed"),
+        }
+
+        outgoing.abort();
+        incoming.abort();
+
+        Ok(())
+    }
+}
+
+/// Authenticate to the proxy and ask it to relay UDP. Returns the address of the UDP relay.
+async fn associate(stream: &mut TcpStream, auth: Option<&SocksAuth>) -> Result<SocketAddr, Error> {
+    let method = if auth.is_some() {
+        AUTH_PASSWORD
+    } else {
+        AUTH_NONE
+    };
+    write(stream, &[SOCKS_VERSION, 1, method]).await?;
+    let [version, selected_method] = read(stream).await?;
+    if version != SOCKS_VERSION {
+        return Err(Error::Protocol("unexpected SOCKS version"));
+    }
+    if selected_method != method {
+        return Err(Error::AuthMethodRejected);
+    }
+
+    // See RFC 1929: <https://datatracker.ietf.org/doc/html/rfc1929>
+    if let Some(auth) = auth {
+        // `SocksAuth` guarantees that both are between 1 and 255 bytes
+        let mut request = vec![AUTH_PASSWORD_VERSION, auth.username().len() as u8];
+        request.extend_from_slice(auth.username().as_bytes());
+        request.push(auth.password().len() as u8);
+        request.extend_from_slice(auth.password().as_bytes());
+        write(stream, &request).await?;
+        let [_version, status] = read(stream).await?;
+        if status != 0 {
+            return Err(Error::AuthenticationFailed);
+        }
+    }
+
+    // The address that datagrams will be sent from is not known, so leave it unspecified
+    let mut request = vec![SOCKS_VERSION, CMD_UDP_ASSOCIATE, 0];
+    request.extend(encode_address(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0))));
+    write(stream, &request).await?;
+
+    let [version, reply, _reserved, address_type] = read(stream).await?;
+    if version != SOCKS_VERSION {
+        return Err(Error::Protocol("unexpected SOCKS version"));
+    }
+    if reply != REPLY_SUCCEEDED {
+        return Err(Error::AssociateFailed(reply));
+    }
+    let ip = match address_type {
+        ATYP_IPV4 => IpAddr::from(read::<4>(stream).await?),
+        ATYP_IPV6 => IpAddr::from(read::<16>(stream).await?),
+        _ => return Err(Error::Protocol("unsupported relay address type")),
+    };
+    let port = u16::from_be_bytes(read(stream).await?);
+    Ok(SocketAddr::new(ip, port))
+}
+
+async fn write(stream: &mut TcpStream, data: &[u8]) -> Result<(), Error> {
+    stream.write_all(data).await.map_err(Error::Handshake)
+}
+
+async fn read<const N: usize>(stream: &mut TcpStream) -> Result<[u8; N], Error> {
+    let mut buf = [0u8; N];
+    stream
+        .read_exact(&mut buf)
+        .await
+        .map_err(Error::Handshake)?;
+    Ok(buf)
+}
+
+/// Wait until the proxy closes the TCP connection. Nothing is expected to be sent on it.
+async fn wait_for_close(stream: &mut TcpStream) {
+    let mut buf = [0u8; 64];
+    while let Ok(1..) = stream.read(&mut buf).await {}
+}
+
+/// Encode an address as `ATYP | ADDR | PORT`.
+fn encode_address(addr: SocketAddr) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(1 + 16 + 2);
+    match addr.ip() {
+        IpAddr::V4(ip) => {
+            encoded.push(ATYP_IPV4);
+            encoded.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            encoded.push(ATYP_IPV6);
+            encoded.extend_from_slice(&ip.octets());
+        }
+    }
+    encoded.extend_from_slice(&addr.port().to_be_bytes());
+    encoded
+}
+
+/// Header of a relayed datagram: `RSV | FRAG | ATYP | DST.ADDR | DST.PORT`.
+fn datagram_header(destination: SocketAddr) -> Vec<u8> {
+    let mut header = vec![0, 0, 0];
+    header.extend(encode_address(destination));
+    header
+}
+
+/// Split a relayed datagram into its source address and payload. Returns `None` if the datagram
+/// is malformed or fragmented.
+fn parse_datagram(datagram: &[u8]) -> Option<(SocketAddr, &[u8])> {
+    let [0, 0, 0, address_type, rest @ ..] = datagram else {
+        return None;
+    };
+    let (ip, rest) = match *address_type {
+        ATYP_IPV4 => {
+            let (ip, rest) = rest.split_first_chunk::<4>()?;
+            (IpAddr::from(Ipv4Addr::from(*ip)), rest)
+        }
+        ATYP_IPV6 => {
+            let (ip, rest) = rest.split_first_chunk::<16>()?;
+            (IpAddr::from(Ipv6Addr::from(*ip)), rest)
+        }
+        _ => return None,
+    };
+    let (port, payload) = rest.split_first_chunk::<2>()?;
+    Some((SocketAddr::new(ip, u16::from_be_bytes(*port)), payload))
+}
+
+async fn handle_outgoing(
+    local_udp: Arc<UdpSocket>,
+    remote_udp: Arc<UdpSocket>,
+    wg_addr: SocketAddr,
+) {
+    let header = datagram_header(wg_addr);
+    let mut buffer = vec![0u8; header.len() + usize::from(u16::MAX)];
+    buffer[..header.len()].copy_from_slice(&header);
+
+    loop {
+        let read_n = match local_udp.recv(&mut buffer[header.len()..]).await {
+            Ok(read_n) => read_n,
+            Err(error) => {
+                log::error!("Failed to read from local UDP socket: {error}");
+                break;
+            }
+        };
+        if let Err(error) = remote_udp.send(&buffer[..header.len() + read_n]).await {
+            log::error!("Failed to write to SOCKS5 UDP relay: {error}");
+            break;
+        }
+    }
+}
+
+async fn handle_incoming(
+    remote_udp: Arc<UdpSocket>,
+    local_udp: Arc<UdpSocket>,
+    wg_addr: SocketAddr,
+) {
+    let mut buffer = vec![0u8; usize::from(u16::MAX)];
+
+    loop {
+        let read_n = match remote_udp.recv(&mut buffer).await {
+            Ok(read_n) => read_n,
+            Err(error) => {
+                log::error!("Failed to read from SOCKS5 UDP relay: {error}");
+                break;
+            }
+        };
+        let Some((source, payload)) = parse_datagram(&buffer[..read_n]) else {
+            log::trace!("Ignoring malformed datagram from SOCKS5 UDP relay");
+            continue;
+        };
+        if source != wg_addr {
+            log::trace!("Ignoring packet from unexpected source: {source}");
+            continue;
+        }
+        if let Err(error) = local_udp.send(payload).await {
+            log::error!("Failed to write to local UDP socket: {error}");
+            break;
+        }
+    }
+}
+
+#[async_trait]
+impl LocalSocketObfuscator for Socks5 {
+    fn endpoint(&self) -> SocketAddr {
+        self.local_udp_socket
+            .local_addr()
+            .expect("the local socket is bound")
+    }
+
+    async fn run(self: Box<Self>) -> crate::Result<()> {
+        self.forward()
+            .await
+            .map_err(crate::Error::RunSocks5Obfuscator)
+    }
+
+    fn packet_overhead(&self) -> u16 {
+        let overhead = datagram_header(self.settings.wireguard_endpoint).len();
+        u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use talpid_net::bypass::NoopBypass;
+    use tokio::net::TcpListener;
+
+    /// Stand-in for a SOCKS5 proxy that requires authentication. It relays UDP on the same port
+    /// as it listens on for TCP, and echoes every datagram back as if it came from the
+    /// destination.
+    async fn spawn_proxy(username: &'static str, password: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let relay = UdpSocket::bind(addr).await.unwrap();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let [version, n_methods] = read(&mut stream).await.unwrap();
+            assert_eq!(version, SOCKS_VERSION);
+            let mut methods = vec![0u8; usize::from(n_methods)];
+            stream.read_exact(&mut methods).await.unwrap();
+            assert!(methods.contains(&AUTH_PASSWORD));
+            stream
+                .write_all(&[SOCKS_VERSION, AUTH_PASSWORD])
+                .await
+                .unwrap();
+
+            let mut expected = vec![AUTH_PASSWORD_VERSION, username.len() as u8];
+            expected.extend_from_slice(username.as_bytes());
+            expected.push(password.len() as u8);
+            expected.extend_from_slice(password.as_bytes());
+            let mut auth = vec![0u8; expected.len()];
+            stream.read_exact(&mut auth).await.unwrap();
+            assert_eq!(auth, expected);
+            stream.write_all(&[AUTH_PASSWORD_VERSION, 0]).await.unwrap();
+
+            let request: [u8; 10] = read(&mut stream).await.unwrap();
+            assert_eq!(request[..2], [SOCKS_VERSION, CMD_UDP_ASSOCIATE]);
+            let mut reply = vec![SOCKS_VERSION, REPLY_SUCCEEDED, 0];
+            reply.extend(encode_address(SocketAddr::from((
+                Ipv4Addr::UNSPECIFIED,
+                addr.port(),
+            ))));
+            stream.write_all(&reply).await.unwrap();
+
+            let mut buffer = vec![0u8; 1024];
+            loop {
+                let (n, client) = relay.recv_from(&mut buffer).await.unwrap();
+                relay.send_to(&buffer[..n], client).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_socks5_forwarding() {
+        let proxy_endpoint = spawn_proxy("user", "hunter2").await;
+        let settings = Settings {
+            proxy_endpoint,
+            auth: Some(SocksAuth::new("user".to_owned(), "hunter2".to_owned()).unwrap()),
+            wireguard_endpoint: "192.0.2.1:51820".parse().unwrap(),
+        };
+
+        let obfuscator = Socks5::new(Arc::new(NoopBypass), &settings).await.unwrap();
+        let obfuscator_endpoint = obfuscator.endpoint();
+        tokio::spawn(Box::new(obfuscator).run());
+
+        let client_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        client_socket
+            .send_to(b"Ping!", obfuscator_endpoint)
+            .await
+            .unwrap();
+
+        let mut buffer = vec![0u8; 1024];
+        let (n, _) = client_socket.recv_from(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..n], b"Ping!");
+    }
+
+    #[test]
+    fn test_parse_datagram() {
+        let source: SocketAddr = "[2001:db8::1]:51820".parse().unwrap();
+        let mut datagram = datagram_header(source);
+        datagram.extend_from_slice(b"payload");
+        assert_eq!(parse_datagram(&datagram), Some((source, &b"payload"[..])));
+
+        // Fragmented datagrams are not supported
+        datagram[2] = 1;
+        assert_eq!(parse_datagram(&datagram), None);
+
+        assert_eq!(parse_datagram(&[0, 0, 0, ATYP_IPV4, 127, 0]), None);
+    }
+}